@@ -3,34 +3,39 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
 
+use santorini_ai::analysis::positions::{self, Category};
 use santorini_ai::mcts::santorini::{SantoriniNode, SantoriniSimulation};
-use santorini_ai::mcts::{Node, Simulation};
+use santorini_ai::mcts::{MoveStats, Node, Simulation};
 use santorini_ai::player::mcts_ai::MctsSantoriniParams;
-use santorini_ai::santorini::{self, Point};
+use santorini_ai::santorini;
 
 fn default_node() -> SantoriniNode {
-    let g = santorini::new_game();
-    let p1 = Point::new(1.into(), 1.into());
-    let p2 = Point::new(2.into(), 1.into());
-    let p3 = Point::new(1.into(), 2.into());
-    let p4 = Point::new(2.into(), 2.into());
-
-    let action = g.can_place(p1, p2).expect("Invalid placement");
-    let g = g.apply(action);
-    let action = g.can_place(p3, p4).expect("Invalid placement");
-    g.apply(action).into()
+    positions::find("diamond")
+        .expect("Missing benchmark position")
+        .game()
+        .into()
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     let s_node = default_node();
-    let mut rng = SmallRng::from_entropy();
+    // Fixed seed so every run simulates the same playouts
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut stats = MoveStats::default();
 
     {
         let mut group = c.benchmark_group("small");
         group.sample_size(500);
         group.bench_function("simulate", |b| {
-            b.iter(|| SantoriniSimulation {}.simulate(&s_node, &mut rng))
+            b.iter(|| SantoriniSimulation {}.simulate(&s_node, &mut rng, &mut stats))
         });
+
+        for position in positions::category(Category::Middlegame) {
+            let node: SantoriniNode = position.game().into();
+            let mut rng = SmallRng::seed_from_u64(0);
+            group.bench_function(format!("simulate {}", position.name), |b| {
+                b.iter(|| SantoriniSimulation {}.simulate(&node, &mut rng, &mut stats))
+            });
+        }
     }
 
     let mut params = MctsSantoriniParams::default();
@@ -54,6 +59,12 @@ fn criterion_benchmark(c: &mut Criterion) {
             n2
         })
     });
+    group.bench_function("perft 2", |b| {
+        let game = positions::find("diamond")
+            .expect("Missing benchmark position")
+            .game();
+        b.iter(|| santorini::perft(game, 2))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,513 @@
+//! A long-running analysis service.
+//!
+//! The daemon keeps its search tree and a cache of finished analyses in
+//! memory between requests, so analyzing the same or a nearby position again
+//! does not start from a cold tree. Clients talk to it over a unix socket
+//! using a simple line based protocol:
+//!
+//! ```text
+//! analyze <position> [budget]  ->  ok <from> <to> <build|-> <score> <iterations>
+//! stats                        ->  ok <hits> <misses> <reused trees>
+//! gods                         ->  ok <god> ...
+//! shutdown                     ->  ok
+//! ```
+//!
+//! Failed requests are answered with `error <message>`.
+//!
+//! Operational counters are collected in [`metrics::Metrics`] and can be
+//! exposed for Prometheus to scrape with [`metrics::serve_http`].
+
+use cached::{Cached, SizedCache};
+use rand::rngs::SmallRng;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use thiserror::Error;
+
+use crate::mcts::santorini::SantoriniNode;
+use crate::mcts::{Budget, Mcts, Node};
+use crate::player::MctsSantoriniParams;
+use crate::santorini::notation::{
+    encode_board, encode_workers, parse_board, parse_workers, NotationError,
+};
+pub use crate::santorini::notation::{encode_point, parse_point};
+use crate::santorini::{AnyGame, Game, God, Move, Phase, Player, Point};
+
+pub mod metrics;
+pub mod positions;
+pub mod solver;
+
+use metrics::Metrics;
+
+#[derive(Error, Debug)]
+pub enum AnalysisError {
+    #[error("issue communicating with the analysis server")]
+    IoError(#[from] io::Error),
+    #[error("invalid position: {0}")]
+    InvalidPosition(String),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("server error: {0}")]
+    ServerError(String),
+}
+
+/// The outcome of analyzing a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Analysis {
+    pub from: Point,
+    pub to: Point,
+    pub build: Option<Point>,
+    /// The expected outcome of the recommended turn for the player to move,
+    /// between -1.0 and 1.0.
+    pub score: f64,
+    pub iterations: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub reused_trees: u64,
+}
+
+/// The socket used when none is specified.
+pub fn default_socket_path() -> PathBuf {
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    dir.join("santorini-analysis.sock")
+}
+
+/// Encode a position as `<heights> <p1 workers> <p2 workers> <player>`, where
+/// the heights are given row by row separated by `/`, with 4 for a dome.
+///
+/// For example: `00000/01000/00200/00000/00004 b2,c3 d4,e1 1`
+///
+/// This is the start of the notation in `santorini::notation`, which also
+/// covers the other phases of the game and gods.
+pub fn encode_position(game: &Game<Move>) -> String {
+    let workers = |player| encode_workers(game.workers(player));
+    let player = game.player().index() + 1;
+
+    format!(
+        "{} {} {} {}",
+        encode_board(&game.board()),
+        workers(Player::PlayerOne),
+        workers(Player::PlayerTwo),
+        player
+    )
+}
+
+/// Parse a position produced by `encode_position`.
+pub fn parse_position(s: &str) -> Result<Game<Move>, AnalysisError> {
+    let invalid = |msg: &str| AnalysisError::InvalidPosition(msg.to_string());
+    let notation = |err: NotationError| AnalysisError::InvalidPosition(err.to_string());
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() != 4 {
+        return Err(invalid("expected 4 fields"));
+    }
+
+    let board = parse_board(fields[0]).map_err(notation)?;
+    let workers = |field: &str| match parse_workers(field).map_err(notation)? {
+        Some(workers) if workers.len() == 2 => Ok(workers),
+        _ => Err(invalid("each player needs exactly 2 workers")),
+    };
+    let p1 = workers(fields[1])?;
+    let p2 = workers(fields[2])?;
+
+    let player = match fields[3] {
+        "1" => Player::PlayerOne,
+        "2" => Player::PlayerTwo,
+        _ => return Err(invalid("player must be 1 or 2")),
+    };
+
+    match Game::try_from_parts(board, &p1, &p2, player, Phase::Move) {
+        Ok(AnyGame::Move(game)) => Ok(game),
+        Ok(_) => unreachable!("Set up a position in the wrong phase!"),
+        Err(err) => Err(invalid(&err.to_string())),
+    }
+}
+
+/// Search for the node matching game, at most depth plies below node.
+fn find_node(
+    node: Node<SantoriniNode>,
+    game: &Game<Move>,
+    depth: u8,
+) -> Option<Node<SantoriniNode>> {
+    if node.state.matches(*game) {
+        return Some(node);
+    }
+    if depth == 0 {
+        return None;
+    }
+    node.children?
+        .into_iter()
+        .find_map(|child| find_node(child, game, depth - 1))
+}
+
+/// The warm state shared by every request to the daemon.
+pub struct Analyzer {
+    budget: u32,
+    tree: Option<Mcts<SantoriniNode, SmallRng>>,
+    cache: SizedCache<(Game<Move>, u32), Analysis>,
+    stats: CacheStats,
+    metrics: Arc<Metrics>,
+}
+
+impl Analyzer {
+    pub fn new(budget: u32) -> Self {
+        Analyzer {
+            budget,
+            tree: None,
+            cache: SizedCache::with_size(4096),
+            stats: CacheStats::default(),
+            metrics: Arc::new(Metrics::default()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    pub fn analyze(
+        &mut self,
+        game: Game<Move>,
+        budget: Option<u32>,
+    ) -> Result<Analysis, AnalysisError> {
+        let budget = budget.unwrap_or(self.budget);
+        if let Some(analysis) = self.cache.cache_get(&(game, budget)) {
+            self.stats.hits += 1;
+            self.metrics.cache_hit();
+            return Ok(*analysis);
+        }
+        self.stats.misses += 1;
+
+        if !game.active_pawns().iter().any(|pawn| pawn.has_actions()) {
+            return Err(AnalysisError::InvalidPosition(
+                "the player to move has no legal moves".to_string(),
+            ));
+        }
+
+        // Reuse the existing tree if it has already explored this position,
+        // either directly or as a reply to the last recommendation.
+        let mut tree = match self.tree.take() {
+            Some(Mcts { params, root_node }) => match find_node(root_node, &game, 2) {
+                Some(root_node) => {
+                    self.stats.reused_trees += 1;
+                    Mcts { params, root_node }
+                }
+                None => Mcts::new(params, game.into()),
+            },
+            None => Mcts::new(MctsSantoriniParams::default(), game.into()),
+        };
+
+        tree.params.budget = Budget::Iterations(budget);
+        let start = Instant::now();
+        tree.advance();
+        self.metrics.search(budget, start.elapsed());
+        let node = &tree.root_node;
+        let mv = node.state.mv.expect("Missing move action!");
+        let analysis = Analysis {
+            from: mv.from(),
+            to: mv.to(),
+            build: node.state.build.map(|build| build.loc()),
+            score: node.score,
+            iterations: node.iterations,
+        };
+        self.tree = Some(tree);
+        self.cache.cache_set((game, budget), analysis);
+
+        Ok(analysis)
+    }
+
+    /// Handle a single protocol line, returning the response and whether the
+    /// server should shut down.
+    pub fn handle(&mut self, request: &str) -> (String, bool) {
+        let mut words = request.splitn(2, ' ');
+        let response = match (words.next(), words.next()) {
+            (Some("analyze"), Some(args)) => self.handle_analyze(args),
+            (Some("stats"), None) => {
+                let stats = self.stats;
+                Ok(format!(
+                    "{} {} {}",
+                    stats.hits, stats.misses, stats.reused_trees
+                ))
+            }
+            (Some("gods"), None) => Ok(God::all()
+                .map(|god| god.name())
+                .collect::<Vec<_>>()
+                .join(" ")),
+            (Some("shutdown"), None) => return ("ok".to_string(), true),
+            _ => Err(AnalysisError::InvalidRequest(request.to_string())),
+        };
+
+        self.metrics.request(response.is_ok());
+        match response {
+            Ok(msg) => (format!("ok {}", msg), false),
+            Err(err) => (format!("error {}", err), false),
+        }
+    }
+
+    fn handle_analyze(&mut self, args: &str) -> Result<String, AnalysisError> {
+        let fields: Vec<&str> = args.split_whitespace().collect();
+        let (position, budget) = match fields.len() {
+            4 => (fields.join(" "), None),
+            5 => {
+                let budget = fields[4]
+                    .parse()
+                    .map_err(|_| AnalysisError::InvalidRequest("invalid budget".to_string()))?;
+                (fields[..4].join(" "), Some(budget))
+            }
+            _ => return Err(AnalysisError::InvalidRequest(args.to_string())),
+        };
+        let analysis = self.analyze(parse_position(&position)?, budget)?;
+        Ok(format!(
+            "{} {} {} {} {}",
+            encode_point(analysis.from),
+            encode_point(analysis.to),
+            analysis.build.map_or("-".to_string(), encode_point),
+            analysis.score,
+            analysis.iterations
+        ))
+    }
+}
+
+/// Serve requests on the given socket until a client asks for a shutdown.
+///
+/// Each client is handled on its own thread, but analyses are run one at a
+/// time against the shared tree.
+pub fn serve<P: AsRef<Path>>(path: P, analyzer: Analyzer) -> Result<(), AnalysisError> {
+    let path = path.as_ref();
+    if path.exists() {
+        // Remove the socket left behind by a previous daemon
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let metrics = analyzer.metrics();
+    let analyzer = Arc::new(Mutex::new(analyzer));
+    let shutdown = Arc::new(AtomicBool::new(false));
+
+    for stream in listener.incoming() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = stream?;
+        let analyzer = Arc::clone(&analyzer);
+        let metrics = Arc::clone(&metrics);
+        let shutdown = Arc::clone(&shutdown);
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            metrics.client_connected();
+            let stop = handle_client(stream, &analyzer).unwrap_or(false);
+            metrics.client_disconnected();
+            if stop {
+                shutdown.store(true, Ordering::SeqCst);
+                // Wake up the accept loop so it notices the shutdown
+                let _ = UnixStream::connect(path);
+            }
+        });
+    }
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Answer requests from one client, returning whether it asked for a
+/// shutdown.
+fn handle_client(mut stream: UnixStream, analyzer: &Mutex<Analyzer>) -> io::Result<bool> {
+    let reader = BufReader::new(stream.try_clone()?);
+    for line in reader.lines() {
+        let (response, stop) = analyzer
+            .lock()
+            .expect("Analyzer poisoned!")
+            .handle(line?.trim());
+        writeln!(stream, "{}", response)?;
+        if stop {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// A connection to a running analysis daemon.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, AnalysisError> {
+        let writer = UnixStream::connect(path)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Client { reader, writer })
+    }
+
+    fn request(&mut self, request: &str) -> Result<Vec<String>, AnalysisError> {
+        writeln!(self.writer, "{}", request)?;
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        let mut words = line.split_whitespace().map(str::to_string);
+        match words.next().as_deref() {
+            Some("ok") => Ok(words.collect()),
+            Some("error") => Err(AnalysisError::ServerError(
+                words.collect::<Vec<_>>().join(" "),
+            )),
+            _ => Err(AnalysisError::ServerError(format!(
+                "unexpected response: {}",
+                line.trim()
+            ))),
+        }
+    }
+
+    pub fn analyze(
+        &mut self,
+        game: &Game<Move>,
+        budget: Option<u32>,
+    ) -> Result<Analysis, AnalysisError> {
+        let request = match budget {
+            Some(budget) => format!("analyze {} {}", encode_position(game), budget),
+            None => format!("analyze {}", encode_position(game)),
+        };
+        let fields = self.request(&request)?;
+        let bad = || AnalysisError::ServerError(format!("malformed analysis: {:?}", fields));
+        if fields.len() != 5 {
+            return Err(bad());
+        }
+
+        Ok(Analysis {
+            from: parse_point(&fields[0]).ok_or_else(bad)?,
+            to: parse_point(&fields[1]).ok_or_else(bad)?,
+            build: match fields[2].as_str() {
+                "-" => None,
+                build => Some(parse_point(build).ok_or_else(bad)?),
+            },
+            score: fields[3].parse().map_err(|_| bad())?,
+            iterations: fields[4].parse().map_err(|_| bad())?,
+        })
+    }
+
+    pub fn stats(&mut self) -> Result<CacheStats, AnalysisError> {
+        let fields = self.request("stats")?;
+        let parse = |idx: usize| {
+            fields
+                .get(idx)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| AnalysisError::ServerError("malformed stats".to_string()))
+        };
+        Ok(CacheStats {
+            hits: parse(0)?,
+            misses: parse(1)?,
+            reused_trees: parse(2)?,
+        })
+    }
+
+    /// The gods the daemon knows about.
+    pub fn gods(&mut self) -> Result<Vec<God>, AnalysisError> {
+        self.request("gods")?
+            .iter()
+            .map(|name| name.parse().map_err(AnalysisError::ServerError))
+            .collect()
+    }
+
+    pub fn shutdown(mut self) -> Result<(), AnalysisError> {
+        self.request("shutdown").map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+    use crate::santorini::{self, Board, CoordLevel};
+
+    fn opening() -> Game<Move> {
+        let g = santorini::new_game();
+        let action = g
+            .can_place(&[
+                Point::new(1.into(), 1.into()),
+                Point::new(3.into(), 3.into()),
+            ])
+            .expect("Invalid placement!");
+        let g = g.apply(action);
+        let action = g
+            .can_place(&[
+                Point::new(1.into(), 3.into()),
+                Point::new(3.into(), 1.into()),
+            ])
+            .expect("Invalid placement!");
+        g.apply(action).unwrap()
+    }
+
+    #[test]
+    fn position_round_trip() {
+        let g = opening();
+        let encoded = encode_position(&g);
+        let empty = encode_board(&Board::new());
+        assert_eq!(encoded, format!("{} b2,d4 b4,d2 1", empty));
+        assert!(parse_position(&encoded).unwrap().same_position(&g));
+
+        let pawn = g.active_pawns()[0];
+        let g = g.apply(pawn.actions().next().unwrap()).unwrap();
+        let build = g.active_pawn().actions().next().unwrap();
+        let g = g.apply(build).unwrap();
+        assert!(parse_position(&encode_position(&g))
+            .unwrap()
+            .same_position(&g));
+    }
+
+    #[test]
+    fn invalid_positions() {
+        let empty = encode_board(&Board::new());
+        let mut raised = Board::new();
+        raised.set_level(Point::new(1.into(), 1.into()), CoordLevel::Three);
+        let raised = encode_board(&raised);
+        assert!(parse_position(&format!("{} b2,d4 b4,d2", empty)).is_err());
+        assert!(parse_position(&format!("{} b2,b2 b4,d2 1", empty)).is_err());
+        assert!(parse_position(&format!("{} b2,d4 b4,g2 1", empty)).is_err());
+        assert!(parse_position(&format!("{} b2,d4 b4,d2 1", raised)).is_err());
+        assert!(parse_position(&format!("{} b2,d4 b4,d2 3", empty)).is_err());
+    }
+
+    #[test]
+    fn cached_analysis() {
+        let mut analyzer = Analyzer::new(20);
+        let g = opening();
+        let first = analyzer.analyze(g, None).unwrap();
+        let second = analyzer.analyze(g, None).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            analyzer.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                reused_trees: 0
+            }
+        );
+    }
+
+    #[test]
+    fn handle_requests() {
+        let mut analyzer = Analyzer::new(20);
+        let (response, stop) = analyzer.handle(&format!("analyze {}", encode_position(&opening())));
+        assert!(response.starts_with("ok "), "{}", response);
+        assert!(!stop);
+
+        let (response, _) = analyzer.handle("analyze nonsense");
+        assert!(response.starts_with("error "), "{}", response);
+
+        assert_eq!(analyzer.handle("stats"), ("ok 0 1 0".to_string(), false));
+        let (response, _) = analyzer.handle("gods");
+        assert!(response.starts_with("ok Mortal Apollo "), "{}", response);
+        assert!(response.ends_with(" Zeus"), "{}", response);
+        assert_eq!(analyzer.handle("shutdown"), ("ok".to_string(), true));
+    }
+}
@@ -0,0 +1,192 @@
+//! Counters describing the analysis daemon, served over HTTP in the
+//! Prometheus text exposition format.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct Metrics {
+    connected_clients: AtomicU64,
+    clients: AtomicU64,
+    requests: AtomicU64,
+    failed_requests: AtomicU64,
+    analyses: AtomicU64,
+    cache_hits: AtomicU64,
+    search_iterations: AtomicU64,
+    search_micros: AtomicU64,
+}
+
+impl Metrics {
+    pub fn client_connected(&self) {
+        self.clients.fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn request(&self, succeeded: bool) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !succeeded {
+            self.failed_requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cache_hit(&self) {
+        self.analyses.fetch_add(1, Ordering::Relaxed);
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn search(&self, iterations: u32, elapsed: Duration) {
+        self.analyses.fetch_add(1, Ordering::Relaxed);
+        self.search_iterations
+            .fetch_add(iterations.into(), Ordering::Relaxed);
+        self.search_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// Render the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!("# HELP {} {}\n", name, help));
+            out.push_str(&format!("# TYPE {} {}\n", name, kind));
+            out.push_str(&format!("{} {}\n", name, value));
+        };
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        metric(
+            "santorini_connected_clients",
+            "gauge",
+            "Clients currently connected to the analysis socket.",
+            load(&self.connected_clients).to_string(),
+        );
+        metric(
+            "santorini_clients_total",
+            "counter",
+            "Clients that have connected to the analysis socket.",
+            load(&self.clients).to_string(),
+        );
+        metric(
+            "santorini_requests_total",
+            "counter",
+            "Protocol requests handled.",
+            load(&self.requests).to_string(),
+        );
+        metric(
+            "santorini_failed_requests_total",
+            "counter",
+            "Protocol requests answered with an error.",
+            load(&self.failed_requests).to_string(),
+        );
+        metric(
+            "santorini_analyses_total",
+            "counter",
+            "Positions analyzed, including cache hits.",
+            load(&self.analyses).to_string(),
+        );
+        metric(
+            "santorini_cache_hits_total",
+            "counter",
+            "Analyses answered from the cache.",
+            load(&self.cache_hits).to_string(),
+        );
+        metric(
+            "santorini_search_iterations_total",
+            "counter",
+            "MCTS iterations run.",
+            load(&self.search_iterations).to_string(),
+        );
+        metric(
+            "santorini_search_seconds_total",
+            "counter",
+            "Time spent searching.",
+            (load(&self.search_micros) as f64 / 1e6).to_string(),
+        );
+
+        out
+    }
+}
+
+/// Serve `GET /metrics` on the given address from a background thread.
+pub fn serve_http<A: ToSocketAddrs>(addr: A, metrics: Arc<Metrics>) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            // A misbehaving scraper shouldn't take the endpoint down
+            let _ = stream.and_then(|stream| respond(stream, &metrics));
+        }
+    }))
+}
+
+fn respond<S: io::Read + Write>(mut stream: S, metrics: &Metrics) -> io::Result<()> {
+    let mut request = String::new();
+    BufReader::new(&mut stream).read_line(&mut request)?;
+
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", "Not Found\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.0 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::default();
+        metrics.client_connected();
+        metrics.client_connected();
+        metrics.client_disconnected();
+        metrics.request(true);
+        metrics.request(false);
+        metrics.cache_hit();
+        metrics.search(500, Duration::from_millis(250));
+
+        let text = metrics.render();
+        assert!(text.contains("santorini_connected_clients 1\n"));
+        assert!(text.contains("santorini_clients_total 2\n"));
+        assert!(text.contains("santorini_requests_total 2\n"));
+        assert!(text.contains("santorini_failed_requests_total 1\n"));
+        assert!(text.contains("santorini_analyses_total 2\n"));
+        assert!(text.contains("santorini_search_iterations_total 500\n"));
+        assert!(text.contains("santorini_search_seconds_total 0.25\n"));
+        assert!(text.contains("# TYPE santorini_cache_hits_total counter\n"));
+    }
+
+    #[test]
+    fn respond_to_scrape() {
+        let metrics = Metrics::default();
+        let mut stream = Cursor::new(b"GET /metrics HTTP/1.1\r\n\r\n".to_vec());
+        respond(&mut stream, &metrics).unwrap();
+        let response = String::from_utf8(stream.into_inner()).unwrap();
+        assert!(response.contains("HTTP/1.0 200 OK"));
+        assert!(response.ends_with(&metrics.render()));
+
+        let mut stream = Cursor::new(b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        respond(&mut stream, &metrics).unwrap();
+        let response = String::from_utf8(stream.into_inner()).unwrap();
+        assert!(response.contains("HTTP/1.0 404 Not Found"));
+    }
+}
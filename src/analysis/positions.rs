@@ -0,0 +1,163 @@
+//! A library of named reference positions.
+//!
+//! Positions are stored in the format read by `analysis::parse_position`.
+
+use crate::analysis::parse_position;
+use crate::santorini::{Game, Move};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Opening,
+    Trap,
+    Middlegame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedPosition {
+    pub name: &'static str,
+    pub category: Category,
+    pub description: &'static str,
+    pub position: &'static str,
+}
+
+impl NamedPosition {
+    pub fn game(&self) -> Game<Move> {
+        parse_position(self.position)
+            .unwrap_or_else(|err| panic!("Invalid named position {}: {}", self.name, err))
+    }
+}
+
+pub static POSITIONS: [NamedPosition; 8] = [
+    NamedPosition {
+        name: "cross",
+        category: Category::Opening,
+        description: "Both players take opposite corners of the center square",
+        position: "00000/00000/00000/00000/00000 b2,d4 b4,d2 1",
+    },
+    NamedPosition {
+        name: "diamond",
+        category: Category::Opening,
+        description: "Workers surround the center from each side",
+        position: "00000/00000/00000/00000/00000 c2,c4 b3,d3 1",
+    },
+    NamedPosition {
+        name: "edge",
+        category: Category::Opening,
+        description: "Player One hangs back on the edge while Player Two takes the center",
+        position: "00000/00000/00000/00000/00000 a3,e3 c2,c4 1",
+    },
+    NamedPosition {
+        name: "double-threat",
+        category: Category::Trap,
+        description: "A worker on level two between two towers; only one can be capped",
+        position: "00000/01100/03230/00100/00000 c3,a5 e1,e5 2",
+    },
+    NamedPosition {
+        name: "cornered",
+        category: Category::Trap,
+        description: "Raising c1 leaves Player Two's workers without a move",
+        position: "00100/22200/00000/00000/00000 d2,c4 a1,b1 1",
+    },
+    NamedPosition {
+        name: "midgame-open",
+        category: Category::Middlegame,
+        description: "A spread out middlegame with many low buildings",
+        position: "01000/12100/01210/00120/00010 b2,d4 c2,d3 1",
+    },
+    NamedPosition {
+        name: "midgame-tower",
+        category: Category::Middlegame,
+        description: "Play revolves around a domed tower in the center",
+        position: "00120/01320/02420/00100/00000 b2,e3 c4,a1 2",
+    },
+    NamedPosition {
+        name: "endgame-race",
+        category: Category::Middlegame,
+        description: "A crowded board where both players are close to climbing",
+        position: "10231/02320/13410/02100/00010 a1,c4 e5,a3 1",
+    },
+];
+
+/// Look up a position by name.
+pub fn find(name: &str) -> Option<&'static NamedPosition> {
+    POSITIONS.iter().find(|position| position.name == name)
+}
+
+/// All positions in the given category.
+pub fn category(category: Category) -> impl Iterator<Item = &'static NamedPosition> {
+    POSITIONS
+        .iter()
+        .filter(move |position| position.category == category)
+}
+
+#[cfg(test)]
+mod positions_tests {
+    use super::*;
+    use crate::santorini::{ActionResult, Player};
+
+    #[test]
+    // The named positions are written out for the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn positions_are_playable() {
+        for (idx, position) in POSITIONS.iter().enumerate() {
+            let game = position.game();
+            assert!(
+                game.active_pawns().iter().any(|pawn| pawn.has_actions()),
+                "{} has no legal moves",
+                position.name
+            );
+            assert!(
+                POSITIONS[idx + 1..]
+                    .iter()
+                    .all(|other| other.name != position.name),
+                "{} is not unique",
+                position.name
+            );
+        }
+    }
+
+    #[test]
+    fn find_positions() {
+        assert_eq!(find("cross").unwrap().name, "cross");
+        assert_eq!(find("nonexistent"), None);
+        assert_eq!(category(Category::Trap).count(), 2);
+    }
+
+    #[test]
+    // The trap is laid out on the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn double_threat_trap() {
+        let game = find("double-threat").unwrap().game();
+        for pawn in game.active_pawns().iter() {
+            for mv in pawn.actions() {
+                let game = game.apply(mv).unwrap();
+                for build in game.active_pawn().actions() {
+                    let game = game.apply(build).unwrap();
+                    let wins = game
+                        .active_pawns()
+                        .iter()
+                        .flat_map(|pawn| pawn.actions())
+                        .any(|mv| matches!(game.apply(mv), ActionResult::Victory(_)));
+                    assert!(wins, "Player Two escaped the trap");
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Like the trap above, this one is set on the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn cornered_trap() {
+        let game = find("cornered").unwrap().game();
+        let pawn = game.active_pawns()[0];
+        let action = pawn.can_move(crate::analysis::parse_point("d1").unwrap());
+        let game = game.apply(action.unwrap()).unwrap();
+        let build = game
+            .active_pawn()
+            .can_build(crate::analysis::parse_point("c1").unwrap());
+        match game.apply(build.unwrap()) {
+            ActionResult::Victory(game) => assert_eq!(game.player(), Player::PlayerOne),
+            ActionResult::Continue(_) => panic!("Victory not detected!"),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Proving forced wins with proof-number search.
+//!
+//! The solver grows a tree from the position, always expanding the node that
+//! settles the most for the least work: where the player to act picks a turn,
+//! one winning turn proves the node, while where an opponent picks, every
+//! turn has to be answered. Positions still open once the ply limit is
+//! reached count as not won, so a proof is a win within the limit whatever
+//! the opponents do.
+
+use crate::santorini::{ActionResult, Game, Move, Player, TurnActions};
+
+/// Proof and disproof numbers too large to ever be reached.
+const INFINITY: u32 = u32::MAX;
+
+/// What the solver established about a position, for the player to act.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The player wins within this many plies, counting their own turns and
+    /// their opponents'. No quicker win exists.
+    Win(u32),
+    /// There is no forced win within the ply limit.
+    NoWin,
+    /// The node limit was reached before the position was settled.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    pub outcome: Outcome,
+    /// The turn the win starts with, for wins.
+    pub turn: Option<TurnActions>,
+}
+
+struct PnNode {
+    /// The turn from the parent that reached this node.
+    turn: Option<TurnActions>,
+    game: Game<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    depth: u32,
+    proof: u32,
+    disproof: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Solver {
+    /// How many plies a win may take.
+    pub plies: u32,
+    /// How many nodes a search may grow before giving up.
+    pub node_limit: usize,
+}
+
+impl Solver {
+    pub fn new(plies: u32) -> Solver {
+        Solver {
+            plies,
+            node_limit: 1_000_000,
+        }
+    }
+
+    pub fn node_limit(self, node_limit: usize) -> Self {
+        Solver { node_limit, ..self }
+    }
+
+    /// Look for a forced win for the player to act. Wins come on their own
+    /// turns, so the search deepens two plies at a time to find the quickest.
+    pub fn solve(&self, game: &Game<Move>) -> Solution {
+        let mut unknown = false;
+        for plies in (1..=self.plies).step_by(2) {
+            match Search::new(*game, plies).run(self.node_limit) {
+                Some(Some(turn)) => {
+                    return Solution {
+                        outcome: Outcome::Win(plies),
+                        turn: Some(turn),
+                    }
+                }
+                Some(None) => (),
+                // A deeper search can still find a win a shallower one had
+                // no room to settle
+                None => unknown = true,
+            }
+        }
+        Solution {
+            outcome: if unknown {
+                Outcome::Unknown
+            } else {
+                Outcome::NoWin
+            },
+            turn: None,
+        }
+    }
+}
+
+/// A single proof-number search to a fixed depth.
+struct Search {
+    nodes: Vec<PnNode>,
+    attacker: Player,
+    plies: u32,
+}
+
+impl Search {
+    fn new(game: Game<Move>, plies: u32) -> Search {
+        Search {
+            nodes: vec![PnNode {
+                turn: None,
+                game,
+                parent: None,
+                children: vec![],
+                depth: 0,
+                proof: 1,
+                disproof: 1,
+            }],
+            attacker: game.player(),
+            plies,
+        }
+    }
+
+    /// The turn proving the win, None if it was disproved, or None at the
+    /// outer level if the node limit was reached first.
+    fn run(mut self, node_limit: usize) -> Option<Option<TurnActions>> {
+        while self.nodes[0].proof != 0 && self.nodes[0].disproof != 0 {
+            if self.nodes.len() >= node_limit {
+                return None;
+            }
+            let leaf = self.most_proving();
+            self.expand(leaf);
+            self.update(leaf);
+        }
+
+        if self.nodes[0].proof != 0 {
+            return Some(None);
+        }
+        let proved = self.nodes[0]
+            .children
+            .iter()
+            .find(|child| self.nodes[**child].proof == 0)
+            .expect("Proved without a winning turn!");
+        Some(self.nodes[*proved].turn)
+    }
+
+    /// Whether the attacker picks the turn at a node.
+    fn attacking(&self, idx: usize) -> bool {
+        self.nodes[idx].game.player() == self.attacker
+    }
+
+    /// Follow the children that would do the most to settle the root down to
+    /// a leaf.
+    fn most_proving(&self) -> usize {
+        let mut idx = 0;
+        while !self.nodes[idx].children.is_empty() {
+            let children = &self.nodes[idx].children;
+            idx = if self.attacking(idx) {
+                *children
+                    .iter()
+                    .min_by_key(|child| self.nodes[**child].proof)
+                    .expect("No children!")
+            } else {
+                *children
+                    .iter()
+                    .min_by_key(|child| self.nodes[**child].disproof)
+                    .expect("No children!")
+            };
+        }
+        idx
+    }
+
+    fn expand(&mut self, idx: usize) {
+        let depth = self.nodes[idx].depth + 1;
+        let game = self.nodes[idx].game;
+        for (turn, result) in game.legal_turns() {
+            let (game, proof, disproof) = match result {
+                ActionResult::Victory(won) if won.player() == self.attacker => (game, 0, INFINITY),
+                ActionResult::Victory(_) => (game, INFINITY, 0),
+                ActionResult::Continue(next) if depth >= self.plies => (next, INFINITY, 0),
+                ActionResult::Continue(next) => (next, 1, 1),
+            };
+            let child = self.nodes.len();
+            self.nodes.push(PnNode {
+                turn: Some(turn),
+                game,
+                parent: Some(idx),
+                children: vec![],
+                depth,
+                proof,
+                disproof,
+            });
+            self.nodes[idx].children.push(child);
+        }
+    }
+
+    /// Recompute the numbers of a node and its ancestors from their children.
+    fn update(&mut self, idx: usize) {
+        let mut next = Some(idx);
+        while let Some(idx) = next {
+            let children = &self.nodes[idx].children;
+            let proofs = children.iter().map(|child| self.nodes[*child].proof);
+            let disproofs = children.iter().map(|child| self.nodes[*child].disproof);
+            // A player without a turn to take has lost
+            let (proof, disproof) = if self.attacking(idx) {
+                (
+                    proofs.min().unwrap_or(INFINITY),
+                    disproofs.fold(0, u32::saturating_add),
+                )
+            } else {
+                (
+                    proofs.fold(0, u32::saturating_add),
+                    disproofs.min().unwrap_or(INFINITY),
+                )
+            };
+            let node = &mut self.nodes[idx];
+            node.proof = proof;
+            node.disproof = disproof;
+            next = node.parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod solver_tests {
+    use super::*;
+    use crate::santorini::{Board, GameBuilder, God, Point, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// Player Two's workers along the bottom edge, in the far corner and
+    /// beside it.
+    fn far() -> [Point; 2] {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        [pt(w - 2, h - 1), pt(w - 1, h - 1)]
+    }
+
+    /// A game between mortals with the heights given as `(x, y, level)`.
+    fn moving(
+        heights: &[(i8, i8, i8)],
+        p1: [Point; 2],
+        p2: [Point; 2],
+        player: Player,
+    ) -> Game<Move> {
+        let mut board = Board::new();
+        for &(x, y, level) in heights {
+            board.set_level(pt(x, y), level.into());
+        }
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &p1)
+            .workers(Player::PlayerTwo, &p2)
+            .player(player)
+            .build()
+            .unwrap()
+    }
+
+    /// Whether the player to act can force a win within the plies, by
+    /// trying everything.
+    fn wins_within(game: &Game<Move>, attacker: Player, plies: u32) -> bool {
+        if plies == 0 {
+            return false;
+        }
+        let mut results = game.legal_turns().map(|(_, result)| match result {
+            ActionResult::Victory(won) => won.player() == attacker,
+            ActionResult::Continue(next) => wins_within(&next, attacker, plies - 1),
+        });
+        if game.player() == attacker {
+            results.any(|won| won)
+        } else {
+            results.all(|won| won)
+        }
+    }
+
+    #[test]
+    fn immediate_wins() {
+        // Climbing from a1 to b1 wins
+        let game = moving(
+            &[(0, 0, 2), (1, 0, 3)],
+            [pt(0, 0), pt(2, 2)],
+            far(),
+            Player::PlayerOne,
+        );
+        let solution = Solver::new(5).solve(&game);
+        assert_eq!(solution.outcome, Outcome::Win(1));
+        assert_eq!(solution.turn.unwrap().mv.to(), "b1".parse().unwrap());
+    }
+
+    #[test]
+    fn wins_in_three() {
+        // Moving to b1 and building c1 up to three can't be answered from
+        // the far corner
+        let game = moving(
+            &[(0, 0, 2), (1, 0, 2), (2, 0, 2)],
+            [pt(0, 0), pt(0, 2)],
+            far(),
+            Player::PlayerOne,
+        );
+        assert_eq!(Solver::new(1).solve(&game).outcome, Outcome::NoWin);
+        let solution = Solver::new(3).solve(&game);
+        assert_eq!(solution.outcome, Outcome::Win(3));
+        let turn = solution.turn.unwrap();
+        let after = match game.apply(turn.mv) {
+            ActionResult::Continue(game) => game.apply(turn.build.unwrap()),
+            ActionResult::Victory(_) => panic!("Won early!"),
+        };
+        match after {
+            // The opponent has nothing that wins first
+            ActionResult::Continue(next) => assert!(!wins_within(&next, next.player(), 1)),
+            ActionResult::Victory(_) => panic!("Won early!"),
+        }
+        let solution = Solver::new(3).node_limit(2).solve(&game);
+        assert_eq!(solution.outcome, Outcome::Unknown);
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let ledge = [(0, 0, 2), (1, 0, 2), (2, 0, 2)];
+        let steps = [
+            (0, 0, 1),
+            (1, 0, 2),
+            (0, 1, 2),
+            (1, 1, 1),
+            (2, 2, 3),
+            (3, 3, 4),
+        ];
+        let corner = pt(BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let games = [
+            moving(&ledge, [pt(0, 0), pt(0, 2)], far(), Player::PlayerOne),
+            moving(
+                &ledge,
+                [pt(0, 0), pt(0, 2)],
+                [pt(2, 1), corner],
+                Player::PlayerOne,
+            ),
+            moving(
+                &steps,
+                [pt(0, 0), pt(1, 1)],
+                [pt(3, 2), pt(0, 3)],
+                Player::PlayerOne,
+            ),
+            moving(
+                &steps,
+                [pt(0, 0), pt(1, 1)],
+                [pt(3, 2), pt(0, 3)],
+                Player::PlayerTwo,
+            ),
+        ];
+        for game in games.iter() {
+            let won = Solver::new(3).solve(game).outcome != Outcome::NoWin;
+            assert_eq!(won, wins_within(game, game.player(), 3), "{:?}", game);
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Playing AIs against each other without a UI.
+//!
+//! [`play_game`] plays two players out from workers placed at random, and
+//! [`play_from`] from any position under the [`Rules`] given. Both return the
+//! result from Player One's side along with how the game ended, how long it
+//! went on and how long each player thought.
+
+use rand::rngs::SmallRng;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::player::{FullPlayer, Player, StepResult};
+use crate::santorini::{self, AnyGame, Build, Game, Move, PlaceOne, PlaceResult, PlaceTwo};
+use crate::ui::UpdateError;
+
+/// Rules for ending a game early once its outcome is clear.
+///
+/// A game is adjudicated as a win once both players' evaluations favor the
+/// same player by at least `win_threshold` for `consecutive` moves in a row.
+/// It is adjudicated as a draw once `draw_after` moves have been played and
+/// both evaluations have stayed within `draw_threshold` of zero for
+/// `consecutive` moves in a row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjudication {
+    pub win_threshold: f64,
+    pub draw_threshold: f64,
+    pub draw_after: u32,
+    pub consecutive: u32,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            win_threshold: 0.9,
+            draw_threshold: 0.05,
+            draw_after: 80,
+            consecutive: 4,
+        }
+    }
+}
+
+struct Adjudicator {
+    rules: Adjudication,
+    moves: u32,
+    // The latest evaluation from each player, from Player One's perspective
+    evals: [Option<f64>; 2],
+    win_streak: u32,
+    draw_streak: u32,
+}
+
+impl Adjudicator {
+    fn new(rules: Adjudication) -> Self {
+        Adjudicator {
+            rules,
+            moves: 0,
+            evals: [None, None],
+            win_streak: 0,
+            draw_streak: 0,
+        }
+    }
+
+    /// Record the evaluation reported by `mover` after completing a move.
+    fn record(&mut self, mover: santorini::Player, eval: Option<f64>) {
+        self.moves += 1;
+        let (idx, eval) = match mover {
+            santorini::Player::PlayerOne => (0, eval),
+            santorini::Player::PlayerTwo => (1, eval.map(|e| -e)),
+            santorini::Player::PlayerThree => unreachable!("Matches only have two players!"),
+        };
+        self.evals[idx] = eval;
+
+        let (e1, e2) = match self.evals {
+            [Some(e1), Some(e2)] => (e1, e2),
+            _ => {
+                self.win_streak = 0;
+                self.draw_streak = 0;
+                return;
+            }
+        };
+
+        let threshold = self.rules.win_threshold;
+        if (e1 >= threshold && e2 >= threshold) || (e1 <= -threshold && e2 <= -threshold) {
+            self.win_streak += 1;
+        } else {
+            self.win_streak = 0;
+        }
+
+        let threshold = self.rules.draw_threshold;
+        if e1.abs() <= threshold && e2.abs() <= threshold {
+            self.draw_streak += 1;
+        } else {
+            self.draw_streak = 0;
+        }
+    }
+
+    /// The adjudicated result from Player One's perspective, if any.
+    fn verdict(&self) -> Option<f64> {
+        if self.win_streak >= self.rules.consecutive {
+            return self.evals[0].map(|e| if e > 0.0 { 1.0 } else { 0.0 });
+        }
+
+        if self.moves >= self.rules.draw_after && self.draw_streak >= self.rules.consecutive {
+            return Some(0.5);
+        }
+
+        None
+    }
+}
+
+/// How a game is played out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rules {
+    /// Games still going after this many turns are drawn.
+    pub max_turns: u32,
+    /// When to end a game early, if at all.
+    pub adjudication: Option<Adjudication>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            max_turns: 200,
+            adjudication: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ending {
+    Victory,
+    Adjudicated,
+    TurnLimit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameResult {
+    /// Player One's score: 1 for a win, 0 for a loss and a half for a draw.
+    pub score: f64,
+    pub ending: Ending,
+    /// The turns taken, counting both players'.
+    pub turns: u32,
+    /// How long each player spent stepping, Player One's first.
+    pub time: [Duration; 2],
+    /// Where the game ended.
+    pub game: AnyGame,
+}
+
+/// A game with every worker placed at random, so deterministic players
+/// don't play the same game over and over.
+pub fn random_start(seed: u64) -> Game<Move> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let game = santorini::new_game();
+    let placement = game
+        .placements()
+        .choose(&mut rng)
+        .expect("Nowhere to place!");
+    let game = game.apply(placement);
+    let placement = game
+        .placements()
+        .choose(&mut rng)
+        .expect("Nowhere to place!");
+    match game.apply(placement) {
+        PlaceResult::Move(game) => game,
+        PlaceResult::Place(_) => unreachable!("Only two players place!"),
+    }
+}
+
+/// Play a game out from `random_start(seed)` under the default rules.
+pub fn play_game(
+    p1: &mut dyn FullPlayer,
+    p2: &mut dyn FullPlayer,
+    seed: u64,
+) -> Result<GameResult, UpdateError> {
+    play_from(p1, p2, random_start(seed).into(), &Rules::default())
+}
+
+/// Play a game out from the position given.
+pub fn play_from(
+    p1: &mut dyn FullPlayer,
+    p2: &mut dyn FullPlayer,
+    start: AnyGame,
+    rules: &Rules,
+) -> Result<GameResult, UpdateError> {
+    let mut adjudicator = rules.adjudication.map(Adjudicator::new);
+    let mut time = [Duration::default(); 2];
+    let mut turns = 0;
+    let mut game = start;
+    let mut prepare = true;
+    loop {
+        let idx = game.player().index();
+        if let AnyGame::Victory(_) = game {
+            let score = if idx == 0 { 1.0 } else { 0.0 };
+            return Ok(GameResult {
+                score,
+                ending: Ending::Victory,
+                turns,
+                time,
+                game,
+            });
+        }
+        // Stop between turns
+        if turns >= rules.max_turns && matches!(game, AnyGame::Move(_)) {
+            return Ok(GameResult {
+                score: 0.5,
+                ending: Ending::TurnLimit,
+                turns,
+                time,
+                game,
+            });
+        }
+
+        let player: &mut dyn FullPlayer = match idx {
+            0 => &mut *p1,
+            _ => &mut *p2,
+        };
+        let started = Instant::now();
+        macro_rules! step {
+            ($state:ty, $game:expr) => {{
+                if prepare {
+                    Player::<$state>::prepare(player, &$game);
+                }
+                Player::<$state>::step(player, &$game)?
+            }};
+        }
+        let result = match game {
+            AnyGame::PlaceOne(game) => step!(PlaceOne, game),
+            AnyGame::PlaceTwo(game) => step!(PlaceTwo, game),
+            AnyGame::Move(game) => step!(Move, game),
+            AnyGame::Build(game) => step!(Build, game),
+            AnyGame::Victory(_) => unreachable!("The game is over!"),
+        };
+        time[idx] += started.elapsed();
+
+        let next: AnyGame = match result {
+            StepResult::NoMove | StepResult::Undo | StepResult::Pause => {
+                prepare = false;
+                continue;
+            }
+            StepResult::InProgress(_) => {
+                prepare = false;
+                thread::yield_now();
+                continue;
+            }
+            StepResult::PlaceTwo(game) => game.into(),
+            StepResult::Move(game) => game.into(),
+            StepResult::Build(game) => game.into(),
+            StepResult::Victory(game) => game.into(),
+        };
+
+        // Extra moves also lead to building, but only the first move of a
+        // turn comes with an evaluation
+        if let (AnyGame::Move(moved), AnyGame::Build(_)) = (game, next) {
+            turns += 1;
+            if let Some(adjudicator) = &mut adjudicator {
+                adjudicator.record(moved.player(), Player::<Move>::evaluation(player));
+                if let Some(score) = adjudicator.verdict() {
+                    return Ok(GameResult {
+                        score,
+                        ending: Ending::Adjudicated,
+                        turns,
+                        time,
+                        game: next,
+                    });
+                }
+            }
+        }
+        prepare = true;
+        game = next;
+    }
+}
+
+/// A 95% Wilson score interval for a win rate of `score` out of `games`.
+pub fn wilson_interval(score: f64, games: u32) -> (f64, f64) {
+    if games == 0 {
+        return (0.0, 1.0);
+    }
+    let z = 1.96;
+    let n = f64::from(games);
+    let p = score / n;
+    let center = p + z * z / (2.0 * n);
+    let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+    let denominator = 1.0 + z * z / n;
+    (
+        ((center - margin) / denominator).max(0.0),
+        ((center + margin) / denominator).min(1.0),
+    )
+}
+
+/// The results of the games between each pair of a set of contestants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WinMatrix {
+    /// The score of each row against each column.
+    scores: Vec<Vec<f64>>,
+    played: Vec<Vec<u32>>,
+}
+
+impl WinMatrix {
+    pub fn new(contestants: usize) -> Self {
+        WinMatrix {
+            scores: vec![vec![0.0; contestants]; contestants],
+            played: vec![vec![0; contestants]; contestants],
+        }
+    }
+
+    /// Record a game between two contestants, given the first one's score.
+    pub fn record(&mut self, first: usize, second: usize, score: f64) {
+        self.scores[first][second] += score;
+        self.scores[second][first] += 1.0 - score;
+        self.played[first][second] += 1;
+        self.played[second][first] += 1;
+    }
+
+    /// How often a contestant won against another, counting draws as half
+    /// a win, with a 95% confidence interval. None if they haven't played.
+    pub fn win_rate(&self, contestant: usize, opponent: usize) -> Option<(f64, (f64, f64))> {
+        let games = self.played[contestant][opponent];
+        if games == 0 {
+            return None;
+        }
+        let score = self.scores[contestant][opponent];
+        Some((score / f64::from(games), wilson_interval(score, games)))
+    }
+}
+
+#[cfg(test)]
+mod arena_tests {
+    use super::*;
+    use crate::player::{HeuristicAI, RandomAI};
+
+    #[test]
+    fn games_repeat_from_their_seeds() {
+        let play = |seed| {
+            let mut p1 = RandomAI::with_seed(1);
+            let mut p2 = RandomAI::with_seed(2);
+            play_game(&mut *p1, &mut *p2, seed).unwrap()
+        };
+        let result = play(0);
+        assert_eq!(result.ending, Ending::Victory);
+        let winner = match result.game {
+            AnyGame::Victory(game) => game.player(),
+            _ => panic!("Not over!"),
+        };
+        let expected = match winner {
+            santorini::Player::PlayerOne => 1.0,
+            _ => 0.0,
+        };
+        assert_eq!(result.score, expected);
+        assert!(result.turns > 0);
+
+        let again = play(0);
+        assert_eq!((again.game, again.turns), (result.game, result.turns));
+        assert_ne!(random_start(0), random_start(1));
+    }
+
+    #[test]
+    fn turn_limit() {
+        let mut p1 = RandomAI::with_seed(1);
+        let mut p2 = RandomAI::with_seed(2);
+        let rules = Rules {
+            max_turns: 3,
+            ..Rules::default()
+        };
+        let result = play_from(&mut *p1, &mut *p2, random_start(0).into(), &rules).unwrap();
+        assert_eq!(
+            (result.score, result.ending, result.turns),
+            (0.5, Ending::TurnLimit, 3)
+        );
+        assert!(matches!(result.game, AnyGame::Move(_)));
+    }
+
+    #[test]
+    fn adjudication() {
+        let mut adjudicator = Adjudicator::new(Adjudication::default());
+        for _ in 0..2 {
+            adjudicator.record(santorini::Player::PlayerOne, Some(0.95));
+            adjudicator.record(santorini::Player::PlayerTwo, Some(-0.95));
+        }
+        assert_eq!(adjudicator.verdict(), None);
+        adjudicator.record(santorini::Player::PlayerOne, Some(0.95));
+        assert_eq!(adjudicator.verdict(), Some(1.0));
+
+        // Draws are only called late in the game
+        let mut adjudicator = Adjudicator::new(Adjudication::default());
+        for _ in 0..40 {
+            adjudicator.record(santorini::Player::PlayerOne, Some(0.0));
+            assert_eq!(adjudicator.verdict(), None);
+            adjudicator.record(santorini::Player::PlayerTwo, None);
+        }
+        for _ in 0..4 {
+            adjudicator.record(santorini::Player::PlayerTwo, Some(0.01));
+        }
+        assert_eq!(adjudicator.verdict(), Some(0.5));
+
+        // The heuristic AI rates its turns, so its games can be cut short
+        let mut p1 = HeuristicAI::new().boxed();
+        let mut p2 = HeuristicAI::new().boxed();
+        let rules = Rules {
+            adjudication: Some(Adjudication {
+                draw_threshold: 1.0,
+                draw_after: 0,
+                consecutive: 1,
+                ..Adjudication::default()
+            }),
+            ..Rules::default()
+        };
+        let result = play_from(&mut *p1, &mut *p2, random_start(0).into(), &rules).unwrap();
+        assert_eq!(
+            (result.score, result.ending, result.turns),
+            (0.5, Ending::Adjudicated, 2)
+        );
+        assert!(result.time.iter().all(|time| *time > Duration::default()));
+    }
+
+    #[test]
+    fn win_rates() {
+        assert_eq!(wilson_interval(0.0, 0), (0.0, 1.0));
+        let (low, high) = wilson_interval(50.0, 100);
+        assert!((low - 0.404).abs() < 0.001, "{}", low);
+        assert!((high - 0.596).abs() < 0.001, "{}", high);
+        // Never outside of zero and one, even for a clean sweep
+        let (low, high) = wilson_interval(10.0, 10);
+        assert!(low > 0.6 && high == 1.0);
+
+        let mut matrix = WinMatrix::new(3);
+        matrix.record(0, 1, 1.0);
+        matrix.record(1, 0, 0.5);
+        matrix.record(0, 1, 1.0);
+        matrix.record(2, 0, 0.0);
+        let (rate, (low, high)) = matrix.win_rate(0, 1).unwrap();
+        assert_eq!(rate, 2.5 / 3.0);
+        assert!(low < rate && rate < high);
+        assert_eq!(matrix.win_rate(1, 0).unwrap().0, 0.5 / 3.0);
+        assert_eq!(matrix.win_rate(0, 2).unwrap().0, 1.0);
+        assert_eq!(matrix.win_rate(1, 2), None);
+        assert_eq!(matrix.win_rate(1, 1), None);
+    }
+}
@@ -0,0 +1,41 @@
+use santorini_ai::analysis::{self, metrics, AnalysisError, Analyzer};
+use std::env;
+use std::path::PathBuf;
+
+fn main() -> Result<(), AnalysisError> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let metrics_addr = match args.iter().position(|arg| arg == "--metrics") {
+        Some(idx) => {
+            if idx + 1 >= args.len() {
+                return Err(AnalysisError::InvalidRequest(
+                    "--metrics requires an address".to_string(),
+                ));
+            }
+            let addr = args.remove(idx + 1);
+            args.remove(idx);
+            Some(addr)
+        }
+        None => None,
+    };
+
+    let mut args = args.into_iter();
+    let path = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(analysis::default_socket_path);
+    let budget = match args.next() {
+        Some(budget) => budget
+            .parse()
+            .map_err(|_| AnalysisError::InvalidRequest(format!("invalid budget: {}", budget)))?,
+        None => 2000,
+    };
+
+    let analyzer = Analyzer::new(budget);
+    if let Some(addr) = metrics_addr {
+        metrics::serve_http(&addr, analyzer.metrics())?;
+        println!("Serving metrics on http://{}/metrics", addr);
+    }
+
+    println!("Serving analysis on {}", path.display());
+    analysis::serve(path, analyzer)
+}
@@ -0,0 +1,162 @@
+use santorini_ai::analysis::{self, positions, AnalysisError, Analyzer, Client};
+use santorini_ai::mcts::{Mcts, TreeFormat};
+use santorini_ai::player::{heuristic_ai, MctsSantoriniParams};
+use santorini_ai::santorini::{Game, Move, Player};
+use std::env;
+use std::iter;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: analyze <position> [budget]
+       analyze <name> [budget]
+       analyze --tree <depth> <dot|json> <position|name> [budget]
+       analyze --list
+       analyze --stats
+       analyze --shutdown
+
+Positions are written as `<heights> <p1 workers> <p2 workers> <player>`, e.g.
+    00000/01000/00200/00000/00004 b2,c3 d4,e1 1
+or given by the name of a built in position (see --list).
+
+Requests are sent to the analysis daemon (see analysisd) if one is running on
+the socket named by SANTORINI_ANALYSIS_SOCKET or the default path. Otherwise the
+position is analyzed locally from a cold start.
+
+--tree searches locally and prints the search tree down to <depth> plies, as
+Graphviz DOT or JSON, with each node's visits, value and prior.";
+
+fn main() -> Result<(), AnalysisError> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let path = env::var_os("SANTORINI_ANALYSIS_SOCKET")
+        .map(Into::into)
+        .unwrap_or_else(analysis::default_socket_path);
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["--stats"] => {
+            let stats = Client::connect(path)?.stats()?;
+            println!("Cache hits: {}", stats.hits);
+            println!("Cache misses: {}", stats.misses);
+            println!("Reused trees: {}", stats.reused_trees);
+        }
+        ["--shutdown"] => Client::connect(path)?.shutdown()?,
+        ["--list"] => {
+            for position in positions::POSITIONS.iter() {
+                println!("{:<16}{}", position.name, position.description);
+                println!("{:<16}{}", "", position.position);
+            }
+        }
+        ["--tree", depth, format, ref rest @ ..] => {
+            let (depth, format) = match (depth.parse(), format.parse()) {
+                (Ok(depth), Ok(format)) => (depth, format),
+                _ => usage(),
+            };
+            let (game, budget) = position(rest)?;
+            print_tree(game, parse_budget(budget)?, depth, format);
+        }
+        ref rest => {
+            let (game, budget) = position(rest)?;
+            analyze(&path, game, parse_budget(budget)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+/// The position given by the arguments, written out or by name, and the
+/// budget after it.
+fn position<'a>(args: &[&'a str]) -> Result<(Game<Move>, Option<&'a str>), AnalysisError> {
+    match args {
+        [heights, p1, p2, player] | [heights, p1, p2, player, _] => {
+            let game = analysis::parse_position(&[*heights, *p1, *p2, *player].join(" "))?;
+            Ok((game, args.get(4).copied()))
+        }
+        [name] | [name, _] if !name.starts_with('-') => {
+            let game = positions::find(name)
+                .ok_or_else(|| {
+                    AnalysisError::InvalidPosition(format!("no position named {}", name))
+                })?
+                .game();
+            Ok((game, args.get(1).copied()))
+        }
+        _ => usage(),
+    }
+}
+
+fn parse_budget(budget: Option<&str>) -> Result<Option<u32>, AnalysisError> {
+    match budget {
+        Some(budget) => budget
+            .parse()
+            .map(Some)
+            .map_err(|_| AnalysisError::InvalidRequest(format!("invalid budget: {}", budget))),
+        None => Ok(None),
+    }
+}
+
+fn print_tree(game: Game<Move>, budget: Option<u32>, depth: usize, format: TreeFormat) {
+    let params = MctsSantoriniParams::default().budget(budget.unwrap_or(2000));
+    let mut tree = Mcts::new(params, game.into());
+    tree.search();
+    print!("{}", tree.export_tree(depth, format));
+}
+
+fn analyze(path: &Path, game: Game<Move>, budget: Option<u32>) -> Result<(), AnalysisError> {
+    let result = match Client::connect(path) {
+        Ok(mut client) => client.analyze(&game, budget)?,
+        Err(_) => Analyzer::new(2000).analyze(game, budget)?,
+    };
+    let from = analysis::encode_point(result.from);
+    let to = analysis::encode_point(result.to);
+    match result.build {
+        Some(build) => println!(
+            "Best turn: {} -> {}, build {}",
+            from,
+            to,
+            analysis::encode_point(build)
+        ),
+        None => println!("Best turn: {} -> {} (wins)", from, to),
+    }
+    println!("Score: {:+.3}", result.score);
+    println!("Iterations: {}", result.iterations);
+
+    print_evaluation(&game);
+
+    Ok(())
+}
+
+fn player_name(player: Player) -> &'static str {
+    match player {
+        Player::PlayerOne => "P1",
+        Player::PlayerTwo => "P2",
+        Player::PlayerThree => "P3",
+    }
+}
+
+fn print_evaluation(game: &Game<Move>) {
+    let eval = heuristic_ai::evaluate(game, game.player());
+    println!();
+    println!(
+        "Static evaluation for {}: {:+.3}",
+        player_name(eval.player),
+        eval.total()
+    );
+    println!("  distance          {:+.3}", 0.3 * eval.distance);
+    let opponents = game.players().filter(|player| *player != eval.player);
+    for player in iter::once(eval.player).chain(opponents) {
+        // Opposing pawns count against the total
+        let sign = if player == eval.player { 0.7 } else { -0.7 };
+        for pawn in eval.pawns.iter().filter(|pawn| pawn.player == player) {
+            println!(
+                "  {} {}  height   {:+.3}  mobility {:+.3}",
+                player_name(pawn.player),
+                analysis::encode_point(pawn.pos),
+                sign * pawn.height,
+                sign * pawn.mobility
+            );
+        }
+    }
+}
@@ -1,15 +1,30 @@
 use chrono::Local;
-use santorini_ai::mcts::santorini::ExtendedSantoriniSimulation;
+use santorini_ai::arena::{self, Adjudication, Rules, WinMatrix};
+use santorini_ai::mcts::santorini::{
+    EpsilonGreedyPolicy, ExtendedSantoriniSimulation, MastPolicy, RandomPolicy, RolloutSimulation,
+};
 use santorini_ai::mcts::tree_policy::PUCT;
-use santorini_ai::player::{FullPlayer, HeuristicAI, MctsSantoriniParams, RandomAI, StepResult};
-use santorini_ai::santorini;
+use santorini_ai::player::{
+    ExternalPlayer, FirstPlayer, FullPlayer, HeuristicAI, MctsAI, MctsSantoriniParams, OpeningBook,
+    RandomAI,
+};
+use santorini_ai::santorini::{self, God};
 use santorini_ai::ui::UpdateError;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 
 struct Contestant<'a> {
     name: &'a str,
+    /// The god the contestant plays with.
+    god: God,
     score: f64,
     diff: f64,
+    games: u32,
     instantiation: Box<dyn Fn() -> Box<dyn FullPlayer>>,
 }
 
@@ -17,126 +32,509 @@ impl<'a> Contestant<'a> {
     fn new(name: &'a str, instantiation: Box<dyn Fn() -> Box<dyn FullPlayer>>) -> Self {
         Contestant {
             name,
+            god: God::Mortal,
             score: 1500.0,
             diff: 0.0,
+            games: 0,
             instantiation,
         }
     }
+
+    /// The same AI playing with each god, named after the god.
+    fn gods(instantiation: fn() -> Box<dyn FullPlayer>) -> Vec<Contestant<'static>> {
+        God::all()
+            .map(|god| Contestant {
+                god,
+                ..Contestant::new(god.name(), Box::new(instantiation))
+            })
+            .collect()
+    }
 }
 
-macro_rules! action {
-    ($name:ident, $mode:ty) => {
-        fn $name<'a>(
-            mut p1: &'a mut Box<dyn FullPlayer>,
-            mut p2: &'a mut Box<dyn FullPlayer>,
-            game: santorini::Game<$mode>,
-        ) -> Result<f64, UpdateError> {
-            let p = match game.player() {
-                santorini::Player::PlayerOne => &mut p1,
-                santorini::Player::PlayerTwo => &mut p2,
-            };
+fn play(c1: &Contestant, c2: &Contestant, rules: Rules) -> JoinHandle<Result<f64, UpdateError>> {
+    let mut p1 = (*c1.instantiation)();
+    let mut p2 = (*c2.instantiation)();
+    let gods = [c1.god, c2.god];
 
-            p.prepare(&game);
-
-            loop {
-                match p.step(&game)? {
-                    StepResult::NoMove => (),
-                    StepResult::PlaceTwo(game) => return place_two(p1, p2, game),
-                    StepResult::Move(game) => return mv(p1, p2, game),
-                    StepResult::Build(game) => return build(p1, p2, game),
-                    StepResult::Victory(game) => {
-                        return match game.player() {
-                            santorini::Player::PlayerOne => Ok(1.0),
-                            santorini::Player::PlayerTwo => Ok(0.0),
-                        }
-                    }
-                }
+    thread::spawn(move || {
+        let start = santorini::new_game_with_gods(gods).into();
+        let result = arena::play_from(&mut *p1, &mut *p2, start, &rules)?;
+        Ok(result.score)
+    })
+}
+
+/// The outcome of one game. `p1` placed first.
+struct GameRecord {
+    p1: usize,
+    p2: usize,
+    result: f64,
+}
+
+/// Play every pairing `repeats` times and apply the rating changes with the
+/// given K factor. `games` is the number of times each pairing has already
+/// played this session, which decides the seating.
+fn play_round(
+    players: &mut [Contestant],
+    repeats: usize,
+    games: usize,
+    k: f64,
+    rules: Rules,
+    first: FirstPlayer,
+) -> Result<Vec<GameRecord>, UpdateError> {
+    let mut threads = Vec::new();
+    for repeat in 0..repeats {
+        for i1 in 0..players.len() {
+            for i2 in i1 + 1..players.len() {
+                let (i1, i2) = if first.swap(games + repeat) {
+                    (i2, i1)
+                } else {
+                    (i1, i2)
+                };
+                let p1 = &players[i1];
+                let p2 = &players[i2];
+                threads.push((i1, i2, play(p1, p2, rules)));
             }
         }
-    };
+    }
+
+    let mut records = Vec::new();
+    for (i1, i2, thread) in threads {
+        let p1 = &players[i1];
+        let p2 = &players[i2];
+
+        let ea = (p2.score - p1.score) / 400.0;
+        let ea = 1.0 / (1.0 + 10.0f64.powf(ea));
+
+        let result = thread.join().expect("Game thread panicked!")?;
+
+        let diff = k * (result - ea);
+        players[i1].diff += diff;
+        players[i2].diff -= diff;
+        players[i1].games += 1;
+        players[i2].games += 1;
+        records.push(GameRecord {
+            p1: i1,
+            p2: i2,
+            result,
+        });
+    }
+
+    for player in players.iter_mut() {
+        player.score += player.diff;
+        player.diff = 0.0;
+    }
+
+    Ok(records)
 }
 
-action!(place_one, santorini::PlaceOne);
-action!(place_two, santorini::PlaceTwo);
-action!(mv, santorini::Move);
-action!(build, santorini::Build);
+fn print_scores(players: &[Contestant]) {
+    println!();
+    println!("{}", Local::now());
+    println!("  Scores:");
+    for p in players.iter() {
+        println!("    {}: {}", p.name, p.score);
+    }
+}
 
-fn play(c1: &Contestant, c2: &Contestant) -> JoinHandle<Result<f64, UpdateError>> {
-    let mut p1 = (*c1.instantiation)();
-    let mut p2 = (*c2.instantiation)();
+/// Play `games` games between every pair of contestants and print each
+/// contestant's win rate against each opponent. Adjudicated draws count as
+/// half a win for both sides.
+fn print_matrix(
+    players: &[Contestant],
+    games: usize,
+    rules: Rules,
+    first_player: FirstPlayer,
+) -> Result<(), UpdateError> {
+    let mut threads = Vec::new();
+    for i1 in 0..players.len() {
+        for i2 in i1 + 1..players.len() {
+            for game in 0..games {
+                let (first, second) = if first_player.swap(game) {
+                    (i2, i1)
+                } else {
+                    (i1, i2)
+                };
+                threads.push((
+                    first,
+                    second,
+                    play(&players[first], &players[second], rules),
+                ));
+            }
+        }
+    }
+
+    let mut matrix = WinMatrix::new(players.len());
+    for (first, second, thread) in threads {
+        let result = thread.join().expect("Game thread panicked!")?;
+        matrix.record(first, second, result);
+    }
+
+    println!();
+    println!("Win rates of each row against each column (95% confidence):");
+    for (idx, p) in players.iter().enumerate() {
+        println!("  [{}] {}", idx, p.name);
+    }
+    println!();
+
+    let header: Vec<String> = (0..players.len())
+        .map(|idx| format!("{:^19}", format!("[{}]", idx)))
+        .collect();
+    println!("     {}", header.join(" "));
+    for i1 in 0..players.len() {
+        let cells: Vec<String> = (0..players.len())
+            .map(|i2| match matrix.win_rate(i1, i2) {
+                Some((rate, (low, high))) => format!(
+                    "{:5.1}% ({:4.1}-{:5.1})",
+                    100.0 * rate,
+                    100.0 * low,
+                    100.0 * high
+                ),
+                None => format!("{:^19}", "-"),
+            })
+            .collect();
+        println!("{:>4} {}", format!("[{}]", i1), cells.join(" "));
+    }
 
-    thread::spawn(move || place_one(&mut p1, &mut p2, santorini::new_game()))
+    Ok(())
+}
+
+/// Settings for the continuous arena mode.
+struct Arena {
+    dir: PathBuf,
+    games_per_log: usize,
+    logs_kept: usize,
+    k: f64,
 }
 
+impl Arena {
+    fn ratings_path(&self) -> PathBuf {
+        self.dir.join("ratings.tsv")
+    }
+
+    fn log_dir(&self) -> PathBuf {
+        self.dir.join("logs")
+    }
+
+    /// Restore the ratings saved by a previous run, if any.
+    fn load_ratings(&self, players: &mut [Contestant]) -> io::Result<()> {
+        let contents = match fs::read_to_string(self.ratings_path()) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if let [name, score, games] = fields[..] {
+                if let Some(player) = players.iter_mut().find(|p| p.name == name) {
+                    player.score = score.parse().unwrap_or(player.score);
+                    player.games = games.parse().unwrap_or(player.games);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn save_ratings(&self, players: &[Contestant]) -> io::Result<()> {
+        let mut contents = String::new();
+        for p in players.iter() {
+            contents.push_str(&format!("{}\t{}\t{}\n", p.name, p.score, p.games));
+        }
+
+        // Write then rename so the file is never observed half written
+        let tmp = self.dir.join("ratings.tsv.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(tmp, self.ratings_path())
+    }
+
+    fn new_log(&self, sequence: usize) -> io::Result<File> {
+        let log_dir = self.log_dir();
+        fs::create_dir_all(&log_dir)?;
+
+        let mut logs: Vec<PathBuf> = fs::read_dir(&log_dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+            .collect();
+        logs.sort();
+        while logs.len() >= self.logs_kept {
+            fs::remove_file(logs.remove(0))?;
+        }
+
+        let name = format!(
+            "games-{}-{:04}.log",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            sequence
+        );
+        File::create(log_dir.join(name))
+    }
+
+    fn run(
+        &self,
+        players: &mut [Contestant],
+        rules: Rules,
+        first: FirstPlayer,
+    ) -> Result<(), UpdateError> {
+        fs::create_dir_all(&self.dir)?;
+        self.load_ratings(players)?;
+
+        let mut sequence = 0;
+        let mut log = self.new_log(sequence)?;
+        let mut logged = 0;
+        for round in 0.. {
+            print_scores(players);
+
+            for record in play_round(players, 1, round, self.k, rules, first)? {
+                if logged >= self.games_per_log {
+                    sequence += 1;
+                    log = self.new_log(sequence)?;
+                    logged = 0;
+                }
+                writeln!(
+                    log,
+                    "{}\t{}\t{}\t{}",
+                    Local::now().to_rfc3339(),
+                    players[record.p1].name,
+                    players[record.p2].name,
+                    record.result
+                )?;
+                logged += 1;
+            }
+            log.flush()?;
+
+            self.save_ratings(players)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The MCTS PUCT contestant, which also plays each god in gods mode.
+fn mcts_puct() -> Box<dyn FullPlayer> {
+    MctsSantoriniParams::default()
+        .tree_policy(PUCT {
+            parameter: 0.5,
+            fpu: None,
+        })
+        .budget(400)
+        .boxed()
+}
+
+enum Mode {
+    Ratings,
+    Arena(Arena),
+    Matrix(usize),
+    /// A matrix between the gods rather than the AIs.
+    Gods(usize),
+}
+
+const USAGE: &str = "usage: elo [--first <fixed|alternate|random>] [--engine <name>=<command>]...
+       elo [--first <...>] [--engine <...>]... --arena <dir> [games per log] [logs kept]
+       elo [--first <...>] [--engine <...>]... --matrix [games per pairing]
+       elo [--first <...>] --gods [games per pairing]
+
+By default, plays rounds of games with a decreasing K factor until the ratings
+settle. In arena mode, plays games indefinitely with a fixed K factor, keeping
+the current ratings in <dir>/ratings.tsv and game results in rotating log files
+under <dir>/logs, with the contestant who placed first listed first. In matrix
+mode, plays a fixed number of games between every
+pair of contestants and prints a matrix of win rates with confidence
+intervals. In gods mode, the contestants are instead the MCTS PUCT AI playing
+with each god in turn, Mortal included, 20 games per pairing by default.
+
+Games are adjudicated once both contestants' evaluations agree the outcome is
+clear, and drawn if still going after 200 turns.
+
+--first decides which contestant of each pairing places first: always the one
+listed first, alternating between games (the default), or at random.
+
+--engine adds an external engine to the contestants under <name>, started
+from <command> for every game and spoken to as santorini_ai::player::external
+describes.";
+
 fn main() -> Result<(), UpdateError> {
-    println!("Calculating ELO scores...");
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let first = match args.iter().position(|arg| arg == "--first") {
+        Some(idx) => match args.get(idx + 1).map(|arg| arg.parse()) {
+            Some(Ok(first)) => {
+                args.drain(idx..idx + 2);
+                first
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        },
+        None => FirstPlayer::Alternate,
+    };
+    let mut engines = vec![];
+    while let Some(idx) = args.iter().position(|arg| arg == "--engine") {
+        match args.get(idx + 1).and_then(|arg| arg.split_once('=')) {
+            Some((name, command)) if !name.is_empty() && !command.trim().is_empty() => {
+                engines.push((name.to_string(), command.to_string()));
+                args.drain(idx..idx + 2);
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+    }
+    let mode = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => Mode::Ratings,
+        ["--matrix", ref rest @ ..] if rest.len() <= 1 => {
+            match rest.first().map_or(Some(100), |games| games.parse().ok()) {
+                Some(games) if games > 0 => Mode::Matrix(games),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    process::exit(1);
+                }
+            }
+        }
+        ["--gods", ref rest @ ..] if rest.len() <= 1 => {
+            match rest.first().map_or(Some(20), |games| games.parse().ok()) {
+                Some(games) if games > 0 => Mode::Gods(games),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    process::exit(1);
+                }
+            }
+        }
+        ["--arena", dir, ref rest @ ..] if rest.len() <= 2 => {
+            let parse = |idx: usize, default: usize| match rest.get(idx) {
+                Some(arg) => arg.parse().ok(),
+                None => Some(default),
+            };
+            match (parse(0, 1000), parse(1, 10)) {
+                (Some(games_per_log), Some(logs_kept)) if games_per_log > 0 && logs_kept > 0 => {
+                    Mode::Arena(Arena {
+                        dir: dir.into(),
+                        games_per_log,
+                        logs_kept,
+                        k: 16.0,
+                    })
+                }
+                _ => {
+                    eprintln!("{}", USAGE);
+                    process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
 
-    let mut players = [
+    let mut players = vec![
         Contestant::new("Random", Box::new(|| RandomAI::new())),
-        Contestant::new("Heuristic", Box::new(|| HeuristicAI::new())),
+        Contestant::new("Heuristic", Box::new(|| HeuristicAI::new().boxed())),
+        Contestant::new(
+            "Heuristic Opening Book",
+            Box::new(|| HeuristicAI::with_book(Arc::new(OpeningBook::builtin())).boxed()),
+        ),
         //Contestant::new(
         //    "MCTS UCT",
         //    Box::new(|| MctsSantoriniParams::default().boxed()),
         //),
+        Contestant::new("MCTS PUCT", Box::new(mcts_puct)),
         Contestant::new(
-            "MCTS PUCT",
+            "MCTS PUCT Guarded",
+            Box::new(|| {
+                let params = MctsSantoriniParams::default()
+                    .tree_policy(PUCT {
+                        parameter: 0.5,
+                        fpu: None,
+                    })
+                    .budget(400);
+                MctsAI::from(params).guarded().boxed()
+            }),
+        ),
+        Contestant::new(
+            "MCTS PUCT Extended Simulation",
             Box::new(|| {
                 MctsSantoriniParams::default()
-                    .tree_policy(PUCT { parameter: 0.5 })
-                    .budget(400)
+                    .simulation(ExtendedSantoriniSimulation {
+                        policy: RandomPolicy {},
+                    })
+                    .tree_policy(PUCT {
+                        parameter: 0.5,
+                        fpu: None,
+                    })
+                    .budget(200)
                     .boxed()
             }),
         ),
         Contestant::new(
-            "MCTS PUCT Extended Simulation",
+            "MCTS PUCT Epsilon Greedy Simulation",
+            Box::new(|| {
+                MctsSantoriniParams::default()
+                    .simulation(RolloutSimulation {
+                        policy: EpsilonGreedyPolicy { epsilon: 0.2 },
+                        plies: None,
+                    })
+                    .tree_policy(PUCT {
+                        parameter: 0.5,
+                        fpu: None,
+                    })
+                    .budget(200)
+                    .boxed()
+            }),
+        ),
+        Contestant::new(
+            "MCTS PUCT MAST Simulation",
             Box::new(|| {
                 MctsSantoriniParams::default()
-                    .simulation(ExtendedSantoriniSimulation {})
-                    .tree_policy(PUCT { parameter: 0.5 })
+                    .simulation(RolloutSimulation {
+                        policy: MastPolicy { temperature: 0.5 },
+                        plies: None,
+                    })
+                    .tree_policy(PUCT {
+                        parameter: 0.5,
+                        fpu: None,
+                    })
                     .budget(200)
                     .boxed()
             }),
         ),
     ];
 
-    let mut k = 100.0;
-    loop {
-        println!("");
-        println!("{}", Local::now().to_string());
-        println!("  Scores:");
-        for p in players.iter() {
-            println!("    {}: {}", p.name, p.score);
-        }
+    for (name, command) in engines.iter() {
+        let command = command.clone();
+        players.push(Contestant::new(
+            name,
+            Box::new(move || match ExternalPlayer::from_command_line(&command) {
+                Ok(engine) => engine.boxed(),
+                Err(err) => panic!("Couldn't start {}: {}", command, err),
+            }),
+        ));
+    }
 
-        let mut threads = Vec::new();
-        for _ in 0..5 {
-            for i1 in 0..players.len() {
-                for i2 in i1 + 1..players.len() {
-                    let p1 = &players[i1];
-                    let p2 = &players[i2];
-                    threads.push((i1, i2, play(p1, p2)));
-                }
-            }
+    let rules = Rules {
+        adjudication: Some(Adjudication::default()),
+        ..Rules::default()
+    };
+    match mode {
+        Mode::Ratings => (),
+        Mode::Arena(arena) => {
+            println!("Running arena in {}...", arena.dir.display());
+            return arena.run(&mut players, rules, first);
         }
-
-        for (i1, i2, thread) in threads {
-            let p1 = &players[i1];
-            let p2 = &players[i2];
-
-            let ea = (p2.score - p1.score) / 400.0;
-            let ea = 1.0 / (1.0 + 10.0f64.powf(ea));
-
-            let result = thread.join().expect("Game thread panicked!")?;
-
-            let diff = k * (result - ea);
-            players[i1].diff += diff;
-            players[i2].diff -= diff;
+        Mode::Matrix(games) => {
+            println!("Playing {} games per pairing...", games);
+            return print_matrix(&players, games, rules, first);
         }
-
-        for player in players.iter_mut() {
-            player.score += player.diff;
-            player.diff = 0.0;
+        Mode::Gods(games) => {
+            println!("Playing {} games per pairing of gods...", games);
+            return print_matrix(&Contestant::gods(mcts_puct), games, rules, first);
         }
+    }
+
+    println!("Calculating ELO scores...");
+    let mut k = 100.0;
+    for round in 0.. {
+        print_scores(&players);
+        play_round(&mut players, 5, 5 * round, k, rules, first)?;
 
         k *= 0.75;
         if k < 10.0 {
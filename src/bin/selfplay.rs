@@ -0,0 +1,286 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use santorini_ai::dataset::export::{Example, ExampleWriter};
+use santorini_ai::dataset::{Dataset, Split};
+use santorini_ai::mcts::nn::turn_index;
+use santorini_ai::mcts::santorini::{NodeState, SantoriniExpansion, SantoriniSimulation};
+use santorini_ai::mcts::tree_policy::PUCT;
+use santorini_ai::mcts::{Mcts, MctsParams, Node, SelectionCriterion};
+use santorini_ai::santorini::{self, Game, Move, Player};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const GAMES_PER_SHARD: usize = 100;
+
+/// Turns are sampled in proportion to their visits for this many turns, which
+/// keeps the openings varied, and the most visited turn is taken after.
+const SAMPLED_TURNS: u16 = 10;
+
+/// Settings for a self-play run.
+struct SelfPlay {
+    dir: PathBuf,
+    shards: usize,
+    workers: usize,
+    budget: u32,
+    seed: u64,
+    /// The share of positions kept for validation.
+    validation: f64,
+}
+
+/// A position the search was made from, and the visits of each turn the
+/// search tried by its index in the network's policy.
+struct Sample {
+    game: Game<Move>,
+    visits: BTreeMap<usize, u32>,
+}
+
+impl SelfPlay {
+    fn shard_path(&self, shard: usize, split: Split) -> PathBuf {
+        let set = match split {
+            Split::Train => "train",
+            Split::Validation => "validation",
+        };
+        self.dir.join(set).join(format!("shard-{:06}.bin", shard))
+    }
+
+    /// Every game is played from its own seed, so a run can be picked up
+    /// where it left off as long as the base seed is the same.
+    fn game_seed(&self, game: usize) -> u64 {
+        self.seed ^ (game as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    /// Record the base seed on the first run, and refuse to resume with
+    /// another one, which would mix up games from different runs.
+    fn check_seed(&self) -> io::Result<()> {
+        let path = self.dir.join("seed");
+        match fs::read_to_string(&path) {
+            Ok(seed) if seed.trim() == self.seed.to_string() => Ok(()),
+            Ok(seed) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} was started with seed {}",
+                    self.dir.display(),
+                    seed.trim()
+                ),
+            )),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                fs::write(path, format!("{}\n", self.seed))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn run(self) -> io::Result<()> {
+        fs::create_dir_all(self.dir.join("train"))?;
+        fs::create_dir_all(self.dir.join("validation"))?;
+        self.check_seed()?;
+
+        let dataset = Arc::new(Mutex::new(Dataset::new(self.validation)));
+        let settings = Arc::new(self);
+        let next = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..settings.workers)
+            .map(|_| {
+                let settings = Arc::clone(&settings);
+                let dataset = Arc::clone(&dataset);
+                let next = Arc::clone(&next);
+                thread::spawn(move || -> io::Result<()> {
+                    loop {
+                        let shard = next.fetch_add(1, Ordering::SeqCst);
+                        if shard >= settings.shards {
+                            return Ok(());
+                        }
+                        // Shards are only written once finished, the training
+                        // half last
+                        let path = settings.shard_path(shard, Split::Train);
+                        if !path.exists() {
+                            settings.write_shard(shard, &dataset)?;
+                            println!("Wrote {}", path.display());
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().expect("Self-play thread panicked!")?;
+        }
+        let summary = dataset
+            .lock()
+            .expect("Self-play thread panicked!")
+            .summary();
+        println!("{}", summary);
+        Ok(())
+    }
+
+    /// Play a shard's games, writing each position not seen before in this
+    /// run to the training or validation half of the shard.
+    fn write_shard(&self, shard: usize, dataset: &Mutex<Dataset>) -> io::Result<()> {
+        let train = self.shard_path(shard, Split::Train);
+        let validation = self.shard_path(shard, Split::Validation);
+        // Write then rename so the shard is never observed half written
+        let create = |path: &Path| -> io::Result<_> {
+            let tmp = path.with_extension("bin.tmp");
+            ExampleWriter::new(BufWriter::new(File::create(tmp)?))
+        };
+        let mut writers = [create(&train)?, create(&validation)?];
+
+        for game in shard * GAMES_PER_SHARD..(shard + 1) * GAMES_PER_SHARD {
+            let (samples, winner) = self.play(game);
+            let positions: Vec<Game<Move>> = samples.iter().map(|sample| sample.game).collect();
+            let kept = dataset
+                .lock()
+                .expect("Self-play thread panicked!")
+                .add_game(&positions, Some(winner));
+            for (sample, kept) in samples.into_iter().zip(kept) {
+                let kept = match kept {
+                    Some(kept) => kept,
+                    None => continue,
+                };
+                let visits: Vec<(usize, u32)> = sample.visits.into_iter().collect();
+                let writer = match kept.split {
+                    Split::Train => &mut writers[0],
+                    Split::Validation => &mut writers[1],
+                };
+                writer.write(&Example::new(&sample.game, &visits, kept.value as f32))?;
+            }
+        }
+
+        let [train_writer, validation_writer] = writers;
+        train_writer.finish()?;
+        validation_writer.finish()?;
+        fs::rename(validation.with_extension("bin.tmp"), validation)?;
+        fs::rename(train.with_extension("bin.tmp"), train)
+    }
+
+    /// Play a game against itself, returning the positions searched along
+    /// the way and the winner.
+    fn play(&self, game: usize) -> (Vec<Sample>, Player) {
+        let rng = SmallRng::seed_from_u64(self.game_seed(game));
+        let params = MctsParams::new(SantoriniSimulation {}, SantoriniExpansion {}, rng)
+            .tree_policy(PUCT {
+                parameter: 0.5,
+                fpu: None,
+            })
+            .budget(self.budget);
+        let mut tree = Mcts::new(params, santorini::new_game().into());
+
+        let mut samples = vec![];
+        let mut turns = 0;
+        loop {
+            tree.search();
+            let children = tree
+                .root_node
+                .children
+                .as_ref()
+                .expect("Root node missing children");
+            if let NodeState::Move(game) = tree.root_node.state.game {
+                let mut visits = BTreeMap::new();
+                for child in children {
+                    if let Some(idx) = turn_index(&game, &child.state) {
+                        *visits.entry(idx).or_insert(0) += child.iterations;
+                    }
+                }
+                samples.push(Sample { game, visits });
+                turns += 1;
+            }
+
+            let idx = if turns <= SAMPLED_TURNS {
+                sample_visits(children, &mut tree.params.rng)
+            } else {
+                SelectionCriterion::MostVisits.select(children)
+            };
+            tree.descend(idx);
+            if let NodeState::Victory(winner) = tree.root_node.state.game {
+                return (samples, winner);
+            }
+        }
+    }
+}
+
+fn sample_visits<T, R: Rng>(children: &[Node<T>], rng: &mut R) -> usize {
+    let total: u32 = children.iter().map(|child| child.iterations).sum();
+    let mut pick = rng.gen_range(0, total);
+    for (idx, child) in children.iter().enumerate() {
+        if pick < child.iterations {
+            return idx;
+        }
+        pick -= child.iterations;
+    }
+    unreachable!("Picked past the last child!")
+}
+
+const USAGE: &str =
+    "usage: selfplay [--seed <n>] [--validation <fraction>] <dir> [shards] [workers] [budget]
+
+Plays MCTS against itself and writes an example for every position it searched
+to shard files of 100 games each under <dir>, in the training data format of
+santorini_ai::dataset::export. Each example holds the position, the share of
+the search's visits each turn got and the result for the player to act.
+
+Positions already written in the run, or symmetric to one that was, are
+dropped. The rest are split between <dir>/train and <dir>/validation by their
+hash, with the fraction given (0.1 by default) kept for validation, and a
+summary of the games and positions is printed at the end.
+
+Defaults to 10 shards, 4 workers and a budget of 800 iterations per turn.
+Every game is seeded from its number and the base seed (0 by default), so
+running again with the same seed resumes, only playing the missing shards.
+Positions are only checked against those written by the same run.";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+/// Take `<flag> <value>` out of the arguments, if it's there.
+fn take_flag<T: FromStr>(args: &mut Vec<String>, flag: &str) -> Option<T> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+    match args.get(idx + 1).map(|arg| arg.parse()) {
+        Some(Ok(value)) => {
+            args.drain(idx..idx + 2);
+            Some(value)
+        }
+        _ => usage(),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let seed = take_flag(&mut args, "--seed").unwrap_or(0);
+    let validation = take_flag(&mut args, "--validation").unwrap_or(0.1);
+    if !(0.0..=1.0).contains(&validation) {
+        usage();
+    }
+    let settings = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [dir, ref rest @ ..] if rest.len() <= 3 && !dir.starts_with('-') => {
+            let parse = |idx: usize, default: usize| match rest.get(idx) {
+                Some(arg) => arg.parse().ok(),
+                None => Some(default),
+            };
+            match (parse(0, 10), parse(1, 4), parse(2, 800)) {
+                (Some(shards), Some(workers), Some(budget)) if workers > 0 && budget > 0 => {
+                    SelfPlay {
+                        dir: dir.into(),
+                        shards,
+                        workers,
+                        budget: budget as u32,
+                        seed,
+                        validation,
+                    }
+                }
+                _ => usage(),
+            }
+        }
+        _ => usage(),
+    };
+
+    settings.run()
+}
@@ -0,0 +1,454 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use santorini_ai::arena::{self, Rules};
+use santorini_ai::mcts::tree_policy::{PUCT, UCB1};
+use santorini_ai::mcts::Widening;
+use santorini_ai::player::{FullPlayer, HeuristicAI, HeuristicWeights, MctsSantoriniParams};
+use santorini_ai::santorini::AnyGame;
+use std::env;
+use std::f64::consts::SQRT_2;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+/// How many iterations pass between measuring the current weights.
+const EVALUATE_EVERY: u32 = 10;
+
+/// How many pairs of games a candidate constant plays between checks of
+/// whether it's told apart from the default yet.
+const PAIR_BATCH: usize = 8;
+
+/// The weights as a vector: the four levels, then height, mobility,
+/// distance and pawns.
+type Vector = [f64; 8];
+
+fn to_vector(weights: &HeuristicWeights) -> Vector {
+    let [ground, one, two, three] = weights.levels;
+    [
+        ground,
+        one,
+        two,
+        three,
+        weights.height,
+        weights.mobility,
+        weights.distance,
+        weights.pawns,
+    ]
+}
+
+fn to_weights(vector: &Vector) -> HeuristicWeights {
+    HeuristicWeights {
+        levels: [vector[0], vector[1], vector[2], vector[3]],
+        height: vector[4],
+        mobility: vector[5],
+        distance: vector[6],
+        pawns: vector[7],
+    }
+}
+
+/// Written one term to a line, as `height 0.7`.
+fn format_weights(weights: &HeuristicWeights) -> String {
+    let levels: Vec<String> = weights.levels.iter().map(f64::to_string).collect();
+    format!(
+        "levels {}\nheight {}\nmobility {}\ndistance {}\npawns {}\n",
+        levels.join(" "),
+        weights.height,
+        weights.mobility,
+        weights.distance,
+        weights.pawns
+    )
+}
+
+type Opponent = Box<dyn Fn() -> Box<dyn FullPlayer> + Send + Sync>;
+
+/// Settings for a tuning run.
+struct Tuner {
+    output: PathBuf,
+    iterations: u32,
+    /// How many starting positions each candidate plays from, once in each
+    /// seat against each opponent.
+    positions: usize,
+    seed: u64,
+    /// The players candidates are measured against.
+    opponents: Arc<Vec<Opponent>>,
+}
+
+/// Play a game out, returning Player One's result.
+fn play(mut players: [Box<dyn FullPlayer>; 2], start: AnyGame) -> f64 {
+    let [p1, p2] = &mut players;
+    arena::play_from(&mut **p1, &mut **p2, start, &Rules::default())
+        .expect("Step failed!")
+        .score
+}
+
+impl Tuner {
+    /// The share of its games a player with the weights scores against the
+    /// opponents.
+    fn score(&self, vector: &Vector) -> f64 {
+        let weights = to_weights(vector);
+        let mut games = vec![];
+        for position in 0..self.positions {
+            let start = AnyGame::from(arena::random_start(self.seed ^ position as u64));
+            for opponent in 0..self.opponents.len() {
+                for swap in [false, true].iter() {
+                    let opponents = Arc::clone(&self.opponents);
+                    let swap = *swap;
+                    games.push(thread::spawn(move || {
+                        let candidate = HeuristicAI::with_weights(weights).boxed();
+                        let opponent = (opponents[opponent])();
+                        if swap {
+                            1.0 - play([opponent, candidate], start)
+                        } else {
+                            play([candidate, opponent], start)
+                        }
+                    }));
+                }
+            }
+        }
+        let count = games.len();
+        let total: f64 = games
+            .into_iter()
+            .map(|game| game.join().expect("Game thread panicked!"))
+            .sum();
+        total / count as f64
+    }
+
+    fn save(&self, vector: &Vector) -> io::Result<()> {
+        // Write then rename so the file is never observed half written
+        let tmp = self.output.with_extension("tmp");
+        fs::write(&tmp, format_weights(&to_weights(vector)))?;
+        fs::rename(tmp, &self.output)
+    }
+
+    /// Simultaneous perturbation stochastic approximation: each iteration
+    /// nudges every weight at once in a random direction and back, and
+    /// steps along the difference the two candidates score. The gains
+    /// shrink as Spall recommends, so the steps settle down.
+    fn run(self) -> io::Result<()> {
+        let (a, c, stability) = (0.1, 0.1, f64::from(self.iterations) / 10.0);
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let mut theta = to_vector(&HeuristicWeights::default());
+        let mut best = (self.score(&theta), theta);
+        println!("Default weights score {:.3}", best.0);
+        self.save(&theta)?;
+
+        for k in 0..self.iterations {
+            let ak = a / (f64::from(k) + 1.0 + stability).powf(0.602);
+            let ck = c / (f64::from(k) + 1.0).powf(0.101);
+            let delta: Vector = [(); 8].map(|_| if rng.gen() { 1.0 } else { -1.0 });
+            let mut plus = theta;
+            let mut minus = theta;
+            for idx in 0..theta.len() {
+                plus[idx] += ck * delta[idx];
+                minus[idx] -= ck * delta[idx];
+            }
+
+            let (score_plus, score_minus) = (self.score(&plus), self.score(&minus));
+            for idx in 0..theta.len() {
+                theta[idx] += ak * (score_plus - score_minus) / (2.0 * ck * delta[idx]);
+            }
+            println!(
+                "Iteration {}: {:.3} against {:.3}",
+                k + 1,
+                score_plus,
+                score_minus
+            );
+
+            if (k + 1) % EVALUATE_EVERY == 0 || k + 1 == self.iterations {
+                let score = self.score(&theta);
+                println!("Current weights score {:.3}", score);
+                if score > best.0 {
+                    best = (score, theta);
+                    self.save(&theta)?;
+                }
+            }
+        }
+
+        println!(
+            "Best weights score {:.3}:\n{}",
+            best.0,
+            format_weights(&to_weights(&best.1))
+        );
+        Ok(())
+    }
+}
+
+/// Builds a player's search params from the seed for its search.
+type MakeParams = Box<dyn Fn(u64) -> MctsSantoriniParams + Send + Sync>;
+
+/// A value of the MCTS constant being tuned.
+struct Setting {
+    label: String,
+    params: MakeParams,
+}
+
+/// The values of an MCTS constant worth trying, the default first.
+fn settings(constant: &str, budget: u32) -> Option<Vec<Setting>> {
+    let ucb1 = |parameter: f64| Setting {
+        label: format!("UCB1 {:.3}", parameter),
+        params: Box::new(move |seed| {
+            MctsSantoriniParams::with_seed(seed)
+                .tree_policy(UCB1 { parameter })
+                .budget(budget)
+        }),
+    };
+    let puct = |parameter: f64, fpu: Option<f64>, widening: Option<Widening>| {
+        let mut label = format!("PUCT {}", parameter);
+        if let Some(fpu) = fpu {
+            label += &format!(", FPU {}", fpu);
+        }
+        if let Some(Widening { constant, exponent }) = widening {
+            label += &format!(", widening {} n^{}", constant, exponent);
+        }
+        Setting {
+            label,
+            params: Box::new(move |seed| {
+                let params = MctsSantoriniParams::with_seed(seed)
+                    .tree_policy(PUCT { parameter, fpu })
+                    .budget(budget);
+                match widening {
+                    Some(widening) => params.widening(widening),
+                    None => params,
+                }
+            }),
+        }
+    };
+
+    // PUCT is measured from 0.5, which the self-play and elo players use
+    let settings = match constant {
+        "ucb1" => [SQRT_2, 0.5, 1.0, 2.0, 3.0]
+            .iter()
+            .map(|&parameter| ucb1(parameter))
+            .collect(),
+        "puct" => [0.5, 0.25, 1.0, 1.5, 2.0, 3.0]
+            .iter()
+            .map(|&parameter| puct(parameter, None, None))
+            .collect(),
+        "fpu" => [None, Some(-1.0), Some(-0.5), Some(0.0), Some(0.5)]
+            .iter()
+            .map(|&fpu| puct(0.5, fpu, None))
+            .collect(),
+        "widening" => {
+            let mut settings = vec![puct(0.5, None, None)];
+            for &constant in [1.0, 2.0, 4.0].iter() {
+                for &exponent in [0.25, 0.5].iter() {
+                    settings.push(puct(0.5, None, Some(Widening { constant, exponent })));
+                }
+            }
+            settings
+        }
+        _ => return None,
+    };
+    Some(settings)
+}
+
+/// A candidate's results against the default, each the share of a pair of
+/// games it scored.
+#[derive(Default)]
+struct Match {
+    pairs: Vec<f64>,
+}
+
+impl Match {
+    fn score(&self) -> f64 {
+        self.pairs.iter().sum::<f64>() / self.pairs.len() as f64
+    }
+
+    /// The 95% confidence margin on the score. Taking it from the spread of
+    /// the pairs rather than of the games leaves out the luck of the
+    /// starting positions, which both sides share.
+    fn margin(&self) -> f64 {
+        let n = self.pairs.len() as f64;
+        let score = self.score();
+        let variance = self
+            .pairs
+            .iter()
+            .map(|pair| (pair - score).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        1.96 * (variance / n).sqrt()
+    }
+
+    /// Whether the candidate is told apart from the default, for better or
+    /// worse.
+    fn decided(&self) -> bool {
+        self.pairs.len() > 1 && (self.score() - 0.5).abs() > self.margin()
+    }
+}
+
+/// Settings for tuning an MCTS constant.
+struct ConstantTuner {
+    output: PathBuf,
+    settings: Arc<Vec<Setting>>,
+    /// The most pairs of games a candidate plays against the default.
+    max_pairs: usize,
+    seed: u64,
+}
+
+impl ConstantTuner {
+    /// Play the pairs of games between the candidate and the default. Both
+    /// games of a pair start from the same position, with the seats
+    /// swapped.
+    fn play_pairs(&self, candidate: usize, pairs: Range<usize>) -> Vec<f64> {
+        let games: Vec<_> = pairs
+            .map(|pair| {
+                let settings = Arc::clone(&self.settings);
+                let seed = self.seed ^ (pair as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                thread::spawn(move || {
+                    let start = AnyGame::from(arena::random_start(seed));
+                    let player = |setting: usize| -> Box<dyn FullPlayer> {
+                        (settings[setting].params)(seed).boxed()
+                    };
+                    let first = play([player(candidate), player(0)], start);
+                    let second = 1.0 - play([player(0), player(candidate)], start);
+                    (first + second) / 2.0
+                })
+            })
+            .collect();
+        games
+            .into_iter()
+            .map(|game| game.join().expect("Game thread panicked!"))
+            .collect()
+    }
+
+    /// Match every candidate against the default, stopping once the
+    /// candidate is told apart from it or has played all its pairs, and
+    /// keep the best candidate that is.
+    fn run(self) -> io::Result<()> {
+        let baseline = &self.settings[0].label;
+        let mut results = vec![];
+        for candidate in 1..self.settings.len() {
+            let mut matched = Match::default();
+            while matched.pairs.len() < self.max_pairs && !matched.decided() {
+                let played = matched.pairs.len();
+                let pairs = played..self.max_pairs.min(played + PAIR_BATCH);
+                matched.pairs.extend(self.play_pairs(candidate, pairs));
+            }
+            println!(
+                "{} against {}: {:.3} ± {:.3} over {} pairs",
+                self.settings[candidate].label,
+                baseline,
+                matched.score(),
+                matched.margin(),
+                matched.pairs.len()
+            );
+            results.push((candidate, matched));
+        }
+
+        let best = results
+            .iter()
+            .filter(|(_, matched)| matched.decided() && matched.score() > 0.5)
+            .max_by(|(_, a), (_, b)| a.score().partial_cmp(&b.score()).expect("NaN score!"))
+            .map_or(0, |(candidate, _)| *candidate);
+        println!("Best: {}", self.settings[best].label);
+
+        let mut out = format!(
+            "best\t{}\nsetting\tscore\tmargin\tpairs\n",
+            self.settings[best].label
+        );
+        for (candidate, matched) in &results {
+            out += &format!(
+                "{}\t{:.3}\t{:.3}\t{}\n",
+                self.settings[*candidate].label,
+                matched.score(),
+                matched.margin(),
+                matched.pairs.len()
+            );
+        }
+        fs::write(&self.output, out)
+    }
+}
+
+const USAGE: &str = "usage: tune [--seed <n>] <output> [iterations] [positions]
+       tune [--seed <n>] --mcts <constant> <output> [budget] [pairs]
+
+Tunes the heuristic AI's evaluation weights with SPSA, starting from the
+defaults. Every candidate plays each of [positions] starting positions, with
+the workers placed at random, in both seats against the default heuristic AI
+and one looking a ply ahead. The best weights measured so far are kept in
+<output>, one term to a line.
+
+Defaults to 100 iterations and 8 positions. The positions and the search are
+seeded from --seed, 0 by default.
+
+With --mcts, tunes one of the MCTS constants instead: the UCB1 or PUCT
+exploration parameter (ucb1, puct), PUCT's first play urgency (fpu) or
+progressive widening (widening). Each value tried plays pairs of games against
+the default, from the same random position with the seats swapped, until its
+score is 95% sure to be above or below a half or it has played [pairs] pairs.
+The best value told apart from the default, and every value's score, are
+written to <output>.
+
+Searches get [budget] iterations a turn. Defaults to a budget of 200 and 64
+pairs.";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let seed = match args.iter().position(|arg| arg == "--seed") {
+        Some(idx) => match args.get(idx + 1).map(|arg| arg.parse()) {
+            Some(Ok(seed)) => {
+                args.drain(idx..idx + 2);
+                seed
+            }
+            _ => usage(),
+        },
+        None => 0,
+    };
+    let constant = match args.iter().position(|arg| arg == "--mcts") {
+        Some(idx) if idx + 1 < args.len() => Some(args.drain(idx..idx + 2).nth(1).unwrap()),
+        Some(_) => usage(),
+        None => None,
+    };
+    let (output, rest) = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [output, ref rest @ ..] if rest.len() <= 2 && !output.starts_with('-') => (
+            PathBuf::from(output),
+            rest.iter().map(|arg| arg.parse().ok()).collect::<Vec<_>>(),
+        ),
+        _ => usage(),
+    };
+    let parse = |idx: usize, default: usize| match rest.get(idx) {
+        Some(arg) => *arg,
+        None => Some(default),
+    };
+
+    if let Some(constant) = constant {
+        return match (parse(0, 200), parse(1, 64)) {
+            (Some(budget), Some(max_pairs)) if budget > 0 && max_pairs > 0 => {
+                let settings = settings(&constant, budget as u32).unwrap_or_else(|| usage());
+                ConstantTuner {
+                    output,
+                    settings: Arc::new(settings),
+                    max_pairs,
+                    seed,
+                }
+                .run()
+            }
+            _ => usage(),
+        };
+    }
+
+    let opponents: Vec<Opponent> = vec![
+        Box::new(|| HeuristicAI::new().boxed()),
+        Box::new(|| HeuristicAI::with_search(1, None).boxed()),
+    ];
+    match (parse(0, 100), parse(1, 8)) {
+        (Some(iterations), Some(positions)) if iterations > 0 && positions > 0 => Tuner {
+            output,
+            iterations: iterations as u32,
+            positions,
+            seed,
+            opponents: Arc::new(opponents),
+        }
+        .run(),
+        _ => usage(),
+    }
+}
@@ -0,0 +1,327 @@
+//! Collection of self-play positions for training.
+//!
+//! Positions are deduplicated by a canonical hash that is invariant under the
+//! 8 symmetries of the board and under swapping the player labels, so the same
+//! position reached by mirrored or rotated play is only stored once. The
+//! train/validation split is also decided by this hash, so it is stable from
+//! run to run and a position can never land in both sets.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::santorini::{Game, God, Move, Player, Point, Symmetry, BOARD_SQUARES, BOARD_WIDTH};
+
+pub mod export;
+
+fn index(pt: Point) -> usize {
+    (*pt.y() * BOARD_WIDTH.0 + *pt.x()) as usize
+}
+
+type PositionKey = ([i8; BOARD_SQUARES], Vec<Vec<usize>>, Vec<God>, bool);
+
+fn key(game: &Game<Move>, symmetry: Symmetry) -> PositionKey {
+    let mut heights = [0; BOARD_SQUARES];
+    for pt in Point::all() {
+        heights[index(symmetry.apply(pt))] = game.board().level_at(pt).into();
+    }
+
+    let workers = |player| {
+        let mut locs: Vec<usize> = game
+            .player_pawns(player)
+            .iter()
+            .map(|pawn| index(symmetry.apply(pawn.pos())))
+            .collect();
+        locs.sort_unstable();
+        locs
+    };
+
+    // Workers and gods are described in turn order from the player to move,
+    // so positions that only differ in which player is which collapse
+    // together.
+    let players: Vec<Player> = game.players().collect();
+    let (before, after) = players.split_at(game.player().index());
+    let seats: Vec<Player> = after.iter().chain(before).cloned().collect();
+    (
+        heights,
+        seats.iter().map(|player| workers(*player)).collect(),
+        seats.iter().map(|player| game.god(*player)).collect(),
+        game.climbing_blocked(),
+    )
+}
+
+/// A hash of the position that is identical for every symmetric position.
+pub fn canonical_hash(game: &Game<Move>) -> u64 {
+    let key = Symmetry::all()
+        .map(|symmetry| key(game, symmetry))
+        .min()
+        .expect("No symmetries!");
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The set a position is kept for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    Train,
+    Validation,
+}
+
+/// A position and the eventual result of the game it was taken from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub game: Game<Move>,
+    pub hash: u64,
+    /// 1.0 if the player to move went on to win, -1.0 if they lost, and 0.0
+    /// for an unfinished game.
+    pub value: f64,
+    pub split: Split,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Summary {
+    pub games: usize,
+    pub player_one_wins: usize,
+    pub player_two_wins: usize,
+    pub unfinished: usize,
+    pub positions: usize,
+    pub unique_positions: usize,
+    pub train: usize,
+    pub validation: usize,
+}
+
+impl Summary {
+    pub fn average_game_length(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.positions as f64 / self.games as f64
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |count: usize| {
+            if self.games == 0 {
+                0.0
+            } else {
+                100.0 * count as f64 / self.games as f64
+            }
+        };
+
+        writeln!(f, "Games: {}", self.games)?;
+        writeln!(
+            f,
+            "  Average length: {:.1} turns",
+            self.average_game_length()
+        )?;
+        writeln!(
+            f,
+            "  Player One wins: {} ({:.1}%)",
+            self.player_one_wins,
+            percent(self.player_one_wins)
+        )?;
+        writeln!(
+            f,
+            "  Player Two wins: {} ({:.1}%)",
+            self.player_two_wins,
+            percent(self.player_two_wins)
+        )?;
+        writeln!(f, "  Unfinished: {}", self.unfinished)?;
+        writeln!(f, "Positions: {}", self.positions)?;
+        writeln!(
+            f,
+            "  Unique: {} ({} duplicates dropped)",
+            self.unique_positions,
+            self.positions - self.unique_positions
+        )?;
+        writeln!(f, "  Train: {}", self.train)?;
+        write!(f, "  Validation: {}", self.validation)
+    }
+}
+
+/// Keeps track of the positions added so far, to let through only those not
+/// seen before and to split them into train and validation sets.
+pub struct Dataset {
+    validation_fraction: f64,
+    seen: HashSet<u64>,
+    summary: Summary,
+}
+
+impl Dataset {
+    pub fn new(validation_fraction: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&validation_fraction),
+            "Validation fraction must be between 0 and 1"
+        );
+        Dataset {
+            validation_fraction,
+            seen: HashSet::new(),
+            summary: Summary::default(),
+        }
+    }
+
+    fn is_validation(&self, hash: u64) -> bool {
+        (hash % 10_000) as f64 / 10_000.0 < self.validation_fraction
+    }
+
+    /// Add every position from a two player game, given the winner if it
+    /// finished. Each position not seen before gets a sample, in the same
+    /// place in the list returned, and those seen before get None.
+    pub fn add_game(
+        &mut self,
+        positions: &[Game<Move>],
+        winner: Option<Player>,
+    ) -> Vec<Option<Sample>> {
+        self.summary.games += 1;
+        match winner {
+            Some(Player::PlayerOne) => self.summary.player_one_wins += 1,
+            Some(Player::PlayerTwo) => self.summary.player_two_wins += 1,
+            Some(Player::PlayerThree) => panic!("Datasets only hold two player games!"),
+            None => self.summary.unfinished += 1,
+        }
+
+        positions
+            .iter()
+            .map(|game| {
+                self.summary.positions += 1;
+                let hash = canonical_hash(game);
+                if !self.seen.insert(hash) {
+                    return None;
+                }
+                self.summary.unique_positions += 1;
+
+                let value = match winner {
+                    Some(player) if player == game.player() => 1.0,
+                    Some(_) => -1.0,
+                    None => 0.0,
+                };
+                let split = if self.is_validation(hash) {
+                    self.summary.validation += 1;
+                    Split::Validation
+                } else {
+                    self.summary.train += 1;
+                    Split::Train
+                };
+                Some(Sample {
+                    game: *game,
+                    hash,
+                    value,
+                    split,
+                })
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+}
+
+#[cfg(test)]
+mod dataset_tests {
+    use super::*;
+    use crate::analysis::positions;
+    use crate::santorini::{Board, GameBuilder, BOARD_HEIGHT};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// A game between mortals with the heights given as `(x, y, level)`.
+    fn position(
+        heights: &[(i8, i8, i8)],
+        p1: [Point; 2],
+        p2: [Point; 2],
+        player: Player,
+    ) -> Game<Move> {
+        let mut board = Board::new();
+        for &(x, y, level) in heights {
+            board.set_level(pt(x, y), level.into());
+        }
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &p1)
+            .workers(Player::PlayerTwo, &p2)
+            .player(player)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn symmetric_positions_collide() {
+        let (r, b) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let heights = [(1, 0, 1), (2, 1, 2), (r, b, 3)];
+        let game = position(
+            &heights,
+            [pt(0, 0), pt(2, 2)],
+            [pt(r, 0), pt(1, b - 1)],
+            Player::PlayerOne,
+        );
+        // Mirrored left to right
+        let mirror = position(
+            &heights.map(|(x, y, level)| (r - x, y, level)),
+            [pt(r, 0), pt(r - 2, 2)],
+            [pt(0, 0), pt(r - 1, b - 1)],
+            Player::PlayerOne,
+        );
+        // Rotated and with the players swapped
+        let rotated = position(
+            &heights.map(|(x, y, level)| (r - x, b - y, level)),
+            [pt(r - 1, 1), pt(0, b)],
+            [pt(r - 2, b - 2), pt(r, b)],
+            Player::PlayerTwo,
+        );
+        let other = position(
+            &heights,
+            [pt(0, 0), pt(2, 2)],
+            [pt(r, 0), pt(1, b)],
+            Player::PlayerOne,
+        );
+
+        assert_eq!(canonical_hash(&game), canonical_hash(&mirror));
+        assert_eq!(canonical_hash(&game), canonical_hash(&rotated));
+        assert_ne!(canonical_hash(&game), canonical_hash(&other));
+    }
+
+    #[test]
+    // Counts on the named positions, which only fit the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn deduplicate_and_split() {
+        let games: Vec<_> = positions::POSITIONS.iter().map(|p| p.game()).collect();
+        let mut dataset = Dataset::new(0.5);
+        let samples = dataset.add_game(&games, Some(Player::PlayerOne));
+        let again = dataset.add_game(&games, None);
+
+        let summary = dataset.summary();
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.player_one_wins, 1);
+        assert_eq!(summary.unfinished, 1);
+        assert_eq!(summary.positions, 2 * games.len());
+        // The opening positions are all distinct from one another
+        assert_eq!(summary.unique_positions, games.len());
+        assert_eq!(summary.train + summary.validation, games.len());
+        assert!(again.iter().all(Option::is_none));
+        let validation = samples
+            .iter()
+            .flatten()
+            .filter(|sample| sample.split == Split::Validation)
+            .count();
+        assert_eq!(validation, summary.validation);
+        assert_eq!(summary.average_game_length(), games.len() as f64);
+
+        for (game, sample) in games.iter().zip(&samples) {
+            let sample = sample.expect("Every position is new!");
+            assert_eq!(sample.game, *game);
+            let expected = if game.player() == Player::PlayerOne {
+                1.0
+            } else {
+                -1.0
+            };
+            assert_eq!(sample.value, expected);
+        }
+    }
+}
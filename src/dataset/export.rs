@@ -0,0 +1,299 @@
+//! The file format training data is exported in, for training scripts outside
+//! this crate.
+//!
+//! A file starts with a 32 byte header: the magic bytes `SANTDATA`, then the
+//! format version, the number of planes, the board height, the board width
+//! and the number of turns in the policy, each a little endian `u32`, and
+//! four reserved zero bytes. Examples follow back to back, each the same
+//! size:
+//!
+//! - the input planes, one byte per square, plane after plane, each 0 or 1
+//! - the policy target, a little endian `f32` for each turn
+//! - the value target, a little endian `f32`
+//!
+//! so the examples can be read with a single structured numpy dtype. The
+//! planes and turns are as `mcts::nn` encodes them, taken from the canonical
+//! form of the position.
+
+use std::io::{self, Read, Write};
+
+use thiserror::Error;
+
+use crate::mcts::nn::{encode, transform_turn, PLANES, TURNS};
+use crate::santorini::{Game, Move, BOARD_HEIGHT, BOARD_SQUARES, BOARD_WIDTH};
+
+const MAGIC: &[u8; 8] = b"SANTDATA";
+pub const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 32;
+
+/// The size of an example in bytes.
+pub const EXAMPLE_SIZE: usize = PLANES * BOARD_SQUARES + 4 * TURNS + 4;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a training data file")]
+    NotTrainingData,
+    #[error("Unsupported format version {0}")]
+    Version(u32),
+    #[error("Examples are for a different board or network")]
+    Shape,
+    #[error("File ends part way through an example")]
+    Truncated,
+}
+
+/// A position prepared for training.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    /// The network's input for the canonical form of the position, each 0
+    /// or 1.
+    pub planes: Vec<u8>,
+    /// How likely each turn is to be chosen. Sums to one, unless nothing is
+    /// known about the turns, when it is all zero.
+    pub policy: Vec<f32>,
+    /// The result for the player to act, 1 for a win and -1 for a loss.
+    pub value: f32,
+}
+
+impl Example {
+    /// An example from a position, the visits the search made to each turn
+    /// by its index in the policy and the result for the player to act.
+    pub fn new(game: &Game<Move>, visits: &[(usize, u32)], value: f32) -> Example {
+        let symmetry = game.canonical_symmetry();
+        let planes = encode(&game.transform(symmetry))
+            .iter()
+            .map(|plane| if *plane > 0.0 { 1 } else { 0 })
+            .collect();
+
+        let mut policy = vec![0.0; TURNS];
+        let total: u32 = visits.iter().map(|(_, count)| count).sum();
+        for (idx, count) in visits {
+            if let Some(idx) = transform_turn(game, *idx, symmetry) {
+                policy[idx] += *count as f32 / total as f32;
+            }
+        }
+        Example {
+            planes,
+            policy,
+            value,
+        }
+    }
+}
+
+fn header() -> [u8; HEADER_SIZE] {
+    let mut header = [0; HEADER_SIZE];
+    header[..8].copy_from_slice(MAGIC);
+    let fields = [
+        VERSION,
+        PLANES as u32,
+        BOARD_HEIGHT.0 as u32,
+        BOARD_WIDTH.0 as u32,
+        TURNS as u32,
+    ];
+    for (idx, field) in fields.iter().enumerate() {
+        header[8 + 4 * idx..12 + 4 * idx].copy_from_slice(&field.to_le_bytes());
+    }
+    header
+}
+
+pub struct ExampleWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ExampleWriter<W> {
+    /// Start a file, writing the header.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(&header())?;
+        Ok(ExampleWriter { inner })
+    }
+
+    pub fn write(&mut self, example: &Example) -> io::Result<()> {
+        assert_eq!(
+            example.planes.len(),
+            PLANES * BOARD_SQUARES,
+            "Invalid planes!"
+        );
+        assert_eq!(example.policy.len(), TURNS, "Invalid policy!");
+        let mut bytes = Vec::with_capacity(EXAMPLE_SIZE);
+        bytes.extend_from_slice(&example.planes);
+        for p in example.policy.iter() {
+            bytes.extend_from_slice(&p.to_le_bytes());
+        }
+        bytes.extend_from_slice(&example.value.to_le_bytes());
+        self.inner.write_all(&bytes)
+    }
+
+    /// Flush the file, handing back what it was written to.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads the examples of a file in order.
+pub struct ExampleReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> ExampleReader<R> {
+    /// Open a file, checking its header.
+    pub fn new(mut inner: R) -> Result<Self, ExportError> {
+        let mut found = [0; HEADER_SIZE];
+        inner
+            .read_exact(&mut found)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => ExportError::NotTrainingData,
+                _ => err.into(),
+            })?;
+        if &found[..8] != MAGIC {
+            return Err(ExportError::NotTrainingData);
+        }
+        let version = u32::from_le_bytes([found[8], found[9], found[10], found[11]]);
+        if version != VERSION {
+            return Err(ExportError::Version(version));
+        }
+        if found != header() {
+            return Err(ExportError::Shape);
+        }
+        Ok(ExampleReader { inner })
+    }
+
+    /// The next example, or None at the end of the file.
+    pub fn read(&mut self) -> Result<Option<Example>, ExportError> {
+        let mut bytes = vec![0; EXAMPLE_SIZE];
+        let mut filled = 0;
+        while filled < EXAMPLE_SIZE {
+            match self.inner.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(ExportError::Truncated),
+                Ok(count) => filled += count,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let float = |offset: usize| {
+            f32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let planes_size = PLANES * BOARD_SQUARES;
+        Ok(Some(Example {
+            planes: bytes[..planes_size].to_vec(),
+            policy: (0..TURNS).map(|idx| float(planes_size + 4 * idx)).collect(),
+            value: float(planes_size + 4 * TURNS),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for ExampleReader<R> {
+    type Item = Result<Example, ExportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use crate::mcts::nn::turn_index;
+    use crate::mcts::santorini::SantoriniExpansion;
+    use crate::mcts::Expansion;
+    use crate::santorini::{
+        Board, CoordLevel, GameBuilder, God, Player, Point, Symmetry, BOARD_HEIGHT, BOARD_WIDTH,
+    };
+
+    /// A game with a few levels built and the workers spread out.
+    fn game() -> Game<Move> {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let (r, b) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::One);
+        board.set_level(pt(2, 1), CoordLevel::Two);
+        board.set_level(pt(r, b), CoordLevel::Three);
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(2, 2)])
+            .workers(Player::PlayerTwo, &[pt(r, 0), pt(1, b - 1)])
+            .build()
+            .unwrap()
+    }
+
+    /// Visits to the first few turns of a game.
+    fn visits(game: &Game<Move>) -> Vec<(usize, u32)> {
+        SantoriniExpansion {}
+            .expand(&(*game).into())
+            .iter()
+            .take(4)
+            .enumerate()
+            .map(|(count, child)| (turn_index(game, child).unwrap(), count as u32 + 1))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let game = game();
+        let examples = vec![
+            Example::new(&game, &visits(&game), 1.0),
+            Example::new(&game, &[], -1.0),
+        ];
+        assert!((examples[0].policy.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+
+        let mut writer = ExampleWriter::new(Vec::new()).unwrap();
+        for example in examples.iter() {
+            writer.write(example).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE + 2 * EXAMPLE_SIZE);
+
+        let read: Vec<Example> = ExampleReader::new(&bytes[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, examples);
+
+        let truncated = &bytes[..bytes.len() - 1];
+        let mut reader = ExampleReader::new(truncated).unwrap();
+        assert!(reader.read().unwrap().is_some());
+        assert!(matches!(reader.read(), Err(ExportError::Truncated)));
+    }
+
+    #[test]
+    fn symmetric_positions_match() {
+        let game = game();
+        let example = Example::new(&game, &visits(&game), 1.0);
+        for symmetry in Symmetry::all() {
+            let transformed = game.transform(symmetry);
+            let visits: Vec<(usize, u32)> = visits(&game)
+                .into_iter()
+                .map(|(idx, count)| (transform_turn(&game, idx, symmetry).unwrap(), count))
+                .collect();
+            assert_eq!(Example::new(&transformed, &visits, 1.0), example);
+        }
+    }
+
+    #[test]
+    fn invalid_headers() {
+        assert!(matches!(
+            ExampleReader::new(&b"SANT"[..]),
+            Err(ExportError::NotTrainingData)
+        ));
+        let mut bytes = header();
+        bytes[8] = 2;
+        assert!(matches!(
+            ExampleReader::new(&bytes[..]),
+            Err(ExportError::Version(2))
+        ));
+        let mut bytes = header();
+        bytes[12] += 1;
+        assert!(matches!(
+            ExampleReader::new(&bytes[..]),
+            Err(ExportError::Shape)
+        ));
+    }
+}
@@ -1,3 +1,6 @@
+pub mod analysis;
+pub mod arena;
+pub mod dataset;
 pub mod mcts;
 pub mod player;
 pub mod santorini;
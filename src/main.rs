@@ -1,19 +1,45 @@
-use std::io;
-use termion::input::MouseTerminal;
-use termion::raw::IntoRawMode;
-use tui::backend::TermionBackend;
+use std::env;
+use std::process;
 use tui::Terminal;
 
-use santorini_ai::ui::{self, UpdateError};
+use santorini_ai::ui::{self, input, settings, Back, TerminalGuard, UpdateError};
 
 fn main() -> Result<(), UpdateError> {
-    let stdout = MouseTerminal::from(io::stdout().into_raw_mode()?);
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    let mut app = ui::main_menu();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut replay = None;
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => (),
+        ["--record", path] => input::record(path)?,
+        ["--replay", path] => replay = Some(path),
+        _ => {
+            eprintln!("usage: santorini-ai [--record <file> | --replay <file>]");
+            process::exit(1);
+        }
+    }
 
-    terminal.clear()?;
-    loop {
-        app = app.update(&mut terminal)?;
+    match settings::load() {
+        Ok(loaded) => settings::set_settings(loaded),
+        Err(err) => {
+            eprintln!(
+                "santorini-ai: {}: {}",
+                settings::config_path().display(),
+                err
+            );
+            process::exit(1);
+        }
+    }
+
+    // Replays run without a terminal, printing the screen they end on
+    if let Some(path) = replay {
+        let recording = input::Recording::load(path).unwrap_or_else(|err| {
+            eprintln!("santorini-ai: {}: {}", path, err);
+            process::exit(1);
+        });
+        println!("{}", ui::screen_text(&ui::replay_headless(recording)?));
+        return Ok(());
     }
+
+    let mut terminal = TerminalGuard::new(Terminal::new(Back::terminal()?)?);
+    terminal.clear()?;
+    ui::run(&mut terminal, ui::main_menu())
 }
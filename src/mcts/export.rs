@@ -0,0 +1,182 @@
+//! Writing out a search tree, to see why a search chose what it did.
+//!
+//! Each node is written with the action that reached it, its visits, its
+//! value for the player who took that action and its prior. Graphviz DOT
+//! draws the tree as a graph; JSON nests each node's children in it:
+//!
+//! ```text
+//! {"action":"root","visits":500,"value":-0.1,"prior":1,"children":[...]}
+//! ```
+
+use rand::Rng;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use super::{Mcts, Node};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeFormat {
+    Dot,
+    Json,
+}
+
+impl FromStr for TreeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(TreeFormat::Dot),
+            "json" => Ok(TreeFormat::Json),
+            _ => Err(format!("unknown tree format: {}", s)),
+        }
+    }
+}
+
+impl<T: fmt::Display, R: Rng> Mcts<T, R> {
+    /// The tree down to `depth_limit` plies below the root, in the format
+    /// given. A limit of zero writes the root alone.
+    pub fn export_tree(&self, depth_limit: usize, format: TreeFormat) -> String {
+        let mut out = String::new();
+        match format {
+            TreeFormat::Dot => {
+                out.push_str("digraph mcts {\n");
+                write_dot(&self.root_node, depth_limit, &mut 0, &mut out);
+                out.push_str("}\n");
+            }
+            TreeFormat::Json => {
+                write_json(&self.root_node, depth_limit, &mut out);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// Write a node and its children as DOT statements, numbering the nodes in
+/// the order they're written from `next`. Returns the node's number.
+fn write_dot<T: fmt::Display>(
+    node: &Node<T>,
+    depth: usize,
+    next: &mut usize,
+    out: &mut String,
+) -> usize {
+    let id = *next;
+    *next += 1;
+    let label = format!(
+        "{}\\nvisits {}\\nvalue {:+.3}\\nprior {:.3}",
+        escape(&node.state.to_string()),
+        node.iterations,
+        node.score,
+        node.prior
+    );
+    // Writing to a String can't fail
+    let _ = writeln!(out, "    n{} [label=\"{}\"];", id, label);
+    if depth > 0 {
+        for child in node.children.iter().flatten() {
+            let child = write_dot(child, depth - 1, next, out);
+            let _ = writeln!(out, "    n{} -> n{};", id, child);
+        }
+    }
+    id
+}
+
+fn write_json<T: fmt::Display>(node: &Node<T>, depth: usize, out: &mut String) {
+    let _ = write!(
+        out,
+        "{{\"action\":\"{}\",\"visits\":{},\"value\":{},\"prior\":{},\"children\":[",
+        escape(&node.state.to_string()),
+        node.iterations,
+        node.score,
+        node.prior
+    );
+    if depth > 0 {
+        for (idx, child) in node.children.iter().flatten().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            write_json(child, depth - 1, out);
+        }
+    }
+    out.push_str("]}");
+}
+
+/// Escape a label for a quoted string, which DOT and JSON escape alike.
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use crate::mcts::santorini::SantoriniNode;
+    use crate::player::MctsSantoriniParams;
+    use crate::santorini::{GameBuilder, God, Player, Point, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn tree() -> Mcts<SantoriniNode, rand::rngs::SmallRng> {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let game = GameBuilder::new([God::Mortal; 2])
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(1, 0)])
+            .workers(Player::PlayerTwo, &[pt(w - 2, h - 1), pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        let mut tree = Mcts::new(MctsSantoriniParams::with_seed(0).budget(50), game.into());
+        tree.search();
+        tree
+    }
+
+    #[test]
+    fn dot() {
+        let tree = tree();
+        let children = tree.root_node.children.as_ref().unwrap().len();
+        let dot = tree.export_tree(1, TreeFormat::Dot);
+        assert!(dot.starts_with("digraph mcts {\n    n0 [label=\"root\\nvisits "));
+        assert_eq!(dot.matches(" -> ").count(), children);
+        assert_eq!(dot.matches("[label=").count(), children + 1);
+        assert_eq!(
+            tree.export_tree(0, TreeFormat::Dot)
+                .matches("[label=")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn json() {
+        let tree = tree();
+        let json: serde_json::Value =
+            serde_json::from_str(&tree.export_tree(2, TreeFormat::Json)).unwrap();
+        assert_eq!(json["action"], "root");
+        assert_eq!(json["visits"], tree.root_node.iterations);
+        let children = json["children"].as_array().unwrap();
+        assert_eq!(
+            children.len(),
+            tree.root_node.children.as_ref().unwrap().len()
+        );
+        let first = &tree.root_node.children.as_ref().unwrap()[0];
+        assert_eq!(children[0]["action"], first.state.to_string());
+        // Parsing the JSON can be off in the last bit
+        let value = children[0]["value"].as_f64().unwrap();
+        assert!((value - first.score).abs() < 1e-12);
+        // Nothing is written past the limit
+        for child in children {
+            for grandchild in child["children"].as_array().unwrap() {
+                assert!(grandchild["children"].as_array().unwrap().is_empty());
+            }
+        }
+
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
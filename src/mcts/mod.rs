@@ -1,19 +1,245 @@
 use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 mod node;
 pub use node::Node;
 
+pub mod export;
+pub use export::TreeFormat;
+
 pub mod tree_policy;
 pub use tree_policy::TreePolicy;
 
 pub mod santorini;
 
+pub mod nn;
+
+/// What a simulation found out about a state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationResult {
+    /// The result for the player who moved to reach the state, from -1.0
+    /// for a loss to 1.0 for a win.
+    pub score: f64,
+    /// How many plies were played out before the result was settled.
+    pub plies: u32,
+}
+
 pub trait Simulation<T, R: Rng>: Send {
-    fn simulate(&self, state: &T, rng: &mut R) -> f64;
+    /// Play out a state, returning the result. `stats` is shared by every
+    /// simulation in the search, for simulations that learn as it goes.
+    fn simulate(&self, state: &T, rng: &mut R, stats: &mut MoveStats) -> SimulationResult;
+
+    /// Simulate as `simulate` does, also listing the moves made along the
+    /// way. Simulations that don't keep track of their moves list none.
+    fn simulate_moves(
+        &self,
+        state: &T,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> (SimulationResult, Vec<u64>) {
+        (self.simulate(state, rng, stats), vec![])
+    }
+
+    /// Simulate each of the states as `simulate_moves` does, in order.
+    /// Simulations that can play out several states at once for less than
+    /// one at a time, such as those evaluating a network, override this.
+    fn simulate_batch(
+        &self,
+        states: &[T],
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> Vec<(SimulationResult, Vec<u64>)> {
+        states
+            .iter()
+            .map(|state| self.simulate_moves(state, rng, stats))
+            .collect()
+    }
+}
+
+/// How each move has done in the simulations that played it, by move id.
+#[derive(Debug, Default, Clone)]
+pub struct MoveStats {
+    moves: HashMap<u64, (u32, f64)>,
+}
+
+impl MoveStats {
+    /// Record a simulation that played a move, with its result for the
+    /// player who made the move.
+    pub fn record(&mut self, id: u64, score: f64) {
+        let (count, total) = self.moves.entry(id).or_insert((0, 0.0));
+        *count += 1;
+        *total += score;
+    }
+
+    /// The average result of the simulations that played a move, or None if
+    /// none have.
+    pub fn value(&self, id: u64) -> Option<f64> {
+        self.moves
+            .get(&id)
+            .map(|(count, total)| total / *count as f64)
+    }
 }
 
 pub trait Expansion<T>: Send {
     fn expand(&self, state: &T) -> Vec<T>;
+
+    /// The children of a state, each with a prior: how likely it is to be
+    /// the best child, going by what the expansion knows. The priors sum to
+    /// one, and are even unless the expansion has an opinion.
+    fn expand_with_priors(&self, state: &T) -> Vec<(T, f64)> {
+        let children = self.expand(state);
+        let prior = 1.0 / children.len() as f64;
+        children.into_iter().map(|child| (child, prior)).collect()
+    }
+}
+
+/// States that know the move that reached them, so the statistics of a move
+/// can be shared between the places it is played.
+pub trait MoveId {
+    /// Identifies the move that reached this state, if there was one. The
+    /// same move made by different players should have different ids.
+    fn move_id(&self) -> Option<u64>;
+}
+
+/// How much searching `Mcts::advance` does before choosing a move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Budget {
+    /// Run a fixed number of iterations.
+    Iterations(u32),
+    /// Search until the time is up.
+    Time(Duration),
+    /// Search until the tree has grown by this many nodes, or has stopped
+    /// growing.
+    Nodes(u64),
+}
+
+impl From<u32> for Budget {
+    fn from(iterations: u32) -> Budget {
+        Budget::Iterations(iterations)
+    }
+}
+
+impl Budget {
+    /// Whether a search that has taken this long has spent the budget.
+    fn spent(&self, iterations: u32, nodes: u64, elapsed: Duration) -> bool {
+        match *self {
+            Budget::Iterations(budget) => iterations >= budget,
+            Budget::Time(budget) => elapsed >= budget,
+            Budget::Nodes(budget) => nodes >= budget,
+        }
+    }
+}
+
+/// How many steps in a row a search with a node budget may take without
+/// growing the tree before giving up on growing it.
+const STALLED_STEPS: u32 = 100;
+
+/// Shared with searches running on other threads, to follow how far they
+/// have got and to cut them short.
+#[derive(Debug, Default)]
+pub struct Interrupt {
+    stop: AtomicBool,
+    iterations: AtomicU32,
+}
+
+impl Interrupt {
+    pub fn new() -> Interrupt {
+        Interrupt::default()
+    }
+
+    /// Have the searches finish after their current iteration, whatever is
+    /// left of their budgets.
+    pub fn stop(&self) {
+        self.stop.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(AtomicOrdering::Relaxed)
+    }
+
+    /// The iterations run by every search since the last reset.
+    pub fn iterations(&self) -> u32 {
+        self.iterations.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Start counting afresh and let searches run again.
+    pub fn reset(&self) {
+        self.stop.store(false, AtomicOrdering::Relaxed);
+        self.iterations.store(0, AtomicOrdering::Relaxed);
+    }
+}
+
+/// Progressive widening, which holds back some of a node's children until it
+/// has been visited enough. A node visited `n` times has up to
+/// `constant * n^exponent` children, and always at least one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Widening {
+    pub constant: f64,
+    pub exponent: f64,
+}
+
+impl Widening {
+    fn limit(&self, iterations: u32) -> usize {
+        let limit = self.constant * (iterations as f64).powf(self.exponent);
+        limit.ceil().max(1.0) as usize
+    }
+}
+
+/// How the child moved on to after a search is chosen.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SelectionCriterion {
+    /// The child searched the most, known as the robust child. Ties go to
+    /// the higher mean score.
+    MostVisits,
+    /// The child with the highest mean score, however little it was searched.
+    HighestMean,
+    /// The child with the highest lower confidence bound: its mean score less
+    /// this many standard errors. Scores are between -1 and 1, so their
+    /// standard deviation is taken to be at most 1.
+    LCB(f64),
+}
+
+impl SelectionCriterion {
+    /// How a child ranks, for the criteria that score children one at a
+    /// time.
+    fn value<T>(&self, child: &Node<T>) -> f64 {
+        match *self {
+            SelectionCriterion::MostVisits => child.iterations as f64,
+            SelectionCriterion::HighestMean => child.score,
+            SelectionCriterion::LCB(errors) => {
+                child.score - errors / (child.iterations.max(1) as f64).sqrt()
+            }
+        }
+    }
+
+    fn compare<T>(&self, a: &Node<T>, b: &Node<T>) -> Ordering {
+        let order = self
+            .value(a)
+            .partial_cmp(&self.value(b))
+            .unwrap_or(Ordering::Equal);
+        match self {
+            SelectionCriterion::MostVisits => {
+                order.then(a.score.partial_cmp(&b.score).unwrap_or(Ordering::Equal))
+            }
+            _ => order,
+        }
+    }
+
+    /// The index of the child to choose. Ties go to the first.
+    pub fn select<T>(&self, children: &[Node<T>]) -> usize {
+        assert!(!children.is_empty(), "No children!");
+        let mut best = 0;
+        for idx in 1..children.len() {
+            if self.compare(&children[idx], &children[best]) == Ordering::Greater {
+                best = idx;
+            }
+        }
+        best
+    }
 }
 
 pub struct MctsParams<T, R: Rng> {
@@ -21,7 +247,22 @@ pub struct MctsParams<T, R: Rng> {
     pub simulation: Box<dyn Simulation<T, R>>,
     pub expansion: Box<dyn Expansion<T>>,
     pub rng: R,
-    pub budget: u32,
+    pub budget: Budget,
+    /// None to add every child of a node as soon as it is expanded.
+    pub widening: Option<Widening>,
+    /// The most nodes the tree may hold, or None to let it grow without
+    /// bound. Past the limit the least visited parts of the tree are pruned.
+    pub node_limit: Option<usize>,
+    /// What the simulations have learnt about each move so far.
+    pub move_stats: MoveStats,
+    /// How the child moved on to after a search is chosen.
+    pub selection: SelectionCriterion,
+    /// What a result is worth for each ply between it and the node it's
+    /// scored for, below one to prefer quick wins and slow losses. None to
+    /// count every result in full.
+    pub discount: Option<f64>,
+    /// Followed by searches to report their iterations and to stop early.
+    pub interrupt: Option<Arc<Interrupt>>,
 }
 
 impl<T, R: Rng> MctsParams<T, R> {
@@ -35,7 +276,13 @@ impl<T, R: Rng> MctsParams<T, R> {
             simulation: Box::new(simulation),
             expansion: Box::new(expansion),
             rng,
-            budget: 500,
+            budget: Budget::Iterations(500),
+            widening: None,
+            node_limit: None,
+            move_stats: MoveStats::default(),
+            selection: SelectionCriterion::HighestMean,
+            discount: None,
+            interrupt: None,
         }
     }
 
@@ -53,58 +300,362 @@ impl<T, R: Rng> MctsParams<T, R> {
         }
     }
 
-    pub fn budget(self, budget: u32) -> Self {
-        MctsParams { budget, ..self }
+    pub fn widening(self, widening: Widening) -> Self {
+        MctsParams {
+            widening: Some(widening),
+            ..self
+        }
+    }
+
+    pub fn node_limit(self, node_limit: usize) -> Self {
+        MctsParams {
+            node_limit: Some(node_limit),
+            ..self
+        }
+    }
+
+    pub fn budget<B: Into<Budget>>(self, budget: B) -> Self {
+        MctsParams {
+            budget: budget.into(),
+            ..self
+        }
+    }
+
+    pub fn selection(self, selection: SelectionCriterion) -> Self {
+        MctsParams { selection, ..self }
+    }
+
+    pub fn discount(self, discount: f64) -> Self {
+        MctsParams {
+            discount: Some(discount),
+            ..self
+        }
+    }
+
+    pub fn interrupt(self, interrupt: Arc<Interrupt>) -> Self {
+        MctsParams {
+            interrupt: Some(interrupt),
+            ..self
+        }
+    }
+
+    /// Whether the search has been asked to stop early.
+    fn interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|interrupt| interrupt.stopped())
+    }
+
+    /// A result `plies` plies away, as it counts towards a node's score.
+    fn discounted(&self, score: f64, plies: u32) -> f64 {
+        match self.discount {
+            Some(discount) => score * discount.powi(plies as i32),
+            None => score,
+        }
     }
 }
 
+/// What a search found out about the position at its root.
+#[derive(Debug, Clone)]
+pub struct SearchStats<T> {
+    /// The score of the child the search picks, for the player to act at the
+    /// root.
+    pub value: f64,
+    /// The iterations run through the root, including those of earlier
+    /// searches that reached it.
+    pub iterations: u32,
+    /// Each of the root's children, with the iterations run through it.
+    pub visits: Vec<(T, u32)>,
+    /// The line of play the search expects: the child it picks, then the
+    /// most visited child of each node after, for as long as those have been
+    /// searched past their first simulation.
+    pub principal_variation: Vec<T>,
+}
+
 pub struct Mcts<T, R: Rng> {
     pub params: MctsParams<T, R>,
     pub root_node: Node<T>,
 }
 
-impl<T, R: Rng> Mcts<T, R> {
+impl<T: MoveId, R: Rng> Mcts<T, R> {
     pub fn new(mut params: MctsParams<T, R>, root_node: T) -> Self {
         let root_node = Node::new(&mut params, root_node);
         Mcts { params, root_node }
     }
 
-    pub fn advance(&mut self) {
-        for _ in 0..self.params.budget {
-            self.root_node.step(&mut self.params);
+    /// Throw the tree away and search from a new position, keeping the
+    /// params.
+    pub fn reroot(&mut self, root_node: T) {
+        self.root_node = Node::new(&mut self.params, root_node);
+    }
+
+    /// Grow the tree until the budget is spent, or the interrupt stops it.
+    pub fn search(&mut self) {
+        let start = Instant::now();
+        let mut iterations = 0;
+        let mut nodes = 0;
+        let mut stalled = 0;
+        let mut size = match self.params.node_limit {
+            Some(_) => self.root_node.size(),
+            None => 0,
+        };
+        let budget = self.params.budget;
+        loop {
+            // Searches with no budget left, or cut short by the interrupt,
+            // still expand the root, so it has children to choose from
+            let unexpanded = self.root_node.children.is_none();
+            if !unexpanded
+                && (budget.spent(iterations, nodes, start.elapsed()) || self.params.interrupted())
+            {
+                break;
+            }
+
+            let (count, _) = self.root_node.step(&mut self.params);
+            iterations += 1;
+            if let Some(interrupt) = &self.params.interrupt {
+                interrupt.iterations.fetch_add(1, AtomicOrdering::Relaxed);
+            }
+            nodes += count as u64;
+            if let Some(limit) = self.params.node_limit {
+                size += count as usize;
+                if size > limit {
+                    // Prune a little extra, so pruning isn't needed again
+                    // right away
+                    size -= self.root_node.prune(size - limit + limit / 10);
+                }
+            }
+
+            // Steps that reach a finished game don't grow the tree. Once every
+            // node has been expanded none will, and a search that keeps
+            // choosing finished games may never grow it again either
+            if count > 0 {
+                stalled = 0;
+            } else if let Budget::Nodes(_) = budget {
+                stalled += 1;
+                if stalled >= STALLED_STEPS || self.root_node.fully_expanded() {
+                    break;
+                }
+            }
         }
+    }
+
+    /// Search, then move the root on to the child the selection criterion
+    /// picks, returning what the search found out about the old root.
+    pub fn advance(&mut self) -> SearchStats<T>
+    where
+        T: Clone,
+    {
+        self.search();
+        let stats = self.stats();
+        let idx = self.best_child();
+        self.descend(idx);
+        stats
+    }
 
+    /// What the searches so far found out about the root.
+    pub fn stats(&self) -> SearchStats<T>
+    where
+        T: Clone,
+    {
         let children = self
             .root_node
             .children
             .as_ref()
             .expect("Root node missing children");
-        assert!(children.len() > 0, "Root node has no children!");
-
-        let mut best_score = children[0].score as f64 / children[0].iterations as f64;
-        let mut best_score_idx = 0;
+        let best = &children[self.best_child()];
 
-        // let mut most_visits = children[0].iterations;
-        // let mut most_visits_idx = 0;
-
-        for (index, child) in children.iter().enumerate() {
-            if child.score > best_score {
-                best_score = child.score;
-                best_score_idx = index;
-            }
+        let mut principal_variation = vec![best.state.clone()];
+        let mut node = best;
+        while let Some(next) = node
+            .children
+            .iter()
+            .flatten()
+            .max_by_key(|child| child.iterations)
+            .filter(|child| child.iterations > 1)
+        {
+            principal_variation.push(next.state.clone());
+            node = next;
+        }
 
-            // if child.iterations > most_visits {
-            //     most_visits = child.iterations;
-            //     most_visits_idx = index;
-            // }
+        SearchStats {
+            value: best.score,
+            iterations: self.root_node.iterations,
+            visits: children
+                .iter()
+                .map(|child| (child.state.clone(), child.iterations))
+                .collect(),
+            principal_variation,
         }
+    }
+
+    /// The index of the root's child the selection criterion picks.
+    pub fn best_child(&self) -> usize {
+        let children = self
+            .root_node
+            .children
+            .as_ref()
+            .expect("Root node missing children");
+        assert!(!children.is_empty(), "Root node has no children!");
+        self.params.selection.select(children)
+    }
 
+    /// Move the root on to one of its children, throwing the rest of the
+    /// tree away.
+    pub fn descend(&mut self, idx: usize) {
         take_mut::take(&mut self.root_node, |node| {
             node.children
                 .unwrap()
                 .into_iter()
-                .nth(best_score_idx)
-                .expect("Invalid best child index!")
+                .nth(idx)
+                .expect("Invalid child index!")
         });
     }
 }
+
+#[cfg(test)]
+mod selection_tests {
+    use super::*;
+
+    fn node(iterations: u32, score: f64) -> Node<()> {
+        Node {
+            children: None,
+            iterations,
+            score,
+            amaf_iterations: 0,
+            amaf_score: 0.0,
+            prior: 0.5,
+            pending: vec![],
+            state: (),
+        }
+    }
+
+    #[test]
+    fn criteria() {
+        // A well searched child doing fairly well, a barely searched one
+        // doing very well, and one in between
+        let children = vec![node(100, 0.3), node(2, 0.9), node(25, 0.5)];
+        assert_eq!(SelectionCriterion::MostVisits.select(&children), 0);
+        assert_eq!(SelectionCriterion::HighestMean.select(&children), 1);
+        assert_eq!(SelectionCriterion::LCB(1.0).select(&children), 2);
+        assert_eq!(SelectionCriterion::LCB(0.0).select(&children), 1);
+
+        let tied = vec![node(10, 0.1), node(10, 0.2), node(10, 0.2)];
+        assert_eq!(SelectionCriterion::MostVisits.select(&tied), 1);
+        assert_eq!(SelectionCriterion::HighestMean.select(&tied), 1);
+    }
+}
+
+#[cfg(test)]
+mod budget_tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    /// A game where each state is how deep it is, with two ways to go on
+    /// from each until the depth limit, if there is one.
+    struct Tree(Option<u32>);
+
+    impl MoveId for u32 {
+        fn move_id(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    impl Expansion<u32> for Tree {
+        fn expand(&self, state: &u32) -> Vec<u32> {
+            match self.0 {
+                Some(depth) if *state >= depth => vec![],
+                _ => vec![state + 1, state + 1],
+            }
+        }
+    }
+
+    struct Draw;
+
+    impl Simulation<u32, SmallRng> for Draw {
+        fn simulate(&self, _: &u32, _: &mut SmallRng, _: &mut MoveStats) -> SimulationResult {
+            SimulationResult {
+                score: 0.0,
+                plies: 0,
+            }
+        }
+    }
+
+    /// Searches whatever hasn't been expanded yet, first child first.
+    struct Exhaustive;
+
+    impl TreePolicy<u32> for Exhaustive {
+        fn select(&self, _: &Node<u32>, children: &Vec<Node<u32>>) -> usize {
+            children
+                .iter()
+                .position(|child| !child.fully_expanded())
+                .unwrap_or(0)
+        }
+    }
+
+    fn search(depth: Option<u32>, budget: Budget) -> (Mcts<u32, SmallRng>, u32) {
+        let interrupt = Arc::new(Interrupt::new());
+        let params = MctsParams::new(Draw, Tree(depth), SmallRng::seed_from_u64(0))
+            .budget(budget)
+            .interrupt(interrupt.clone());
+        let mut tree = Mcts::new(params, 0);
+        tree.search();
+        (tree, interrupt.iterations())
+    }
+
+    #[test]
+    fn iterations() {
+        // Even no iterations at all expand the root
+        let (tree, iterations) = search(None, Budget::Iterations(0));
+        assert_eq!(iterations, 1);
+        assert_eq!(tree.root_node.children.as_ref().unwrap().len(), 2);
+
+        let params = MctsParams::new(Draw, Tree(None), SmallRng::seed_from_u64(0))
+            .budget(Budget::Iterations(0));
+        let mut tree = Mcts::new(params, 0);
+        let stats = tree.advance();
+        assert_eq!(stats.visits.len(), 2);
+        assert_eq!(tree.root_node.state, 1);
+
+        let (tree, iterations) = search(None, Budget::Iterations(7));
+        assert_eq!(iterations, 7);
+        assert_eq!(tree.root_node.size(), 15);
+    }
+
+    #[test]
+    fn time() {
+        let start = Instant::now();
+        let (_, iterations) = search(Some(3), Budget::Time(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(iterations > 1);
+
+        // Even no time at all expands the root
+        let (tree, iterations) = search(None, Budget::Time(Duration::from_secs(0)));
+        assert_eq!(iterations, 1);
+        assert_eq!(tree.root_node.children.as_ref().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn nodes() {
+        let (tree, _) = search(None, Budget::Nodes(100));
+        assert!((101..=102).contains(&tree.root_node.size()));
+
+        // Reaching the end of the game doesn't stop the search while there's
+        // more to expand
+        let interrupt = Arc::new(Interrupt::new());
+        let params = MctsParams::new(Draw, Tree(Some(3)), SmallRng::seed_from_u64(0))
+            .tree_policy(Exhaustive)
+            .budget(Budget::Nodes(1000))
+            .interrupt(interrupt.clone());
+        let mut tree = Mcts::new(params, 0);
+        tree.search();
+        assert!(tree.root_node.fully_expanded());
+        assert_eq!(tree.root_node.size(), 15);
+        assert!(interrupt.iterations() < STALLED_STEPS);
+
+        // Nor does it run forever when it keeps choosing a finished game
+        let (tree, iterations) = search(Some(1), Budget::Nodes(1000));
+        assert_eq!(tree.root_node.size(), 3);
+        assert!(iterations <= STALLED_STEPS + 2);
+    }
+}
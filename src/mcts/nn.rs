@@ -0,0 +1,200 @@
+//! Evaluating positions with a neural network, the path to an AlphaZero style
+//! player.
+//!
+//! Positions are encoded as planes over the board: one for each height a
+//! square can be, then one for the workers of each player, starting with the
+//! player to act. Turns are encoded by the index of the worker that moves,
+//! the direction it moves in and the direction it builds in from where it
+//! ends up.
+//!
+//! With the `nn` feature, `Network` runs an ONNX model that takes the planes
+//! as a `[N, PLANES, BOARD_HEIGHT, BOARD_WIDTH]` tensor of `f32`s for a batch
+//! of N positions. It should have a `policy` output of `TURNS` logits and a
+//! `value` output, the expected result for the player to act between -1 and
+//! 1, for each position. Networks taking only single positions still work
+//! one at a time, but not with `NetworkSimulation`, which values all the
+//! children of a node together.
+//! `NetworkSimulation` and `NetworkExpansion` use it in place of playouts and
+//! to give priors.
+
+use super::santorini::SantoriniNode;
+use crate::santorini::{
+    Coord, Direction, Game, Move, Player, Point, Symmetry, WorkerId, BOARD_HEIGHT, BOARD_SQUARES,
+    BOARD_WIDTH, MAX_PLAYERS, MAX_WORKERS,
+};
+
+#[cfg(feature = "nn")]
+mod network;
+#[cfg(feature = "nn")]
+pub use network::{Network, NetworkError, NetworkExpansion, NetworkSimulation};
+
+/// Ground to dome.
+const LEVELS: usize = 5;
+
+/// The number of planes a position is encoded as.
+pub const PLANES: usize = LEVELS + MAX_PLAYERS;
+
+/// The number of turns the policy chooses between: a worker, one of eight
+/// directions to move in and one of nine to build in, the last for building
+/// beneath itself or not building at all.
+pub const TURNS: usize = MAX_WORKERS * 8 * 9;
+
+fn square(pt: Point) -> usize {
+    (*pt.y() * BOARD_WIDTH.0 + *pt.x()) as usize
+}
+
+/// Encode a position as the network's input, plane after plane.
+pub fn encode(game: &Game<Move>) -> Vec<f32> {
+    let mut planes = vec![0.0; PLANES * BOARD_SQUARES];
+    let board = game.board();
+    for pt in Point::all() {
+        let level = i8::from(board.level_at(pt)) as usize;
+        planes[level * BOARD_SQUARES + square(pt)] = 1.0;
+    }
+    let count = game.player_count();
+    for player in Player::iter().take(count) {
+        // Seat the player to act first, whoever they are
+        let seat = (player.index() + count - game.player().index()) % count;
+        for loc in game.workers(*player).iter().flatten() {
+            planes[(LEVELS + seat) * BOARD_SQUARES + square(*loc)] = 1.0;
+        }
+    }
+    planes
+}
+
+/// Which of the eight directions leads from one square to a neighboring one.
+fn direction(from: Point, to: Point) -> Option<usize> {
+    let offset = (*to.x() - *from.x(), *to.y() - *from.y());
+    Direction::ALL
+        .iter()
+        .position(|direction| direction.offset() == offset)
+}
+
+/// The index in the policy of the turn that reached a node from a game. None
+/// if the node wasn't reached by a turn.
+pub fn turn_index(game: &Game<Move>, node: &SantoriniNode) -> Option<usize> {
+    let mv = node.mv?;
+    let worker = game.worker_on(mv.from()).map_or(0, |id| id.index());
+    let moved = direction(mv.from(), mv.to())?;
+    let at = node.extra_move.map_or(mv.to(), |extra| extra.to());
+    let built = node
+        .build
+        .and_then(|build| direction(at, build.loc()))
+        .unwrap_or(8);
+    Some((worker * 8 + moved) * 9 + built)
+}
+
+/// A direction seen through a symmetry of the board.
+fn transform_direction(direction: usize, symmetry: Symmetry) -> usize {
+    let centre = Point::new(Coord(BOARD_WIDTH.0 / 2), Coord(BOARD_HEIGHT.0 / 2));
+    let to = (centre + Direction::ALL[direction]).expect("Board too small!");
+    self::direction(symmetry.apply(centre), symmetry.apply(to)).expect("Invalid symmetry!")
+}
+
+/// The index in the policy of a turn taken in a game, once the game is seen
+/// through a symmetry of the board. None if the index isn't a turn of one of
+/// the active player's workers.
+pub fn transform_turn(game: &Game<Move>, idx: usize, symmetry: Symmetry) -> Option<usize> {
+    if idx >= TURNS {
+        return None;
+    }
+    let (worker, moved, built) = (idx / 72, idx / 9 % 8, idx % 9);
+    let loc = game.worker(WorkerId::new(game.player(), worker))?;
+    // Transforming puts the workers back in order, which can renumber them
+    let worker = game
+        .transform(symmetry)
+        .worker_on(symmetry.apply(loc))?
+        .index();
+    let moved = transform_direction(moved, symmetry);
+    let built = if built == 8 {
+        built
+    } else {
+        transform_direction(built, symmetry)
+    };
+    Some((worker * 8 + moved) * 9 + built)
+}
+
+#[cfg(test)]
+mod nn_tests {
+    use super::*;
+    use crate::mcts::santorini::{NodeState, SantoriniExpansion};
+    use crate::mcts::Expansion;
+    use crate::santorini::{Board, CoordLevel, GameBuilder, God};
+    use std::collections::HashSet;
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// A game with Player Two's workers in the bottom right corner.
+    fn moving(board: Board, p1: [Point; 2], player: Player) -> Game<Move> {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &p1)
+            .workers(Player::PlayerTwo, &[pt(w - 2, h - 1), pt(w - 1, h - 1)])
+            .player(player)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn planes() {
+        let mut board = Board::new();
+        board.set_level(pt(0, 0), CoordLevel::Capped);
+        board.set_level(pt(1, 1), CoordLevel::One);
+        let game = moving(board, [pt(1, 0), pt(2, 2)], Player::PlayerTwo);
+        let planes = encode(&game);
+        assert_eq!(planes.len(), PLANES * BOARD_SQUARES);
+        // Every square has exactly one height
+        for pt in Point::all() {
+            let heights =
+                (0..LEVELS).filter(|level| planes[level * BOARD_SQUARES + square(pt)] == 1.0);
+            assert_eq!(heights.count(), 1);
+        }
+        let at = |plane: usize, x: i8, y: i8| planes[plane * BOARD_SQUARES + square(pt(x, y))];
+        assert_eq!(at(4, 0, 0), 1.0);
+        assert_eq!(at(1, 1, 1), 1.0);
+        // Player Two is to act, so their workers come first
+        assert_eq!(at(LEVELS, BOARD_WIDTH.0 - 2, BOARD_HEIGHT.0 - 1), 1.0);
+        assert_eq!(at(LEVELS + 1, 1, 0), 1.0);
+        let workers: f32 = planes[LEVELS * BOARD_SQUARES..].iter().sum();
+        assert_eq!(workers, 4.0);
+    }
+
+    #[test]
+    fn turn_indices() {
+        let game = moving(Board::new(), [pt(0, 0), pt(2, 2)], Player::PlayerOne);
+        let children = SantoriniExpansion {}.expand(&game.into());
+        let indices: HashSet<usize> = children
+            .iter()
+            .map(|child| turn_index(&game, child).unwrap())
+            .collect();
+        // Mortal turns never share an index
+        assert_eq!(indices.len(), children.len());
+        assert!(indices.iter().all(|idx| *idx < TURNS));
+    }
+
+    #[test]
+    fn transformed_turns() {
+        let game = moving(Board::new(), [pt(0, 0), pt(2, 2)], Player::PlayerOne);
+        let after = |node: &SantoriniNode, symmetry| match node.game {
+            NodeState::Move(game) => game.transform(symmetry),
+            _ => panic!("Game over!"),
+        };
+        for symmetry in Symmetry::all() {
+            let transformed = game.transform(symmetry);
+            let children = SantoriniExpansion {}.expand(&transformed.into());
+            let originals = SantoriniExpansion {}.expand(&game.into());
+            for child in originals {
+                // The transformed turn leads to the transformed position
+                let idx = transform_turn(&game, turn_index(&game, &child).unwrap(), symmetry);
+                let image = children
+                    .iter()
+                    .find(|other| turn_index(&transformed, other) == idx)
+                    .unwrap();
+                assert_eq!(after(image, Symmetry::IDENTITY), after(&child, symmetry));
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{encode, turn_index, PLANES, TURNS};
+use crate::mcts::santorini::{playout_start, NodeState, SantoriniExpansion, SantoriniNode};
+use crate::mcts::{Expansion, MoveStats, Simulation, SimulationResult};
+use crate::santorini::{Game, Move, Player, BOARD_HEIGHT, BOARD_WIDTH};
+use ort::session::Session;
+use ort::value::Tensor;
+use rand::Rng;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error("ONNX runtime error: {0}")]
+    Runtime(#[from] ort::Error),
+    #[error("The network's {0} output has the wrong size")]
+    Output(&'static str),
+}
+
+/// A policy and value network, run by the ONNX runtime. The runtime library
+/// is loaded when the first network is, from `ORT_DYLIB_PATH` if it is set.
+pub struct Network {
+    session: Mutex<Session>,
+}
+
+impl Network {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Network, NetworkError> {
+        let session = Session::builder()?.commit_from_file(path)?;
+        Ok(Network {
+            session: Mutex::new(session),
+        })
+    }
+
+    /// The network's logits for each of the `TURNS` turns, and its value of
+    /// the position for the player to act.
+    pub fn evaluate(&self, game: &Game<Move>) -> Result<(Vec<f32>, f32), NetworkError> {
+        let mut evaluations = self.evaluate_batch(&[*game])?;
+        evaluations.pop().ok_or(NetworkError::Output("value"))
+    }
+
+    /// Evaluate each of the games as `evaluate` does, in a single run of the
+    /// network.
+    pub fn evaluate_batch(
+        &self,
+        games: &[Game<Move>],
+    ) -> Result<Vec<(Vec<f32>, f32)>, NetworkError> {
+        if games.is_empty() {
+            return Ok(vec![]);
+        }
+        let shape = [
+            games.len() as i64,
+            PLANES as i64,
+            BOARD_HEIGHT.0 as i64,
+            BOARD_WIDTH.0 as i64,
+        ];
+        let planes: Vec<f32> = games.iter().flat_map(encode).collect();
+        let input = Tensor::from_array((shape, planes))?;
+        let mut session = self.session.lock().expect("Network poisoned!");
+        let outputs = session.run(ort::inputs![input])?;
+
+        let (_, policy) = outputs["policy"].try_extract_tensor::<f32>()?;
+        if policy.len() != TURNS * games.len() {
+            return Err(NetworkError::Output("policy"));
+        }
+        let (_, value) = outputs["value"].try_extract_tensor::<f32>()?;
+        if value.len() != games.len() {
+            return Err(NetworkError::Output("value"));
+        }
+        Ok(policy
+            .chunks(TURNS)
+            .map(<[f32]>::to_vec)
+            .zip(value.iter().copied())
+            .collect())
+    }
+}
+
+/// Scores nodes with the network's value instead of playing them out. Workers
+/// left to place are placed at random first. Only suited to two player games.
+pub struct NetworkSimulation {
+    pub network: Arc<Network>,
+}
+
+/// The result of a playout the network valued, for `player`, who moved to
+/// reach the node, or a win for them if the game was already over.
+fn network_result(start: Option<(Player, Game<Move>)>, value: Option<f32>) -> SimulationResult {
+    let score = match (start, value) {
+        (Some((player, game)), Some(value)) => {
+            let value = (value as f64).clamp(-1.0, 1.0);
+            if game.player() == player {
+                -value
+            } else {
+                value
+            }
+        }
+        _ => 1.0,
+    };
+    SimulationResult { score, plies: 0 }
+}
+
+impl<R: Rng> Simulation<SantoriniNode, R> for NetworkSimulation {
+    fn simulate(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        _stats: &mut MoveStats,
+    ) -> SimulationResult {
+        let start = playout_start(state.game, rng);
+        let value = start.map(|(_, game)| {
+            let (_, value) = self
+                .network
+                .evaluate(&game)
+                .expect("Network evaluation failed!");
+            value
+        });
+        network_result(start, value)
+    }
+
+    /// Value every state still being played in one run of the network.
+    fn simulate_batch(
+        &self,
+        states: &[SantoriniNode],
+        rng: &mut R,
+        _stats: &mut MoveStats,
+    ) -> Vec<(SimulationResult, Vec<u64>)> {
+        let starts: Vec<_> = states
+            .iter()
+            .map(|state| playout_start(state.game, rng))
+            .collect();
+        let games: Vec<Game<Move>> = starts.iter().flatten().map(|(_, game)| *game).collect();
+        let mut values = self
+            .network
+            .evaluate_batch(&games)
+            .expect("Network evaluation failed!")
+            .into_iter()
+            .map(|(_, value)| value);
+        starts
+            .into_iter()
+            .map(|start| {
+                let value = start.and_then(|_| values.next());
+                (network_result(start, value), vec![])
+            })
+            .collect()
+    }
+}
+
+/// Expands as `SantoriniExpansion` does, taking each turn's prior from the
+/// network's policy. Placements get even priors.
+pub struct NetworkExpansion {
+    pub network: Arc<Network>,
+}
+
+impl Expansion<SantoriniNode> for NetworkExpansion {
+    fn expand(&self, state: &SantoriniNode) -> Vec<SantoriniNode> {
+        SantoriniExpansion {}.expand(state)
+    }
+
+    fn expand_with_priors(&self, state: &SantoriniNode) -> Vec<(SantoriniNode, f64)> {
+        let game = match state.game {
+            NodeState::Move(game) => game,
+            _ => {
+                let children = self.expand(state);
+                let prior = 1.0 / children.len() as f64;
+                return children.into_iter().map(|child| (child, prior)).collect();
+            }
+        };
+        let (policy, _) = self
+            .network
+            .evaluate(&game)
+            .expect("Network evaluation failed!");
+
+        // Softmax over the legal turns, shifted by the best logit to keep the
+        // exponents small
+        let children = self.expand(state);
+        let logits: Vec<f64> = children
+            .iter()
+            .map(|child| turn_index(&game, child).map_or(0.0, |idx| policy[idx] as f64))
+            .collect();
+        let best = logits.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = logits.iter().map(|logit| (logit - best).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        children
+            .into_iter()
+            .zip(weights)
+            .map(|(child, weight)| (child, weight / total))
+            .collect()
+    }
+}
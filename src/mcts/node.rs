@@ -1,4 +1,5 @@
-use super::MctsParams;
+use super::{MctsParams, MoveId, SimulationResult};
+use rand::seq::SliceRandom;
 use rand::Rng;
 
 #[derive(Clone)]
@@ -6,57 +7,273 @@ pub struct Node<T> {
     pub children: Option<Vec<Node<T>>>,
     pub iterations: u32,
     pub score: f64,
+    /// The simulations that played this node's move later on, counted as if
+    /// it had been played here, for all-moves-as-first policies.
+    pub amaf_iterations: u32,
+    pub amaf_score: f64,
+    /// The expansion's estimate of how likely this node is to be its
+    /// parent's best child.
+    pub prior: f64,
+    /// Children held back by progressive widening, with their priors, to be
+    /// added as the node is visited more. The most likely come last.
+    pub pending: Vec<(T, f64)>,
     pub state: T,
 }
 
+/// The moves a simulation made and its result, from the perspective of the
+/// player who moved to reach the node being updated.
+struct Rollout {
+    moves: Vec<u64>,
+    score: f64,
+}
+
 impl<T> Node<T> {
+    /// The number of nodes in the tree rooted here.
+    pub fn size(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .flatten()
+            .map(|child| child.size())
+            .sum::<usize>()
+    }
+
+    /// Whether this node and every node below it have been expanded, with
+    /// no children held back, so searching here can't grow the tree.
+    pub fn fully_expanded(&self) -> bool {
+        self.pending.is_empty()
+            && match &self.children {
+                Some(children) => children.iter().all(|child| child.fully_expanded()),
+                None => false,
+            }
+    }
+
+    /// Free at least `count` nodes if possible by dropping the children of
+    /// the least visited nodes whose children are all leaves, returning
+    /// how many were freed. The nodes keep their own statistics, and are
+    /// expanded again if the search comes back to them. The root's children
+    /// are never dropped.
+    pub fn prune(&mut self, count: usize) -> usize {
+        let mut freed = 0;
+        while freed < count {
+            let mut frontier = vec![];
+            for (idx, child) in self.children.iter().flatten().enumerate() {
+                child.frontier(&mut vec![idx], &mut frontier);
+            }
+            if frontier.is_empty() {
+                break;
+            }
+
+            frontier.sort_by_key(|(iterations, _)| *iterations);
+            for (_, path) in frontier {
+                if freed >= count {
+                    break;
+                }
+                let node = path.iter().fold(&mut *self, |node, idx| {
+                    &mut node.children.as_mut().expect("Invalid path!")[*idx]
+                });
+                node.pending.clear();
+                freed += node.children.take().map_or(0, |children| children.len());
+            }
+        }
+        freed
+    }
+
+    /// Gather the paths to the nodes below this one whose children are all
+    /// leaves, along with how often they've been visited.
+    fn frontier(&self, path: &mut Vec<usize>, frontier: &mut Vec<(u32, Vec<usize>)>) {
+        let children = match &self.children {
+            Some(children) if !children.is_empty() => children,
+            _ => return,
+        };
+        let leaf = |child: &Node<T>| match &child.children {
+            Some(children) => children.is_empty(),
+            None => true,
+        };
+        if children.iter().all(leaf) {
+            frontier.push((self.iterations, path.clone()));
+            return;
+        }
+        for (idx, child) in children.iter().enumerate() {
+            path.push(idx);
+            child.frontier(path, frontier);
+            path.pop();
+        }
+    }
+}
+
+impl<T: MoveId> Node<T> {
     pub fn new<R: Rng>(params: &mut MctsParams<T, R>, state: T) -> Self {
-        let score = params.simulation.simulate(&state, &mut params.rng);
-        Node {
+        Node::simulated(params, state, 1.0).0
+    }
+
+    fn simulated<R: Rng>(params: &mut MctsParams<T, R>, state: T, prior: f64) -> (Self, Rollout) {
+        let (result, moves) =
+            params
+                .simulation
+                .simulate_moves(&state, &mut params.rng, &mut params.move_stats);
+        Node::from_result(params, state, prior, result, moves)
+    }
+
+    /// A leaf for a state that has been simulated, with the moves the
+    /// simulation made.
+    fn from_result<R: Rng>(
+        params: &MctsParams<T, R>,
+        state: T,
+        prior: f64,
+        result: SimulationResult,
+        mut moves: Vec<u64>,
+    ) -> (Self, Rollout) {
+        let score = params.discounted(result.score, result.plies);
+        moves.extend(state.move_id());
+        let node = Node {
             children: None,
             iterations: 1,
             score,
+            amaf_iterations: 0,
+            amaf_score: 0.0,
+            prior,
+            pending: Vec::new(),
             state,
-        }
+        };
+        (node, Rollout { moves, score })
     }
 
     pub fn expand<R: Rng>(&mut self, params: &mut MctsParams<T, R>) -> (u32, f64) {
+        let (new_nodes, new_scores, _) = self.expand_rollouts(params);
+        (new_nodes, new_scores)
+    }
+
+    fn expand_rollouts<R: Rng>(
+        &mut self,
+        params: &mut MctsParams<T, R>,
+    ) -> (u32, f64, Vec<Rollout>) {
         assert!(self.children.is_none(), "Node has already been expanded!");
 
+        let mut states = params.expansion.expand_with_priors(&self.state);
+        if let Some(widening) = params.widening {
+            // Hold back the least likely children, choosing at random between
+            // those that are as likely as each other
+            states.shuffle(&mut params.rng);
+            states.sort_by(|(_, a), (_, b)| b.partial_cmp(a).expect("Invalid prior!"));
+            let limit = widening.limit(self.iterations).min(states.len());
+            self.pending = states.split_off(limit);
+            self.pending.reverse();
+        }
+        self.children = Some(Vec::new());
+        self.add_children(params, states)
+    }
+
+    /// Whether progressive widening lets the node take another child.
+    fn can_widen<R: Rng>(&self, params: &MctsParams<T, R>) -> bool {
+        let children = self.children.as_ref().map_or(0, |children| children.len());
+        match params.widening {
+            Some(widening) => {
+                !self.pending.is_empty() && children < widening.limit(self.iterations)
+            }
+            None => false,
+        }
+    }
+
+    /// Simulate and add children to an expanded node, simulating them all
+    /// in one batch.
+    fn add_children<R: Rng>(
+        &mut self,
+        params: &mut MctsParams<T, R>,
+        states: Vec<(T, f64)>,
+    ) -> (u32, f64, Vec<Rollout>) {
+        let (states, priors): (Vec<T>, Vec<f64>) = states.into_iter().unzip();
+        let results =
+            params
+                .simulation
+                .simulate_batch(&states, &mut params.rng, &mut params.move_stats);
+        assert_eq!(results.len(), states.len(), "Invalid batch!");
+
         let mut children = Vec::new();
+        let mut rollouts = Vec::new();
         let mut new_scores: f64 = 0.0;
-        for child in params.expansion.expand(&self.state) {
-            let node = Node::new(params, child);
-            new_scores += -1.0 * node.score;
+        for ((child, prior), (result, moves)) in states.into_iter().zip(priors).zip(results) {
+            let (node, rollout) = Node::from_result(params, child, prior, result, moves);
+            new_scores += -params.discounted(node.score, 1);
             children.push(node);
+            rollouts.push(rollout);
         }
 
         let new_nodes = children.len() as u32;
         let new_score = self.score * (self.iterations as f64) + new_scores;
         self.iterations += new_nodes;
         self.score = new_score / (self.iterations as f64);
-        self.children = Some(children);
+        self.children
+            .as_mut()
+            .expect("Node hasn't been expanded!")
+            .extend(children);
 
-        (new_nodes, new_scores)
+        (new_nodes, new_scores, rollouts)
     }
 
     pub fn step<R: Rng>(&mut self, params: &mut MctsParams<T, R>) -> (u32, f64) {
-        match self.children.as_ref() {
-            None => self.expand(params),
+        let (count, delta, _) = self.step_rollouts(params);
+        (count, delta)
+    }
+
+    fn step_rollouts<R: Rng>(&mut self, params: &mut MctsParams<T, R>) -> (u32, f64, Vec<Rollout>) {
+        let (count, delta, rollouts) = match self.children.as_ref() {
+            None => self.expand_rollouts(params),
+            Some(_) if self.can_widen(params) => {
+                let state = self.pending.pop().expect("No children held back!");
+                self.add_children(params, vec![state])
+            }
             Some(children) => {
                 if children.len() == 0 {
-                    (0, 0.0)
+                    (0, 0.0, vec![])
                 } else {
                     let immutable_children: &Vec<Node<T>> = &*children;
                     let idx = params.tree_policy.select(self, immutable_children);
 
-                    let (count, delta) = self.children.as_mut().unwrap()[idx].step(params);
+                    let (count, delta, rollouts) =
+                        self.children.as_mut().unwrap()[idx].step_rollouts(params);
+                    let delta = params.discounted(delta, 1);
                     let new_score = self.score * self.iterations as f64 - delta;
                     self.iterations += count;
                     self.score = new_score / (self.iterations as f64);
-                    (count, -delta)
+                    (count, -delta, rollouts)
                 }
             }
+        };
+
+        if !params.tree_policy.uses_amaf() {
+            return (count, delta, vec![]);
+        }
+        self.update_amaf(&rollouts);
+
+        // Hand the simulations up from the perspective of this node's mover
+        let rollouts = rollouts
+            .into_iter()
+            .map(|mut rollout| {
+                rollout.moves.extend(self.state.move_id());
+                rollout.score = -rollout.score;
+                rollout
+            })
+            .collect();
+        (count, delta, rollouts)
+    }
+
+    /// Credit each child with the simulations below this node that played
+    /// its move.
+    fn update_amaf(&mut self, rollouts: &[Rollout]) {
+        for child in self.children.iter_mut().flatten() {
+            let id = match child.state.move_id() {
+                Some(id) => id,
+                None => continue,
+            };
+            for rollout in rollouts
+                .iter()
+                .filter(|rollout| rollout.moves.contains(&id))
+            {
+                let total = child.amaf_score * child.amaf_iterations as f64 + rollout.score;
+                child.amaf_iterations += 1;
+                child.amaf_score = total / child.amaf_iterations as f64;
+            }
         }
     }
 }
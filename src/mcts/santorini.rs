@@ -1,66 +1,215 @@
-use super::{Expansion, Simulation};
-use crate::santorini::{ActionResult, BuildAction, Game, Move, MoveAction, Player};
-use rand::seq::SliceRandom;
+use super::{Expansion, MoveId, MoveStats, Simulation, SimulationResult};
+use crate::player::heuristic_ai::evaluate;
+use crate::santorini::{
+    ActionResult, AnyGame, Build, BuildAction, ExtraMoveAction, Game, Move, MoveAction, PlaceOne,
+    PlaceResult, PlaceTwo, Player, Point, PreMoveAction, Turn, TurnActions, WorkerLocs,
+    BOARD_WIDTH,
+};
+use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
+use std::fmt;
+use std::iter;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum NodeState {
+    PlaceOne(Game<PlaceOne>),
+    PlaceTwo(Game<PlaceTwo>),
     Move(Game<Move>),
     Victory(Player),
 }
 
+impl From<PlaceResult> for NodeState {
+    fn from(result: PlaceResult) -> NodeState {
+        match result {
+            PlaceResult::Place(game) => NodeState::PlaceTwo(game),
+            PlaceResult::Move(game) => NodeState::Move(game),
+        }
+    }
+}
+
+impl From<ActionResult<Move>> for NodeState {
+    fn from(result: ActionResult<Move>) -> NodeState {
+        match result {
+            ActionResult::Victory(game) => NodeState::Victory(game.player()),
+            ActionResult::Continue(game) => NodeState::Move(game),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SantoriniNode {
+    /// Where the workers were placed, for nodes reached by a placement.
+    pub placement: Option<WorkerLocs>,
+    pub pre_move: Option<PreMoveAction>,
     pub mv: Option<MoveAction>,
+    pub extra_move: Option<ExtraMoveAction>,
+    pub other_move: Option<ExtraMoveAction>,
     pub build: Option<BuildAction>,
+    pub extra_build: Option<BuildAction>,
+    /// Identifies the turn that reached this node, for sharing statistics
+    /// between the places it is played.
+    pub turn_id: Option<u64>,
     pub game: NodeState,
 }
 
-impl From<Game<Move>> for SantoriniNode {
-    fn from(game: Game<Move>) -> SantoriniNode {
+impl From<NodeState> for SantoriniNode {
+    fn from(game: NodeState) -> SantoriniNode {
         SantoriniNode {
+            placement: None,
+            pre_move: None,
             mv: None,
+            extra_move: None,
+            other_move: None,
             build: None,
-            game: NodeState::Move(game),
+            extra_build: None,
+            turn_id: None,
+            game,
         }
     }
 }
 
+impl From<Game<PlaceOne>> for SantoriniNode {
+    fn from(game: Game<PlaceOne>) -> SantoriniNode {
+        NodeState::PlaceOne(game).into()
+    }
+}
+
+impl From<Game<PlaceTwo>> for SantoriniNode {
+    fn from(game: Game<PlaceTwo>) -> SantoriniNode {
+        NodeState::PlaceTwo(game).into()
+    }
+}
+
+impl From<Game<Move>> for SantoriniNode {
+    fn from(game: Game<Move>) -> SantoriniNode {
+        NodeState::Move(game).into()
+    }
+}
+
+impl MoveId for SantoriniNode {
+    fn move_id(&self) -> Option<u64> {
+        self.turn_id
+    }
+}
+
+/// Identifies a turn by who took it, where they moved and where they built.
+/// Powers used along the way are left out, as they are rarely what makes the
+/// same turn good in different positions.
+fn turn_id(player: Player, mv: MoveAction, build: Option<BuildAction>) -> u64 {
+    let square = |loc: Point| (*loc.y() * BOARD_WIDTH.0 + *loc.x()) as u64;
+    let build = build.map_or(0, |build| square(build.loc()) + 1);
+    (player.index() as u64) << 24 | square(mv.from()) << 16 | square(mv.to()) << 8 | build
+}
+
 impl SantoriniNode {
-    pub fn matches(&self, game: Game<Move>) -> bool {
-        match self.game {
-            NodeState::Move(g) => g == game,
+    /// The turn that reached this node, for nodes reached by one.
+    pub fn turn(&self) -> Option<Turn> {
+        if let Some(locs) = self.placement {
+            return Some(Turn::Place(locs));
+        }
+        Some(self.actions()?.into())
+    }
+
+    /// The actions of the turn that reached this node, for nodes reached by
+    /// a move.
+    pub fn actions(&self) -> Option<TurnActions> {
+        Some(TurnActions {
+            pre_move: self.pre_move,
+            mv: self.mv?,
+            extra_move: self.extra_move,
+            other_move: self.other_move,
+            build: self.build,
+            extra_build: self.extra_build,
+        })
+    }
+
+    pub fn matches<G: Into<AnyGame>>(&self, game: G) -> bool {
+        match (self.game, game.into()) {
+            (NodeState::PlaceOne(g), AnyGame::PlaceOne(game)) => g.same_position(&game),
+            (NodeState::PlaceTwo(g), AnyGame::PlaceTwo(game)) => g.same_position(&game),
+            (NodeState::Move(g), AnyGame::Move(game)) => g.same_position(&game),
             _ => false,
         }
     }
 }
 
-pub struct SantoriniSimulation {}
+/// Written as the turn that reached the node, or `root` for nodes not
+/// reached by one.
+impl fmt::Display for SantoriniNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.turn() {
+            Some(turn) => write!(f, "{}", turn),
+            None => write!(f, "root"),
+        }
+    }
+}
 
-enum PossibleAction {
+/// The player to act in a node's game, and the game once any workers left
+/// to place have been placed at random. None if the game is over.
+pub(crate) fn playout_start<R: Rng>(state: NodeState, rng: &mut R) -> Option<(Player, Game<Move>)> {
+    let (player, mut game) = match state {
+        NodeState::PlaceOne(game) => {
+            let placement = game.placements().choose(rng).expect("Nowhere to place!");
+            (game.player(), game.apply(placement))
+        }
+        NodeState::PlaceTwo(game) => (game.player(), game),
+        NodeState::Move(game) => return Some((game.player(), game)),
+        NodeState::Victory(_) => return None,
+    };
+    loop {
+        let placement = game.placements().choose(rng).expect("Nowhere to place!");
+        match game.apply(placement) {
+            PlaceResult::Place(next) => game = next,
+            PlaceResult::Move(next) => return Some((player, next)),
+        }
+    }
+}
+
+/// The turn a rollout policy chose.
+pub enum PossibleAction {
+    /// The player to act can win, so the playout is over.
     Victory,
-    Continue(Game<Move>),
+    /// The game after the chosen turn, and the id of that turn.
+    Continue(Game<Move>, Option<u64>),
+}
+
+/// The ways the workers can be ready to build after moving: where they are,
+/// or after any extra move the god allows.
+fn extra_moves(
+    game: Game<Build>,
+) -> impl Iterator<Item = (Option<ExtraMoveAction>, ActionResult<Build>)> {
+    let pawns = game.active_pawns();
+    let moves: Vec<_> = pawns.iter().flat_map(|pawn| pawn.moves()).collect();
+    iter::once((None, ActionResult::Continue(game))).chain(
+        moves
+            .into_iter()
+            .map(move |extra| (Some(extra), game.apply_move(extra))),
+    )
 }
 
-fn find_action<R: Rng>(game: Game<Move>, rng: &mut R) -> PossibleAction {
-    let mut choice = game;
-    let mut count = 0.0;
+/// Call `visit` with the id of every turn in the game and where it leads,
+/// stopping early to return true if one of them wins.
+fn visit_turns(game: Game<Move>, mut visit: impl FnMut(u64, Game<Move>)) -> bool {
+    let player = game.player();
     for mv in game
         .active_pawns()
         .iter()
         .map(|pawn| pawn.actions())
         .flatten()
     {
-        match game.apply(mv) {
-            ActionResult::Victory(_) => return PossibleAction::Victory,
-            ActionResult::Continue(game) => {
-                for build in game.active_pawn().actions() {
-                    match game.apply(build) {
-                        ActionResult::Victory(_) => return PossibleAction::Victory,
-                        ActionResult::Continue(game) => {
-                            count += 1.0;
-                            if rng.gen::<f64>() < 1.0 / count {
-                                choice = game;
+        let game = match game.apply(mv) {
+            ActionResult::Victory(_) => return true,
+            ActionResult::Continue(game) => game,
+        };
+        for (_, result) in extra_moves(game) {
+            match result {
+                ActionResult::Victory(_) => return true,
+                ActionResult::Continue(game) => {
+                    for build in game.build_actions() {
+                        match game.apply(build) {
+                            ActionResult::Victory(_) => return true,
+                            ActionResult::Continue(game) => {
+                                visit(turn_id(player, mv, Some(build)), game)
                             }
                         }
                     }
@@ -68,97 +217,271 @@ fn find_action<R: Rng>(game: Game<Move>, rng: &mut R) -> PossibleAction {
             }
         }
     }
-    PossibleAction::Continue(choice)
+    false
+}
+
+/// How a playout chooses each player's turn.
+pub trait RolloutPolicy: Send {
+    /// Choose a turn for the player to act, winning if they can. `stats`
+    /// holds what the search has learnt about each turn so far.
+    fn select<R: Rng>(&self, game: Game<Move>, rng: &mut R, stats: &MoveStats) -> PossibleAction;
+
+    /// Whether the turns of each playout should be recorded in the search's
+    /// statistics.
+    fn learns(&self) -> bool {
+        false
+    }
 }
 
+/// Takes a turn at random.
+pub struct RandomPolicy {}
+
+impl RolloutPolicy for RandomPolicy {
+    fn select<R: Rng>(&self, game: Game<Move>, rng: &mut R, _stats: &MoveStats) -> PossibleAction {
+        let mut choice = (game, None);
+        let mut count = 0.0;
+        let won = visit_turns(game, |id, game| {
+            count += 1.0;
+            if rng.gen::<f64>() < 1.0 / count {
+                choice = (game, Some(id));
+            }
+        });
+        if won {
+            return PossibleAction::Victory;
+        }
+        PossibleAction::Continue(choice.0, choice.1)
+    }
+}
+
+/// Takes the turn the heuristic evaluation likes best, except for a random
+/// turn with probability `epsilon`. Only suited to two player games.
+pub struct EpsilonGreedyPolicy {
+    pub epsilon: f64,
+}
+
+impl RolloutPolicy for EpsilonGreedyPolicy {
+    fn select<R: Rng>(&self, game: Game<Move>, rng: &mut R, stats: &MoveStats) -> PossibleAction {
+        if rng.gen::<f64>() < self.epsilon {
+            return RandomPolicy {}.select(game, rng, stats);
+        }
+
+        let player = game.player();
+        let mut best = (f64::NEG_INFINITY, game, None);
+        let won = visit_turns(game, |id, game| {
+            let score = evaluate(&game, player).total();
+            if score > best.0 {
+                best = (score, game, Some(id));
+            }
+        });
+        if won {
+            return PossibleAction::Victory;
+        }
+        PossibleAction::Continue(best.1, best.2)
+    }
+}
+
+/// Takes a turn at random, favouring the turns that have done well in earlier
+/// playouts by Move-Average Sampling: each is weighted by
+/// `exp(value / temperature)`, where turns not yet played are valued at zero.
+pub struct MastPolicy {
+    /// How far apart the average results of turns have to be for one to be
+    /// much more likely than another. Higher temperatures play more at random.
+    pub temperature: f64,
+}
+
+impl RolloutPolicy for MastPolicy {
+    fn select<R: Rng>(&self, game: Game<Move>, rng: &mut R, stats: &MoveStats) -> PossibleAction {
+        let mut turns = vec![];
+        let won = visit_turns(game, |id, game| {
+            let value = stats.value(id).unwrap_or(0.0);
+            turns.push(((value / self.temperature).exp(), id, game));
+        });
+        if won {
+            return PossibleAction::Victory;
+        }
+
+        let total: f64 = turns.iter().map(|(weight, _, _)| weight).sum();
+        let mut pick = rng.gen::<f64>() * total;
+        for (weight, id, game) in &turns {
+            if pick < *weight {
+                return PossibleAction::Continue(*game, Some(*id));
+            }
+            pick -= weight;
+        }
+        // Rounding can leave a little over, which belongs to the last turn
+        match turns.last() {
+            Some((_, id, game)) => PossibleAction::Continue(*game, Some(*id)),
+            None => PossibleAction::Continue(game, None),
+        }
+    }
+
+    fn learns(&self) -> bool {
+        true
+    }
+}
+
+/// Plays out games, choosing each turn with a rollout policy. Games still
+/// going after `plies` turns, if given, are scored by the heuristic
+/// evaluation, which is only suited to two player games.
+///
+/// Scores as `SantoriniSimulation` does.
+pub struct RolloutSimulation<P> {
+    pub policy: P,
+    pub plies: Option<u32>,
+}
+
+impl<P: RolloutPolicy, R: Rng> Simulation<SantoriniNode, R> for RolloutSimulation<P> {
+    fn simulate(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> SimulationResult {
+        self.simulate_moves(state, rng, stats).0
+    }
+
+    fn simulate_moves(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> (SimulationResult, Vec<u64>) {
+        let (player, mut game) = match playout_start(state.game, rng) {
+            Some(start) => start,
+            None => {
+                let result = SimulationResult {
+                    score: 1.0,
+                    plies: 0,
+                };
+                return (result, vec![]);
+            }
+        };
+
+        // The turns taken, and whether the player to act at the start took
+        // them
+        let mut turns = vec![];
+        let mut played = 0;
+        let score = loop {
+            if Some(played) == self.plies {
+                break -evaluate(&game, player).total().clamp(-1.0, 1.0);
+            }
+            played += 1;
+            match self.policy.select(game, rng, stats) {
+                PossibleAction::Victory => {
+                    break if game.player() == player { -1.0 } else { 1.0 };
+                }
+                PossibleAction::Continue(choice, id) => {
+                    turns.extend(id.map(|id| (id, game.player() == player)));
+                    game = choice;
+                }
+            }
+        };
+
+        if self.policy.learns() {
+            for (id, first) in &turns {
+                stats.record(*id, if *first { -score } else { score });
+            }
+        }
+        let result = SimulationResult {
+            score,
+            plies: played,
+        };
+        (result, turns.into_iter().map(|(id, _)| id).collect())
+    }
+}
+
+pub struct SantoriniSimulation {}
+
 impl<R: Rng> Simulation<SantoriniNode, R> for SantoriniSimulation {
     /// Move for each player until the game ends according to the following policy:
     ///   1. If there exists a winning action, take it.
     ///   2. Otherwise, pick a random action.
     ///
-    /// Returns -1.0 if the active player in the provided game wins and 1.0 if the
+    /// Scores -1.0 if the active player in the provided game wins and 1.0 if the
     /// other player wins.
     ///
     /// In other words, we return 1.0 if the player who moved to get to this state
     /// wins---which is what we want because in MCTS we consider Games from the
     /// perspective of the previous turn.
-    fn simulate(&self, state: &SantoriniNode, rng: &mut R) -> f64 {
-        let mut game = match state.game {
-            NodeState::Victory(_) => return 1.0,
-            NodeState::Move(game) => game,
-        };
-
-        let player = game.player();
+    fn simulate(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> SimulationResult {
+        self.simulate_moves(state, rng, stats).0
+    }
 
-        loop {
-            match find_action(game, rng) {
-                PossibleAction::Victory => return if game.player() == player { -1.0 } else { 1.0 },
-                PossibleAction::Continue(choice) => game = choice,
-            }
-        }
+    /// Simulate as `simulate` does, listing the ids of the turns taken.
+    fn simulate_moves(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> (SimulationResult, Vec<u64>) {
+        let simulation = RolloutSimulation {
+            policy: RandomPolicy {},
+            plies: None,
+        };
+        simulation.simulate_moves(state, rng, stats)
     }
 }
 
-pub struct ExtendedSantoriniSimulation {}
-
-fn possible_actions<'a>(
-    game: &'a Game<Move>,
-) -> impl Iterator<
-    Item = (
-        (Option<MoveAction>, Option<BuildAction>),
-        ActionResult<Move>,
-    ),
-> + 'a {
-    game.active_pawns()
-        .to_vec()
-        .into_iter()
-        .map(|pawn| pawn.actions())
-        .flatten()
-        .map(move |mv| match game.apply(mv) {
-            ActionResult::Victory(game) => vec![((Some(mv), None), ActionResult::Victory(game))],
-            ActionResult::Continue(game) => game
-                .active_pawn()
-                .actions()
-                .map(|build| ((Some(mv), Some(build)), game.apply(build)))
-                .collect(),
-        })
-        .flatten()
+/// Plays out games choosing turns with a rollout policy, but when a player is
+/// about to win, looks back for a turn the other player could have taken to
+/// stop them and plays that instead.
+pub struct ExtendedSantoriniSimulation<P> {
+    pub policy: P,
 }
 
-impl<R: Rng> Simulation<SantoriniNode, R> for ExtendedSantoriniSimulation {
-    fn simulate(&self, state: &SantoriniNode, rng: &mut R) -> f64 {
-        let mut game = match state.game {
-            NodeState::Victory(_) => return 1.0,
-            NodeState::Move(game) => game,
+impl<P: RolloutPolicy, R: Rng> Simulation<SantoriniNode, R> for ExtendedSantoriniSimulation<P> {
+    fn simulate(
+        &self,
+        state: &SantoriniNode,
+        rng: &mut R,
+        stats: &mut MoveStats,
+    ) -> SimulationResult {
+        let (player, mut game) = match playout_start(state.game, rng) {
+            Some(start) => start,
+            None => {
+                return SimulationResult {
+                    score: 1.0,
+                    plies: 0,
+                }
+            }
+        };
+        let settled = |game: Game<Move>, plies| SimulationResult {
+            score: if game.player() == player { -1.0 } else { 1.0 },
+            plies,
         };
-
-        let player = game.player();
 
         let mut previous = game;
 
-        match find_action(game, rng) {
-            PossibleAction::Victory => return if game.player() == player { -1.0 } else { 1.0 },
-            PossibleAction::Continue(choice) => game = choice,
+        match self.policy.select(game, rng, stats) {
+            PossibleAction::Victory => return settled(game, 1),
+            PossibleAction::Continue(choice, _) => game = choice,
         }
 
+        let mut played = 1;
         loop {
-            match find_action(game, rng) {
-                PossibleAction::Continue(choice) => {
+            played += 1;
+            match self.policy.select(game, rng, stats) {
+                PossibleAction::Continue(choice, _) => {
                     previous = game;
                     game = choice;
                 }
                 PossibleAction::Victory => {
                     // Back track to see if this could be avoided
-                    let mut actions: Vec<_> = possible_actions(&previous).collect();
+                    let mut actions: Vec<_> = previous.legal_turns().collect();
                     &mut actions.shuffle(rng);
                     let mut found = false;
                     for (_, result) in actions {
                         // We know this can't be a winning move, otherwise we would have
                         // already taken it instead of getting here.
                         let new_game = result.unwrap();
-                        match find_action(new_game, rng) {
+                        match self.policy.select(new_game, rng, stats) {
                             PossibleAction::Victory => (),
-                            PossibleAction::Continue(choice) => {
+                            PossibleAction::Continue(choice, _) => {
                                 // Found a blocking move
                                 previous = new_game;
                                 game = choice;
@@ -170,7 +493,7 @@ impl<R: Rng> Simulation<SantoriniNode, R> for ExtendedSantoriniSimulation {
 
                     // Legitimate win
                     if !found {
-                        return if game.player() == player { -1.0 } else { 1.0 };
+                        return settled(game, played);
                     }
                 }
             }
@@ -184,16 +507,81 @@ impl Expansion<SantoriniNode> for SantoriniExpansion {
     fn expand(&self, state: &SantoriniNode) -> Vec<SantoriniNode> {
         match state.game {
             NodeState::Victory(_) => vec![],
-            NodeState::Move(game) => possible_actions(&game)
-                .map(|((mv, build), result)| SantoriniNode {
-                    mv,
-                    build,
-                    game: match result {
-                        ActionResult::Victory(game) => NodeState::Victory(game.player()),
-                        ActionResult::Continue(game) => NodeState::Move(game),
-                    },
+            NodeState::PlaceOne(game) => game
+                .placements()
+                .map(|placement| SantoriniNode {
+                    placement: Some(placement.locs()),
+                    ..NodeState::PlaceTwo(game.apply(placement)).into()
+                })
+                .collect(),
+            NodeState::PlaceTwo(game) => game
+                .placements()
+                .map(|placement| SantoriniNode {
+                    placement: Some(placement.locs()),
+                    ..NodeState::from(game.apply(placement)).into()
+                })
+                .collect(),
+            NodeState::Move(game) => game
+                .legal_turns()
+                .map(|(turn, result)| SantoriniNode {
+                    placement: None,
+                    turn_id: Some(turn_id(game.player(), turn.mv, turn.build)),
+                    pre_move: turn.pre_move,
+                    mv: Some(turn.mv),
+                    extra_move: turn.extra_move,
+                    other_move: turn.other_move,
+                    build: turn.build,
+                    extra_build: turn.extra_build,
+                    game: result.into(),
                 })
                 .collect(),
         }
     }
 }
+
+/// Expands as `SantoriniExpansion` does, giving each turn a prior from the
+/// heuristic evaluation of where it leads. Placements and games of more than
+/// two players get even priors.
+pub struct HeuristicExpansion {
+    /// How far apart evaluations have to be for one turn to be much more
+    /// likely than another. Higher temperatures even the priors out.
+    pub temperature: f64,
+}
+
+/// The evaluation given to winning turns, above any other evaluation.
+const VICTORY_SCORE: f64 = 2.0;
+
+impl Expansion<SantoriniNode> for HeuristicExpansion {
+    fn expand(&self, state: &SantoriniNode) -> Vec<SantoriniNode> {
+        SantoriniExpansion {}.expand(state)
+    }
+
+    fn expand_with_priors(&self, state: &SantoriniNode) -> Vec<(SantoriniNode, f64)> {
+        let player = match state.game {
+            NodeState::Move(game) if game.player_count() == 2 => Some(game.player()),
+            _ => None,
+        };
+        let children = self.expand(state);
+        let scores: Vec<f64> = children
+            .iter()
+            .map(|child| match (child.game, player) {
+                (NodeState::Victory(_), Some(_)) => VICTORY_SCORE,
+                (NodeState::Move(game), Some(player)) => evaluate(&game, player).total(),
+                _ => 0.0,
+            })
+            .collect();
+
+        // Softmax, shifted by the best score to keep the exponents small
+        let best = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = scores
+            .iter()
+            .map(|score| ((score - best) / self.temperature).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        children
+            .into_iter()
+            .zip(weights)
+            .map(|(child, weight)| (child, weight / total))
+            .collect()
+    }
+}
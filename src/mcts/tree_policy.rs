@@ -2,6 +2,12 @@ use super::Node;
 
 pub trait TreePolicy<T>: Send {
     fn select(&self, parent: &Node<T>, children: &Vec<Node<T>>) -> usize;
+
+    /// Whether the policy reads the all-moves-as-first statistics, which are
+    /// only kept up to date for policies that do.
+    fn uses_amaf(&self) -> bool {
+        false
+    }
 }
 
 pub struct UCB1 {
@@ -47,8 +53,14 @@ impl<T> TreePolicy<T> for UCB1 {
     }
 }
 
+/// PUCT, which explores children in proportion to their priors. The priors
+/// are scaled so that even ones explore as much as one would without them.
 pub struct PUCT {
     pub parameter: f64,
+    /// First play urgency: the score, between -1 and 1, given to children
+    /// that have never been selected, in place of the single rollout made
+    /// when they were added. None to go by the rollout.
+    pub fpu: Option<f64>,
 }
 
 impl<T> TreePolicy<T> for PUCT {
@@ -56,11 +68,16 @@ impl<T> TreePolicy<T> for PUCT {
         let mut best_index = None;
         let mut best_weight = None;
         for (index, child) in children.iter().enumerate() {
+            let score = match self.fpu {
+                Some(fpu) if child.children.is_none() => fpu,
+                _ => child.score,
+            };
             // Rescale to be between 0 and 1
-            let child_score = (1.0 + child.score) / 2.0;
+            let child_score = (1.0 + score) / 2.0;
 
+            let prior = child.prior * children.len() as f64;
             let augment = f64::sqrt(parent.iterations as f64);
-            let augment = augment / (child.iterations as f64);
+            let augment = prior * augment / (child.iterations as f64);
             let weight = child_score + self.parameter * augment;
             match best_weight {
                 None => {
@@ -79,3 +96,107 @@ impl<T> TreePolicy<T> for PUCT {
         best_index.expect("No children!")
     }
 }
+
+/// UCB1 blended with all-moves-as-first statistics, which credit a move with
+/// every simulation that played it later on. These estimates are rough but
+/// plentiful, so they carry a child until it has been visited around
+/// `equivalence` times itself.
+pub struct RAVE {
+    pub parameter: f64,
+    pub equivalence: f64,
+}
+
+impl Default for RAVE {
+    fn default() -> RAVE {
+        RAVE {
+            parameter: f64::sqrt(2.0),
+            equivalence: 1000.0,
+        }
+    }
+}
+
+impl<T> TreePolicy<T> for RAVE {
+    fn select(&self, parent: &Node<T>, children: &Vec<Node<T>>) -> usize {
+        let mut best_index = None;
+        let mut best_weight = None;
+        for (index, child) in children.iter().enumerate() {
+            // Rescale to be between 0 and 1
+            let child_score = (1.0 + child.score) / 2.0;
+            let amaf_score = (1.0 + child.amaf_score) / 2.0;
+
+            let iterations = child.iterations as f64;
+            let beta = if child.amaf_iterations == 0 {
+                0.0
+            } else {
+                f64::sqrt(self.equivalence / (3.0 * iterations + self.equivalence))
+            };
+            let score = (1.0 - beta) * child_score + beta * amaf_score;
+
+            let augment = f64::ln(parent.iterations as f64);
+            let augment = augment / iterations;
+            let augment = f64::sqrt(augment);
+
+            let weight = score + self.parameter * augment;
+            match best_weight {
+                None => {
+                    best_weight = Some(weight);
+                    best_index = Some(index);
+                }
+                Some(best) => {
+                    if weight > best {
+                        best_weight = Some(weight);
+                        best_index = Some(index);
+                    }
+                }
+            }
+        }
+
+        best_index.expect("No children!")
+    }
+
+    fn uses_amaf(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tree_policy_tests {
+    use super::*;
+
+    fn node(iterations: u32, score: f64, expanded: bool) -> Node<()> {
+        Node {
+            children: if expanded { Some(vec![]) } else { None },
+            iterations,
+            score,
+            amaf_iterations: 0,
+            amaf_score: 0.0,
+            prior: 0.5,
+            pending: vec![],
+            state: (),
+        }
+    }
+
+    #[test]
+    fn first_play_urgency() {
+        let parent = node(12, 0.0, true);
+        // A visited child doing fairly well, and an unvisited one whose only
+        // rollout went badly
+        let children = vec![node(10, 0.2, true), node(1, -1.0, false)];
+
+        let rollouts = PUCT {
+            parameter: 0.1,
+            fpu: None,
+        };
+        assert_eq!(rollouts.select(&parent, &children), 0);
+        let urgent = PUCT {
+            parameter: 0.1,
+            fpu: Some(1.0),
+        };
+        assert_eq!(urgent.select(&parent, &children), 1);
+        let cautious = PUCT {
+            parameter: 0.1,
+            fpu: Some(-1.0),
+        };
+        assert_eq!(cautious.select(&parent, &children), 0);
+    }
+}
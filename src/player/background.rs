@@ -0,0 +1,237 @@
+use std::panic;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::mcts::Interrupt;
+use crate::player::{FullPlayer, Player, Progress, SearchInfo, StepResult};
+use crate::santorini::{self, Board, Game, GameState, Point};
+use crate::ui::{BoardWidget, UpdateError};
+
+/// Wraps an AI so each of its steps runs on a thread of its own.
+///
+/// Stepping starts the step and returns `StepResult::InProgress` until it's
+/// done, so the UI can keep drawing and reading keys in the meantime. Asking
+/// the player to stop has a searching AI finish with what it has found.
+pub struct Background {
+    /// The player, whenever it isn't off stepping.
+    player: Option<Box<dyn FullPlayer>>,
+    interrupt: Arc<Interrupt>,
+    running: Option<Running>,
+}
+
+type Stepped = (Box<dyn FullPlayer>, Result<StepResult, UpdateError>);
+
+struct Running {
+    started: Instant,
+    handle: JoinHandle<Stepped>,
+    /// What the player drew before it was sent off.
+    snapshot: Snapshot,
+}
+
+/// A board widget that owns its highlights.
+struct Snapshot {
+    board: Board,
+    player: santorini::Player,
+    cursor: Option<Point>,
+    highlights: Vec<Point>,
+    locs: [Vec<Point>; 3],
+}
+
+impl From<BoardWidget<'_>> for Snapshot {
+    fn from(widget: BoardWidget) -> Snapshot {
+        Snapshot {
+            board: widget.board,
+            player: widget.player,
+            cursor: widget.cursor,
+            highlights: widget.highlights.clone(),
+            locs: [
+                widget.player1_locs,
+                widget.player2_locs,
+                widget.player3_locs,
+            ],
+        }
+    }
+}
+
+impl Snapshot {
+    fn widget(&self) -> BoardWidget<'_> {
+        BoardWidget {
+            board: self.board,
+            player: self.player,
+            cursor: self.cursor,
+
+            highlights: &self.highlights,
+            suggestions: vec![],
+            player1_locs: self.locs[0].clone(),
+            player2_locs: self.locs[1].clone(),
+            player3_locs: self.locs[2].clone(),
+        }
+    }
+}
+
+impl Background {
+    pub fn new(player: Box<dyn FullPlayer>) -> Self {
+        Background {
+            player: Some(player),
+            interrupt: Arc::new(Interrupt::new()),
+            running: None,
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn FullPlayer> {
+        Box::new(self)
+    }
+}
+
+impl<T> Player<T> for Background
+where
+    T: GameState + Copy + Send + 'static,
+    dyn FullPlayer: Player<T>,
+{
+    fn prepare(&mut self, game: &Game<T>) {
+        let player = self.player.as_mut().expect("Prepared while stepping!");
+        Player::<T>::prepare(&mut **player, game);
+    }
+
+    fn render(&self, game: &Game<T>) -> BoardWidget<'_> {
+        match (&self.player, &self.running) {
+            (Some(player), _) => Player::<T>::render(&**player, game),
+            (None, Some(running)) => running.snapshot.widget(),
+            (None, None) => unreachable!("The player was lost!"),
+        }
+    }
+
+    fn step(&mut self, game: &Game<T>) -> Result<StepResult, UpdateError> {
+        if let Some(running) = &self.running {
+            if !running.handle.is_finished() {
+                return Ok(StepResult::InProgress(Progress {
+                    elapsed: running.started.elapsed(),
+                    simulations: self.interrupt.iterations(),
+                }));
+            }
+            let running = self.running.take().expect("Missing step!");
+            return match running.handle.join() {
+                Ok((player, result)) => {
+                    self.player = Some(player);
+                    result
+                }
+                Err(err) => panic::resume_unwind(err),
+            };
+        }
+
+        let mut player = self.player.take().expect("The player was lost!");
+        let snapshot = Player::<T>::render(&*player, game).into();
+        self.interrupt.reset();
+        Player::<T>::attach(&mut *player, &self.interrupt);
+        let game = *game;
+        self.running = Some(Running {
+            started: Instant::now(),
+            handle: thread::spawn(move || {
+                let result = Player::<T>::step(&mut *player, &game);
+                (player, result)
+            }),
+            snapshot,
+        });
+        Ok(StepResult::InProgress(Progress {
+            elapsed: Default::default(),
+            simulations: 0,
+        }))
+    }
+
+    fn evaluation(&self) -> Option<f64> {
+        self.player
+            .as_ref()
+            .and_then(|player| Player::<T>::evaluation(&**player))
+    }
+
+    fn claim(&self) -> Option<u32> {
+        self.player
+            .as_ref()
+            .and_then(|player| Player::<T>::claim(&**player))
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.player
+            .as_ref()
+            .and_then(|player| Player::<T>::search_info(&**player))
+    }
+
+    fn attach(&mut self, interrupt: &Arc<Interrupt>) {
+        self.interrupt = Arc::clone(interrupt);
+    }
+
+    fn stop(&self) {
+        self.interrupt.stop();
+    }
+}
+
+impl Drop for Background {
+    /// Don't leave a search running for a game that's gone.
+    fn drop(&mut self) {
+        self.interrupt.stop();
+    }
+}
+
+#[cfg(test)]
+mod background_tests {
+    use super::*;
+    use crate::mcts::Budget;
+    use crate::player::{MctsSantoriniParams, RandomAI};
+    use crate::santorini::{GameBuilder, God, Move, Point, BOARD_HEIGHT, BOARD_WIDTH};
+    use std::time::Duration;
+
+    /// Step until the step is done, calling `waiting` with the progress
+    /// until then.
+    fn finish<T, F>(player: &mut Background, game: &Game<T>, mut waiting: F) -> StepResult
+    where
+        T: GameState + Copy + Send + 'static,
+        dyn FullPlayer: Player<T>,
+        F: FnMut(&Background, Progress),
+    {
+        loop {
+            match Player::<T>::step(player, game).unwrap() {
+                StepResult::InProgress(progress) => waiting(player, progress),
+                result => return result,
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn steps_in_the_background() {
+        let mut player = Background::new(RandomAI::with_seed(0));
+        let game = santorini::new_game();
+        let result = finish(&mut player, &game, |player, _| {
+            assert!(player.player.is_none());
+        });
+        assert!(matches!(result, StepResult::PlaceTwo(_)));
+        assert!(player.player.is_some());
+    }
+
+    #[test]
+    fn stopping_a_search() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let game = GameBuilder::new([God::Mortal; 2])
+            .workers(santorini::Player::PlayerOne, &[pt(0, 0), pt(2, 2)])
+            .workers(santorini::Player::PlayerTwo, &[pt(w - 1, 0), pt(1, h - 2)])
+            .build()
+            .unwrap();
+        let params =
+            MctsSantoriniParams::with_seed(0).budget(Budget::Time(Duration::from_secs(60)));
+        let mut player = Background::new(params.boxed());
+        let started = Instant::now();
+        let mut simulations = 0;
+        let result = finish(&mut player, &game, |player, progress| {
+            simulations = progress.simulations;
+            if simulations > 0 {
+                Player::<Move>::stop(player);
+            }
+        });
+        assert!(matches!(result, StepResult::Build(_)));
+        assert!(started.elapsed() < Duration::from_secs(60));
+        let info = Player::<Move>::search_info(&player).unwrap();
+        assert!(info.simulations >= simulations);
+    }
+}
@@ -0,0 +1,80 @@
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+
+use crate::mcts::santorini::{HeuristicExpansion, RandomPolicy, RolloutSimulation};
+use crate::mcts::tree_policy::PUCT;
+use crate::mcts::MctsParams;
+use crate::player::{FullPlayer, MctsAI, MctsSantoriniParams, OpeningBook, RandomAI, Thresholds};
+
+/// How strong an opponent the computer makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Plays at random.
+    Easy,
+    /// A short MCTS search.
+    Medium,
+    /// MCTS guided by the heuristic evaluation, both in which turns it tries
+    /// first and in scoring its playouts, which are cut short. Uses the
+    /// opening book, resigns lost games and announces forced wins.
+    Hard,
+    /// The same search as Hard with a larger budget, on every core.
+    Expert,
+}
+
+/// The search Hard and Expert make, with the given budget per thread.
+fn heuristic_params(budget: u32) -> MctsSantoriniParams {
+    let simulation = RolloutSimulation {
+        policy: RandomPolicy {},
+        plies: Some(4),
+    };
+    let expansion = HeuristicExpansion { temperature: 0.2 };
+    MctsParams::new(simulation, expansion, SmallRng::from_entropy())
+        .tree_policy(PUCT {
+            parameter: 0.5,
+            fpu: None,
+        })
+        .budget(budget)
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    pub fn player(self) -> Box<dyn FullPlayer> {
+        match self {
+            Difficulty::Easy => RandomAI::new(),
+            Difficulty::Medium => MctsSantoriniParams::default().budget(100).boxed(),
+            Difficulty::Hard => MctsAI::from(heuristic_params(1000))
+                .book(Arc::new(OpeningBook::builtin()))
+                .thresholds(Thresholds::default())
+                .boxed(),
+            Difficulty::Expert => {
+                let threads = thread::available_parallelism().map_or(1, |count| count.get());
+                MctsAI::from(heuristic_params(2000))
+                    .parallel(threads, || heuristic_params(2000))
+                    .book(Arc::new(OpeningBook::builtin()))
+                    .thresholds(Thresholds::default())
+                    .boxed()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,354 @@
+//! Playing engines that run as programs of their own.
+//!
+//! The engine is started as a child process and asked for a turn whenever it
+//! has one to take, one request per line on its stdin and one response per
+//! line on its stdout:
+//!
+//! ```text
+//! turn <position>  ->  ok <turn> [evaluation]
+//! quit
+//! ```
+//!
+//! Positions are in game notation and turns in turn notation, as `notation`
+//! and `Turn` write them; `resign` concedes. The evaluation is optional and
+//! ranges from -1.0 to 1.0, as `Player::evaluation` does. An engine that
+//! can't answer replies `error <message>`. `quit` is sent once the player is
+//! done with the engine, which should then exit.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::player::{FullPlayer, Player, StepResult};
+use crate::santorini::notation;
+use crate::santorini::{
+    self, ActionResult, AnyGame, Build, Game, GameState, Move, NormalState, PlaceOne, PlaceTwo,
+    Point, Turn, TurnActions,
+};
+use crate::ui::{placed_locs, worker_locs, BoardWidget, UpdateError};
+
+static EMPTY: Vec<Point> = Vec::new();
+
+pub struct ExternalPlayer {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The turn being played out, action by action.
+    plan: Option<TurnActions>,
+    /// The engine's evaluation of its last turn, if it gave one.
+    eval: Option<f64>,
+}
+
+/// A failure to get a usable turn out of the engine.
+fn engine_error(message: String) -> UpdateError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+impl ExternalPlayer {
+    /// Start the engine, talking to it over its stdin and stdout.
+    pub fn spawn(mut command: Command) -> io::Result<ExternalPlayer> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("Missing engine stdin!");
+        let stdout = BufReader::new(child.stdout.take().expect("Missing engine stdout!"));
+        Ok(ExternalPlayer {
+            child,
+            stdin,
+            stdout,
+            plan: None,
+            eval: None,
+        })
+    }
+
+    /// Start the engine from a command line, split on whitespace.
+    pub fn from_command_line(command_line: &str) -> io::Result<ExternalPlayer> {
+        let mut words = command_line.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut command = Command::new(program);
+        command.args(words);
+        ExternalPlayer::spawn(command)
+    }
+
+    pub fn boxed(self) -> Box<dyn FullPlayer> {
+        Box::new(self)
+    }
+
+    /// Ask the engine for its turn in the game.
+    fn request<G: Into<AnyGame>>(&mut self, game: G) -> Result<Turn, UpdateError> {
+        let position = notation::encode(game);
+        writeln!(self.stdin, "turn {}", position)?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(engine_error("engine exited".into()));
+        }
+        let line = line.trim();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (turn, eval) = match fields[..] {
+            ["ok", turn] => (turn, None),
+            ["ok", turn, eval] => (turn, Some(eval)),
+            ["error", ..] => {
+                return Err(engine_error(format!(
+                    "engine error for {}: {}",
+                    position,
+                    line["error".len()..].trim()
+                )))
+            }
+            _ => return Err(engine_error(format!("invalid engine response: {}", line))),
+        };
+        let invalid = || engine_error(format!("invalid engine response: {}", line));
+        self.eval = match eval {
+            Some(eval) => Some(eval.parse().map_err(|_| invalid())?),
+            None => None,
+        };
+        turn.parse().map_err(|_| invalid())
+    }
+}
+
+impl Drop for ExternalPlayer {
+    fn drop(&mut self) {
+        // The engine may already be gone, which is just as good
+        let _ = writeln!(self.stdin, "quit").and_then(|_| self.stdin.flush());
+        let _ = self.child.wait();
+    }
+}
+
+/// The engine's turn wasn't legal in the game.
+fn illegal(game: AnyGame, turn: Turn) -> UpdateError {
+    engine_error(format!(
+        "illegal engine turn {} in {}",
+        turn,
+        notation::encode(game)
+    ))
+}
+
+fn default_render<'a, T: GameState + NormalState>(game: &Game<T>) -> BoardWidget<'a> {
+    BoardWidget {
+        board: game.board(),
+        player: game.player(),
+        cursor: None,
+
+        highlights: &EMPTY,
+        suggestions: vec![],
+        player1_locs: worker_locs(game, santorini::Player::PlayerOne),
+        player2_locs: worker_locs(game, santorini::Player::PlayerTwo),
+        player3_locs: worker_locs(game, santorini::Player::PlayerThree),
+    }
+}
+
+impl Player<PlaceOne> for ExternalPlayer {
+    fn prepare(&mut self, _: &Game<PlaceOne>) {}
+
+    fn render(&self, game: &Game<PlaceOne>) -> BoardWidget<'_> {
+        BoardWidget {
+            board: game.board(),
+            player: game.player(),
+            cursor: None,
+
+            highlights: &EMPTY,
+            suggestions: vec![],
+            player1_locs: vec![],
+            player2_locs: vec![],
+            player3_locs: vec![],
+        }
+    }
+
+    fn step(&mut self, game: &Game<PlaceOne>) -> Result<StepResult, UpdateError> {
+        let turn = self.request(*game)?;
+        match game.apply_turn(turn) {
+            Some(game) => Ok(StepResult::PlaceTwo(game)),
+            None => Err(illegal((*game).into(), turn)),
+        }
+    }
+}
+
+impl Player<PlaceTwo> for ExternalPlayer {
+    fn prepare(&mut self, _: &Game<PlaceTwo>) {}
+
+    fn render(&self, game: &Game<PlaceTwo>) -> BoardWidget<'_> {
+        BoardWidget {
+            board: game.board(),
+            player: game.player(),
+            cursor: None,
+
+            highlights: &EMPTY,
+            suggestions: vec![],
+            player1_locs: placed_locs(game, santorini::Player::PlayerOne),
+            player2_locs: placed_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: placed_locs(game, santorini::Player::PlayerThree),
+        }
+    }
+
+    fn step(&mut self, game: &Game<PlaceTwo>) -> Result<StepResult, UpdateError> {
+        let turn = self.request(*game)?;
+        match game.apply_turn(turn) {
+            Some(result) => Ok(result.into()),
+            None => Err(illegal((*game).into(), turn)),
+        }
+    }
+}
+
+impl Player<Move> for ExternalPlayer {
+    fn prepare(&mut self, _: &Game<Move>) {}
+
+    fn render(&self, game: &Game<Move>) -> BoardWidget<'_> {
+        default_render(game)
+    }
+
+    fn step(&mut self, game: &Game<Move>) -> Result<StepResult, UpdateError> {
+        // Coming back from a power used before moving, the plan is kept
+        if game.acted_before_moving().is_none() {
+            let turn = self.request(*game)?;
+            if turn == Turn::Resign {
+                return match game.resign() {
+                    ActionResult::Continue(game) => Ok(StepResult::Move(game)),
+                    ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+                };
+            }
+            // Find the actions making up the turn, to take them one by one
+            let result = game
+                .apply_turn(turn)
+                .ok_or_else(|| illegal((*game).into(), turn))?;
+            let (actions, _) = game
+                .legal_turns()
+                .find(|(_, other)| *other == result)
+                .ok_or_else(|| illegal((*game).into(), turn))?;
+            self.plan = Some(actions);
+            if let Some(action) = actions.pre_move {
+                return Ok(StepResult::Move(game.apply_pre_move(action)));
+            }
+        }
+
+        let plan = self.plan.expect("No turn planned!");
+        match game.apply(plan.mv) {
+            ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+            ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+        }
+    }
+
+    fn evaluation(&self) -> Option<f64> {
+        self.eval
+    }
+}
+
+impl Player<Build> for ExternalPlayer {
+    fn prepare(&mut self, _: &Game<Build>) {}
+
+    fn render(&self, game: &Game<Build>) -> BoardWidget<'_> {
+        default_render(game)
+    }
+
+    fn step(&mut self, game: &Game<Build>) -> Result<StepResult, UpdateError> {
+        let plan = self.plan.as_mut().expect("No turn planned!");
+        if let Some(action) = plan.extra_move.take().or_else(|| plan.other_move.take()) {
+            return match game.apply_move(action) {
+                ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+            };
+        }
+
+        if plan.extra_build.is_some() {
+            if let Some(action) = plan.build.take() {
+                return Ok(StepResult::Build(game.build_again(action)));
+            }
+        }
+
+        let action = plan
+            .build
+            .take()
+            .or_else(|| plan.extra_build.take())
+            .expect("No build planned!");
+        match game.apply(action) {
+            ActionResult::Continue(game) => Ok(StepResult::Move(game)),
+            ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod external_tests {
+    use super::*;
+    use crate::santorini::{GameBuilder, God, BOARD_HEIGHT, BOARD_WIDTH};
+
+    /// An engine that answers every request with the same response.
+    fn engine(response: &str) -> ExternalPlayer {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!(
+            "while read line; do [ \"$line\" = quit ] && exit; echo \"{}\"; done",
+            response
+        ));
+        ExternalPlayer::spawn(command).unwrap()
+    }
+
+    /// A game with Player One's workers on a1 and c3.
+    fn moving() -> Game<Move> {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        GameBuilder::new([God::Mortal; 2])
+            .workers(santorini::Player::PlayerOne, &[pt(0, 0), pt(2, 2)])
+            .workers(santorini::Player::PlayerTwo, &[pt(w - 1, 0), pt(1, h - 2)])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn placements() {
+        let mut player = engine("ok b2,c3");
+        let game = santorini::new_game();
+        let placed = match player.step(&game).unwrap() {
+            StepResult::PlaceTwo(placed) => placed,
+            _ => panic!("Not placed!"),
+        };
+        assert_eq!(
+            placed_locs(&placed, santorini::Player::PlayerOne),
+            vec!["b2".parse().unwrap(), "c3".parse().unwrap()]
+        );
+        // The same squares are taken for Player Two
+        assert!(player.step(&placed).is_err());
+    }
+
+    #[test]
+    fn turns() {
+        let game = moving();
+        let mut player = engine("ok c3>c4^d4 0.25");
+        let built = match player.step(&game).unwrap() {
+            StepResult::Build(built) => built,
+            _ => panic!("Not moved!"),
+        };
+        assert_eq!(Player::<Move>::evaluation(&player), Some(0.25));
+        let after = match player.step(&built).unwrap() {
+            StepResult::Move(after) => after,
+            _ => panic!("Not built!"),
+        };
+        let expected = AnyGame::from(game).apply_turn("c3>c4^d4".parse().unwrap());
+        assert_eq!(expected, Some(after.into()));
+
+        let mut player = engine("ok resign");
+        assert!(matches!(
+            player.step(&game).unwrap(),
+            StepResult::Victory(_)
+        ));
+    }
+
+    #[test]
+    fn bad_responses() {
+        let game = moving();
+        for response in ["ok c3>e5^e4", "ok", "maybe c3>c4^d4", "error no idea"].iter() {
+            let mut player = engine(response);
+            assert!(player.step(&game).is_err(), "{}", response);
+        }
+        // Requests carry the position
+        match Player::<Move>::step(&mut engine("error $line"), &game) {
+            Err(UpdateError::IoError(err)) => {
+                assert!(err
+                    .to_string()
+                    .ends_with(&format!(": turn {}", notation::encode(game))))
+            }
+            _ => panic!("Not refused!"),
+        }
+    }
+}
@@ -1,29 +1,116 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use cached::proc_macro::cached;
 use cached::SizedCache;
-use rand::Rng;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::mem;
+use std::sync::Arc;
 
-use crate::player::{FullPlayer, Player, StepResult};
+use crate::player::{FullPlayer, OpeningBook, Player, StepResult};
 use crate::santorini::{
-    self, ActionResult, Build, BuildAction, CoordLevel, Game, GameState, Move, MoveAction,
-    NormalState, PlaceOne, PlaceTwo, Point,
+    self, ActionResult, AnyGame, Build, BuildAction, CoordLevel, ExtraMoveAction, Game, GameState,
+    Move, MoveAction, NormalState, PlaceOne, PlaceTwo, Point, PreMoveAction, Turn, TurnActions,
+    WorkerLocs,
 };
-use crate::ui::{BoardWidget, UpdateError};
+use crate::ui::{placed_locs, worker_locs, BoardWidget, UpdateError};
 
 static EMPTY: Vec<Point> = Vec::new();
 
 pub struct HeuristicAI {
+    pre_move: Option<PreMoveAction>,
     mv: Option<MoveAction>,
+    extra_move: Option<ExtraMoveAction>,
+    other_move: Option<ExtraMoveAction>,
     build: Option<BuildAction>,
+    extra_build: Option<BuildAction>,
+    eval: Option<f64>,
+    book: Option<Arc<OpeningBook>>,
+    weights: HeuristicWeights,
+    /// How many plies to look ahead of each turn considered.
+    depth: u8,
+    /// The most positions to visit choosing a turn, or None for no limit.
+    node_limit: Option<u64>,
 }
 
 impl HeuristicAI {
-    pub fn new() -> Box<dyn FullPlayer> {
-        Box::new(HeuristicAI {
+    pub fn new() -> HeuristicAI {
+        HeuristicAI {
+            pre_move: None,
             mv: None,
+            extra_move: None,
+            other_move: None,
             build: None,
-        })
+            extra_build: None,
+            eval: None,
+            book: None,
+            weights: HeuristicWeights::default(),
+            depth: 2,
+            node_limit: None,
+        }
+    }
+
+    /// A player that takes the book's turn whenever it has one.
+    pub fn with_book(book: Arc<OpeningBook>) -> HeuristicAI {
+        HeuristicAI {
+            book: Some(book),
+            ..HeuristicAI::new()
+        }
+    }
+
+    /// A player evaluating positions with the given weights.
+    pub fn with_weights(weights: HeuristicWeights) -> HeuristicAI {
+        HeuristicAI {
+            weights,
+            ..HeuristicAI::new()
+        }
+    }
+
+    /// A player looking `depth` plies ahead of each turn it considers, the
+    /// default being two. Once it has visited `node_limit` positions
+    /// choosing a turn, split evenly between the turns, it evaluates the
+    /// positions left where they stand instead.
+    pub fn with_search(depth: u8, node_limit: Option<u64>) -> HeuristicAI {
+        HeuristicAI {
+            depth,
+            node_limit,
+            ..HeuristicAI::new()
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn FullPlayer> {
+        Box::new(self)
+    }
+
+    /// Where the book places the workers, or else the best scoring of the
+    /// legal placements, the first listed among equals.
+    fn placement<G: Into<AnyGame>>(
+        &self,
+        game: G,
+        placements: impl Iterator<Item = WorkerLocs>,
+    ) -> Option<Vec<Point>> {
+        let game = game.into();
+        if let Some(Turn::Place(locs)) = self.book.as_ref().and_then(|book| book.turn(game)) {
+            return Some(locs.to_vec());
+        }
+
+        // Everyone placed so far is an opponent
+        let opponents = game.pawn_locs();
+        let mut best: Option<(Vec<Point>, f64)> = None;
+        for locs in placements {
+            let locs = locs.to_vec();
+            let score = placement_score(&locs, &opponents);
+            if !best.as_ref().is_some_and(|(_, best)| score <= *best) {
+                best = Some((locs, score));
+            }
+        }
+        best.map(|(locs, _)| locs)
+    }
+}
+
+impl Default for HeuristicAI {
+    fn default() -> Self {
+        HeuristicAI::new()
     }
 }
 
@@ -34,78 +121,148 @@ fn default_render<'a, T: GameState + NormalState>(game: &Game<T>) -> BoardWidget
         cursor: None,
 
         highlights: &EMPTY,
-        player1_locs: game
-            .player_pawns(santorini::Player::PlayerOne)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
-        player2_locs: game
-            .player_pawns(santorini::Player::PlayerTwo)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
+        suggestions: vec![],
+        player1_locs: worker_locs(game, santorini::Player::PlayerOne),
+        player2_locs: worker_locs(game, santorini::Player::PlayerTwo),
+        player3_locs: worker_locs(game, santorini::Player::PlayerThree),
     }
 }
 
-fn possible_actions(
-    game: &Game<Move>,
-) -> Vec<((MoveAction, Option<BuildAction>), ActionResult<Move>)> {
-    game.clone()
-        .active_pawns()
-        .iter()
-        .map(|pawn| pawn.actions())
-        .flatten()
-        .map(|mv| match game.clone().apply(mv.clone()) {
-            ActionResult::Victory(game) => vec![((mv, None), ActionResult::Victory(game))],
-            ActionResult::Continue(game) => game
-                .active_pawn()
-                .actions()
-                .into_iter()
-                .map(|build| ((mv.clone(), Some(build.clone())), game.clone().apply(build)))
-                .collect(),
-        })
-        .flatten()
-        .collect()
+/// The weights the heuristic evaluation combines its terms with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HeuristicWeights {
+    /// The score of each level, from the ground to level three, for a pawn
+    /// standing on it or next to it. Domes score nothing.
+    pub levels: [f64; 4],
+    /// How much the height a pawn stands at counts.
+    pub height: f64,
+    /// How much the height of a pawn's neighbors counts.
+    pub mobility: f64,
+    /// How much the distance between opposing pawns counts.
+    pub distance: f64,
+    /// How much the difference between the players' pawn terms counts.
+    pub pawns: f64,
 }
 
-fn height_score(height: CoordLevel) -> f64 {
-    match height {
-        CoordLevel::Ground => 0.0,
-        CoordLevel::One => 0.3,
-        CoordLevel::Two => 0.8,
-        CoordLevel::Three => 1.0,
-        CoordLevel::Capped => 0.0,
+impl Default for HeuristicWeights {
+    fn default() -> HeuristicWeights {
+        HeuristicWeights {
+            levels: [0.0, 0.3, 0.8, 1.0],
+            height: 0.7,
+            mobility: 0.3,
+            distance: 0.3,
+            pawns: 0.7,
+        }
     }
 }
 
-fn player_score(game: &Game<Move>, player: santorini::Player) -> f64 {
-    let pawn_score: f64 = game
-        .player_pawns(player)
-        .iter()
-        .map(|pawn| height_score(game.board().level_at(pawn.pos())))
-        .sum();
-    let pawn_score = pawn_score / 2.0;
+impl HeuristicWeights {
+    fn height_score(&self, height: CoordLevel) -> f64 {
+        match height {
+            CoordLevel::Ground => self.levels[0],
+            CoordLevel::One => self.levels[1],
+            CoordLevel::Two => self.levels[2],
+            CoordLevel::Three => self.levels[3],
+            CoordLevel::Capped => 0.0,
+        }
+    }
 
-    let move_scores: Vec<f64> = game
-        .player_pawns(player)
-        .iter()
-        .map(|pawn| {
-            pawn.neighbors()
-                .into_iter()
-                .map(|loc| height_score(game.board().level_at(loc)))
-        })
-        .flatten()
-        .collect();
-    let move_sum: f64 = move_scores.iter().sum();
-    let move_score: f64 = move_sum / (move_scores.len() as f64);
+    /// The weights exactly, for telling apart evaluations made with
+    /// different weights.
+    fn key(&self) -> [u64; 8] {
+        let [ground, one, two, three] = self.levels;
+        let weights = [
+            ground,
+            one,
+            two,
+            three,
+            self.height,
+            self.mobility,
+            self.distance,
+            self.pawns,
+        ];
+        weights.map(f64::to_bits)
+    }
+}
+
+/// A single pawn's contribution to its player's score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PawnEvaluation {
+    pub pos: Point,
+    pub player: santorini::Player,
+    /// The contribution from the height of the pawn itself.
+    pub height: f64,
+    /// The contribution from the height of the pawn's neighbors.
+    pub mobility: f64,
+}
+
+impl PawnEvaluation {
+    pub fn total(&self) -> f64 {
+        self.height + self.mobility
+    }
+}
+
+/// The static evaluation of a position, broken down into its terms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    /// The player the evaluation favors when positive.
+    pub player: santorini::Player,
+    /// The contribution from the distance between opposing pawns.
+    pub distance: f64,
+    pub pawns: Vec<PawnEvaluation>,
+    /// The weights the evaluation was made with.
+    pub weights: HeuristicWeights,
+}
+
+impl Evaluation {
+    /// The sum of the pawn terms for the given player.
+    pub fn player_score(&self, player: santorini::Player) -> f64 {
+        self.pawns
+            .iter()
+            .filter(|pawn| pawn.player == player)
+            .map(PawnEvaluation::total)
+            .sum()
+    }
 
-    pawn_score * 0.7 + move_score * 0.3
+    pub fn total(&self) -> f64 {
+        let opponents: f64 = self
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.player != self.player)
+            .map(PawnEvaluation::total)
+            .sum();
+        let diff = self.player_score(self.player) - opponents;
+        self.weights.distance * self.distance + self.weights.pawns * diff
+    }
 }
 
-fn diff_score(game: &Game<Move>) -> f64 {
-    let s1 = player_score(game, game.player());
-    let s2 = player_score(game, game.player().other());
-    s1 - s2
+fn pawn_evaluations(
+    game: &Game<Move>,
+    player: santorini::Player,
+    weights: &HeuristicWeights,
+) -> Vec<PawnEvaluation> {
+    let pawns = game.player_pawns(player);
+    let neighbor_count: usize = pawns.iter().map(|pawn| pawn.neighbors().count()).sum();
+
+    pawns
+        .iter()
+        .map(|pawn| {
+            let height = weights.height_score(game.board().level_at(pawn.pos())) / 2.0;
+            let mobility: f64 = pawn
+                .neighbors()
+                .map(|loc| weights.height_score(game.board().level_at(loc)))
+                .sum();
+            let mobility = mobility / (neighbor_count as f64);
+
+            PawnEvaluation {
+                pos: pawn.pos(),
+                player,
+                height: height * weights.height,
+                mobility: mobility * weights.mobility,
+            }
+        })
+        .collect()
 }
 
 fn dist_score(game: &Game<Move>) -> f64 {
@@ -115,11 +272,87 @@ fn dist_score(game: &Game<Move>) -> f64 {
             max_dist = i8::max(max_dist, p1.pos().distance(p2.pos()));
         }
     }
-    let dist_score = 1.0 - (max_dist as f64) / 5.0;
+    // The furthest apart any two squares can be
+    let furthest = santorini::BOARD_WIDTH.0.max(santorini::BOARD_HEIGHT.0) - 1;
+    let dist_score = 1.0 - (max_dist as f64) / (furthest as f64);
     dist_score * dist_score
 }
 
-fn score_recurse(action: &ActionResult<Move>, active_player: bool, depth: u8) -> f64 {
+/// Statically evaluate the position from the perspective of the given player.
+pub fn evaluate(game: &Game<Move>, player: santorini::Player) -> Evaluation {
+    evaluate_weighted(game, player, &HeuristicWeights::default())
+}
+
+/// Evaluate as `evaluate` does, with the given weights.
+pub fn evaluate_weighted(
+    game: &Game<Move>,
+    player: santorini::Player,
+    weights: &HeuristicWeights,
+) -> Evaluation {
+    let pawns = game
+        .players()
+        .flat_map(|player| pawn_evaluations(game, player, weights))
+        .collect();
+    Evaluation {
+        player,
+        distance: dist_score(game),
+        pawns,
+        weights: *weights,
+    }
+}
+
+/// A cheap guess at how good a turn is, for looking at the likeliest turns
+/// first: wins, then the turns that climb the most, with turns building
+/// next to an opponent's worker after the others that climb as much.
+fn order_key(
+    game: &Game<Move>,
+    actions: &TurnActions,
+    result: &ActionResult<Move>,
+) -> (bool, i8, bool) {
+    let won = matches!(result, ActionResult::Victory(_));
+    let to = actions
+        .extra_move
+        .map_or(actions.mv.to(), |action| action.to());
+    let level = |loc: Point| i8::from(game.board().level_at(loc));
+    let climb = level(to) - level(actions.mv.from());
+    let opponents = game.inactive_pawns();
+    let builds_next_to_opponent =
+        actions
+            .build
+            .iter()
+            .chain(actions.extra_build.iter())
+            .any(|build| {
+                opponents
+                    .iter()
+                    .any(|pawn| pawn.pos().distance(build.loc()) == 1)
+            });
+    (won, climb, !builds_next_to_opponent)
+}
+
+/// The legal turns in a game, likeliest to be good first, so searches that
+/// stop at a win or a loss get there sooner. Turns that look alike keep the
+/// order `legal_turns` gives them.
+pub fn ordered_turns(game: &Game<Move>) -> Vec<(TurnActions, ActionResult<Move>)> {
+    let mut turns: Vec<_> = game.legal_turns().collect();
+    turns.sort_by_cached_key(|(actions, result)| Reverse(order_key(game, actions, result)));
+    turns
+}
+
+/// What the search needs to score a position: the weights to evaluate it
+/// with, and how many more positions it may visit.
+struct Lookahead<'a> {
+    /// The player whose turn is being scored.
+    player: santorini::Player,
+    weights: &'a HeuristicWeights,
+    nodes: u64,
+}
+
+fn score_recurse(
+    action: &ActionResult<Move>,
+    active_player: bool,
+    depth: u8,
+    lookahead: &mut Lookahead,
+) -> f64 {
     match action {
         ActionResult::Victory(_) => {
             if active_player {
@@ -129,16 +362,13 @@ fn score_recurse(action: &ActionResult<Move>, active_player: bool, depth: u8) ->
             }
         }
         ActionResult::Continue(game) => {
-            if depth == 0 {
-                if active_player {
-                    0.3 * dist_score(game) - 0.7 * diff_score(game)
-                } else {
-                    0.3 * dist_score(game) + 0.7 * diff_score(game)
-                }
+            lookahead.nodes = lookahead.nodes.saturating_sub(1);
+            if depth == 0 || lookahead.nodes == 0 {
+                evaluate_weighted(game, lookahead.player, lookahead.weights).total()
             } else {
-                let scores = possible_actions(game)
-                    .into_iter()
-                    .map(|(_, action)| score_recurse(&action, !active_player, depth - 1));
+                let scores = ordered_turns(game).into_iter().map(|(_, action)| {
+                    score_recurse(&action, !active_player, depth - 1, lookahead)
+                });
                 if active_player {
                     let mut min = f64::MAX;
                     for score in scores {
@@ -163,32 +393,101 @@ fn score_recurse(action: &ActionResult<Move>, active_player: bool, depth: u8) ->
     }
 }
 
+/// Score a turn's result by searching `depth` plies past it, visiting at
+/// most `nodes` positions.
 #[cached(
-    type = "SizedCache<ActionResult<Move>, f64>",
+    type = "SizedCache<(ActionResult<Move>, [u64; 8], u8, u64), f64>",
     create = "{ SizedCache::with_size(128) }",
-    convert = "{ action.clone() }"
+    convert = "{ (action.clone(), weights.key(), depth, nodes) }"
 )]
-fn score(action: &ActionResult<Move>) -> f64 {
-    score_recurse(action, true, 2)
+fn score(
+    player: santorini::Player,
+    action: &ActionResult<Move>,
+    weights: &HeuristicWeights,
+    depth: u8,
+    nodes: u64,
+) -> f64 {
+    let mut lookahead = Lookahead {
+        player,
+        weights,
+        nodes,
+    };
+    score_recurse(action, true, depth, &mut lookahead)
+}
+
+/// Score a turn's result for the player taking it with the default
+/// weights, searching `depth` plies past it.
+pub fn score_turn(player: santorini::Player, result: &ActionResult<Move>, depth: u8) -> f64 {
+    score(
+        player,
+        result,
+        &HeuristicWeights::default(),
+        depth,
+        u64::MAX,
+    )
+}
+
+/// The best scoring turn for the player to act and its score, searching
+/// `depth` plies past each turn with the default weights, or None if they
+/// have no turn.
+pub fn best_turn(game: &Game<Move>, depth: u8) -> Option<(TurnActions, f64)> {
+    game.legal_turns()
+        .map(|(actions, result)| (actions, score_turn(game.player(), &result, depth)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
 }
 
-fn choose_action(game: &Game<Move>) -> (MoveAction, Option<BuildAction>) {
-    possible_actions(game)
-        .into_iter()
-        .max_by(|a, b| {
-            score(&a.1)
-                .partial_cmp(&score(&b.1))
-                .unwrap_or(Ordering::Equal)
+/// The best scoring build to finish the turn with, scored as `best_turn`
+/// scores turns.
+pub fn best_build(game: &Game<Build>, depth: u8) -> Option<BuildAction> {
+    game.build_actions()
+        .map(|action| {
+            (
+                action,
+                score_turn(game.player(), &game.apply(action), depth),
+            )
         })
-        .expect("No good moves found!")
-        .0
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .map(|(action, _)| action)
 }
 
-fn random_pt() -> Point {
-    let mut rng = rand::thread_rng();
-    let x: i8 = rng.gen_range(1, santorini::BOARD_WIDTH.0 - 1);
-    let y: i8 = rng.gen_range(1, santorini::BOARD_HEIGHT.0 - 1);
-    Point::new(x.into(), y.into())
+impl HeuristicAI {
+    fn choose_action(&self, game: &Game<Move>) -> (TurnActions, f64) {
+        let turns: Vec<_> = game.legal_turns().collect();
+        let nodes = match self.node_limit {
+            Some(limit) => (limit / turns.len() as u64).max(1),
+            None => u64::MAX,
+        };
+        turns
+            .into_iter()
+            .map(|(actions, result)| {
+                (
+                    actions,
+                    score(game.player(), &result, &self.weights, self.depth, nodes),
+                )
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .expect("No good moves found!")
+    }
+}
+
+/// How good a set of squares is to place workers on: squares near the
+/// center leave the most room to move, and squares next to an opponent's
+/// workers hem them in.
+fn placement_score(locs: &[Point], opponents: &[Point]) -> f64 {
+    let center = Point::new(
+        (santorini::BOARD_WIDTH.0 / 2).into(),
+        (santorini::BOARD_HEIGHT.0 / 2).into(),
+    );
+    locs.iter()
+        .map(|loc| {
+            let central = 2 - loc.distance(center);
+            let adjacent = opponents
+                .iter()
+                .filter(|opponent| loc.distance(**opponent) == 1)
+                .count();
+            central as f64 + 0.5 * adjacent as f64
+        })
+        .sum()
 }
 
 impl Player<PlaceOne> for HeuristicAI {
@@ -201,15 +500,19 @@ impl Player<PlaceOne> for HeuristicAI {
             cursor: None,
 
             highlights: &EMPTY,
+            suggestions: vec![],
             player1_locs: vec![],
             player2_locs: vec![],
+            player3_locs: vec![],
         }
     }
 
     fn step(&mut self, game: &Game<PlaceOne>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt();
-        let pt2 = random_pt();
-        match game.can_place(pt1, pt2) {
+        let placements = game.placements().map(|placement| placement.locs());
+        match self
+            .placement(*game, placements)
+            .and_then(|pts| game.can_place(&pts))
+        {
             Some(action) => Ok(StepResult::PlaceTwo(game.clone().apply(action))),
             None => Ok(StepResult::NoMove),
         }
@@ -226,25 +529,39 @@ impl Player<PlaceTwo> for HeuristicAI {
             cursor: None,
 
             highlights: &EMPTY,
-            player1_locs: game.player1_locs().to_vec(),
-            player2_locs: vec![],
+            suggestions: vec![],
+            player1_locs: placed_locs(game, santorini::Player::PlayerOne),
+            player2_locs: placed_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: placed_locs(game, santorini::Player::PlayerThree),
         }
     }
 
     fn step(&mut self, game: &Game<PlaceTwo>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt();
-        let pt2 = random_pt();
-        match game.can_place(pt1, pt2) {
-            Some(action) => Ok(StepResult::Move(game.clone().apply(action))),
+        let placements = game.placements().map(|placement| placement.locs());
+        match self
+            .placement(*game, placements)
+            .and_then(|pts| game.can_place(&pts))
+        {
+            Some(action) => Ok(game.clone().apply(action).into()),
             None => Ok(StepResult::NoMove),
         }
     }
 }
 
 impl Player<Move> for HeuristicAI {
-    fn prepare(&mut self, _: &Game<Move>) {
+    fn prepare(&mut self, game: &Game<Move>) {
+        // Keep the plan when coming back from a power used before moving
+        if game.acted_before_moving().is_some() {
+            return;
+        }
+
+        self.pre_move = None;
         self.mv = None;
+        self.extra_move = None;
+        self.other_move = None;
         self.build = None;
+        self.extra_build = None;
+        self.eval = None;
     }
 
     fn render(&self, game: &Game<Move>) -> BoardWidget {
@@ -253,9 +570,24 @@ impl Player<Move> for HeuristicAI {
 
     fn step(&mut self, game: &Game<Move>) -> Result<StepResult, UpdateError> {
         if let None = self.mv {
-            let (mv, build) = choose_action(game);
-            self.mv = Some(mv);
-            self.build = build;
+            let (turn, eval) = match self.book.as_ref().and_then(|book| book.actions(game)) {
+                Some((turn, _)) => (turn, None),
+                None => {
+                    let (turn, eval) = self.choose_action(game);
+                    (turn, Some(eval))
+                }
+            };
+            self.pre_move = turn.pre_move;
+            self.mv = Some(turn.mv);
+            self.extra_move = turn.extra_move;
+            self.other_move = turn.other_move;
+            self.build = turn.build;
+            self.extra_build = turn.extra_build;
+            self.eval = eval;
+        }
+
+        if let Some(action) = self.pre_move.take() {
+            return Ok(StepResult::Move(game.apply_pre_move(action)));
         }
 
         let action = mem::replace(&mut self.mv, None).expect("No move selected!");
@@ -264,6 +596,10 @@ impl Player<Move> for HeuristicAI {
             ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
         }
     }
+
+    fn evaluation(&self) -> Option<f64> {
+        self.eval
+    }
 }
 
 impl Player<Build> for HeuristicAI {
@@ -274,10 +610,251 @@ impl Player<Build> for HeuristicAI {
     }
 
     fn step(&mut self, game: &Game<Build>) -> Result<StepResult, UpdateError> {
-        let action = mem::replace(&mut self.build, None).expect("No build selected!");
+        if let Some(action) = self.extra_move.take().or_else(|| self.other_move.take()) {
+            return match game.apply_move(action) {
+                ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+            };
+        }
+
+        if self.extra_build.is_some() {
+            if let Some(action) = self.build.take() {
+                return Ok(StepResult::Build(game.build_again(action)));
+            }
+        }
+
+        let action = mem::replace(&mut self.build, None)
+            .or_else(|| self.extra_build.take())
+            .expect("No build selected!");
         match game.clone().apply(action) {
             ActionResult::Continue(game) => Ok(StepResult::Move(game)),
             ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
         }
     }
 }
+
+#[cfg(test)]
+mod evaluation_tests {
+    use super::*;
+    use crate::santorini::{Board, GameBuilder, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// A domed tower near the top left corner, with Player Two to move.
+    pub fn tower() -> Game<Move> {
+        let mut board = Board::new();
+        let heights = [
+            (2, 0, 1),
+            (3, 0, 2),
+            (1, 1, 1),
+            (2, 1, 3),
+            (3, 1, 2),
+            (1, 2, 2),
+            (2, 2, 4),
+            (3, 2, 2),
+            (2, 3, 1),
+        ];
+        for &(x, y, level) in heights.iter() {
+            board.set_level(pt(x, y), level.into());
+        }
+        GameBuilder::new([santorini::God::Mortal; 2])
+            .board(board)
+            .workers(
+                santorini::Player::PlayerOne,
+                &[pt(1, 1), pt(BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1)],
+            )
+            .workers(santorini::Player::PlayerTwo, &[pt(2, 3), pt(0, 0)])
+            .player(santorini::Player::PlayerTwo)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn evaluation_breakdown() {
+        // Both players take opposite corners of a square around b2 and d4
+        let game = GameBuilder::new([santorini::God::Mortal; 2])
+            .workers(santorini::Player::PlayerOne, &[pt(1, 1), pt(3, 3)])
+            .workers(santorini::Player::PlayerTwo, &[pt(1, 3), pt(3, 1)])
+            .build()
+            .unwrap();
+        let eval = evaluate(&game, santorini::Player::PlayerOne);
+        assert_eq!(eval.pawns.len(), 4);
+        assert!(eval.pawns.iter().all(|pawn| pawn.height == 0.0));
+        // Every pawn is two squares from both of its opponents
+        let furthest = BOARD_WIDTH.0.max(BOARD_HEIGHT.0) - 1;
+        let distance = 1.0 - 2.0 / (furthest as f64);
+        assert_eq!(eval.distance, distance * distance);
+        assert_eq!(eval.total(), 0.3 * distance * distance);
+
+        let game = tower();
+        let p1 = evaluate(&game, santorini::Player::PlayerOne);
+        let p2 = evaluate(&game, santorini::Player::PlayerTwo);
+        let diff1 = p1.total() - 0.3 * p1.distance;
+        let diff2 = p2.total() - 0.3 * p2.distance;
+        assert!((diff1 + diff2).abs() < 1e-12);
+
+        // The pawn on b2 stands on level one
+        let pawn = p1.pawns[0];
+        assert_eq!(pawn.player, santorini::Player::PlayerOne);
+        assert!((pawn.height - 0.7 * 0.3 / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn custom_weights() {
+        let game = tower();
+        let weights = HeuristicWeights {
+            levels: [0.0, 1.0, 1.0, 1.0],
+            height: 1.0,
+            distance: 1.0,
+            pawns: 0.0,
+            ..HeuristicWeights::default()
+        };
+        let eval = evaluate_weighted(&game, santorini::Player::PlayerOne, &weights);
+        assert_eq!(eval.total(), eval.distance);
+        assert_eq!(eval.pawns[0].height, 0.5);
+        assert_ne!(
+            evaluate(&game, santorini::Player::PlayerOne).pawns[0],
+            eval.pawns[0]
+        );
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::evaluation_tests::tower;
+    use super::*;
+    use crate::santorini::{Board, CoordLevel, GameBuilder, BOARD_HEIGHT, BOARD_WIDTH};
+
+    /// A game where Player One can climb from b2 onto a third level on b1.
+    fn climbing() -> Game<Move> {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(0, 0), CoordLevel::Two);
+        board.set_level(pt(1, 0), CoordLevel::Three);
+        board.set_level(pt(1, 1), CoordLevel::One);
+        GameBuilder::new([santorini::God::Mortal; 2])
+            .board(board)
+            .workers(santorini::Player::PlayerOne, &[pt(0, 0), pt(2, 2)])
+            .workers(
+                santorini::Player::PlayerTwo,
+                &[pt(1, 2), pt(BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1)],
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn depth_and_node_limit() {
+        let game = tower();
+        let ai = |depth, node_limit| HeuristicAI {
+            depth,
+            node_limit,
+            ..HeuristicAI::new()
+        };
+
+        // Without lookahead a turn scores as the position it leads to
+        let (turn, eval) = ai(0, None).choose_action(&game);
+        let best = game
+            .legal_turns()
+            .map(|(_, result)| match result {
+                ActionResult::Continue(next) => evaluate(&next, game.player()).total(),
+                ActionResult::Victory(_) => 1.0,
+            })
+            .fold(f64::MIN, f64::max);
+        assert_eq!(eval, best);
+
+        // Running out of nodes right away stops the lookahead
+        let (limited, limited_eval) = ai(2, Some(1)).choose_action(&game);
+        assert_eq!((limited, limited_eval), (turn, eval));
+    }
+
+    #[test]
+    fn turn_ordering() {
+        let game = climbing();
+        let turns = ordered_turns(&game);
+        assert_eq!(turns.len(), game.legal_turns().count());
+        assert!(matches!(turns[0].1, ActionResult::Victory(_)));
+
+        let keys: Vec<_> = turns
+            .iter()
+            .map(|(actions, result)| order_key(&game, actions, result))
+            .collect();
+        assert!(keys.windows(2).all(|pair| pair[0] >= pair[1]));
+        // Climbing from c3 to b2 comes before staying on the ground
+        let climb = keys.iter().position(|key| *key == (false, 1, true));
+        let flat = keys.iter().position(|key| *key == (false, 0, true));
+        assert!(climb.unwrap() < flat.unwrap());
+        assert!(keys.contains(&(false, 0, false)));
+    }
+
+    #[test]
+    fn hints() {
+        let game = climbing();
+        let (turn, score) = best_turn(&game, 1).unwrap();
+        assert_eq!(turn.mv.to(), "b1".parse().unwrap());
+        assert_eq!(score, 1.0);
+
+        // Moving from c3 to d3 instead leaves a build to suggest
+        let pawn = game.active_pawns()[1];
+        let action = pawn.can_move("d3".parse().unwrap()).unwrap();
+        let game = match game.apply(action) {
+            ActionResult::Continue(game) => game,
+            ActionResult::Victory(_) => panic!("Won moving!"),
+        };
+        let build = best_build(&game, 1).unwrap();
+        assert!(game.active_pawn().can_build(build.loc()).is_some());
+    }
+
+    #[test]
+    fn three_player_hints() {
+        let game: AnyGame = santorini::new_three_player_game([santorini::God::Mortal; 3]).into();
+        let mut game = ["a1,b1", "d1,c1", "a4,b4"]
+            .iter()
+            .fold(game, |g, turn| g.apply_turn(turn.parse().unwrap()).unwrap());
+        // Every seat gets a hint, scored against both of its opponents
+        for player in santorini::Player::iter() {
+            let g = match game {
+                AnyGame::Move(g) => g,
+                _ => panic!("Not moving!"),
+            };
+            assert_eq!(g.player(), *player);
+            assert_eq!(evaluate(&g, *player).pawns.len(), 6);
+            let (turn, _) = best_turn(&g, 1).unwrap();
+            game = AnyGame::from(g).apply_turn(turn.into()).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod placement_tests {
+    use super::*;
+
+    #[test]
+    fn central_and_adjacent() {
+        let mut ai = HeuristicAI::new();
+        let game = santorini::new_game();
+        let placed = match ai.step(&game).unwrap() {
+            StepResult::PlaceTwo(placed) => placed,
+            _ => panic!("Not placed!"),
+        };
+        let center: Point = "c3".parse().unwrap();
+        let mine = placed_locs(&placed, santorini::Player::PlayerOne);
+        assert!(mine.contains(&center));
+        assert_eq!(mine.iter().map(|loc| loc.distance(center)).sum::<i8>(), 1);
+
+        // The second player crowds the first, squeezing in next to both
+        // workers
+        let moving = match ai.step(&placed).unwrap() {
+            StepResult::Move(moving) => moving,
+            _ => panic!("Not placed!"),
+        };
+        let theirs = worker_locs(&moving, santorini::Player::PlayerTwo);
+        for loc in theirs {
+            assert!(mine.iter().all(|other| loc.distance(*other) == 1));
+        }
+        // Choosing again makes the same choice
+        assert!(matches!(ai.step(&placed).unwrap(), StepResult::Move(again) if again == moving));
+    }
+}
@@ -1,25 +1,45 @@
-use std::io;
-use termion::event::{Event, Key};
-use termion::input::TermRead;
-
-use crate::player::{FullPlayer, Player, StepResult};
+use crate::player::{heuristic_ai, FullPlayer, Player, StepResult};
 use crate::santorini::{
-    self, ActionResult, Build, Game, GameState, Move, NormalState, Pawn, PlaceOne, PlaceTwo, Point,
+    self, ActionResult, Build, BuildAction, Direction, Game, GameState, Move, NormalState, Pawn,
+    PlaceOne, PlaceTwo, Point, PreMoveAction,
 };
-use crate::ui::{BoardWidget, UpdateError};
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::Action;
+use crate::ui::{input, placed_locs, settings, worker_locs, BoardWidget, UpdateError};
 
 pub struct HumanPlayer {
     cursor: Point,
     highlights: Vec<Point>,
     intermediate_loc: Option<Point>,
+    /// The squares chosen so far for the workers being placed.
+    placing: Vec<Point>,
+    /// Whether the human is choosing an extra move instead of a build.
+    moving_again: bool,
+    /// Whether the human is choosing a power to use before moving.
+    before_moving: bool,
+    /// The squares of the turn the hint suggests, if one was asked for.
+    suggestions: Vec<Point>,
+    /// Whether the human pressed F6 and has yet to confirm resigning.
+    resigning: bool,
 }
 
+/// Asked before resigning, as a mistyped F6 would lose the game.
+const RESIGN_PROMPT: &str = "Resign the game? (y/n)";
+
+/// How many plies past each turn a hint looks, kept short so it's quick.
+const HINT_DEPTH: u8 = 1;
+
 impl HumanPlayer {
     pub fn new() -> Box<dyn FullPlayer> {
         Box::new(HumanPlayer {
             cursor: Point::new(0.into(), 0.into()),
             highlights: vec![],
             intermediate_loc: None,
+            placing: vec![],
+            moving_again: false,
+            before_moving: false,
+            suggestions: vec![],
+            resigning: false,
         })
     }
 
@@ -41,8 +61,7 @@ impl HumanPlayer {
 
     fn move_up(&mut self) {
         if self.highlights.is_empty() {
-            self.cursor =
-                Point::new_(self.cursor.x(), self.cursor.y() + (-1).into()).unwrap_or(self.cursor);
+            self.cursor = (self.cursor + Direction::North).unwrap_or(self.cursor);
         } else {
             let cursor_y = self.cursor.y();
             self.move_with_highlights(|point| point.y() < cursor_y);
@@ -51,8 +70,7 @@ impl HumanPlayer {
 
     fn move_down(&mut self) {
         if self.highlights.is_empty() {
-            self.cursor =
-                Point::new_(self.cursor.x(), self.cursor.y() + 1.into()).unwrap_or(self.cursor);
+            self.cursor = (self.cursor + Direction::South).unwrap_or(self.cursor);
         } else {
             let cursor_y = self.cursor.y();
             self.move_with_highlights(|point| point.y() > cursor_y);
@@ -61,8 +79,7 @@ impl HumanPlayer {
 
     fn move_left(&mut self) {
         if self.highlights.is_empty() {
-            self.cursor =
-                Point::new_(self.cursor.x() + (-1).into(), self.cursor.y()).unwrap_or(self.cursor);
+            self.cursor = (self.cursor + Direction::West).unwrap_or(self.cursor);
         } else {
             let cursor_x = self.cursor.x();
             self.move_with_highlights(|point| point.x() < cursor_x);
@@ -71,26 +88,67 @@ impl HumanPlayer {
 
     fn move_right(&mut self) {
         if self.highlights.is_empty() {
-            self.cursor =
-                Point::new_(self.cursor.x() + 1.into(), self.cursor.y()).unwrap_or(self.cursor);
+            self.cursor = (self.cursor + Direction::East).unwrap_or(self.cursor);
         } else {
             let cursor_x = self.cursor.x();
             self.move_with_highlights(|point| point.x() > cursor_x);
         }
     }
 
-    fn default_input_handler(&mut self, event: Event) -> Result<(), UpdateError> {
-        match event {
-            Event::Key(Key::Ctrl('c')) => return Err(UpdateError::Shutdown),
-            Event::Key(Key::Up) | Event::Key(Key::Char('w')) => self.move_up(),
-            Event::Key(Key::Left) | Event::Key(Key::Char('a')) => self.move_left(),
-            Event::Key(Key::Down) | Event::Key(Key::Char('s')) => self.move_down(),
-            Event::Key(Key::Right) | Event::Key(Key::Char('d')) => self.move_right(),
+    fn default_input_handler(
+        &mut self,
+        key: Key,
+        action: Option<Action>,
+    ) -> Result<(), UpdateError> {
+        if key == Key::Ctrl('c') {
+            return Err(UpdateError::Shutdown);
+        }
+        match action.and_then(Action::direction) {
+            Some(Direction::North) => self.move_up(),
+            Some(Direction::West) => self.move_left(),
+            Some(Direction::South) => self.move_down(),
+            Some(Direction::East) => self.move_right(),
             _ => (),
         }
         Ok(())
     }
 
+    /// Resign if the human confirms it, or carry on with the turn.
+    fn confirm_resign(
+        &mut self,
+        resign: impl FnOnce() -> ActionResult<Move>,
+    ) -> Result<StepResult, UpdateError> {
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(StepResult::NoMove),
+        };
+        self.resigning = false;
+        match (key, settings::action(key)) {
+            (Key::Ctrl('c'), _) => Err(UpdateError::Shutdown),
+            (Key::Char('y'), _) | (_, Some(Action::Select)) => match resign() {
+                ActionResult::Continue(game) => Ok(StepResult::Move(game)),
+                ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+            },
+            _ => Ok(StepResult::NoMove),
+        }
+    }
+
+    /// Pick the worker to move, if it has anywhere to go.
+    fn select(&mut self, pawn: &Pawn<Move>) {
+        if let Some(action) = pawn.actions().next() {
+            self.intermediate_loc = Some(pawn.pos());
+            self.cursor = action.to();
+            self.highlights = pawn.actions().map(|pair| pair.to()).collect();
+        }
+    }
+
+    /// Pick the worker to move again before building.
+    fn select_mover(&mut self, pawn: &Pawn<Build>) {
+        self.intermediate_loc = Some(pawn.pos());
+        self.highlights = pawn.moves().map(|mv| mv.to()).collect();
+        self.cursor = self.highlights[0];
+    }
+
     fn default_render<T: GameState + NormalState>(&self, game: &Game<T>) -> BoardWidget {
         BoardWidget {
             board: game.board(),
@@ -98,16 +156,33 @@ impl HumanPlayer {
             cursor: Some(self.cursor),
 
             highlights: &self.highlights,
-            player1_locs: game
-                .player_pawns(santorini::Player::PlayerOne)
-                .iter()
-                .map(|pawn| pawn.pos())
-                .collect(),
-            player2_locs: game
-                .player_pawns(santorini::Player::PlayerTwo)
-                .iter()
-                .map(|pawn| pawn.pos())
-                .collect(),
+            suggestions: self.suggestions.clone(),
+            player1_locs: worker_locs(game, santorini::Player::PlayerOne),
+            player2_locs: worker_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: worker_locs(game, santorini::Player::PlayerThree),
+        }
+    }
+
+    /// The placed workers of a player, or the squares the current player has
+    /// chosen so far.
+    fn placed_locs(&self, game: &Game<PlaceTwo>, player: santorini::Player) -> Vec<Point> {
+        if player == game.player() {
+            self.placing.clone()
+        } else {
+            placed_locs(game, player)
+        }
+    }
+
+    /// Choose the square under the cursor for the next worker. Returns the
+    /// squares for every worker once they have all been chosen.
+    fn choose_square(&mut self, worker_count: usize) -> Option<Vec<Point>> {
+        if !self.placing.contains(&self.cursor) {
+            self.placing.push(self.cursor);
+        }
+        if self.placing.len() == worker_count {
+            Some(std::mem::take(&mut self.placing))
+        } else {
+            None
         }
     }
 }
@@ -124,7 +199,7 @@ fn pawn_at<T: GameState + NormalState + Clone>(game: &Game<T>, loc: Point) -> Op
 impl Player<PlaceOne> for HumanPlayer {
     fn prepare(&mut self, _: &Game<PlaceOne>) {
         self.highlights = vec![];
-        self.intermediate_loc = None;
+        self.placing = vec![];
     }
 
     fn render(&self, game: &Game<PlaceOne>) -> BoardWidget {
@@ -134,28 +209,32 @@ impl Player<PlaceOne> for HumanPlayer {
             cursor: Some(self.cursor),
 
             highlights: &self.highlights,
-            player1_locs: self.intermediate_loc.iter().cloned().collect(),
+            suggestions: vec![],
+            player1_locs: self.placing.clone(),
             player2_locs: vec![],
+            player3_locs: vec![],
         }
     }
 
     fn step(&mut self, game: &Game<PlaceOne>) -> Result<StepResult, UpdateError> {
-        match io::stdin().events().next().unwrap()? {
-            Event::Key(Key::Char('q')) | Event::Key(Key::Esc) => {
-                if !self.intermediate_loc.is_none() {
-                    self.intermediate_loc = None;
-                }
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(StepResult::NoMove),
+        };
+        match settings::action(key) {
+            Some(Action::Undo) => return Ok(StepResult::Undo),
+            Some(Action::Pause) => return Ok(StepResult::Pause),
+            Some(Action::Deselect) => {
+                self.placing.clear();
             }
-            Event::Key(Key::Char('\n')) | Event::Key(Key::Char('e')) => {
-                if let Some(pos1) = self.intermediate_loc {
-                    if let Some(action) = game.can_place(pos1, self.cursor) {
+            Some(Action::Select) => {
+                if let Some(locs) = self.choose_square(game.worker_count()) {
+                    if let Some(action) = game.can_place(&locs) {
                         return Ok(StepResult::PlaceTwo(game.clone().apply(action)));
                     }
-                } else {
-                    self.intermediate_loc = Some(self.cursor);
                 }
             }
-            event => self.default_input_handler(event)?,
+            action => self.default_input_handler(key, action)?,
         }
 
         Ok(StepResult::NoMove)
@@ -165,7 +244,7 @@ impl Player<PlaceOne> for HumanPlayer {
 impl Player<PlaceTwo> for HumanPlayer {
     fn prepare(&mut self, _: &Game<PlaceTwo>) {
         self.highlights = vec![];
-        self.intermediate_loc = None;
+        self.placing = vec![];
     }
 
     fn render(&self, game: &Game<PlaceTwo>) -> BoardWidget {
@@ -175,34 +254,38 @@ impl Player<PlaceTwo> for HumanPlayer {
             cursor: Some(self.cursor),
 
             highlights: &self.highlights,
-            player1_locs: game.player1_locs().to_vec(),
-            player2_locs: self.intermediate_loc.iter().cloned().collect(),
+            suggestions: vec![],
+            player1_locs: self.placed_locs(game, santorini::Player::PlayerOne),
+            player2_locs: self.placed_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: self.placed_locs(game, santorini::Player::PlayerThree),
         }
     }
 
     fn step(&mut self, game: &Game<PlaceTwo>) -> Result<StepResult, UpdateError> {
-        match io::stdin().events().next().unwrap()? {
-            Event::Key(Key::Char('q')) | Event::Key(Key::Esc) => {
-                if !self.intermediate_loc.is_none() {
-                    self.intermediate_loc = None;
-                }
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(StepResult::NoMove),
+        };
+        match settings::action(key) {
+            Some(Action::Undo) => return Ok(StepResult::Undo),
+            Some(Action::Pause) => return Ok(StepResult::Pause),
+            Some(Action::Deselect) => {
+                self.placing.clear();
             }
-            Event::Key(Key::Char('\n')) | Event::Key(Key::Char('e')) => {
-                for pos in game.player1_locs().iter() {
-                    if *pos == self.cursor {
-                        return Ok(StepResult::NoMove);
-                    }
+            Some(Action::Select) => {
+                let taken = santorini::Player::iter()
+                    .any(|player| placed_locs(game, *player).contains(&self.cursor));
+                if taken {
+                    return Ok(StepResult::NoMove);
                 }
 
-                if let Some(pos1) = self.intermediate_loc {
-                    if let Some(action) = game.can_place(pos1, self.cursor) {
-                        return Ok(StepResult::Move(game.clone().apply(action)));
+                if let Some(locs) = self.choose_square(game.worker_count()) {
+                    if let Some(action) = game.can_place(&locs) {
+                        return Ok(game.clone().apply(action).into());
                     }
-                } else {
-                    self.intermediate_loc = Some(self.cursor);
                 }
             }
-            event => self.default_input_handler(event)?,
+            action => self.default_input_handler(key, action)?,
         }
 
         Ok(StepResult::NoMove)
@@ -211,29 +294,75 @@ impl Player<PlaceTwo> for HumanPlayer {
 
 impl Player<Move> for HumanPlayer {
     fn prepare(&mut self, game: &Game<Move>) {
+        self.before_moving = false;
+        self.suggestions = vec![];
+        self.resigning = false;
         self.highlights = game.active_pawns().iter().map(|pawn| pawn.pos()).collect();
         self.cursor = self.highlights[0];
         self.intermediate_loc = None;
+
+        // After using a power first, only the worker that used it may move
+        if let Some(pawn) = game
+            .acted_before_moving()
+            .and_then(|loc| pawn_at(game, loc))
+        {
+            self.select(&pawn);
+        }
     }
 
     fn render(&self, game: &Game<Move>) -> BoardWidget {
         self.default_render(game)
     }
 
+    fn prompt(&self) -> Option<&str> {
+        Some(RESIGN_PROMPT).filter(|_| self.resigning)
+    }
+
     fn step(&mut self, game: &Game<Move>) -> Result<StepResult, UpdateError> {
-        match io::stdin().events().next().unwrap()? {
-            Event::Key(Key::F(6)) => return Ok(StepResult::Victory(game.clone().resign())),
-            Event::Key(Key::Char('q')) | Event::Key(Key::Esc) => {
+        if self.resigning {
+            return self.confirm_resign(|| game.resign());
+        }
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(StepResult::NoMove),
+        };
+        match settings::action(key) {
+            Some(Action::Undo) => return Ok(StepResult::Undo),
+            Some(Action::Pause) => return Ok(StepResult::Pause),
+            Some(Action::Resign) => self.resigning = true,
+            Some(Action::Hint) => {
+                if let Some((turn, _)) = heuristic_ai::best_turn(game, HINT_DEPTH) {
+                    let squares = turn
+                        .pre_move
+                        .map(|action| action.target())
+                        .into_iter()
+                        .chain(vec![turn.mv.from(), turn.mv.to()])
+                        .chain(turn.extra_move.map(|action| action.to()))
+                        .chain(turn.other_move.map(|action| action.to()))
+                        .chain(turn.build.map(|action| action.loc()))
+                        .chain(turn.extra_build.map(|action| action.loc()));
+                    self.suggestions = squares.collect();
+                }
+            }
+            Some(Action::Deselect) => {
                 if !self.intermediate_loc.is_none() {
                     self.prepare(game);
                 }
             }
-            Event::Key(Key::Char('\n')) | Event::Key(Key::Char('e')) => {
+            Some(Action::Select) => {
                 if let Some(pawn) = self
                     .intermediate_loc
                     .map(|loc| pawn_at(&game, loc))
                     .flatten()
                 {
+                    if self.before_moving {
+                        let action = pawn
+                            .pre_moves()
+                            .find(|action| action.target() == self.cursor)
+                            .unwrap();
+                        return Ok(StepResult::Move(game.apply_pre_move(action)));
+                    }
+
                     let action = pawn.can_move(self.cursor).unwrap();
                     return match game.clone().apply(action) {
                         ActionResult::Continue(game) => Ok(StepResult::Build(game)),
@@ -241,45 +370,142 @@ impl Player<Move> for HumanPlayer {
                     };
                 } else {
                     let pawn = pawn_at(&game, self.cursor).unwrap();
-                    if let Some(action) = pawn.actions().next() {
-                        self.intermediate_loc = Some(self.cursor);
-                        self.cursor = action.to();
-                        self.highlights = pawn.actions().map(|pair| pair.to()).collect();
+                    self.select(&pawn);
+                }
+            }
+            Some(power @ Action::BuildFirst) | Some(power @ Action::Force)
+                if !self.before_moving =>
+            {
+                if let Some(pawn) = self.intermediate_loc.and_then(|loc| pawn_at(game, loc)) {
+                    let targets: Vec<_> = pawn
+                        .pre_moves()
+                        .filter(|action| match action {
+                            PreMoveAction::Build(_) => power == Action::BuildFirst,
+                            PreMoveAction::Force(_) => power == Action::Force,
+                        })
+                        .map(|action| action.target())
+                        .collect();
+                    if let Some(loc) = targets.first() {
+                        self.before_moving = true;
+                        self.cursor = *loc;
+                        self.highlights = targets;
                     }
                 }
             }
-            event => self.default_input_handler(event)?,
+            action => self.default_input_handler(key, action)?,
         }
 
         Ok(StepResult::NoMove)
     }
 }
 
+/// Make the build, leaving the player to build again if their god allows it.
+fn build(game: &Game<Build>, action: BuildAction) -> StepResult {
+    if game.can_build_again() {
+        return StepResult::Build(game.build_again(action));
+    }
+    match game.apply(action) {
+        ActionResult::Continue(game) => StepResult::Move(game),
+        ActionResult::Victory(game) => StepResult::Victory(game),
+    }
+}
+
 impl Player<Build> for HumanPlayer {
     fn prepare(&mut self, game: &Game<Build>) {
+        self.moving_again = false;
+        self.suggestions = vec![];
+        self.resigning = false;
+        self.intermediate_loc = None;
         self.highlights = game
-            .active_pawn()
-            .actions()
+            .build_actions()
+            .filter(|build| !build.dome())
             .map(|build| build.loc())
             .collect();
-        self.cursor = self.highlights[0];
+        // An optional build may have nowhere left to go
+        if let Some(loc) = self.highlights.first() {
+            self.cursor = *loc;
+        }
     }
 
     fn render(&self, game: &Game<Build>) -> BoardWidget {
         self.default_render(game)
     }
 
+    fn prompt(&self) -> Option<&str> {
+        Some(RESIGN_PROMPT).filter(|_| self.resigning)
+    }
+
     fn step(&mut self, game: &Game<Build>) -> Result<StepResult, UpdateError> {
-        match io::stdin().events().next().unwrap()? {
-            Event::Key(Key::F(6)) => return Ok(StepResult::Victory(game.clone().resign())),
-            Event::Key(Key::Char('\n')) | Event::Key(Key::Char('e')) => {
-                let action = game.active_pawn().can_build(self.cursor).unwrap();
-                return match game.clone().apply(action) {
+        if self.resigning {
+            return self.confirm_resign(|| game.resign());
+        }
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(StepResult::NoMove),
+        };
+        match settings::action(key) {
+            Some(Action::Undo) => return Ok(StepResult::Undo),
+            Some(Action::Pause) => return Ok(StepResult::Pause),
+            Some(Action::Resign) => self.resigning = true,
+            Some(Action::MoveAgain) if !self.moving_again && game.can_move_again() => {
+                self.moving_again = true;
+                let movers: Vec<_> = game
+                    .active_pawns()
+                    .into_iter()
+                    .filter(|pawn| pawn.moves().next().is_some())
+                    .collect();
+                if let [pawn] = movers.as_slice() {
+                    self.select_mover(pawn);
+                } else {
+                    // Hermes chooses which worker moves along its level
+                    self.highlights = movers.iter().map(|pawn| pawn.pos()).collect();
+                    self.cursor = self.highlights[0];
+                }
+            }
+            Some(Action::Pass) if game.can_pass() => {
+                return match game.pass() {
                     ActionResult::Continue(game) => Ok(StepResult::Move(game)),
                     ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
                 };
             }
-            event => self.default_input_handler(event)?,
+            Some(Action::Hint) => {
+                self.suggestions = heuristic_ai::best_build(game, HINT_DEPTH)
+                    .map(|action| action.loc())
+                    .into_iter()
+                    .collect();
+            }
+            Some(Action::Deselect) => {
+                if self.moving_again {
+                    self.prepare(game);
+                }
+            }
+            Some(Action::Select) => {
+                if self.moving_again {
+                    let pawn = match self.intermediate_loc.and_then(|loc| pawn_at(game, loc)) {
+                        Some(pawn) => pawn,
+                        None => {
+                            let pawn = pawn_at(game, self.cursor).unwrap();
+                            self.select_mover(&pawn);
+                            return Ok(StepResult::NoMove);
+                        }
+                    };
+                    let action = pawn.can_move(self.cursor).unwrap();
+                    return match game.apply_move(action) {
+                        ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                        ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+                    };
+                }
+
+                if let Some(action) = game.can_build(self.cursor) {
+                    return Ok(build(game, action));
+                }
+            }
+            Some(Action::Dome) if !self.moving_again => {
+                if let Some(action) = game.can_build_dome(self.cursor) {
+                    return Ok(build(game, action));
+                }
+            }
+            action => self.default_input_handler(key, action)?,
         }
 
         Ok(StepResult::NoMove)
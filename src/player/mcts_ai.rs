@@ -1,13 +1,18 @@
-use crate::player::{FullPlayer, Player, StepResult};
+use crate::analysis::solver::{Outcome, Solver};
+use crate::player::heuristic_ai::score_turn;
+use crate::player::{FullPlayer, OpeningBook, Player, SearchInfo, StepResult};
 use crate::santorini::{
-    self, ActionResult, Build, Game, GameState, Move, NormalState, PlaceOne, PlaceTwo, Point,
+    self, ActionResult, AnyGame, Build, Game, GameState, Move, NormalState, PlaceOne, PlaceTwo,
+    Point, Turn, TurnActions, WorkerLocs,
 };
-use crate::ui::{BoardWidget, UpdateError};
+use crate::ui::{placed_locs, worker_locs, BoardWidget, UpdateError};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use std::thread;
 
-use crate::mcts::santorini::{SantoriniExpansion, SantoriniNode, SantoriniSimulation};
-use crate::mcts::{Mcts, MctsParams};
+use crate::mcts::santorini::{NodeState, SantoriniExpansion, SantoriniNode, SantoriniSimulation};
+use crate::mcts::{Interrupt, Mcts, MctsParams, MoveId, Node, SearchStats};
 
 pub enum MctsOrParams<T, R: Rng> {
     Params(MctsParams<T, R>),
@@ -20,14 +25,7 @@ impl<T, R: Rng> From<MctsParams<T, R>> for MctsOrParams<T, R> {
     }
 }
 
-impl<T, R: Rng> MctsOrParams<T, R> {
-    fn params(&mut self) -> &mut MctsParams<T, R> {
-        match self {
-            MctsOrParams::Tree(tree) => &mut tree.params,
-            MctsOrParams::Params(params) => params,
-        }
-    }
-
+impl<T: MoveId, R: Rng> MctsOrParams<T, R> {
     fn tree(&mut self, node: T) -> &mut Mcts<T, R> {
         take_mut::take(self, move |mcts_or_params| match mcts_or_params {
             MctsOrParams::Params(params) => MctsOrParams::Tree(Mcts::new(params, node)),
@@ -41,6 +39,13 @@ impl<T, R: Rng> MctsOrParams<T, R> {
         }
     }
 
+    fn params_mut(&mut self) -> &mut MctsParams<T, R> {
+        match self {
+            MctsOrParams::Params(params) => params,
+            MctsOrParams::Tree(tree) => &mut tree.params,
+        }
+    }
+
     fn expect<S: 'static + Send>(&self, message: S) -> &Mcts<T, R> {
         match self {
             MctsOrParams::Tree(tree) => tree,
@@ -59,17 +64,346 @@ impl MctsSantoriniParams {
         )
     }
 
+    /// The default params, with the search seeded so it makes the same
+    /// choices every time it's given the same games.
+    pub fn with_seed(seed: u64) -> Self {
+        MctsSantoriniParams::new(
+            SantoriniSimulation {},
+            SantoriniExpansion {},
+            SmallRng::seed_from_u64(seed),
+        )
+    }
+
     pub fn boxed(self) -> Box<dyn FullPlayer> {
         MctsAI::from(self).boxed()
     }
 }
 
-pub type MctsAI = MctsOrParams<SantoriniNode, SmallRng>;
+/// When an AI gives a game up as lost, or announces a win as certain.
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// The evaluation below which a turn counts towards resigning.
+    pub resign_below: f64,
+    /// How many turns in a row have to be rated below `resign_below` before
+    /// resigning.
+    pub resign_turns: u32,
+    /// The solver run before each turn to prove a win, if any. Proven wins
+    /// are played out and announced instead of searched for.
+    pub claim: Option<Solver>,
+}
+
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+        Thresholds {
+            resign_below: -0.95,
+            resign_turns: 3,
+            claim: Some(Solver::new(3).node_limit(20_000)),
+        }
+    }
+}
+
+pub struct MctsAI {
+    mcts: MctsOrParams<SantoriniNode, SmallRng>,
+    /// Trees searched from scratch on other threads alongside the main one,
+    /// whose statistics for the root's children are pooled with it.
+    helpers: Vec<MctsOrParams<SantoriniNode, SmallRng>>,
+    book: Option<Arc<OpeningBook>>,
+    /// Whether the last turn was the book's rather than the search's.
+    booked: bool,
+    thresholds: Option<Thresholds>,
+    /// How many turns in a row have been rated below the resignation
+    /// threshold.
+    losing: u32,
+    /// The plies to the win proven before the last turn, if one was.
+    claim: Option<u32>,
+    /// What the search for the last turn found, if it was searched for.
+    info: Option<SearchInfo>,
+    /// Whether to check the search's turns with a shallow minimax search.
+    guard: bool,
+    /// The minimax score of the last turn, if it was taken in place of the
+    /// search's.
+    veto: Option<f64>,
+}
+
+impl From<MctsSantoriniParams> for MctsAI {
+    fn from(params: MctsSantoriniParams) -> MctsAI {
+        MctsAI {
+            mcts: params.into(),
+            helpers: vec![],
+            book: None,
+            booked: false,
+            thresholds: None,
+            losing: 0,
+            claim: None,
+            info: None,
+            guard: false,
+            veto: None,
+        }
+    }
+}
 
 impl MctsAI {
-    fn boxed(self) -> Box<dyn FullPlayer> {
+    /// Take the book's turn whenever it has one, searching only once out of
+    /// the book.
+    pub fn book(self, book: Arc<OpeningBook>) -> Self {
+        MctsAI {
+            book: Some(book),
+            ..self
+        }
+    }
+
+    /// Search on `threads` threads, each growing its own tree. The main tree
+    /// keeps the params it was made with, while the others are made from
+    /// `params`, which should seed them differently.
+    pub fn parallel<F: Fn() -> MctsSantoriniParams>(self, threads: usize, params: F) -> Self {
+        MctsAI {
+            helpers: (1..threads).map(|_| params().into()).collect(),
+            ..self
+        }
+    }
+
+    /// Resign and announce wins as the thresholds say. Without them the AI
+    /// plays every game out.
+    pub fn thresholds(self, thresholds: Thresholds) -> Self {
+        MctsAI {
+            thresholds: Some(thresholds),
+            ..self
+        }
+    }
+
+    /// Check each turn the search chooses with a two ply minimax search over
+    /// the heuristic evaluation: a win on the spot is always taken and
+    /// announced, and a
+    /// turn letting the opponent win right after is swapped for the best
+    /// scoring turn that doesn't, if there is one.
+    pub fn guarded(self) -> Self {
+        MctsAI {
+            guard: true,
+            ..self
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn FullPlayer> {
         Box::new(self)
     }
+
+    /// Have every tree report to and stop for the interrupt.
+    fn attach_interrupt(&mut self, interrupt: &Arc<Interrupt>) {
+        self.mcts.params_mut().interrupt = Some(Arc::clone(interrupt));
+        for helper in self.helpers.iter_mut() {
+            helper.params_mut().interrupt = Some(Arc::clone(interrupt));
+        }
+    }
+
+    /// Where the book places the workers in the game, if it has a placement.
+    fn book_placement<G: Into<AnyGame>>(&self, game: G) -> Option<WorkerLocs> {
+        match self.book.as_ref()?.turn(game)? {
+            Turn::Place(locs) => Some(locs),
+            _ => None,
+        }
+    }
+
+    /// Move the root of the tree on to the book's turn in the game, returning
+    /// false if it has none.
+    fn follow_book(&mut self, game: Game<Move>) -> bool {
+        match self.book.as_ref().and_then(|book| book.actions(&game)) {
+            Some((turn, result)) => {
+                self.follow_turn(game, turn, result);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the root of the tree on to the first turn of a forced win in the
+    /// game, returning the plies the win takes, if the solver proves one.
+    fn follow_proof(&mut self, game: Game<Move>) -> Option<u32> {
+        let solution = self.thresholds?.claim?.solve(&game);
+        let plies = match solution.outcome {
+            Outcome::Win(plies) => plies,
+            Outcome::NoWin | Outcome::Unknown => return None,
+        };
+        let turn = solution.turn?;
+        let (_, result) = game.legal_turns().find(|(other, _)| *other == turn)?;
+        self.follow_turn(game, turn, result);
+        Some(plies)
+    }
+
+    /// Move the root of the tree on to a turn taken in the game, skipping
+    /// the search.
+    fn follow_turn(&mut self, game: Game<Move>, turn: TurnActions, result: ActionResult<Move>) {
+        let node = SantoriniNode {
+            pre_move: turn.pre_move,
+            mv: Some(turn.mv),
+            extra_move: turn.extra_move,
+            other_move: turn.other_move,
+            build: turn.build,
+            extra_build: turn.extra_build,
+            ..NodeState::from(result).into()
+        };
+        self.mcts.tree(game.into()).reroot(node);
+    }
+
+    /// Replace the turn the search chose in the game if the minimax search
+    /// shows it up, as `guarded` describes.
+    fn check_turn(&mut self, game: Game<Move>) {
+        let chosen = match &self.mcts {
+            MctsOrParams::Tree(tree) => tree.root_node.state.actions(),
+            MctsOrParams::Params(_) => None,
+        };
+        let turns: Vec<_> = game.legal_turns().collect();
+        if let Some((turn, result)) = turns
+            .iter()
+            .find(|(_, result)| matches!(result, ActionResult::Victory(_)))
+        {
+            if chosen != Some(*turn) {
+                self.follow_turn(game, *turn, *result);
+                self.info = None;
+            }
+            self.claim = Some(1);
+            return;
+        }
+
+        // Scoring one ply past each turn searches the opponent's replies
+        let scored: Vec<_> = turns
+            .into_iter()
+            .map(|(turn, result)| (turn, result, score_turn(game.player(), &result, 1)))
+            .collect();
+        let blunder = scored
+            .iter()
+            .any(|(turn, _, score)| Some(*turn) == chosen && *score <= -1.0);
+        if !blunder {
+            return;
+        }
+        let best = scored.into_iter().fold(None, |best, turn| match best {
+            Some((_, _, score)) if turn.2 <= score => best,
+            _ => Some(turn),
+        });
+        if let Some((turn, result, score)) = best {
+            if score > -1.0 {
+                self.follow_turn(game, turn, result);
+                self.info = None;
+                self.veto = Some(score);
+            }
+        }
+    }
+
+    /// Count the turn just chosen towards resigning, returning whether it's
+    /// time to.
+    fn resigning(&mut self) -> bool {
+        let thresholds = match self.thresholds {
+            Some(thresholds) => thresholds,
+            None => return false,
+        };
+        match Player::<Move>::evaluation(self) {
+            Some(eval) if eval < thresholds.resign_below => self.losing += 1,
+            _ => self.losing = 0,
+        }
+        self.losing >= thresholds.resign_turns
+    }
+
+    /// Move the root of the tree on to the game, which should be the result
+    /// of one of its children.
+    fn follow<G>(&mut self, game: G)
+    where
+        G: Copy + Into<AnyGame> + Into<SantoriniNode>,
+    {
+        let tree = self.mcts.tree(game.into());
+        if tree.root_node.state.matches(game) {
+            return;
+        }
+
+        let children = tree.root_node.children.take().unwrap_or_default();
+        match children.into_iter().find(|child| child.state.matches(game)) {
+            Some(child) => tree.root_node = child,
+            // The opponent played a move the search never expanded
+            None => tree.reroot(game.into()),
+        }
+    }
+
+    /// Search for the best reply to the game, returning the node it leads
+    /// to. The search is skipped if it has already been made.
+    fn search<G>(&mut self, game: G) -> &SantoriniNode
+    where
+        G: Copy + Into<AnyGame> + Into<SantoriniNode>,
+    {
+        let tree = self.mcts.tree(game.into());
+        if !tree.root_node.state.matches(game) {
+            return &tree.root_node.state;
+        }
+        if self.helpers.is_empty() {
+            self.info = Some(tree.advance().into());
+            return &tree.root_node.state;
+        }
+
+        let helpers: Vec<_> = self
+            .helpers
+            .iter_mut()
+            .map(|helper| {
+                let helper = helper.tree(game.into());
+                helper.reroot(game.into());
+                helper
+            })
+            .collect();
+        thread::scope(|scope| {
+            for helper in helpers {
+                scope.spawn(move || helper.search());
+            }
+            tree.search();
+        });
+        for helper in self.helpers.iter() {
+            let helper = helper.expect("Unitialized helper!");
+            pool(&mut tree.root_node, &helper.root_node);
+        }
+        self.info = Some(tree.stats().into());
+        let idx = tree.best_child();
+        tree.descend(idx);
+        &tree.root_node.state
+    }
+}
+
+/// Add what another search found about the children of a root to the same
+/// children of this one. Children this root hasn't expanded are skipped.
+fn pool(root: &mut Node<SantoriniNode>, other: &Node<SantoriniNode>) {
+    let children = match root.children.as_mut() {
+        Some(children) => children,
+        None => return,
+    };
+    for theirs in other.children.iter().flatten() {
+        let ours = children
+            .iter_mut()
+            .find(|ours| ours.state.game == theirs.state.game);
+        if let Some(ours) = ours {
+            let iterations = ours.iterations + theirs.iterations;
+            if iterations > 0 {
+                let total =
+                    ours.score * ours.iterations as f64 + theirs.score * theirs.iterations as f64;
+                ours.score = total / iterations as f64;
+            }
+            ours.iterations = iterations;
+        }
+    }
+}
+
+/// The statistics in terms of turns. The principal variation stops short of
+/// any node not reached by a turn.
+impl From<SearchStats<SantoriniNode>> for SearchInfo {
+    fn from(stats: SearchStats<SantoriniNode>) -> SearchInfo {
+        SearchInfo {
+            value: stats.value,
+            simulations: stats.iterations,
+            principal_variation: stats
+                .principal_variation
+                .iter()
+                .map_while(|node| node.turn())
+                .collect(),
+            visits: stats
+                .visits
+                .iter()
+                .filter_map(|(node, visits)| Some((node.turn()?, *visits)))
+                .collect(),
+        }
+    }
 }
 
 static EMPTY: Vec<Point> = Vec::new();
@@ -81,28 +415,17 @@ fn default_render<'a, T: GameState + NormalState>(game: &Game<T>) -> BoardWidget
         cursor: None,
 
         highlights: &EMPTY,
-        player1_locs: game
-            .player_pawns(santorini::Player::PlayerOne)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
-        player2_locs: game
-            .player_pawns(santorini::Player::PlayerTwo)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
+        suggestions: vec![],
+        player1_locs: worker_locs(game, santorini::Player::PlayerOne),
+        player2_locs: worker_locs(game, santorini::Player::PlayerTwo),
+        player3_locs: worker_locs(game, santorini::Player::PlayerThree),
     }
 }
 
-// TODO: Add support for placement to the tree
-fn random_pt<R: Rng>(rng: &mut R) -> Point {
-    let x: i8 = rng.gen_range(1, santorini::BOARD_WIDTH.0 - 1);
-    let y: i8 = rng.gen_range(1, santorini::BOARD_HEIGHT.0 - 1);
-    Point::new(x.into(), y.into())
-}
-
 impl Player<PlaceOne> for MctsAI {
-    fn prepare(&mut self, _: &Game<PlaceOne>) {}
+    fn prepare(&mut self, game: &Game<PlaceOne>) {
+        self.follow(*game);
+    }
 
     fn render(&self, game: &Game<PlaceOne>) -> BoardWidget {
         BoardWidget {
@@ -111,23 +434,36 @@ impl Player<PlaceOne> for MctsAI {
             cursor: None,
 
             highlights: &EMPTY,
+            suggestions: vec![],
             player1_locs: vec![],
             player2_locs: vec![],
+            player3_locs: vec![],
         }
     }
 
     fn step(&mut self, game: &Game<PlaceOne>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt(&mut self.params().rng);
-        let pt2 = random_pt(&mut self.params().rng);
-        match game.can_place(pt1, pt2) {
-            Some(action) => Ok(StepResult::PlaceTwo(game.clone().apply(action))),
-            None => Ok(StepResult::NoMove),
-        }
+        self.info = None;
+        let locs = match self.book_placement(*game) {
+            Some(locs) => locs,
+            None => self.search(*game).placement.expect("Missing placement!"),
+        };
+        let action = game.can_place(&locs).expect("Invalid placement!");
+        Ok(StepResult::PlaceTwo(game.clone().apply(action)))
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.clone()
+    }
+
+    fn attach(&mut self, interrupt: &Arc<Interrupt>) {
+        self.attach_interrupt(interrupt);
     }
 }
 
 impl Player<PlaceTwo> for MctsAI {
-    fn prepare(&mut self, _: &Game<PlaceTwo>) {}
+    fn prepare(&mut self, game: &Game<PlaceTwo>) {
+        self.follow(*game);
+    }
 
     fn render(&self, game: &Game<PlaceTwo>) -> BoardWidget {
         BoardWidget {
@@ -136,38 +472,40 @@ impl Player<PlaceTwo> for MctsAI {
             cursor: None,
 
             highlights: &EMPTY,
-            player1_locs: game.player1_locs().to_vec(),
-            player2_locs: vec![],
+            suggestions: vec![],
+            player1_locs: placed_locs(game, santorini::Player::PlayerOne),
+            player2_locs: placed_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: placed_locs(game, santorini::Player::PlayerThree),
         }
     }
 
     fn step(&mut self, game: &Game<PlaceTwo>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt(&mut self.params().rng);
-        let pt2 = random_pt(&mut self.params().rng);
-        match game.can_place(pt1, pt2) {
-            Some(action) => Ok(StepResult::Move(game.clone().apply(action))),
-            None => Ok(StepResult::NoMove),
-        }
+        self.info = None;
+        let locs = match self.book_placement(*game) {
+            Some(locs) => locs,
+            None => self.search(*game).placement.expect("Missing placement!"),
+        };
+        let action = game.can_place(&locs).expect("Invalid placement!");
+        Ok(game.clone().apply(action).into())
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.clone()
+    }
+
+    fn attach(&mut self, interrupt: &Arc<Interrupt>) {
+        self.attach_interrupt(interrupt);
     }
 }
 
 impl Player<Move> for MctsAI {
     fn prepare(&mut self, game: &Game<Move>) {
-        let tree = self.tree((*game).into());
-        let node = &tree.root_node;
-        if node.state.matches(*game) {
+        // A power used before moving happens partway through the chosen turn
+        if game.acted_before_moving().is_some() {
             return;
         }
 
-        take_mut::take(&mut tree.root_node, |node| {
-            for child in node.children.expect("Unexpanded root node!") {
-                if child.state.matches(*game) {
-                    return child;
-                }
-            }
-
-            panic!("Current game state not in tree!");
-        });
+        self.follow(*game);
     }
 
     fn render(&self, game: &Game<Move>) -> BoardWidget {
@@ -175,17 +513,62 @@ impl Player<Move> for MctsAI {
     }
 
     fn step(&mut self, game: &Game<Move>) -> Result<StepResult, UpdateError> {
-        let tree = self.tree((*game).into());
-        if tree.root_node.state.matches(*game) {
-            tree.advance();
+        if game.acted_before_moving().is_none() {
+            self.info = None;
+            self.veto = None;
+            self.booked = self.follow_book(*game);
+            self.claim = if self.booked {
+                None
+            } else {
+                self.follow_proof(*game)
+            };
+            self.search(*game);
+            if self.guard && !self.booked && self.claim.is_none() {
+                self.check_turn(*game);
+            }
+            if self.resigning() {
+                return match game.resign() {
+                    ActionResult::Continue(game) => Ok(StepResult::Move(game)),
+                    ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+                };
+            }
+        }
+        // The root has already moved on to the chosen turn
+        let state = self.search(*game);
+        if let Some(action) = state.pre_move {
+            if game.acted_before_moving().is_none() {
+                return Ok(StepResult::Move(game.apply_pre_move(action)));
+            }
         }
 
-        let action = tree.root_node.state.mv.expect("Missing move action!");
+        let action = state.mv.expect("Missing move action!");
         match game.clone().apply(action) {
             ActionResult::Continue(game) => Ok(StepResult::Build(game)),
             ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
         }
     }
+
+    fn evaluation(&self) -> Option<f64> {
+        match &self.mcts {
+            MctsOrParams::Tree(_) if self.booked => None,
+            MctsOrParams::Tree(_) if self.claim.is_some() => Some(1.0),
+            MctsOrParams::Tree(_) if self.veto.is_some() => self.veto,
+            MctsOrParams::Tree(tree) => Some(tree.root_node.score),
+            MctsOrParams::Params(_) => None,
+        }
+    }
+
+    fn claim(&self) -> Option<u32> {
+        self.claim
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.clone()
+    }
+
+    fn attach(&mut self, interrupt: &Arc<Interrupt>) {
+        self.attach_interrupt(interrupt);
+    }
 }
 
 impl Player<Build> for MctsAI {
@@ -196,15 +579,508 @@ impl Player<Build> for MctsAI {
     }
 
     fn step(&mut self, game: &Game<Build>) -> Result<StepResult, UpdateError> {
-        let action = self
-            .expect("Unitialized tree!")
-            .root_node
-            .state
-            .build
-            .expect("Missing build action!");
+        let state = &self.mcts.expect("Unitialized tree!").root_node.state;
+        if let Some(action) = state.extra_move {
+            // The extra move hasn't been played yet if the worker is still
+            // where it started it from
+            if game.active_pawn().pos() == action.from() {
+                return match game.apply_move(action) {
+                    ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                    ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+                };
+            }
+        }
+        if let Some(action) = state.other_move {
+            // Likewise for Hermes' other worker
+            if game.worker_on(action.from()).is_some() {
+                return match game.apply_move(action) {
+                    ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                    ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+                };
+            }
+        }
+
+        let build = state.build.expect("Missing build action!");
+        let action = match state.extra_build {
+            Some(_) if game.builds() == 0 => return Ok(StepResult::Build(game.build_again(build))),
+            Some(extra_build) => extra_build,
+            None => build,
+        };
         match game.clone().apply(action) {
             ActionResult::Continue(game) => Ok(StepResult::Move(game)),
             ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
         }
     }
 }
+
+#[cfg(test)]
+mod mcts_ai_tests {
+    use super::*;
+    use crate::mcts::santorini::{
+        EpsilonGreedyPolicy, HeuristicExpansion, MastPolicy, NodeState, RandomPolicy,
+        RolloutSimulation,
+    };
+    use crate::mcts::tree_policy::RAVE;
+    use crate::mcts::{Expansion, MoveStats, Simulation, SimulationResult, Widening};
+    use crate::player::heuristic_ai::evaluate;
+    use crate::santorini::{
+        AnyGame, Board, CoordLevel, GameBuilder, God, Point, BOARD_HEIGHT, BOARD_WIDTH,
+    };
+    use std::sync::Mutex;
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// A game with the heights given as `(x, y, level)`, and Player Two's
+    /// workers in the bottom right corner.
+    fn moving(heights: &[(i8, i8, i8)], p1: [Point; 2]) -> Game<Move> {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let mut board = Board::new();
+        for &(x, y, level) in heights {
+            board.set_level(pt(x, y), level.into());
+        }
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(santorini::Player::PlayerOne, &p1)
+            .workers(
+                santorini::Player::PlayerTwo,
+                &[pt(w - 2, h - 1), pt(w - 1, h - 1)],
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn reroots_unknown_positions() {
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(5));
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        ai.prepare(&game);
+        ai.step(&game).unwrap();
+
+        // Nothing in the tree leads here, so the search starts over
+        let other = moving(&[], [pt(2, 2), pt(1, 0)]);
+        ai.prepare(&other);
+        assert!(ai.mcts.expect("No tree!").root_node.state.matches(other));
+        assert!(ai.step(&other).is_ok());
+    }
+
+    #[test]
+    fn places_from_the_tree() {
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(1));
+        let game = santorini::new_game();
+        ai.prepare(&game);
+        let placed = match ai.step(&game).unwrap() {
+            StepResult::PlaceTwo(placed) => placed,
+            _ => panic!("Not placed!"),
+        };
+        assert!(ai.mcts.expect("No tree!").root_node.state.matches(placed));
+        assert_eq!(placed.player(), santorini::Player::PlayerTwo);
+    }
+
+    #[test]
+    fn rave_shares_statistics() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let params = MctsSantoriniParams::default().tree_policy(RAVE::default());
+        let mut tree = Mcts::new(params, game.into());
+        for _ in 0..3 {
+            tree.root_node.step(&mut tree.params);
+        }
+        let children = tree.root_node.children.as_ref().unwrap();
+        assert!(children.iter().all(|child| child.state.turn_id.is_some()));
+        assert!(children.iter().any(|child| child.amaf_iterations > 1));
+    }
+
+    #[test]
+    fn widening_holds_back_children() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let params = MctsSantoriniParams::default().widening(Widening {
+            constant: 1.0,
+            exponent: 0.5,
+        });
+        let mut tree = Mcts::new(params, game.into());
+        let turns = game.legal_turns().count();
+        tree.root_node.step(&mut tree.params);
+        let root = &tree.root_node;
+        assert_eq!(root.children.as_ref().unwrap().len(), 1);
+        assert_eq!(root.pending.len(), turns - 1);
+
+        for _ in 0..10 {
+            tree.root_node.step(&mut tree.params);
+        }
+        let root = &tree.root_node;
+        let children = root.children.as_ref().unwrap().len();
+        assert!(children > 1);
+        assert!(children as f64 <= (root.iterations as f64).sqrt().ceil());
+        assert_eq!(children + root.pending.len(), turns);
+    }
+
+    /// Scores every state as a draw, counting the batches it's given.
+    struct BatchCounter {
+        batches: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl<R: Rng> Simulation<SantoriniNode, R> for BatchCounter {
+        fn simulate(&self, _: &SantoriniNode, _: &mut R, _: &mut MoveStats) -> SimulationResult {
+            SimulationResult {
+                score: 0.0,
+                plies: 0,
+            }
+        }
+
+        fn simulate_batch(
+            &self,
+            states: &[SantoriniNode],
+            rng: &mut R,
+            stats: &mut MoveStats,
+        ) -> Vec<(SimulationResult, Vec<u64>)> {
+            self.batches.lock().unwrap().push(states.len());
+            states
+                .iter()
+                .map(|state| (self.simulate(state, rng, stats), vec![]))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn batched_expansion() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let batches = Arc::new(Mutex::new(vec![]));
+        let simulation = BatchCounter {
+            batches: batches.clone(),
+        };
+        let params = MctsParams::new(
+            simulation,
+            SantoriniExpansion {},
+            SmallRng::seed_from_u64(0),
+        );
+        let mut tree = Mcts::new(params, game.into());
+        tree.root_node.expand(&mut tree.params);
+        let turns = game.legal_turns().count();
+        assert_eq!(*batches.lock().unwrap(), vec![turns]);
+        assert_eq!(tree.root_node.iterations as usize, turns + 1);
+    }
+
+    #[test]
+    fn heuristic_priors() {
+        // Moving from a1 up to b1 wins
+        let game = moving(&[(0, 0, 2), (1, 0, 3)], [pt(0, 0), pt(2, 2)]);
+        let expansion = HeuristicExpansion { temperature: 0.2 };
+        let children = expansion.expand_with_priors(&game.into());
+        let total: f64 = children.iter().map(|(_, prior)| prior).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let (best, _) = children
+            .iter()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .unwrap();
+        assert!(match best.game {
+            NodeState::Victory(player) => player == santorini::Player::PlayerOne,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn node_limit() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let mut tree = Mcts::new(MctsSantoriniParams::default(), game.into());
+        for _ in 0..4 {
+            tree.root_node.step(&mut tree.params);
+        }
+        let size = tree.root_node.size();
+        let children = tree.root_node.children.as_ref().unwrap().len();
+        assert!(size > children + 1);
+
+        // Pruning stops once only the root's children are left
+        assert_eq!(tree.root_node.prune(size), size - children - 1);
+        assert_eq!(tree.root_node.size(), children + 1);
+        let iterations: Vec<_> = tree
+            .root_node
+            .children
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|child| child.iterations)
+            .collect();
+        assert!(iterations.iter().any(|iterations| *iterations > 1));
+
+        let params = MctsSantoriniParams::default().budget(6).node_limit(100);
+        let mut tree = Mcts::new(params, game.into());
+        tree.advance();
+        assert!(tree.root_node.size() <= 100);
+    }
+
+    #[test]
+    fn greedy_rollouts() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut stats = MoveStats::default();
+        let simulation = RolloutSimulation {
+            policy: EpsilonGreedyPolicy { epsilon: 0.0 },
+            plies: None,
+        };
+        // Player One wins by climbing from a1 to b1 straight away
+        let game = moving(&[(0, 0, 2), (1, 0, 3)], [pt(0, 0), pt(2, 2)]);
+        let (result, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+        assert_eq!((result.score, result.plies, moves.len()), (-1.0, 1, 0));
+
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let (result, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+        assert!(result.score == 1.0 || result.score == -1.0);
+        assert!(!moves.is_empty());
+        assert!(result.plies as usize > moves.len());
+    }
+
+    #[test]
+    fn truncated_rollouts() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut stats = MoveStats::default();
+        let game = moving(&[(1, 1, 1), (2, 1, 2)], [pt(0, 0), pt(1, 0)]);
+        let evaluation = evaluate(&game, santorini::Player::PlayerOne).total();
+        let simulation = RolloutSimulation {
+            policy: RandomPolicy {},
+            plies: Some(0),
+        };
+        let (result, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+        assert_eq!(
+            (result.score, result.plies, moves.len()),
+            (-evaluation, 0, 0)
+        );
+
+        let simulation = RolloutSimulation {
+            policy: RandomPolicy {},
+            plies: Some(3),
+        };
+        let (result, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+        assert!((-1.0..=1.0).contains(&result.score));
+        assert!(result.plies <= 3);
+        assert!(moves.len() <= 3);
+    }
+
+    #[test]
+    fn discounted_results() {
+        // Player One wins by climbing from a1 to b1 straight away, a ply
+        // into the root's playout
+        let game = moving(&[(0, 0, 2), (1, 0, 3)], [pt(0, 0), pt(2, 2)]);
+        let tree = Mcts::new(MctsSantoriniParams::with_seed(0), game.into());
+        assert_eq!(tree.root_node.score, -1.0);
+
+        let params = MctsSantoriniParams::with_seed(0).discount(0.5).budget(50);
+        let mut tree = Mcts::new(params, game.into());
+        assert_eq!(tree.root_node.score, -0.5);
+        // Only the win on the spot is worth a full point
+        tree.search();
+        for child in tree.root_node.children.iter().flatten() {
+            match child.state.game {
+                NodeState::Victory(_) => assert_eq!(child.score, 1.0),
+                _ => assert!(child.score.abs() <= 0.5),
+            }
+        }
+        let stats = tree.advance();
+        assert_eq!(stats.value, 1.0);
+    }
+
+    #[test]
+    fn mast_rollouts() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let simulation = RolloutSimulation {
+            policy: MastPolicy { temperature: 0.01 },
+            plies: None,
+        };
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let turns = SantoriniExpansion {}.expand(&game.into());
+
+        // Every turn taken is recorded with the result for whoever took it
+        let mut stats = MoveStats::default();
+        let (result, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+        let first = stats.value(moves[0]).unwrap();
+        let second = stats.value(moves[1]).unwrap();
+        assert_eq!((first, second), (-result.score, result.score));
+
+        // A turn that has always won is all but certain to be taken
+        let mut stats = MoveStats::default();
+        let favourite = turns[7].turn_id.unwrap();
+        stats.record(favourite, 1.0);
+        for _ in 0..10 {
+            let mut stats = stats.clone();
+            let (_, moves) = simulation.simulate_moves(&game.into(), &mut rng, &mut stats);
+            assert_eq!(moves[0], favourite);
+        }
+    }
+
+    #[test]
+    fn plays_from_the_book() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let turn: Turn = "a1>a2^a3".parse().unwrap();
+        let mut book = OpeningBook::new();
+        assert!(book.insert(game, turn));
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(1)).book(Arc::new(book));
+        ai.prepare(&game);
+        let built = match ai.step(&game).unwrap() {
+            StepResult::Build(built) => built,
+            _ => panic!("Not moved!"),
+        };
+        assert_eq!(Player::<Move>::evaluation(&ai), None);
+        let next = match ai.step(&built).unwrap() {
+            StepResult::Move(next) => next,
+            _ => panic!("Not built!"),
+        };
+        assert_eq!(AnyGame::from(game).apply_turn(turn), Some(next.into()));
+
+        // Out of the book, the search takes over
+        let other = moving(&[], [pt(0, 0), pt(2, 0)]);
+        ai.prepare(&other);
+        assert!(ai.step(&other).is_ok());
+        assert!(Player::<Move>::evaluation(&ai).is_some());
+    }
+
+    #[test]
+    fn pools_parallel_searches() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let search = || {
+            let mut tree = Mcts::new(MctsSantoriniParams::default().budget(20), game.into());
+            tree.search();
+            tree
+        };
+        let (mut tree, other) = (search(), search());
+        let visits = |root: &Node<SantoriniNode>| -> u32 {
+            root.children
+                .iter()
+                .flatten()
+                .map(|child| child.iterations)
+                .sum()
+        };
+        let expected = visits(&tree.root_node) + visits(&other.root_node);
+        pool(&mut tree.root_node, &other.root_node);
+        assert_eq!(visits(&tree.root_node), expected);
+
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(10))
+            .parallel(3, || MctsSantoriniParams::default().budget(10));
+        ai.prepare(&game);
+        assert!(matches!(ai.step(&game).unwrap(), StepResult::Build(_)));
+        assert!(ai
+            .helpers
+            .iter()
+            .all(|helper| matches!(helper, MctsOrParams::Tree(_))));
+    }
+
+    #[test]
+    fn reports_search_info() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        let mut ai = MctsAI::from(MctsSantoriniParams::with_seed(0).budget(50));
+        ai.prepare(&game);
+        let built = match ai.step(&game).unwrap() {
+            StepResult::Build(built) => built,
+            _ => panic!("Not moved!"),
+        };
+        let next = match ai.step(&built).unwrap() {
+            StepResult::Move(next) => next,
+            _ => panic!("Not built!"),
+        };
+
+        let info = Player::<Move>::search_info(&ai).unwrap();
+        assert_eq!(Some(info.value), Player::<Move>::evaluation(&ai));
+        assert!(info.simulations >= 50);
+        assert_eq!(info.visits.len(), game.legal_turns().count());
+        let played = game.apply_turn(info.principal_variation[0]);
+        assert_eq!(played, Some(ActionResult::Continue(next)));
+    }
+
+    #[test]
+    fn resigns_after_enough_losing_turns() {
+        let game = moving(&[], [pt(0, 0), pt(1, 0)]);
+        // Every evaluation counts as losing
+        let thresholds = Thresholds {
+            resign_below: 2.0,
+            resign_turns: 2,
+            claim: None,
+        };
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(5)).thresholds(thresholds);
+        ai.prepare(&game);
+        assert!(matches!(ai.step(&game).unwrap(), StepResult::Build(_)));
+
+        let other = moving(&[], [pt(0, 0), pt(2, 0)]);
+        ai.prepare(&other);
+        match ai.step(&other).unwrap() {
+            StepResult::Victory(won) => assert_eq!(won.player(), santorini::Player::PlayerTwo),
+            _ => panic!("Not resigned!"),
+        }
+    }
+
+    #[test]
+    fn announces_forced_wins() {
+        let game = moving(&[(0, 0, 2), (1, 0, 2), (2, 0, 2)], [pt(0, 0), pt(0, 2)]);
+        let mut ai = MctsAI::from(MctsSantoriniParams::default().budget(1))
+            .thresholds(Thresholds::default());
+        ai.prepare(&game);
+        let built = match ai.step(&game).unwrap() {
+            StepResult::Build(built) => built,
+            _ => panic!("Not moved!"),
+        };
+        assert_eq!(Player::<Move>::claim(&ai), Some(3));
+        assert_eq!(Player::<Move>::evaluation(&ai), Some(1.0));
+        assert!(matches!(ai.step(&built).unwrap(), StepResult::Move(_)));
+
+        // Nothing is claimed without a proof
+        let other = moving(&[], [pt(0, 0), pt(2, 0)]);
+        ai.prepare(&other);
+        assert!(ai.step(&other).is_ok());
+        assert_eq!(Player::<Move>::claim(&ai), None);
+    }
+
+    /// Play a whole turn of the AI's.
+    fn play_turn(ai: &mut MctsAI, game: &Game<Move>) -> ActionResult<Move> {
+        ai.prepare(game);
+        let built = match ai.step(game).unwrap() {
+            StepResult::Build(built) => built,
+            StepResult::Victory(won) => return ActionResult::Victory(won),
+            _ => panic!("Not moved!"),
+        };
+        match ai.step(&built).unwrap() {
+            StepResult::Move(next) => ActionResult::Continue(next),
+            StepResult::Victory(won) => ActionResult::Victory(won),
+            _ => panic!("Not built!"),
+        }
+    }
+
+    #[test]
+    fn guarded_turns() {
+        // Climbing from a1 to b1 wins
+        let game = moving(&[(0, 0, 2), (1, 0, 3)], [pt(0, 0), pt(2, 2)]);
+        let mut ai = MctsAI::from(MctsSantoriniParams::with_seed(0).budget(1)).guarded();
+        assert!(matches!(
+            play_turn(&mut ai, &game),
+            ActionResult::Victory(_)
+        ));
+        assert_eq!(Player::<Move>::claim(&ai), Some(1));
+
+        // Player Two climbs into the bottom right corner unless it's blocked
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let mut board = Board::new();
+        board.set_level(pt(w - 2, h - 1), CoordLevel::Two);
+        board.set_level(pt(w - 1, h - 1), CoordLevel::Three);
+        let game = GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(santorini::Player::PlayerOne, &[pt(0, 0), pt(w - 2, h - 2)])
+            .workers(
+                santorini::Player::PlayerTwo,
+                &[pt(0, h - 1), pt(w - 2, h - 1)],
+            )
+            .build()
+            .unwrap();
+        let loses = |result| match result {
+            ActionResult::Continue(next) => next
+                .legal_turns()
+                .any(|(_, result)| matches!(result, ActionResult::Victory(_))),
+            ActionResult::Victory(_) => false,
+        };
+        let blundered = (0..8).any(|seed| {
+            let mut ai = MctsAI::from(MctsSantoriniParams::with_seed(seed).budget(1));
+            loses(play_turn(&mut ai, &game))
+        });
+        assert!(blundered);
+        for seed in 0..8 {
+            let mut ai = MctsAI::from(MctsSantoriniParams::with_seed(seed).budget(1)).guarded();
+            assert!(!loses(play_turn(&mut ai, &game)));
+        }
+    }
+}
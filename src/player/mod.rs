@@ -1,28 +1,154 @@
-use crate::santorini::{Build, Game, GameState, Move, PlaceOne, PlaceTwo, Victory};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::mcts::Interrupt;
+use crate::santorini::{
+    Build, Game, GameState, Move, PlaceOne, PlaceResult, PlaceTwo, Turn, Victory,
+};
 use crate::ui::{BoardWidget, UpdateError};
 
+pub mod background;
+pub mod difficulty;
+pub mod external;
 pub mod heuristic_ai;
 pub mod human;
 pub mod mcts_ai;
+pub mod opening_book;
+pub mod paced;
 pub mod random_ai;
 
-pub use heuristic_ai::HeuristicAI;
+pub use background::Background;
+pub use difficulty::Difficulty;
+pub use external::ExternalPlayer;
+pub use heuristic_ai::{HeuristicAI, HeuristicWeights};
 pub use human::HumanPlayer;
-pub use mcts_ai::{MctsAI, MctsSantoriniParams};
+pub use mcts_ai::{MctsAI, MctsSantoriniParams, Thresholds};
+pub use opening_book::OpeningBook;
+pub use paced::{changed_squares, Paced};
 pub use random_ai::RandomAI;
 
 pub enum StepResult {
     NoMove,
+    /// The step is still being worked on in the background.
+    InProgress(Progress),
     PlaceTwo(Game<PlaceTwo>),
     Move(Game<Move>),
     Build(Game<Build>),
     Victory(Game<Victory>),
+    /// The player asks to take back their turn.
+    Undo,
+    /// The player asks for the pause menu.
+    Pause,
+}
+
+impl From<PlaceResult> for StepResult {
+    fn from(result: PlaceResult) -> Self {
+        match result {
+            PlaceResult::Place(game) => StepResult::PlaceTwo(game),
+            PlaceResult::Move(game) => StepResult::Move(game),
+        }
+    }
+}
+
+/// How far a step running in the background has got.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub elapsed: Duration,
+    /// The simulations run so far, by players that search.
+    pub simulations: u32,
+}
+
+/// Written as `3.2s, 12k sims`, leaving out the simulations if there are
+/// none.
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}s", self.elapsed.as_secs_f64())?;
+        if self.simulations >= 1000 {
+            write!(f, ", {}k sims", self.simulations / 1000)?;
+        } else if self.simulations > 0 {
+            write!(f, ", {} sims", self.simulations)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a searching player found out while choosing its last turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    /// The player's evaluation of the turn it chose, as `Player::evaluation`
+    /// gives it.
+    pub value: f64,
+    /// The simulations run from the position the turn was chosen in.
+    pub simulations: u32,
+    /// The turns the player expects to be played, starting with its own.
+    pub principal_variation: Vec<Turn>,
+    /// Each turn the player considered, with the simulations spent on it.
+    pub visits: Vec<(Turn, u32)>,
+}
+
+/// How many turns of the principal variation are written out.
+const SHOWN_TURNS: usize = 5;
+
+/// Written as `eval +0.43, 12k sims, PV: c3>c4^d4 b2>b3^c3`.
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "eval {:+.2}, ", self.value)?;
+        if self.simulations >= 1000 {
+            write!(f, "{}k sims", self.simulations / 1000)?;
+        } else {
+            write!(f, "{} sims", self.simulations)?;
+        }
+        write!(f, ", PV:")?;
+        for turn in self.principal_variation.iter().take(SHOWN_TURNS) {
+            write!(f, " {}", turn)?;
+        }
+        if self.principal_variation.len() > SHOWN_TURNS {
+            write!(f, " …")?;
+        }
+        Ok(())
+    }
 }
 
 pub trait Player<T: GameState> {
     fn prepare(&mut self, game: &Game<T>);
     fn render(&self, game: &Game<T>) -> BoardWidget;
     fn step(&mut self, game: &Game<T>) -> Result<StepResult, UpdateError>;
+
+    /// The player's evaluation of the position produced by its last step, from
+    /// the perspective of the player who took it. Ranges from -1.0 (certain loss)
+    /// to 1.0 (certain win). Players without an evaluation return None.
+    fn evaluation(&self) -> Option<f64> {
+        None
+    }
+
+    /// The number of plies, counting the turn of its last step, within which
+    /// the player has proven it wins whatever its opponents do. Players that
+    /// don't look for forced wins return None.
+    fn claim(&self) -> Option<u32> {
+        None
+    }
+
+    /// What the player's search found out while choosing the turn of its
+    /// last step. Players that don't search return None.
+    fn search_info(&self) -> Option<SearchInfo> {
+        None
+    }
+
+    /// A yes or no question the player's next step waits on an answer to,
+    /// to show over the board. Players that never ask return None.
+    fn prompt(&self) -> Option<&str> {
+        None
+    }
+
+    /// Report the player's searches through the interrupt from now on, and
+    /// stop them early when it says to. Players that don't search ignore it.
+    fn attach(&mut self, _interrupt: &Arc<Interrupt>) {}
+
+    /// Ask a step still in progress to finish as soon as it can, with what
+    /// it has found so far. Players that step straight away ignore this.
+    fn stop(&self) {}
 }
 
 pub trait FullPlayer:
@@ -33,3 +159,171 @@ impl<T> FullPlayer for T where
     T: Send + Player<PlaceOne> + Player<PlaceTwo> + Player<Build> + Player<Move>
 {
 }
+
+/// Which of two contestants places first in each game of a session. Player
+/// One always places first, so this decides who sits in that seat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstPlayer {
+    /// The first contestant is always Player One.
+    Fixed,
+    /// The contestants take turns being Player One.
+    Alternate,
+    /// Player One is picked at random for each game.
+    Random,
+}
+
+impl FirstPlayer {
+    /// Whether the contestants should swap seats for the given game of the
+    /// session, counting from zero.
+    pub fn swap(self, game: usize) -> bool {
+        match self {
+            FirstPlayer::Fixed => false,
+            FirstPlayer::Alternate => game % 2 == 1,
+            FirstPlayer::Random => rand::random(),
+        }
+    }
+}
+
+impl fmt::Display for FirstPlayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirstPlayer::Fixed => write!(f, "fixed"),
+            FirstPlayer::Alternate => write!(f, "alternate"),
+            FirstPlayer::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for FirstPlayer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(FirstPlayer::Fixed),
+            "alternate" => Ok(FirstPlayer::Alternate),
+            "random" => Ok(FirstPlayer::Random),
+            _ => Err(format!("unknown first player option: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod search_info_tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let turn: Turn = "c3>c4^d4".parse().unwrap();
+        let mut info = SearchInfo {
+            value: 0.434,
+            simulations: 12_345,
+            principal_variation: vec![turn],
+            visits: vec![(turn, 12_000)],
+        };
+        assert_eq!(info.to_string(), "eval +0.43, 12k sims, PV: c3>c4^d4");
+
+        info.value = -0.5;
+        info.simulations = 800;
+        info.principal_variation = vec![turn; 6];
+        assert_eq!(
+            info.to_string(),
+            format!("eval -0.50, 800 sims, PV:{} …", " c3>c4^d4".repeat(5))
+        );
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut progress = Progress {
+            elapsed: Duration::from_millis(3240),
+            simulations: 0,
+        };
+        assert_eq!(progress.to_string(), "3.2s");
+        progress.simulations = 800;
+        assert_eq!(progress.to_string(), "3.2s, 800 sims");
+        progress.simulations = 12_345;
+        assert_eq!(progress.to_string(), "3.2s, 12k sims");
+    }
+}
+
+#[cfg(test)]
+mod first_player_tests {
+    use super::*;
+
+    #[test]
+    fn seating() {
+        let fixed: Vec<bool> = (0..4).map(|game| FirstPlayer::Fixed.swap(game)).collect();
+        assert_eq!(fixed, vec![false; 4]);
+        let alternate: Vec<bool> = (0..4)
+            .map(|game| FirstPlayer::Alternate.swap(game))
+            .collect();
+        assert_eq!(alternate, vec![false, true, false, true]);
+
+        assert_eq!("random".parse(), Ok(FirstPlayer::Random));
+        assert!("second".parse::<FirstPlayer>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod seeding_tests {
+    use super::*;
+    use crate::arena::{self, Rules};
+    use crate::santorini::{self, AnyGame, Point, BOARD_HEIGHT, BOARD_WIDTH};
+
+    /// Where two players leave a game after the given number of turns.
+    fn play(mut players: [Box<dyn FullPlayer>; 2], start: AnyGame, turns: u32) -> AnyGame {
+        let [p1, p2] = &mut players;
+        let rules = Rules {
+            max_turns: turns,
+            adjudication: None,
+        };
+        arena::play_from(&mut **p1, &mut **p2, start, &rules)
+            .unwrap()
+            .game
+    }
+
+    #[test]
+    fn seeded_players_repeat_themselves() {
+        let new_game = AnyGame::from(santorini::new_game());
+        let random = |seed| {
+            play(
+                [RandomAI::with_seed(seed), RandomAI::with_seed(2)],
+                new_game,
+                100,
+            )
+        };
+        assert_eq!(random(1), random(1));
+        assert_ne!(random(1), random(3));
+
+        // Searching from the placements takes too long for a test
+        let (width, height) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let game = santorini::new_game();
+        let game = game.apply(game.can_place(&[pt(0, 0), pt(2, 2)]).unwrap());
+        let game = game.apply(
+            game.can_place(&[pt(width - 1, 0), pt(1, height - 2)])
+                .unwrap(),
+        );
+        let game = AnyGame::from(game.unwrap());
+        let heuristic = || {
+            play(
+                [HeuristicAI::new().boxed(), RandomAI::with_seed(2)],
+                game,
+                2,
+            )
+        };
+        assert_eq!(heuristic(), heuristic());
+        let mcts = || {
+            let players = [
+                MctsSantoriniParams::with_seed(1).budget(10).boxed(),
+                MctsSantoriniParams::with_seed(2).budget(10).boxed(),
+            ];
+            play(players, game, 4)
+        };
+        assert_eq!(mcts(), mcts());
+    }
+}
@@ -0,0 +1,225 @@
+//! Turns to play in the first few plies, looked up instead of searched for.
+//!
+//! A book maps positions to the turn recommended in them. Positions are
+//! stored in their canonical form, so one entry covers every position
+//! symmetric to it. Books are written one entry per line, the position in
+//! game notation then the turn, separated by `|`:
+//!
+//! ```text
+//! # Player One's placement
+//! 00000/00000/00000/00000/00000 - - 1 p Mortal,Mortal | b2,c3
+//! ```
+//!
+//! Blank lines and lines starting with `#` are skipped. The position needn't
+//! be canonical; the turn is stored as seen in its canonical form.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::santorini::notation::{self, NotationError};
+use crate::santorini::{ActionResult, AnyGame, Game, Move, Turn, TurnActions};
+
+/// The book built into the crate, covering the placements of Mortal games.
+#[cfg(not(any(board_size = "4", board_size = "6")))]
+const BUILTIN: &str = include_str!("opening_book.txt");
+/// The book's positions are on the standard board, so the other sizes go
+/// without.
+#[cfg(any(board_size = "4", board_size = "6"))]
+const BUILTIN: &str = "";
+
+#[derive(Error, Debug)]
+pub enum BookError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid entry on line {0}: {1}")]
+    Entry(usize, NotationError),
+    #[error("Illegal turn on line {0}")]
+    Illegal(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    /// The turns by the notation of the canonical position they're played in.
+    turns: HashMap<String, Turn>,
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook::default()
+    }
+
+    pub fn builtin() -> OpeningBook {
+        BUILTIN.parse().expect("Invalid built in opening book!")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OpeningBook, BookError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Recommend a turn in a game, replacing any turn recommended before in
+    /// it or a position symmetric to it. Returns false, leaving the book
+    /// alone, if the turn isn't legal.
+    pub fn insert<G: Into<AnyGame>>(&mut self, game: G, turn: Turn) -> bool {
+        let game = game.into();
+        if game.apply_turn(turn).is_none() {
+            return false;
+        }
+        let symmetry = game.canonical_symmetry();
+        self.turns.insert(
+            notation::encode(game.transform(symmetry)),
+            turn.transform(symmetry),
+        );
+        true
+    }
+
+    /// The turn the book recommends in a game, if it has one.
+    pub fn turn<G: Into<AnyGame>>(&self, game: G) -> Option<Turn> {
+        let game = game.into();
+        let symmetry = game.canonical_symmetry();
+        let turn = self
+            .turns
+            .get(&notation::encode(game.transform(symmetry)))?
+            .transform(symmetry.inverse());
+        // Entries are only ever added for legal turns, so this only guards
+        // against positions that differ in what notation leaves out
+        game.apply_turn(turn).map(|_| turn)
+    }
+
+    /// The actions making up the turn the book recommends in a game, for
+    /// players that take turns an action at a time, and where they lead.
+    pub fn actions(&self, game: &Game<Move>) -> Option<(TurnActions, ActionResult<Move>)> {
+        let result = game.apply_turn(self.turn(*game)?)?;
+        game.legal_turns().find(|(_, other)| *other == result)
+    }
+}
+
+impl FromStr for OpeningBook {
+    type Err = BookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut book = OpeningBook::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = |err| BookError::Entry(idx + 1, err);
+            let (game, turn) = line
+                .split_once('|')
+                .ok_or_else(|| entry(NotationError::new("expected <position> | <turn>".into())))?;
+            let game = notation::parse(game.trim()).map_err(entry)?;
+            let turn = turn.trim().parse().map_err(entry)?;
+            if !book.insert(game, turn) {
+                return Err(BookError::Illegal(idx + 1));
+            }
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod opening_book_tests {
+    use super::*;
+    use crate::player::{HeuristicAI, StepResult};
+    use crate::santorini::{
+        self, Board, CoordLevel, GameBuilder, God, Player, Point, Symmetry, BOARD_HEIGHT,
+        BOARD_WIDTH,
+    };
+    use std::sync::Arc;
+
+    /// A game with Player Two's second worker on the given square.
+    fn moving(p2: Point) -> Game<Move> {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::One);
+        GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(2, 2)])
+            .workers(Player::PlayerTwo, &[pt(BOARD_WIDTH.0 - 1, 0), p2])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn symmetric_lookups() {
+        let b = BOARD_HEIGHT.0 - 1;
+        let game = moving(Point::new(1.into(), (b - 1).into()));
+        let turn: Turn = "c3>b2^c2".parse().unwrap();
+        let mut book = OpeningBook::new();
+        assert!(book.insert(game, turn));
+        assert!(!book.insert(game, "c3>c1^c2".parse().unwrap()));
+        assert_eq!(book.len(), 1);
+
+        for symmetry in Symmetry::all() {
+            let other = game.transform(symmetry);
+            assert_eq!(book.turn(other), Some(turn.transform(symmetry)));
+        }
+        let other = moving(Point::new(1.into(), b.into()));
+        assert_eq!(book.turn(other), None);
+
+        let (actions, _) = book.actions(&game).unwrap();
+        assert_eq!(actions.mv.to(), "b2".parse().unwrap());
+        assert_eq!(actions.build.map(|build| build.loc()), "c2".parse().ok());
+    }
+
+    #[test]
+    fn parsing() {
+        let empty = notation::encode_board(&Board::new());
+        let book: OpeningBook = format!("# Comment\n\n{} - - 1 p Mortal,Mortal | b2,c3\n", empty)
+            .parse()
+            .unwrap();
+        assert_eq!(book.len(), 1);
+        assert!(book.turn(santorini::new_game()).is_some());
+
+        let err = format!("{} - - 1 p Mortal,Mortal b2,c3", empty)
+            .parse::<OpeningBook>()
+            .unwrap_err();
+        assert!(matches!(err, BookError::Entry(1, _)));
+        let err = format!("\n{} - - 1 p Mortal,Mortal | b2,b2", empty)
+            .parse::<OpeningBook>()
+            .unwrap_err();
+        assert!(matches!(err, BookError::Illegal(2)));
+    }
+
+    #[test]
+    // The built in book only covers the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn builtin() {
+        let book = OpeningBook::builtin();
+        assert!(!book.is_empty());
+        let placed = match book.turn(santorini::new_game()) {
+            Some(Turn::Place(locs)) => santorini::new_game().apply_turn(Turn::Place(locs)).unwrap(),
+            _ => panic!("No placement!"),
+        };
+        assert!(matches!(book.turn(placed), Some(Turn::Place(_))));
+    }
+
+    #[test]
+    // Without the built in book there's no placement to compare against
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn heuristic_placements() {
+        let book = Arc::new(OpeningBook::builtin());
+        let mut ai = HeuristicAI::with_book(book.clone()).boxed();
+        let game = santorini::new_game();
+        let placed = match ai.step(&game).unwrap() {
+            StepResult::PlaceTwo(placed) => placed,
+            _ => panic!("Not placed!"),
+        };
+        let expected = AnyGame::from(game).apply_turn(book.turn(game).unwrap());
+        assert_eq!(expected, Some(placed.into()));
+        assert!(matches!(ai.step(&placed).unwrap(), StepResult::Move(_)));
+    }
+}
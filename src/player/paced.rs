@@ -0,0 +1,178 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::mcts::Interrupt;
+use crate::player::{FullPlayer, Player, SearchInfo, StepResult};
+use crate::santorini::{AnyGame, Game, GameState, Point};
+use crate::ui::{BoardWidget, UpdateError};
+
+/// Wraps an AI so each of its steps stays on screen for a minimum amount of
+/// time.
+///
+/// The chosen step is held back and highlighted on the board until the delay
+/// is up, stepping without a move meanwhile, so fast engines can be followed
+/// by a spectator.
+pub struct Paced {
+    player: Box<dyn FullPlayer>,
+    delay: Duration,
+    pending: Option<(StepResult, Instant)>,
+    highlights: Vec<Point>,
+}
+
+impl Paced {
+    pub fn new(player: Box<dyn FullPlayer>, delay: Duration) -> Self {
+        Paced {
+            player,
+            delay,
+            pending: None,
+            highlights: vec![],
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn FullPlayer> {
+        Box::new(self)
+    }
+}
+
+fn outcome(result: &StepResult) -> Option<AnyGame> {
+    match result {
+        StepResult::NoMove | StepResult::InProgress(_) | StepResult::Undo | StepResult::Pause => {
+            None
+        }
+        StepResult::PlaceTwo(game) => Some((*game).into()),
+        StepResult::Move(game) => Some((*game).into()),
+        StepResult::Build(game) => Some((*game).into()),
+        StepResult::Victory(game) => Some((*game).into()),
+    }
+}
+
+/// Every square whose height or occupant differs between the two games.
+pub fn changed_squares(before: AnyGame, after: AnyGame) -> Vec<Point> {
+    let (before_locs, after_locs) = (before.pawn_locs(), after.pawn_locs());
+    let mut changed = vec![];
+    for pt in Point::all() {
+        if before.board().level_at(pt) != after.board().level_at(pt)
+            || before_locs.contains(&pt) != after_locs.contains(&pt)
+        {
+            changed.push(pt);
+        }
+    }
+    changed
+}
+
+impl<T> Player<T> for Paced
+where
+    T: GameState + Copy,
+    dyn FullPlayer: Player<T>,
+    AnyGame: From<Game<T>>,
+{
+    fn prepare(&mut self, game: &Game<T>) {
+        self.pending = None;
+        self.highlights.clear();
+        Player::<T>::prepare(&mut *self.player, game);
+    }
+
+    fn render(&self, game: &Game<T>) -> BoardWidget<'_> {
+        let widget = Player::<T>::render(&*self.player, game);
+        if self.pending.is_some() {
+            BoardWidget {
+                highlights: &self.highlights,
+                ..widget
+            }
+        } else {
+            widget
+        }
+    }
+
+    fn step(&mut self, game: &Game<T>) -> Result<StepResult, UpdateError> {
+        if let Some((result, shown)) = self.pending.take() {
+            // Keep the step on screen until the delay is up
+            if shown.elapsed() < self.delay {
+                self.pending = Some((result, shown));
+                return Ok(StepResult::NoMove);
+            }
+            self.highlights.clear();
+            return Ok(result);
+        }
+
+        let result = Player::<T>::step(&mut *self.player, game)?;
+        match outcome(&result) {
+            Some(after) if self.delay > Duration::from_secs(0) => {
+                self.highlights = changed_squares((*game).into(), after);
+                self.pending = Some((result, Instant::now()));
+                Ok(StepResult::NoMove)
+            }
+            _ => Ok(result),
+        }
+    }
+
+    fn evaluation(&self) -> Option<f64> {
+        Player::<T>::evaluation(&*self.player)
+    }
+
+    fn claim(&self) -> Option<u32> {
+        Player::<T>::claim(&*self.player)
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        Player::<T>::search_info(&*self.player)
+    }
+
+    fn attach(&mut self, interrupt: &Arc<Interrupt>) {
+        Player::<T>::attach(&mut *self.player, interrupt);
+    }
+
+    fn stop(&self) {
+        Player::<T>::stop(&*self.player);
+    }
+}
+
+#[cfg(test)]
+mod paced_tests {
+    use super::*;
+    use crate::player::RandomAI;
+    use crate::santorini::{self, Coord, BOARD_HEIGHT, BOARD_WIDTH};
+    use std::thread;
+
+    #[test]
+    fn changed_squares_covers_move_and_build() {
+        let game = santorini::new_game();
+        let pt = |x: i8, y: i8| Point::new(Coord::from(x), Coord::from(y));
+        let (r, b) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let game = game.apply(game.can_place(&[pt(0, 0), pt(r, b)]).unwrap());
+        let game = game
+            .apply(game.can_place(&[pt(0, b), pt(r, 0)]).unwrap())
+            .unwrap();
+        let before = game;
+
+        let pawns = game.active_pawns();
+        let pawn = pawns.iter().find(|pawn| pawn.pos() == pt(0, 0)).unwrap();
+        let mv = pawn.can_move(pt(1, 1)).unwrap();
+        let game = game.apply(mv).unwrap();
+        let build = game.active_pawn().can_build(pt(2, 2)).unwrap();
+        let after = game.apply(build).unwrap();
+
+        assert_eq!(
+            changed_squares(before.into(), after.into()),
+            vec![pt(0, 0), pt(1, 1), pt(2, 2)]
+        );
+    }
+
+    #[test]
+    fn steps_wait_out_the_delay() {
+        let game = santorini::new_game();
+        let mut paced = Paced::new(RandomAI::with_seed(0), Duration::from_millis(50));
+        Player::prepare(&mut paced, &game);
+
+        // The step is shown without being applied, and without blocking
+        let start = Instant::now();
+        assert!(matches!(paced.step(&game), Ok(StepResult::NoMove)));
+        assert!(!paced.highlights.is_empty());
+        assert!(matches!(paced.step(&game), Ok(StepResult::NoMove)));
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(matches!(paced.step(&game), Ok(StepResult::PlaceTwo(_))));
+        assert!(paced.highlights.is_empty());
+    }
+}
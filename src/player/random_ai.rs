@@ -2,16 +2,30 @@ use crate::player::{FullPlayer, Player, StepResult};
 use crate::santorini::{
     self, ActionResult, Build, Game, GameState, Move, NormalState, PlaceOne, PlaceTwo, Point,
 };
-use crate::ui::{BoardWidget, UpdateError};
-use rand::Rng;
+use crate::ui::{placed_locs, worker_locs, BoardWidget, UpdateError};
+use rand::rngs::SmallRng;
+use rand::seq::IteratorRandom;
+use rand::{Rng, SeedableRng};
 
 static EMPTY: Vec<Point> = Vec::new();
 
-pub struct RandomAI {}
+pub struct RandomAI {
+    rng: SmallRng,
+}
 
 impl RandomAI {
     pub fn new() -> Box<dyn FullPlayer> {
-        Box::new(RandomAI {})
+        Box::new(RandomAI {
+            rng: SmallRng::from_entropy(),
+        })
+    }
+
+    /// A player making the same choices every time it's given the same seed
+    /// and the same games.
+    pub fn with_seed(seed: u64) -> Box<dyn FullPlayer> {
+        Box::new(RandomAI {
+            rng: SmallRng::seed_from_u64(seed),
+        })
     }
 }
 
@@ -22,26 +36,13 @@ fn default_render<'a, T: GameState + NormalState>(game: &Game<T>) -> BoardWidget
         cursor: None,
 
         highlights: &EMPTY,
-        player1_locs: game
-            .player_pawns(santorini::Player::PlayerOne)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
-        player2_locs: game
-            .player_pawns(santorini::Player::PlayerTwo)
-            .iter()
-            .map(|pawn| pawn.pos())
-            .collect(),
+        suggestions: vec![],
+        player1_locs: worker_locs(game, santorini::Player::PlayerOne),
+        player2_locs: worker_locs(game, santorini::Player::PlayerTwo),
+        player3_locs: worker_locs(game, santorini::Player::PlayerThree),
     }
 }
 
-fn random_pt() -> Point {
-    let mut rng = rand::thread_rng();
-    let x: i8 = rng.gen_range(0, santorini::BOARD_WIDTH.0);
-    let y: i8 = rng.gen_range(0, santorini::BOARD_HEIGHT.0);
-    Point::new(x.into(), y.into())
-}
-
 impl Player<PlaceOne> for RandomAI {
     fn prepare(&mut self, _: &Game<PlaceOne>) {}
 
@@ -52,16 +53,16 @@ impl Player<PlaceOne> for RandomAI {
             cursor: None,
 
             highlights: &EMPTY,
+            suggestions: vec![],
             player1_locs: vec![],
             player2_locs: vec![],
+            player3_locs: vec![],
         }
     }
 
     fn step(&mut self, game: &Game<PlaceOne>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt();
-        let pt2 = random_pt();
-        match game.can_place(pt1, pt2) {
-            Some(action) => Ok(StepResult::PlaceTwo(game.clone().apply(action))),
+        match game.placements().choose(&mut self.rng) {
+            Some(action) => Ok(StepResult::PlaceTwo(game.apply(action))),
             None => Ok(StepResult::NoMove),
         }
     }
@@ -77,16 +78,16 @@ impl Player<PlaceTwo> for RandomAI {
             cursor: None,
 
             highlights: &EMPTY,
-            player1_locs: game.player1_locs().to_vec(),
-            player2_locs: vec![],
+            suggestions: vec![],
+            player1_locs: placed_locs(game, santorini::Player::PlayerOne),
+            player2_locs: placed_locs(game, santorini::Player::PlayerTwo),
+            player3_locs: placed_locs(game, santorini::Player::PlayerThree),
         }
     }
 
     fn step(&mut self, game: &Game<PlaceTwo>) -> Result<StepResult, UpdateError> {
-        let pt1 = random_pt();
-        let pt2 = random_pt();
-        match game.can_place(pt1, pt2) {
-            Some(action) => Ok(StepResult::Move(game.clone().apply(action))),
+        match game.placements().choose(&mut self.rng) {
+            Some(action) => Ok(game.apply(action).into()),
             None => Ok(StepResult::NoMove),
         }
     }
@@ -106,7 +107,17 @@ impl Player<Move> for RandomAI {
             .map(|pawn| pawn.actions())
             .flatten()
             .collect();
-        let action_idx = rand::thread_rng().gen_range(0, actions.len());
+        let pre_moves: Vec<_> = game
+            .active_pawns()
+            .iter()
+            .flat_map(|pawn| pawn.pre_moves())
+            .collect();
+        let action_idx = self.rng.gen_range(0, actions.len() + pre_moves.len());
+        if action_idx >= actions.len() {
+            let action = pre_moves[action_idx - actions.len()];
+            return Ok(StepResult::Move(game.apply_pre_move(action)));
+        }
+
         let action = actions.into_iter().nth(action_idx).unwrap();
         match game.clone().apply(action) {
             ActionResult::Continue(game) => Ok(StepResult::Build(game)),
@@ -123,17 +134,54 @@ impl Player<Build> for RandomAI {
     }
 
     fn step(&mut self, game: &Game<Build>) -> Result<StepResult, UpdateError> {
-        let actions: Vec<_> = game
+        let moves: Vec<_> = game
             .active_pawns()
             .iter()
-            .map(|pawn| pawn.actions())
-            .flatten()
+            .flat_map(|pawn| pawn.moves())
             .collect();
-        let action_idx = rand::thread_rng().gen_range(0, actions.len());
-        let action = actions.into_iter().nth(action_idx).unwrap();
-        match game.clone().apply(action) {
+        let actions: Vec<_> = game.build_actions().collect();
+        // Declining an optional build counts as one more choice
+        let passes = if game.can_pass() { 1 } else { 0 };
+        let rng = &mut self.rng;
+        let action_idx = rng.gen_range(0, actions.len() + moves.len() + passes);
+        if let Some(action) = moves.get(action_idx).copied() {
+            return match game.apply_move(action) {
+                ActionResult::Continue(game) => Ok(StepResult::Build(game)),
+                ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
+            };
+        }
+        let action_idx = action_idx - moves.len();
+        let result = match actions.into_iter().nth(action_idx) {
+            Some(action) if game.can_build_again() && rng.gen() => {
+                return Ok(StepResult::Build(game.build_again(action)))
+            }
+            Some(action) => game.clone().apply(action),
+            None => game.pass(),
+        };
+        match result {
             ActionResult::Continue(game) => Ok(StepResult::Move(game)),
             ActionResult::Victory(game) => Ok(StepResult::Victory(game)),
         }
     }
 }
+
+#[cfg(test)]
+mod random_ai_tests {
+    use super::*;
+
+    #[test]
+    fn places_in_one_step() {
+        for seed in 0..50 {
+            let mut player = RandomAI::with_seed(seed);
+            let game = santorini::new_game();
+            let placed = match Player::<PlaceOne>::step(&mut *player, &game).unwrap() {
+                StepResult::PlaceTwo(placed) => placed,
+                _ => panic!("Not placed!"),
+            };
+            assert!(matches!(
+                Player::<PlaceTwo>::step(&mut *player, &placed).unwrap(),
+                StepResult::Move(_)
+            ));
+        }
+    }
+}
@@ -1,10 +1,40 @@
 use derive_more::{Add, Display, From};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use std::fmt;
 use std::iter::Iterator;
-use std::ops::Deref;
+use std::ops::{Add, Deref, DerefMut};
 use std::slice::Iter;
+use thiserror::Error;
+
+mod any_game;
+mod builder;
+mod god;
+mod history;
+mod legal;
+pub mod notation;
+mod packed;
+mod record;
+mod symmetry;
+mod turn;
+mod undo;
+mod zobrist;
+
+pub use any_game::{AnyAction, AnyGame};
+pub use builder::{GameBuilder, PositionError};
+pub use god::God;
+pub use history::GameHistory;
+pub use legal::{perft, TurnActions};
+pub use packed::{Phase, Position};
+pub use record::GameRecord;
+pub use symmetry::{Symmetric, Symmetry};
+pub use turn::Turn;
+pub use undo::{BuildUndo, MoveUndo};
+pub use zobrist::ZobristState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Add, Display, From)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Coord(pub i8);
 
 impl Deref for Coord {
@@ -20,14 +50,33 @@ impl From<Coord> for usize {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, PartialOrd, Ord)]
 pub struct Point {
     word: i8,
     nibble: i8,
 }
 
-pub const BOARD_WIDTH: Coord = Coord(5);
-pub const BOARD_HEIGHT: Coord = Coord(5);
+/// The length of a side of the board. Boards are 5x5 unless built with the
+/// experimental `--cfg board_size="4"` or `--cfg board_size="6"`, as in
+/// `RUSTFLAGS='--cfg board_size="6"' cargo build`. Tests of positions on the
+/// standard board are ignored on the others.
+#[cfg(not(any(board_size = "4", board_size = "6")))]
+const BOARD_SIZE: i8 = 5;
+#[cfg(board_size = "4")]
+const BOARD_SIZE: i8 = 4;
+#[cfg(board_size = "6")]
+const BOARD_SIZE: i8 = 6;
+
+pub const BOARD_WIDTH: Coord = Coord(BOARD_SIZE);
+pub const BOARD_HEIGHT: Coord = Coord(BOARD_SIZE);
+pub const BOARD_SQUARES: usize = (BOARD_SIZE as usize) * (BOARD_SIZE as usize);
+
+/// The number of words in the bitboard, which holds 16 squares to a word.
+const WORDS: usize = BOARD_SQUARES.div_ceil(16);
+
+// Move generation reads the neighbors of a square out of a single word, which
+// has to span two rows and three squares.
+const _: () = assert!((2 * BOARD_SIZE as usize + 3) * 4 <= 64, "Board too wide!");
 
 impl Point {
     pub const fn x(&self) -> Coord {
@@ -52,6 +101,12 @@ impl Point {
         (other.x().0 - self.x().0).abs() + (other.y().0 - self.y().0).abs()
     }
 
+    /// Whether the point lies on the edge of the board.
+    pub fn on_perimeter(&self) -> bool {
+        let (x, y) = (self.x().0, self.y().0);
+        x == 0 || y == 0 || x == BOARD_WIDTH.0 - 1 || y == BOARD_HEIGHT.0 - 1
+    }
+
     pub fn new(x: Coord, y: Coord) -> Point {
         match Point::new_(x, y) {
             Some(p) => p,
@@ -78,6 +133,68 @@ impl Point {
             })
         }
     }
+
+    /// Every square on the board, row by row from the top.
+    pub fn all() -> impl Iterator<Item = Point> {
+        (0..BOARD_SQUARES as i8).map(|offset| Point {
+            word: offset / 16,
+            nibble: 4 * (offset % 16),
+        })
+    }
+}
+
+/// One of the eight directions to a neighboring square, where north is
+/// towards the first rank at the top of the board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// How far a step in this direction moves along x and y.
+    pub const fn offset(&self) -> (i8, i8) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+/// Step to the neighboring square in a direction, or None if the step leaves
+/// the board.
+impl Add<Direction> for Point {
+    type Output = Option<Point>;
+
+    fn add(self, direction: Direction) -> Option<Point> {
+        let (dx, dy) = direction.offset();
+        Point::new_(Coord(self.x().0 + dx), Coord(self.y().0 + dy))
+    }
 }
 
 #[cfg(test)]
@@ -86,16 +203,17 @@ mod point_tests {
 
     #[test]
     fn valid_point() {
+        let (width, height) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         Point::new(Coord::from(0), Coord::from(0));
-        Point::new(Coord::from(4), Coord::from(4));
+        Point::new(Coord::from(width - 1), Coord::from(height - 1));
         Point::new_(Coord::from(3), Coord::from(1)).unwrap();
         Point::new_(Coord::from(2), Coord::from(0)).unwrap();
     }
 
     #[test]
     fn x_y() {
-        for x in 0..5 {
-            for y in 0..5 {
+        for x in 0..BOARD_WIDTH.0 {
+            for y in 0..BOARD_HEIGHT.0 {
                 let point = Point::new(Coord::from(x), Coord::from(y));
                 assert_eq!(point.x(), Coord::from(x));
                 assert_eq!(point.y(), Coord::from(y));
@@ -119,18 +237,68 @@ mod point_tests {
     #[test]
     #[should_panic]
     fn large_point() {
-        Point::new(Coord::from(5), Coord::from(2));
+        Point::new(BOARD_WIDTH, Coord::from(2));
     }
 
     #[test]
     fn large_point_() {
-        assert_eq!(Point::new_(Coord::from(5), Coord::from(4)), None);
-        assert_eq!(Point::new_(Coord::from(4), Coord::from(5)), None);
+        let (width, height) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        assert_eq!(
+            Point::new_(Coord::from(width), Coord::from(height - 1)),
+            None
+        );
+        assert_eq!(
+            Point::new_(Coord::from(width - 1), Coord::from(height)),
+            None
+        );
         assert_eq!(Point::new_(Coord::from(7), Coord::from(9)), None);
     }
+
+    #[test]
+    fn all_points() {
+        let points: Vec<Point> = Point::all().collect();
+        assert_eq!(points.len(), BOARD_SQUARES);
+        assert_eq!(points[0], Point::new(Coord::from(0), Coord::from(0)));
+        assert_eq!(points[1], Point::new(Coord::from(1), Coord::from(0)));
+        assert_eq!(
+            points[BOARD_WIDTH.0 as usize],
+            Point::new(Coord::from(0), Coord::from(1))
+        );
+        for (idx, point) in points.iter().enumerate() {
+            assert!(!points[idx + 1..].contains(point));
+        }
+    }
+
+    #[test]
+    fn directions() {
+        let center = Point::new(Coord::from(2), Coord::from(2));
+        assert_eq!(
+            center + Direction::North,
+            Some(Point::new(Coord::from(2), Coord::from(1)))
+        );
+        assert_eq!(
+            center + Direction::SouthWest,
+            Some(Point::new(Coord::from(1), Coord::from(3)))
+        );
+        let neighbors: Vec<Point> = Direction::ALL
+            .iter()
+            .filter_map(|direction| center + *direction)
+            .collect();
+        assert_eq!(neighbors.len(), 8);
+        assert!(neighbors.iter().all(|pt| pt.distance(center) == 1));
+
+        let corner = Point::new(Coord::from(0), Coord::from(0));
+        assert_eq!(corner + Direction::North, None);
+        assert_eq!(corner + Direction::West, None);
+        assert_eq!(
+            corner + Direction::SouthEast,
+            Some(Point::new(Coord::from(1), Coord::from(1)))
+        );
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CoordLevel {
     Ground,
     One,
@@ -176,16 +344,120 @@ impl PartialOrd for CoordLevel {
     }
 }
 
+/// The building pieces left, for games played with a limited number of them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Supply {
+    /// Blocks for the first, second and third levels.
+    blocks: [u8; 3],
+    domes: u8,
+}
+
+impl Supply {
+    /// The pieces that come in the box.
+    pub const OFFICIAL: Supply = Supply {
+        blocks: [22, 18, 14],
+        domes: 18,
+    };
+
+    pub fn new(blocks: [u8; 3], domes: u8) -> Supply {
+        Supply { blocks, domes }
+    }
+
+    /// The blocks left for building up to the given level, or the domes left
+    /// for a dome.
+    pub fn left(&self, level: CoordLevel) -> u8 {
+        match level {
+            CoordLevel::Ground => 0,
+            CoordLevel::Capped => self.domes,
+            level => self.blocks[i8::from(level) as usize - 1],
+        }
+    }
+
+    /// The count of the piece that goes on top of a square at `level`.
+    fn piece(&mut self, level: CoordLevel, dome: bool) -> &mut u8 {
+        match level {
+            CoordLevel::Three | CoordLevel::Capped => &mut self.domes,
+            _ if dome => &mut self.domes,
+            level => &mut self.blocks[i8::from(level) as usize],
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Board {
-    grid: [u64; 2],
+    grid: [u64; WORDS],
+    /// The Zobrist hash of the heights, kept up to date as the board changes.
+    hash: u64,
+    /// The pieces left to build with, or None if they never run out.
+    supply: Option<Supply>,
 }
 
 impl Board {
-    fn new() -> Board {
+    pub(crate) fn new() -> Board {
+        Board {
+            grid: [0x1111_1111_1111_1111; WORDS],
+            hash: 0,
+            supply: None,
+        }
+    }
+
+    pub fn supply(&self) -> Option<Supply> {
+        self.supply
+    }
+
+    /// The board with building limited to the given pieces.
+    pub fn with_supply(self, supply: Supply) -> Board {
         Board {
-            grid: [0x1111_1111_1111_1111; 2],
+            supply: Some(supply),
+            ..self
+        }
+    }
+
+    /// Whether there is a piece left to build on a square with, or a dome if
+    /// `dome` is set.
+    fn has_piece(&self, loc: Point, dome: bool) -> bool {
+        let mut supply = match self.supply {
+            Some(supply) => supply,
+            None => return true,
+        };
+        *supply.piece(self.level_at(loc), dome) > 0
+    }
+
+    /// Use up the piece for building on a square.
+    fn take_piece(&mut self, loc: Point, dome: bool) {
+        let level = self.level_at(loc);
+        if let Some(supply) = &mut self.supply {
+            let count = supply.piece(level, dome);
+            *count = count.checked_sub(1).expect("No pieces left!");
+        }
+    }
+
+    /// Take a build back, returning its piece to the supply.
+    fn unbuild(&mut self, loc: Point, before: CoordLevel) {
+        let after = self.level_at(loc);
+        if let Some(supply) = &mut self.supply {
+            *supply.piece(before, after == CoordLevel::Capped) += 1;
         }
+        self.set_level(loc, before);
+    }
+
+    /// Update the hash for a square changing level.
+    fn rehash(&mut self, loc: Point, before: CoordLevel, after: CoordLevel) {
+        self.hash ^= zobrist::level_key(loc, before) ^ zobrist::level_key(loc, after);
+    }
+
+    /// Set the level of a square directly, bypassing the usual build order.
+    pub(crate) fn set_level(&mut self, loc: Point, level: CoordLevel) {
+        let bits: u64 = match level {
+            CoordLevel::Ground => 0b0001,
+            CoordLevel::One => 0b0010,
+            CoordLevel::Two => 0b0100,
+            CoordLevel::Three => 0b1000,
+            CoordLevel::Capped => 0b0000,
+        };
+        self.rehash(loc, self.level_at(loc), level);
+        let data = &mut self.grid[loc.word as usize];
+        *data = (*data & !(0xF << loc.nibble)) | (bits << loc.nibble);
     }
 
     pub fn level_at(&self, loc: Point) -> CoordLevel {
@@ -215,6 +487,8 @@ impl Board {
     }
 
     fn build(&mut self, loc: Point) {
+        self.take_piece(loc, false);
+        let before = self.level_at(loc);
         let data = &mut self.grid[loc.word as usize];
         let level = (*data >> loc.nibble) & 0xF;
         let mask = match level {
@@ -227,13 +501,30 @@ impl Board {
         };
         let mask = mask << loc.nibble;
         *data ^= mask;
+        self.rehash(loc, before, self.level_at(loc));
+    }
+
+    /// Place a dome on a square, whatever its height.
+    pub fn cap(&mut self, loc: Point) {
+        self.take_piece(loc, true);
+        self.rehash(loc, self.level_at(loc), CoordLevel::Capped);
+        self.clear(loc);
     }
 
-    fn cap(&mut self, loc: Point) {
+    /// Mark a square as unavailable without updating the hash.
+    fn clear(&mut self, loc: Point) {
         let data = &mut self.grid[loc.word as usize];
         let mask1 = !(0xF << loc.nibble);
         *data &= mask1;
     }
+
+    fn apply_build(&mut self, action: BuildAction) {
+        if action.dome {
+            self.cap(action.loc);
+        } else {
+            self.build(action.loc);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -242,21 +533,22 @@ mod board_tests {
 
     #[test]
     fn level_at() {
+        let (width, height) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         let b = Board::new();
         assert_eq!(
             b.level_at(Point::new(0.into(), 0.into())),
             CoordLevel::Ground
         );
         assert_eq!(
-            b.level_at(Point::new(4.into(), 0.into())),
+            b.level_at(Point::new((width - 1).into(), 0.into())),
             CoordLevel::Ground
         );
         assert_eq!(
-            b.level_at(Point::new(0.into(), 4.into())),
+            b.level_at(Point::new(0.into(), (height - 1).into())),
             CoordLevel::Ground
         );
         assert_eq!(
-            b.level_at(Point::new(4.into(), 4.into())),
+            b.level_at(Point::new((width - 1).into(), (height - 1).into())),
             CoordLevel::Ground
         );
         assert_eq!(
@@ -305,6 +597,23 @@ mod board_tests {
         assert_eq!(b.level_at(pt), CoordLevel::Capped);
     }
 
+    #[test]
+    fn set_level() {
+        let pt = Point::new(3.into(), (BOARD_HEIGHT.0 - 1).into());
+        let mut b = Board::new();
+
+        b.set_level(pt, CoordLevel::Two);
+        assert_eq!(b.level_at(pt), CoordLevel::Two);
+        b.set_level(pt, CoordLevel::Capped);
+        assert_eq!(b.level_at(pt), CoordLevel::Capped);
+        b.set_level(pt, CoordLevel::One);
+        assert_eq!(b.level_at(pt), CoordLevel::One);
+        assert_eq!(
+            b.level_at(Point::new(2.into(), (BOARD_HEIGHT.0 - 1).into())),
+            CoordLevel::Ground
+        );
+    }
+
     #[test]
     fn less_than_equals() {
         let pt = Point::new(2.into(), 2.into());
@@ -358,37 +667,169 @@ impl CompositeBoard {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Player {
     PlayerOne,
     PlayerTwo,
+    PlayerThree,
 }
 
+/// The most players a game can seat.
+pub const MAX_PLAYERS: usize = 3;
+
 impl Player {
-    pub fn other(&self) -> Player {
+    /// Every seat, in turn order.
+    pub fn iter() -> Iter<'static, Player> {
+        static PLAYERS: [Player; MAX_PLAYERS] =
+            [Player::PlayerOne, Player::PlayerTwo, Player::PlayerThree];
+        PLAYERS.iter()
+    }
+
+    /// The player's seat, counting from zero.
+    pub fn index(&self) -> usize {
         match self {
-            Player::PlayerOne => Player::PlayerTwo,
-            Player::PlayerTwo => Player::PlayerOne,
+            Player::PlayerOne => 0,
+            Player::PlayerTwo => 1,
+            Player::PlayerThree => 2,
         }
     }
+}
 
-    pub fn iter() -> Iter<'static, Player> {
-        static PLAYERS: [Player; 2] = [Player::PlayerOne, Player::PlayerTwo];
-        PLAYERS.iter()
+/// The most workers a player can have.
+pub const MAX_WORKERS: usize = 3;
+
+/// Where one player's workers stand. Every player in a game has the same
+/// number of workers, which is two in the standard game. Workers keep their
+/// place in the list as they move, which is what their `WorkerId` refers to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WorkerLocs {
+    count: u8,
+    // Unused entries are always the top left square, so equal sets of
+    // workers compare equal.
+    locs: [Point; MAX_WORKERS],
+}
+
+impl WorkerLocs {
+    /// Panics unless there are between one and `MAX_WORKERS` locations.
+    pub fn new(locs: &[Point]) -> WorkerLocs {
+        assert!(
+            !locs.is_empty() && locs.len() <= MAX_WORKERS,
+            "Players need between 1 and {} workers!",
+            MAX_WORKERS
+        );
+        let mut workers = WorkerLocs {
+            count: locs.len() as u8,
+            locs: [Point { word: 0, nibble: 0 }; MAX_WORKERS],
+        };
+        workers.locs[..locs.len()].copy_from_slice(locs);
+        workers
+    }
+}
+
+impl From<[Point; 2]> for WorkerLocs {
+    fn from(locs: [Point; 2]) -> WorkerLocs {
+        WorkerLocs::new(&locs)
+    }
+}
+
+impl Deref for WorkerLocs {
+    type Target = [Point];
+    fn deref(&self) -> &Self::Target {
+        &self.locs[..self.count as usize]
+    }
+}
+
+impl DerefMut for WorkerLocs {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.locs[..self.count as usize]
+    }
+}
+
+impl<'a> IntoIterator for &'a WorkerLocs {
+    type Item = &'a Point;
+    type IntoIter = std::slice::Iter<'a, Point>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A worker, which keeps its identity as it moves around the board. Workers
+/// are lettered in the order their player placed them, so Player One's first
+/// worker is P1A.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WorkerId {
+    player: Player,
+    index: u8,
+}
+
+impl WorkerId {
+    /// Panics unless the index is below `MAX_WORKERS`.
+    pub fn new(player: Player, index: usize) -> WorkerId {
+        assert!(
+            index < MAX_WORKERS,
+            "Players have at most {} workers!",
+            MAX_WORKERS
+        );
+        WorkerId {
+            player,
+            index: index as u8,
+        }
+    }
+
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    /// The worker's place among its player's workers, counting from zero.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+impl fmt::Display for WorkerId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "P{}{}",
+            self.player.index() + 1,
+            (b'A' + self.index) as char
+        )
     }
 }
 
-pub trait GameState {}
+/// Each seat's workers, or None where nobody sits, a player has yet to place
+/// their workers, or they have been eliminated.
+pub type Workers = [Option<WorkerLocs>; MAX_PLAYERS];
+
+pub trait GameState: ZobristState {}
 
 pub trait NormalState {
-    fn player_locs(&self, player: Player) -> [Point; 2];
+    fn workers(&self) -> Workers;
+
+    /// The locations of a player's workers. Panics if the player has no
+    /// workers on the board.
+    fn player_locs(&self, player: Player) -> WorkerLocs {
+        self.workers()[player.index()].expect("The player has no workers on the board!")
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Game<S: GameState> {
     state: S,
     board: Board,
     player: Player,
+    gods: [God; MAX_PLAYERS],
+    /// How many seats the game has, eliminated players included.
+    players: u8,
+    /// How many workers each player has.
+    worker_count: u8,
+    /// The number of turns taken so far, by all players.
+    ply: u16,
 }
 
 impl<S: GameState> Game<S> {
@@ -399,63 +840,244 @@ impl<S: GameState> Game<S> {
     pub fn player(&self) -> Player {
         self.player
     }
+
+    /// The number of players the game started with.
+    pub fn player_count(&self) -> usize {
+        self.players as usize
+    }
+
+    /// How many workers each player has.
+    pub fn worker_count(&self) -> usize {
+        self.worker_count as usize
+    }
+
+    /// The number of plies played so far, where a ply is one player placing
+    /// their workers, taking a turn or resigning. Turns in progress don't
+    /// count until they end.
+    pub fn ply(&self) -> u16 {
+        self.ply
+    }
+
+    /// Check an action was made for this game, going by the hash of the game
+    /// it was made for. Unlike the check `apply` makes in debug builds, this
+    /// one is cheap enough to keep in release builds.
+    fn check_action(&self, game_hash: u64) -> Result<(), StaleAction> {
+        if self.hash() == game_hash {
+            Ok(())
+        } else {
+            Err(StaleAction)
+        }
+    }
+
+    /// Whether the games are the same apart from the plies it took to reach
+    /// them.
+    pub fn same_position(&self, other: &Game<S>) -> bool
+    where
+        S: Copy + PartialEq,
+    {
+        Game {
+            ply: other.ply,
+            ..*self
+        } == *other
+    }
+
+    /// The round of turns in progress, counting from one, where each round
+    /// gives every seat at the game a ply. Eliminated players' seats still
+    /// count, so rounds keep the same length throughout the game.
+    pub fn turn_number(&self) -> u16 {
+        self.ply / self.players as u16 + 1
+    }
+
+    /// The players seated at the game, in turn order.
+    pub fn players(&self) -> impl Iterator<Item = Player> {
+        Player::iter().take(self.player_count()).cloned()
+    }
+
+    /// Whether the player to move wins by moving a worker between the given
+    /// spaces, under any of their god's victory conditions.
+    fn wins_by_moving(&self, from: Point, to: Point) -> bool {
+        self.god(self.player)
+            .victory_conditions()
+            .iter()
+            .any(|condition| condition(&self.board, from, to))
+    }
+
+    pub fn god(&self, player: Player) -> God {
+        self.gods[player.index()]
+    }
+
+    /// The same game with every god moved `seats` seats back in turn order,
+    /// so gods stay with the people who chose them when everyone moves that
+    /// many seats along.
+    pub fn rotate_gods(mut self, seats: usize) -> Self {
+        let players = self.player_count();
+        self.gods[..players].rotate_left(seats % players);
+        self
+    }
 }
 
 pub fn new_game() -> Game<PlaceOne> {
+    new_game_with_gods([God::Mortal; 2])
+}
+
+/// Start a game where Player One and Player Two have the given gods.
+pub fn new_game_with_gods(gods: [God; 2]) -> Game<PlaceOne> {
     Game {
         state: PlaceOne {},
         board: Board::new(),
         player: Player::PlayerOne,
+        gods: [gods[0], gods[1], God::Mortal],
+        players: 2,
+        worker_count: 2,
+        ply: 0,
     }
 }
 
+/// Start a three player game where the players have the given gods.
+pub fn new_three_player_game(gods: [God; 3]) -> Game<PlaceOne> {
+    Game {
+        state: PlaceOne {},
+        board: Board::new(),
+        player: Player::PlayerOne,
+        gods,
+        players: 3,
+        worker_count: 2,
+        ply: 0,
+    }
+}
+
+/// The worker on `loc` among the seats accepted by `seat`.
+fn worker_at(workers: &mut Workers, seat: impl Fn(Player) -> bool, loc: Point) -> &mut Point {
+    Player::iter()
+        .zip(workers.iter_mut())
+        .filter(|(player, _)| seat(**player))
+        .filter_map(|(_, locs)| locs.as_mut())
+        .flat_map(|locs| locs.iter_mut())
+        .find(|worker| **worker == loc)
+        .expect("No worker at location!")
+}
+
 impl<S: GameState + NormalState> Game<S> {
     fn composite_board(&self) -> CompositeBoard {
         let mut board = self.board;
 
-        for player in Player::iter() {
-            for loc in &self.state.player_locs(*player) {
-                board.cap(*loc);
+        for locs in self.state.workers().iter().flatten() {
+            for loc in locs {
+                board.clear(*loc);
             }
         }
 
         CompositeBoard { board }
     }
 
-    pub fn player_pawns(&self, player: Player) -> [Pawn<S>; 2] {
-        // TODO: Use map (currently nightly only)
-        let [l1, l2] = self.state.player_locs(player);
-        [
-            Pawn {
-                game: self,
-                pos: l1,
-                player,
-            },
-            Pawn {
+    /// Whether the player still has workers on the board.
+    pub fn in_play(&self, player: Player) -> bool {
+        self.state.workers()[player.index()].is_some()
+    }
+
+    /// A player's workers, or None if they have been eliminated.
+    pub fn workers(&self, player: Player) -> Option<WorkerLocs> {
+        self.state.workers()[player.index()]
+    }
+
+    /// Where a worker stands, or None if it isn't on the board.
+    pub fn worker(&self, id: WorkerId) -> Option<Point> {
+        self.workers(id.player())?.get(id.index()).cloned()
+    }
+
+    /// The worker standing on a square, if there is one.
+    pub fn worker_on(&self, loc: Point) -> Option<WorkerId> {
+        Player::iter()
+            .zip(self.state.workers().iter())
+            .filter_map(|(player, locs)| {
+                let index = locs.as_ref()?.iter().position(|worker| *worker == loc)?;
+                Some(WorkerId::new(*player, index))
+            })
+            .next()
+    }
+
+    /// The player after the current one in turn order, skipping anyone who
+    /// has been eliminated.
+    pub fn next_player(&self) -> Player {
+        let count = self.player_count();
+        (1..=count)
+            .map(|offset| {
+                *Player::iter()
+                    .nth((self.player.index() + offset) % count)
+                    .unwrap()
+            })
+            .find(|player| self.in_play(*player))
+            .expect("Nobody is left in the game!")
+    }
+
+    /// Where the workers of every player other than the given one stand.
+    fn opponent_locs(&self, player: Player) -> Vec<Point> {
+        Player::iter()
+            .zip(self.state.workers().iter())
+            .filter(|(other, _)| **other != player)
+            .filter_map(|(_, locs)| *locs)
+            .flat_map(|locs| locs.to_vec())
+            .collect()
+    }
+
+    pub fn player_pawns(&self, player: Player) -> Vec<Pawn<S>> {
+        self.state
+            .player_locs(player)
+            .iter()
+            .map(|pos| Pawn {
                 game: self,
-                pos: l2,
+                pos: *pos,
                 player,
-            },
-        ]
+            })
+            .collect()
     }
 
-    pub fn active_pawns(&self) -> [Pawn<S>; 2] {
+    pub fn active_pawns(&self) -> Vec<Pawn<S>> {
         self.player_pawns(self.player)
     }
 
-    pub fn inactive_pawns(&self) -> [Pawn<S>; 2] {
-        self.player_pawns(self.player.other())
+    /// The pawns of the player who acts next.
+    pub fn inactive_pawns(&self) -> Vec<Pawn<S>> {
+        self.player_pawns(self.next_player())
     }
 
-    pub fn resign(self) -> Game<Victory> {
+    /// Concede the game. If only one other player is left they win, otherwise
+    /// the player's workers leave the board and the others play on.
+    fn concede(self, athena: Option<Player>, reason: VictoryReason) -> ActionResult<Move> {
+        let mut workers = self.state.workers();
+        if self
+            .players()
+            .filter(|player| self.in_play(*player))
+            .count()
+            <= 2
+        {
+            return ActionResult::Victory(Game {
+                state: Victory { workers, reason },
+                board: self.board,
+                player: self.next_player(),
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply + 1,
+            });
+        }
+
+        workers[self.player.index()] = None;
         Game {
-            state: Victory {
-                player1_locs: self.state.player_locs(Player::PlayerOne),
-                player2_locs: self.state.player_locs(Player::PlayerTwo),
+            state: Move {
+                workers,
+                no_climbing: false,
+                pre_move: None,
+                athena: athena.filter(|athena| *athena != self.player),
             },
             board: self.board,
-            player: self.player.other(),
+            player: self.player,
+            gods: self.gods,
+            players: self.players,
+            worker_count: self.worker_count,
+            ply: self.ply + 1,
         }
+        .hand_over(None)
     }
 }
 
@@ -528,22 +1150,70 @@ impl<'a, S: GameState> Pawn<'a, S> {
 
 // Victory
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// How a game was won, along with the action that won it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VictoryReason {
+    /// The winner moved a worker from `from` to `to`, up onto the third
+    /// level or in some other way their god wins by.
+    ReachedLevelThree { from: Point, to: Point },
+    /// The last opponent was left unable to move. `build` is where the
+    /// winning build was made, or None if the turn ended without one.
+    OpponentStalemated { build: Option<Point> },
+    /// The last opponent conceded.
+    Resignation,
+    /// The last opponent ran out of time.
+    Timeout,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Victory {
-    player1_locs: [Point; 2],
-    player2_locs: [Point; 2],
+    workers: Workers,
+    reason: VictoryReason,
 }
 impl GameState for Victory {}
 impl NormalState for Victory {
-    fn player_locs(&self, player: Player) -> [Point; 2] {
-        match player {
-            Player::PlayerOne => self.player1_locs,
-            Player::PlayerTwo => self.player2_locs,
-        }
+    fn workers(&self) -> Workers {
+        self.workers
+    }
+}
+
+impl Game<Victory> {
+    pub fn reason(&self) -> VictoryReason {
+        self.state.reason
     }
 }
 
+/// An action applied to a game other than the one it was made for.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("action was made for a different game")]
+pub struct StaleAction;
+
+/// Why a pawn can't move to or build on a space.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IllegalAction {
+    /// The pawn can't act this turn, either because it belongs to another
+    /// player or because another of its player's workers is acting.
+    #[error("that worker can't act this turn")]
+    NotYourTurn,
+    #[error("that space isn't next to the worker")]
+    TooFar,
+    #[error("that space is too high to move up to")]
+    TooHigh,
+    #[error("a worker stands on that space")]
+    Occupied,
+    #[error("that space has a dome")]
+    Capped,
+    /// A god power or a rule of the variant being played forbids it, such as
+    /// running out of building pieces.
+    #[error("that isn't allowed right now")]
+    Forbidden,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ActionResult<T: GameState> {
     Continue(Game<T>),
     Victory(Game<Victory>),
@@ -567,25 +1237,36 @@ impl<T: GameState> ActionResult<T> {
 
 // Moving
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Move {
-    player1_locs: [Point; 2],
-    player2_locs: [Point; 2],
+    workers: Workers,
+    /// Whether the player to move may not move up this turn, because of
+    /// Athena or their own Prometheus build.
+    no_climbing: bool,
+    /// The worker that used its god's power before moving, which then has to
+    /// be the one to move.
+    pre_move: Option<Point>,
+    /// The Athena player who moved up on their last turn, keeping everyone
+    /// else from moving up until their next one. Only needed while more than
+    /// two players are left, since otherwise `no_climbing` covers it.
+    athena: Option<Player>,
 }
 impl GameState for Move {}
 impl NormalState for Move {
-    fn player_locs(&self, player: Player) -> [Point; 2] {
-        match player {
-            Player::PlayerOne => self.player1_locs,
-            Player::PlayerTwo => self.player2_locs,
-        }
+    fn workers(&self) -> Workers {
+        self.workers
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MoveAction {
     from: Point,
     to: Point,
+    displaced: Option<Point>,
+    /// The hash of the game the action was made for.
+    game_hash: u64,
     #[cfg(debug_assertions)]
     game: Game<Move>,
 }
@@ -598,6 +1279,18 @@ impl MoveAction {
     pub fn to(&self) -> Point {
         self.to
     }
+
+    /// Where the opponent worker standing on `to` is forced to, for gods that
+    /// can move into occupied spaces.
+    pub fn displaced(&self) -> Option<Point> {
+        self.displaced
+    }
+}
+
+impl fmt::Display for MoveAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}>{}", self.from, self.to)
+    }
 }
 
 const OFFSETS: [(i8, i8); 8] = [
@@ -611,10 +1304,10 @@ const OFFSETS: [(i8, i8); 8] = [
     (1, 1),
 ];
 
-const fn neighbors_table() -> [[u64; 61]; 2] {
-    let mut array = [[0; 61]; 2];
+const fn neighbors_table() -> [[u64; 61]; WORDS] {
+    let mut array = [[0; 61]; WORDS];
     let mut word = 0;
-    while word < 2 {
+    while word < WORDS as i8 {
         let mut nibble = 0;
         while nibble < 61 {
             if nibble % 4 != 0 {
@@ -637,8 +1330,8 @@ const fn neighbors_table() -> [[u64; 61]; 2] {
                 let (dx, dy) = OFFSETS[index];
                 match Point::new_(Coord(point.x().0 + dx), Coord(point.y().0 + dy)) {
                     Some(point) => {
-                        let off = point.word * 64 + point.nibble;
-                        let diff: i8 = off - prev;
+                        let off = point.word as i16 * 64 + point.nibble as i16;
+                        let diff: i16 = off - prev;
                         let diff: u64 = (diff as u64) << (count * 8);
                         offset |= diff;
                         prev = off;
@@ -656,12 +1349,14 @@ const fn neighbors_table() -> [[u64; 61]; 2] {
     array
 }
 
-static ACTION_LOOKUP_TABLE: [[u64; 61]; 2] = neighbors_table();
+static ACTION_LOOKUP_TABLE: [[u64; 61]; WORDS] = neighbors_table();
 
-const fn mask_table() -> [[[[u64; 2]; 3]; 61]; 2] {
-    let mut array = [[[[0; 2]; 3]; 61]; 2];
+/// For each square and level limit, the squares around it no higher than the
+/// limit.
+const fn mask_table() -> [[[[u64; WORDS]; 4]; 61]; WORDS] {
+    let mut array = [[[[0; WORDS]; 4]; 61]; WORDS];
     let mut word = 0;
-    while word < 2 {
+    while word < WORDS as i8 {
         let mut nibble = 0;
         while nibble < 61 {
             if nibble % 4 != 0 {
@@ -678,16 +1373,11 @@ const fn mask_table() -> [[[[u64; 2]; 3]; 61]; 2] {
             }
 
             let mut level = 0;
-            while level < 3 {
-                let mask = match level {
-                    0 => 0b0011,
-                    1 => 0b0111,
-                    2 => 0b1111,
-                    _ => 0, // We should never get here
-                };
+            while level < 4 {
+                let mask = level_mask(level);
 
                 let mut index = 0;
-                let mut entry = [0; 2];
+                let mut entry = [0; WORDS];
                 while index < 8 {
                     let (dx, dy) = OFFSETS[index];
                     match Point::new_(Coord(point.x().0 + dx), Coord(point.y().0 + dy)) {
@@ -709,69 +1399,202 @@ const fn mask_table() -> [[[[u64; 2]; 3]; 61]; 2] {
     array
 }
 
-static MASK_LOOKUP_TABLE: [[[[u64; 2]; 3]; 61]; 2] = mask_table();
+static MASK_LOOKUP_TABLE: [[[[u64; WORDS]; 4]; 61]; WORDS] = mask_table();
+
+/// The nibble mask matching every level up to and including the given one.
+const fn level_mask(level: i8) -> u64 {
+    match level {
+        0 => 0b0001,
+        1 => 0b0011,
+        2 => 0b0111,
+        3 => 0b1111,
+        _ => 0, // We should never get here
+    }
+}
+
+/// The highest level a worker standing at the given level may move to.
+fn level_limit(level: CoordLevel) -> CoordLevel {
+    match level {
+        CoordLevel::Ground => CoordLevel::One,
+        CoordLevel::One => CoordLevel::Two,
+        // Workers can only be left on the third level by being displaced
+        CoordLevel::Two | CoordLevel::Three => CoordLevel::Three,
+        level => panic!("Pawn at unreachable height: {:?}", level),
+    }
+}
+
+impl<'a, S: GameState + NormalState> Pawn<'a, S> {
+    /// Which worker the pawn is.
+    pub fn id(&self) -> WorkerId {
+        self.game
+            .worker_on(self.pos)
+            .expect("No worker at the pawn's location!")
+    }
+}
 
 impl<'a> Pawn<'a, Move> {
     fn level_limit(&self) -> CoordLevel {
-        match self.game.board.level_at(self.pos) {
-            CoordLevel::Ground => CoordLevel::One,
-            CoordLevel::One => CoordLevel::Two,
-            CoordLevel::Two => CoordLevel::Three,
-            level => panic!("Pawn at unreachable height: {:?}", level),
+        let level = self.game.board.level_at(self.pos);
+        if self.game.state.no_climbing {
+            level
+        } else {
+            level_limit(level)
         }
     }
 
-    pub fn can_move(&self, to: Point) -> Option<MoveAction> {
-        if self.player != self.game.player {
-            return None;
-        }
-
-        if self.pos.distance(to) != 1 {
+    /// Move into a space held by an opponent worker, if this pawn's god allows
+    /// it.
+    fn can_displace(&self, to: Point) -> Option<MoveAction> {
+        let god = self.game.god(self.player);
+        if !god.displaces()
+            || !self.game.opponent_locs(self.player).contains(&to)
+            || !self.game.board.less_than_equals(to, self.level_limit())
+        {
             return None;
         }
 
-        if !self.game.composite_board().check(to, self.level_limit()) {
+        let displaced = god.displace(self.pos, to)?;
+        if displaced != self.pos
+            && !self
+                .game
+                .composite_board()
+                .check(displaced, CoordLevel::Three)
+        {
             return None;
         }
 
-        Some(MoveAction {
+        let action = MoveAction {
             from: self.pos,
             to,
+            displaced: Some(displaced),
+            game_hash: self.game.hash(),
             #[cfg(debug_assertions)]
             game: *self.game,
-        })
+        };
+        if self.game.can_build_after(action) {
+            Some(action)
+        } else {
+            None
+        }
     }
 
-    pub fn has_actions(&self) -> bool {
-        let mask = MASK_LOOKUP_TABLE[self.pos.word as usize][self.pos.nibble as usize][match self
-            .game
-            .board
-            .level_at(self.pos)
-        {
-            CoordLevel::Ground => 0,
-            CoordLevel::One => 1,
-            CoordLevel::Two => 2,
-            level => panic!("Pawn at unreachable height: {:?}", level),
-        }];
-
-        let composite = self.game.composite_board();
-        if composite.board.grid[0] & mask[0] != 0 {
-            return true;
+    /// The moves into occupied spaces available to this pawn. There can be at
+    /// most one per opponent worker.
+    fn displacements(&self) -> Vec<MoveAction> {
+        if self.player != self.game.player || !self.game.god(self.player).displaces() {
+            return vec![];
         }
 
-        if composite.board.grid[1] & mask[1] != 0 {
-            return true;
+        self.game
+            .opponent_locs(self.player)
+            .into_iter()
+            .filter(|loc| self.pos.distance(*loc) == 1)
+            .filter_map(|loc| self.can_displace(loc))
+            .collect()
+    }
+
+    /// Whether a power used before moving this turn leaves this pawn free to
+    /// move.
+    fn pre_move_allows_move(&self) -> bool {
+        match self.game.state.pre_move {
+            Some(worker) => worker == self.pos,
+            None => true,
         }
+    }
 
-        return false;
+    /// Whether this pawn may move at all this turn.
+    fn may_move(&self) -> bool {
+        self.player == self.game.player && self.pre_move_allows_move()
+    }
+
+    /// Why this pawn can't move to a space, or None if it can.
+    pub fn why_cannot_move(&self, to: Point) -> Option<IllegalAction> {
+        if self.can_move(to).is_some() {
+            return None;
+        }
+
+        Some(if !self.may_move() {
+            IllegalAction::NotYourTurn
+        } else if self.pos.distance(to) != 1 {
+            IllegalAction::TooFar
+        } else if self.game.board.level_at(to) == CoordLevel::Capped {
+            IllegalAction::Capped
+        } else if !self.game.board.less_than_equals(to, self.level_limit()) {
+            IllegalAction::TooHigh
+        } else if self.game.worker_on(to).is_some() {
+            IllegalAction::Occupied
+        } else {
+            IllegalAction::Forbidden
+        })
+    }
+
+    pub fn can_move(&self, to: Point) -> Option<MoveAction> {
+        if !self.may_move() {
+            return None;
+        }
+
+        if self.pos.distance(to) != 1 {
+            return None;
+        }
+
+        let action = if self.game.composite_board().check(to, self.level_limit()) {
+            MoveAction {
+                from: self.pos,
+                to,
+                displaced: None,
+                game_hash: self.game.hash(),
+                #[cfg(debug_assertions)]
+                game: *self.game,
+            }
+        } else {
+            self.can_displace(to)?
+        };
+        if self.game.can_build_after(action) {
+            Some(action)
+        } else {
+            None
+        }
+    }
+
+    pub fn has_actions(&self) -> bool {
+        if !self.pre_move_allows_move() {
+            return false;
+        }
+        if self.game.board.supply.is_some() {
+            return self.actions().next().is_some();
+        }
+
+        let limit = i8::from(self.level_limit()) as usize;
+        let mask = MASK_LOOKUP_TABLE[self.pos.word as usize][self.pos.nibble as usize][limit];
+
+        let composite = self.game.composite_board();
+        for (data, mask) in composite.board.grid.iter().zip(mask.iter()) {
+            if data & mask != 0 {
+                return true;
+            }
+        }
+
+        !self.displacements().is_empty()
     }
 
     pub fn actions(&self) -> impl Iterator<Item = MoveAction> {
+        // Only copy the game when the moves have to be checked
+        let game = self.game.board.supply.map(|_| *self.game);
+        self.unchecked_actions().filter(move |action| match game {
+            Some(game) => game.can_build_after(*action),
+            None => true,
+        })
+    }
+
+    /// The moves available, before checking there are pieces left to build
+    /// with afterwards.
+    fn unchecked_actions(&self) -> impl Iterator<Item = MoveAction> {
         struct ActionsIterator {
             board: u64,
             offsets: u64,
             mask: u64,
             action: MoveAction,
+            displacements: std::vec::IntoIter<MoveAction>,
         }
 
         impl Iterator for ActionsIterator {
@@ -780,16 +1603,16 @@ impl<'a> Pawn<'a, Move> {
             fn next(&mut self) -> Option<MoveAction> {
                 loop {
                     if self.offsets == 0 {
-                        return None;
+                        return self.displacements.next();
                     }
 
                     let off = self.offsets & 0xFF;
                     self.offsets = self.offsets >> 8;
                     self.board = self.board >> off;
                     self.action.to.nibble += off as i8;
-                    if self.action.to.nibble & 0b1000000 != 0 {
-                        self.action.to.word = 1;
-                        self.action.to.nibble &= !0b1000000;
+                    if self.action.to.nibble >= 64 {
+                        self.action.to.word += 1;
+                        self.action.to.nibble -= 64;
                     }
 
                     if self.board & self.mask != 0 {
@@ -801,7 +1624,7 @@ impl<'a> Pawn<'a, Move> {
             }
         }
 
-        if self.player != self.game.player {
+        if !self.may_move() {
             return ActionsIterator {
                 board: 0,
                 offsets: 0,
@@ -809,60 +1632,906 @@ impl<'a> Pawn<'a, Move> {
                 action: MoveAction {
                     from: self.pos,
                     to: self.pos,
+                    displaced: None,
+                    game_hash: self.game.hash(),
                     #[cfg(debug_assertions)]
                     game: *self.game,
                 },
+                displacements: vec![].into_iter(),
             };
         }
 
-        let mask = match self.game.board.level_at(self.pos) {
-            CoordLevel::Ground => 0b0011,
-            CoordLevel::One => 0b0111,
-            CoordLevel::Two => 0b1111,
-            level => panic!("Pawn at unreachable height: {:?}", level),
-        };
+        let mask = level_mask(self.level_limit().into());
+
+        let offsets = ACTION_LOOKUP_TABLE[self.pos.word as usize][self.pos.nibble as usize];
+        let off: u64 = offsets & 0xFF;
+        let offsets = offsets & !0xFF;
+
+        let composite = self.game.composite_board();
+        let (word, shift) = ((off / 64) as usize, off % 64);
+        let mut board = composite.board.grid[word] >> shift;
+        if shift > 0 && word + 1 < WORDS {
+            board |= composite.board.grid[word + 1] << (64 - shift);
+        }
+
+        ActionsIterator {
+            board,
+            offsets,
+            mask,
+            action: MoveAction {
+                from: self.pos,
+                to: Point {
+                    word: word as i8,
+                    nibble: shift as i8,
+                },
+                displaced: None,
+                game_hash: self.game.hash(),
+                #[cfg(debug_assertions)]
+                game: *self.game,
+            },
+            displacements: self.displacements().into_iter(),
+        }
+    }
+}
+
+/// A build made before moving, for gods that allow one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PreBuildAction {
+    worker: Point,
+    loc: Point,
+    /// The hash of the game the action was made for.
+    game_hash: u64,
+    #[cfg(debug_assertions)]
+    game: Game<Move>,
+}
+
+impl PreBuildAction {
+    /// The worker that builds, and so has to move afterwards.
+    pub fn worker(&self) -> Point {
+        self.worker
+    }
+
+    pub fn loc(&self) -> Point {
+        self.loc
+    }
+}
+
+impl fmt::Display for PreBuildAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}^{}", self.worker, self.loc)
+    }
+}
+
+/// Charon moving a neighboring opponent worker to the space on the other side
+/// of his worker, before his worker moves.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForceAction {
+    worker: Point,
+    opponent: Point,
+    to: Point,
+    /// The hash of the game the action was made for.
+    game_hash: u64,
+    #[cfg(debug_assertions)]
+    game: Game<Move>,
+}
+
+impl ForceAction {
+    /// The worker that forces, and so has to move afterwards.
+    pub fn worker(&self) -> Point {
+        self.worker
+    }
+
+    /// Where the opponent worker stands before being forced.
+    pub fn opponent(&self) -> Point {
+        self.opponent
+    }
+
+    pub fn to(&self) -> Point {
+        self.to
+    }
+}
+
+impl fmt::Display for ForceAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}*{}", self.worker, self.opponent)
+    }
+}
+
+/// A god power used before moving.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PreMoveAction {
+    Build(PreBuildAction),
+    Force(ForceAction),
+}
+
+impl PreMoveAction {
+    pub fn worker(&self) -> Point {
+        match self {
+            PreMoveAction::Build(action) => action.worker(),
+            PreMoveAction::Force(action) => action.worker(),
+        }
+    }
+
+    /// The space the power is used on: where the worker builds, or the
+    /// opponent worker it forces.
+    pub fn target(&self) -> Point {
+        match self {
+            PreMoveAction::Build(action) => action.loc(),
+            PreMoveAction::Force(action) => action.opponent(),
+        }
+    }
+}
+
+impl fmt::Display for PreMoveAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreMoveAction::Build(action) => action.fmt(f),
+            PreMoveAction::Force(action) => action.fmt(f),
+        }
+    }
+}
+
+impl<'a> Pawn<'a, Move> {
+    /// Build before moving, if this pawn's god allows it. The pawn then has to
+    /// move without moving up, so the build is only allowed if that is still
+    /// possible.
+    pub fn can_build_first(&self, loc: Point) -> Option<PreBuildAction> {
+        if !self.may_move()
+            || self.game.god(self.player) != God::Prometheus
+            || self.game.state.pre_move.is_some()
+            || self.pos.distance(loc) != 1
+            || !self.game.composite_board().check(loc, CoordLevel::Three)
+            || !self.game.board.has_piece(loc, false)
+        {
+            return None;
+        }
+
+        let action = PreBuildAction {
+            worker: self.pos,
+            loc,
+            game_hash: self.game.hash(),
+            #[cfg(debug_assertions)]
+            game: *self.game,
+        };
+        let game = self.game.apply_pre_build(action);
+        let pawn = Pawn {
+            game: &game,
+            ..*self
+        };
+        if pawn.has_actions() {
+            Some(action)
+        } else {
+            None
+        }
+    }
+
+    /// The builds this pawn may make before moving.
+    pub fn pre_builds(&self) -> impl Iterator<Item = PreBuildAction> {
+        let pawn = *self;
+        let builds: Vec<_> = self
+            .neighbors()
+            .filter_map(|loc| pawn.can_build_first(loc))
+            .collect();
+        builds.into_iter()
+    }
+
+    /// Force the opponent worker on `opponent` to the space on the other side
+    /// of this pawn, if this pawn's god allows it. The space has to be free,
+    /// and this pawn still has to be able to move afterwards.
+    pub fn can_force(&self, opponent: Point) -> Option<ForceAction> {
+        if !self.may_move()
+            || self.game.god(self.player) != God::Charon
+            || self.game.state.pre_move.is_some()
+            || self.pos.distance(opponent) != 1
+            || !self.game.opponent_locs(self.player).contains(&opponent)
+        {
+            return None;
+        }
+
+        let to = Point::new_(
+            Coord(2 * self.pos.x().0 - opponent.x().0),
+            Coord(2 * self.pos.y().0 - opponent.y().0),
+        )?;
+        if !self.game.composite_board().check(to, CoordLevel::Three) {
+            return None;
+        }
+
+        let action = ForceAction {
+            worker: self.pos,
+            opponent,
+            to,
+            game_hash: self.game.hash(),
+            #[cfg(debug_assertions)]
+            game: *self.game,
+        };
+        let game = self.game.apply_force(action);
+        let pawn = Pawn {
+            game: &game,
+            ..*self
+        };
+        if pawn.has_actions() {
+            Some(action)
+        } else {
+            None
+        }
+    }
+
+    /// The opponent workers this pawn may force before moving.
+    pub fn forces(&self) -> impl Iterator<Item = ForceAction> {
+        let pawn = *self;
+        let forces: Vec<_> = self
+            .game
+            .opponent_locs(self.player)
+            .into_iter()
+            .filter_map(|loc| pawn.can_force(loc))
+            .collect();
+        forces.into_iter()
+    }
+
+    /// Every power this pawn may use before moving.
+    pub fn pre_moves(&self) -> impl Iterator<Item = PreMoveAction> {
+        self.pre_builds()
+            .map(PreMoveAction::Build)
+            .chain(self.forces().map(PreMoveAction::Force))
+    }
+}
+
+impl Game<Move> {
+    /// Whether the player can still build after making a move, which is only
+    /// in doubt once building pieces run low or when a swapped worker takes
+    /// the space moved from. Players who can't build lose, so moves that
+    /// leave them unable to are not allowed.
+    fn can_build_after(&self, action: MoveAction) -> bool {
+        if self.board.supply.is_none() && action.displaced != Some(action.from) {
+            return true;
+        }
+        match self.apply(action) {
+            ActionResult::Continue(game) => {
+                game.build_actions().next().is_some() || game.can_move_again()
+            }
+            ActionResult::Victory(_) => true,
+        }
+    }
+
+    /// Whether the player to move is barred from moving up this turn, because
+    /// Athena moved up on the last one or they built before moving.
+    pub fn climbing_blocked(&self) -> bool {
+        self.state.no_climbing
+    }
+
+    /// The worker that used its god's power before moving this turn, if any.
+    pub fn acted_before_moving(&self) -> Option<Point> {
+        self.state.pre_move
+    }
+
+    /// Build before moving. The same worker then has to move, and may not
+    /// move up.
+    pub fn apply_pre_build(self, action: PreBuildAction) -> Game<Move> {
+        #[cfg(debug_assertions)]
+        assert!(
+            action.game == self,
+            "Game {:?} received action {:?} associated with a different game!",
+            self,
+            action
+        );
+
+        let mut game = self;
+        game.board.build(action.loc);
+        game.state.no_climbing = true;
+        game.state.pre_move = Some(action.worker);
+        game
+    }
+
+    /// Force an opponent worker before moving. The same worker then has to
+    /// move.
+    pub fn apply_force(self, action: ForceAction) -> Game<Move> {
+        #[cfg(debug_assertions)]
+        assert!(
+            action.game == self,
+            "Game {:?} received action {:?} associated with a different game!",
+            self,
+            action
+        );
+
+        let mut game = self;
+        let player = game.player;
+        *worker_at(
+            &mut game.state.workers,
+            |owner| owner != player,
+            action.opponent,
+        ) = action.to;
+        game.state.pre_move = Some(action.worker);
+        game
+    }
+
+    pub fn apply_pre_move(self, action: PreMoveAction) -> Game<Move> {
+        match action {
+            PreMoveAction::Build(action) => self.apply_pre_build(action),
+            PreMoveAction::Force(action) => self.apply_force(action),
+        }
+    }
+
+    /// Act before moving as `apply_pre_move` does, unless the action was
+    /// made for another game.
+    pub fn try_apply_pre_move(self, action: PreMoveAction) -> Result<Game<Move>, StaleAction> {
+        let game_hash = match action {
+            PreMoveAction::Build(action) => action.game_hash,
+            PreMoveAction::Force(action) => action.game_hash,
+        };
+        self.check_action(game_hash)?;
+        Ok(self.apply_pre_move(action))
+    }
+
+    /// Assemble a game directly from its parts. The caller is responsible for
+    /// ensuring the parts describe a reachable position.
+    #[cfg(test)]
+    pub(crate) fn from_parts(
+        board: Board,
+        player1_locs: [Point; 2],
+        player2_locs: [Point; 2],
+        player: Player,
+        gods: [God; 2],
+    ) -> Game<Move> {
+        Game {
+            state: Move {
+                workers: [Some(player1_locs.into()), Some(player2_locs.into()), None],
+                no_climbing: false,
+                pre_move: None,
+                athena: None,
+            },
+            board,
+            player,
+            gods: [gods[0], gods[1], God::Mortal],
+            players: 2,
+            worker_count: 2,
+            ply: 0,
+        }
+    }
+
+    /// Pass the turn on from the current player to the next one in turn order
+    /// who is able to move. Anyone who can't move is eliminated, removing
+    /// their workers from the board, until only one player is left to win.
+    /// `build` is the build that ended the turn, if any.
+    fn hand_over(self, build: Option<Point>) -> ActionResult<Move> {
+        let mut game = self;
+        loop {
+            game.player = game.next_player();
+            if game.state.athena == Some(game.player) {
+                game.state.athena = None;
+            }
+            game.state.no_climbing = game.state.athena.is_some();
+
+            let left = game
+                .players()
+                .filter(|player| game.in_play(*player))
+                .count();
+            if left <= 2 {
+                // The Athena player's turn comes next, so the restriction
+                // ends with this one
+                game.state.athena = None;
+            }
+
+            // Note that after a move, there is usually at least one valid build
+            // location (the place the pawn moved from), so we just need to check
+            // moves and not builds to determine a stalemate. Once building
+            // pieces run low, or when a swap leaves the place moved from
+            // occupied, moves that can't be followed by a build aren't
+            // generated at all.
+            let pawns = game.active_pawns();
+            if pawns.iter().any(|pawn| pawn.has_actions()) {
+                return ActionResult::Continue(game);
+            }
+
+            if left <= 2 {
+                // The player can't move so the other one wins!
+                return ActionResult::Victory(Game {
+                    state: Victory {
+                        workers: game.state.workers,
+                        reason: VictoryReason::OpponentStalemated { build },
+                    },
+                    board: game.board,
+                    player: game.next_player(),
+                    gods: game.gods,
+                    players: game.players,
+                    worker_count: game.worker_count,
+                    ply: game.ply,
+                });
+            }
+            game.state.workers[game.player.index()] = None;
+        }
+    }
+
+    /// Concede the game. See `Game<Build>::resign`.
+    pub fn resign(self) -> ActionResult<Move> {
+        let athena = self.state.athena;
+        self.concede(athena, VictoryReason::Resignation)
+    }
+
+    /// Lose on time. Handled like resigning, except for the reason given for
+    /// the victory.
+    pub fn time_out(self) -> ActionResult<Move> {
+        let athena = self.state.athena;
+        self.concede(athena, VictoryReason::Timeout)
+    }
+
+    /// Move as `apply` does, unless the action was made for another game.
+    pub fn try_apply(self, action: MoveAction) -> Result<ActionResult<Build>, StaleAction> {
+        self.check_action(action.game_hash)?;
+        Ok(self.apply(action))
+    }
+
+    pub fn apply(self, action: MoveAction) -> ActionResult<Build> {
+        #[cfg(debug_assertions)]
+        assert!(
+            action.game == self,
+            "Game {:?} received action {:?} associated with a different game!",
+            self,
+            action
+        );
+
+        let mut state = Build {
+            workers: self.state.workers,
+            active_loc: action.to,
+            start: action.from,
+            moves: 1,
+            moved_up: self.board.level_at(action.to) > self.board.level_at(action.from),
+            other_moved: false,
+            builds: 0,
+            first_build: None,
+            athena: self.state.athena,
+        };
+        let player = self.player;
+        *worker_at(&mut state.workers, |owner| owner == player, action.from) = action.to;
+        if let Some(displaced) = action.displaced {
+            *worker_at(&mut state.workers, |owner| owner != player, action.to) = displaced;
+        }
+
+        if self.wins_by_moving(action.from, action.to) {
+            ActionResult::Victory(Game {
+                state: Victory {
+                    workers: state.workers,
+                    reason: VictoryReason::ReachedLevelThree {
+                        from: action.from,
+                        to: action.to,
+                    },
+                },
+                board: self.board,
+                player: self.player,
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply + 1,
+            })
+        } else {
+            ActionResult::Continue(Game {
+                state,
+                board: self.board,
+                player: self.player,
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply,
+            })
+        }
+    }
+}
+
+// Building
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Build {
+    workers: Workers,
+
+    active_loc: Point,
+    /// Where the active worker started the turn.
+    start: Point,
+    /// How many times the active worker has moved this turn.
+    moves: u8,
+    /// Whether any of those moves went up a level.
+    moved_up: bool,
+    /// Whether Hermes' other worker has moved this turn.
+    other_moved: bool,
+    /// How many builds have been made this turn.
+    builds: u8,
+    first_build: Option<Point>,
+    /// Carried over from `Move`.
+    athena: Option<Player>,
+}
+impl GameState for Build {}
+impl NormalState for Build {
+    fn workers(&self) -> Workers {
+        self.workers
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BuildAction {
+    loc: Point,
+    dome: bool,
+    /// The hash of the game the action was made for.
+    game_hash: u64,
+    #[cfg(debug_assertions)]
+    game: Game<Build>,
+}
+
+impl BuildAction {
+    pub fn loc(&self) -> Point {
+        self.loc
+    }
+
+    /// Whether this build places a dome regardless of the space's height,
+    /// which only Atlas may do below the third level.
+    pub fn dome(&self) -> bool {
+        self.dome
+    }
+}
+
+/// Written as `^<square>`, with a trailing `!` for a dome, as in a `Turn`.
+impl fmt::Display for BuildAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "^{}", self.loc)?;
+        if self.dome {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+/// An additional move before building, for gods that allow one. This is
+/// usually by the worker that moved, but Hermes' other worker may move too.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExtraMoveAction {
+    from: Point,
+    to: Point,
+    /// The hash of the game the action was made for.
+    game_hash: u64,
+    #[cfg(debug_assertions)]
+    game: Game<Build>,
+}
+
+impl ExtraMoveAction {
+    pub fn from(&self) -> Point {
+        self.from
+    }
+
+    pub fn to(&self) -> Point {
+        self.to
+    }
+}
+
+impl fmt::Display for ExtraMoveAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}>{}", self.from, self.to)
+    }
+}
+
+impl<'a> Pawn<'a, Build> {
+    /// Every space reachable from this pawn through a chain of moves allowed
+    /// by `can_step`, carrying on only from spaces for which `continues`
+    /// holds.
+    fn reachable(
+        &self,
+        can_step: impl Fn(Point, Point) -> bool,
+        continues: impl Fn(Point) -> bool,
+    ) -> Vec<Point> {
+        let mut reached = vec![self.pos];
+        let mut idx = 0;
+        while idx < reached.len() {
+            let from = reached[idx];
+            if idx == 0 || continues(from) {
+                let pawn = Pawn { pos: from, ..*self };
+                for loc in pawn.neighbors() {
+                    if !reached.contains(&loc) && can_step(from, loc) {
+                        reached.push(loc);
+                    }
+                }
+            }
+            idx += 1;
+        }
+        reached.remove(0);
+        reached
+    }
+
+    /// Every space this pawn may move on to before building.
+    ///
+    /// Hermes' moves along a level and Triton's moves along the perimeter are
+    /// collapsed into a single move to any space reachable that way, so each
+    /// worker gets at most one extra move.
+    fn extra_destinations(&self) -> Vec<Point> {
+        let mut destinations = self.unchecked_destinations();
+        if self.game.board.supply.is_some() {
+            // As with the first move, the worker has to be able to build
+            // afterwards
+            destinations.retain(|to| {
+                let action = ExtraMoveAction {
+                    from: self.pos,
+                    to: *to,
+                    game_hash: self.game.hash(),
+                    #[cfg(debug_assertions)]
+                    game: *self.game,
+                };
+                match self.game.apply_move(action) {
+                    ActionResult::Continue(game) => game.build_actions().next().is_some(),
+                    ActionResult::Victory(_) => true,
+                }
+            });
+        }
+        destinations
+    }
+
+    fn unchecked_destinations(&self) -> Vec<Point> {
+        let state = &self.game.state;
+        if self.player != self.game.player || state.builds != 0 {
+            return vec![];
+        }
+
+        let board = &self.game.board;
+        let composite = self.game.composite_board();
+        let level = board.level_at(self.pos);
+        let along_level = || {
+            self.reachable(
+                |_, loc| board.level_at(loc) == level && composite.check(loc, level),
+                |_| true,
+            )
+        };
+        if self.pos != state.active_loc {
+            return if self.game.moved_along_level() && !state.other_moved {
+                along_level()
+            } else {
+                vec![]
+            };
+        }
+        if state.moves != 1 {
+            return vec![];
+        }
+
+        match self.game.god(self.player) {
+            God::Artemis => self
+                .neighbors()
+                .filter(|loc| *loc != state.start && composite.check(*loc, level_limit(level)))
+                .collect(),
+            God::Hermes if self.game.moved_along_level() => along_level(),
+            God::Triton if self.pos.on_perimeter() => self.reachable(
+                |from, loc| composite.check(loc, level_limit(board.level_at(from))),
+                |loc| loc.on_perimeter(),
+            ),
+            _ => vec![],
+        }
+    }
+
+    pub fn can_move(&self, to: Point) -> Option<ExtraMoveAction> {
+        if self.extra_destinations().contains(&to) {
+            Some(ExtraMoveAction {
+                from: self.pos,
+                to,
+                game_hash: self.game.hash(),
+                #[cfg(debug_assertions)]
+                game: *self.game,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The extra moves available before building. Moving again is always
+    /// optional.
+    pub fn moves(&self) -> impl Iterator<Item = ExtraMoveAction> {
+        let from = self.pos;
+        let game_hash = self.game.hash();
+        #[cfg(debug_assertions)]
+        let game = *self.game;
+        self.extra_destinations()
+            .into_iter()
+            .map(move |to| ExtraMoveAction {
+                from,
+                to,
+                game_hash,
+                #[cfg(debug_assertions)]
+                game,
+            })
+    }
+
+    /// Whether this pawn may make the turn's build, which is the job of the
+    /// worker that moved unless Hermes moved along the level.
+    fn may_build(&self) -> bool {
+        let state = &self.game.state;
+        self.pos == state.active_loc
+            || (self.player == self.game.player
+                && state.builds == 0
+                && self.game.moved_along_level())
+    }
+
+    /// Whether this pawn's god lets it build under itself.
+    fn can_build_under(&self) -> bool {
+        self.game.god(self.player) == God::Zeus
+            && self.game.board.less_than_equals(self.pos, CoordLevel::Two)
+    }
+
+    fn can_build_piece(&self, loc: Point, dome: bool) -> Option<BuildAction> {
+        let free = if loc == self.pos {
+            self.can_build_under()
+        } else {
+            self.pos.distance(loc) == 1 && self.game.composite_board().check(loc, CoordLevel::Three)
+        };
+        if self.may_build()
+            && free
+            && self.game.allows_build(loc)
+            && self.game.board.has_piece(loc, dome)
+        {
+            Some(BuildAction {
+                loc,
+                dome,
+                game_hash: self.game.hash(),
+                #[cfg(debug_assertions)]
+                game: *self.game,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn can_build(&self, loc: Point) -> Option<BuildAction> {
+        self.can_build_piece(loc, false)
+    }
+
+    /// Why this pawn can't build a block on a space, or None if it can.
+    pub fn why_cannot_build(&self, loc: Point) -> Option<IllegalAction> {
+        if self.can_build(loc).is_some() {
+            return None;
+        }
+
+        Some(if !self.may_build() {
+            IllegalAction::NotYourTurn
+        } else if loc == self.pos {
+            IllegalAction::Occupied
+        } else if self.pos.distance(loc) != 1 {
+            IllegalAction::TooFar
+        } else if self.game.board.level_at(loc) == CoordLevel::Capped {
+            IllegalAction::Capped
+        } else if self.game.worker_on(loc).is_some() {
+            IllegalAction::Occupied
+        } else {
+            IllegalAction::Forbidden
+        })
+    }
+
+    /// Build a dome on a space below the third level, if this pawn's god
+    /// allows it. Domes on the third level are ordinary builds.
+    pub fn can_build_dome(&self, loc: Point) -> Option<BuildAction> {
+        if self.game.god(self.player) != God::Atlas
+            || !self.game.board.less_than_equals(loc, CoordLevel::Two)
+        {
+            return None;
+        }
+
+        self.can_build_piece(loc, true)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = BuildAction> {
+        let may_build = self.may_build();
+        let game = *self.game;
+        let game_hash = game.hash();
+        let composite = game.composite_board();
+        let domes = game.god(self.player) == God::Atlas;
+        let under = if self.can_build_under() {
+            Some(self.pos)
+        } else {
+            None
+        };
+        self.neighbors()
+            .filter(move |loc| composite.check(*loc, CoordLevel::Three))
+            .chain(under)
+            .filter(move |_| may_build)
+            .filter(move |loc| game.allows_build(*loc))
+            .flat_map(move |loc| {
+                let action = BuildAction {
+                    loc,
+                    dome: false,
+                    game_hash,
+                    #[cfg(debug_assertions)]
+                    game,
+                };
+                let block = if game.board.has_piece(loc, false) {
+                    Some(action)
+                } else {
+                    None
+                };
+                let dome = if domes
+                    && game.board.less_than_equals(loc, CoordLevel::Two)
+                    && game.board.has_piece(loc, true)
+                {
+                    Some(BuildAction {
+                        dome: true,
+                        ..action
+                    })
+                } else {
+                    None
+                };
+                block.into_iter().chain(dome)
+            })
+    }
+}
+
+impl Game<Build> {
+    pub fn active_pawn(&self) -> Pawn<Build> {
+        Pawn {
+            game: self,
+            pos: self.state.active_loc,
+            player: self.player,
+        }
+    }
+
+    /// The worker that moved this turn.
+    pub fn active_worker(&self) -> WorkerId {
+        self.active_pawn().id()
+    }
 
-        let offsets = ACTION_LOOKUP_TABLE[self.pos.word as usize][self.pos.nibble as usize];
-        let off: u64 = offsets & 0xFF;
-        let offsets = offsets & !0xFF;
+    /// Whether Hermes' worker moved without going up or down, which lets
+    /// both workers move along their levels and then either one build.
+    fn moved_along_level(&self) -> bool {
+        self.god(self.player) == God::Hermes
+            && self.board.level_at(self.state.start) == self.board.level_at(self.state.active_loc)
+    }
 
-        let composite = self.game.composite_board();
-        let board;
-        if off >= 64 {
-            let off = off - 64;
-            board = composite.board.grid[1] >> off;
+    /// Whether a worker may move again before building.
+    pub fn can_move_again(&self) -> bool {
+        self.god(self.player).moves_again()
+            && self
+                .active_pawns()
+                .iter()
+                .any(|pawn| pawn.moves().next().is_some())
+    }
+
+    /// Every build the player can make next. These are the active pawn's,
+    /// along with those of Hermes' other worker after moving along the level.
+    pub fn build_actions(&self) -> impl Iterator<Item = BuildAction> {
+        let active = self.state.active_loc;
+        let others: Vec<BuildAction> = if self.moved_along_level() {
+            self.active_pawns()
+                .iter()
+                .filter(|pawn| pawn.pos != active)
+                .flat_map(|pawn| pawn.actions())
+                // Both workers can build next to both of them
+                .filter(|build| active.distance(build.loc) != 1)
+                .collect()
         } else {
-            let board_a = composite.board.grid[0] >> off;
-            let board_b;
-            if off > 0 {
-                board_b = composite.board.grid[1] << (64 - off);
-            } else {
-                board_b = 0;
-            }
-            board = board_a | board_b;
-        }
+            vec![]
+        };
+        self.active_pawn().actions().chain(others)
+    }
 
-        ActionsIterator {
-            board,
-            offsets,
-            mask,
-            action: MoveAction {
-                from: self.pos,
-                to: Point {
-                    word: 0,
-                    nibble: off as i8,
-                },
-                #[cfg(debug_assertions)]
-                game: *self.game,
-            },
-        }
+    /// Build a block on a space with any worker that may build there.
+    pub fn can_build(&self, loc: Point) -> Option<BuildAction> {
+        self.active_pawns()
+            .iter()
+            .find_map(|pawn| pawn.can_build(loc))
     }
-}
 
-// We use a macro because we need to write this function for P1 and P2
-// with minimal differences
-impl Game<Move> {
-    pub fn apply(self, action: MoveAction) -> ActionResult<Build> {
+    /// Build a dome on a space with any worker that may build there, as
+    /// `Pawn::can_build_dome` does.
+    pub fn can_build_dome(&self, loc: Point) -> Option<BuildAction> {
+        self.active_pawns()
+            .iter()
+            .find_map(|pawn| pawn.can_build_dome(loc))
+    }
+
+    /// Move again as `apply_move` does, unless the action was made for
+    /// another game.
+    pub fn try_apply_move(
+        self,
+        action: ExtraMoveAction,
+    ) -> Result<ActionResult<Build>, StaleAction> {
+        self.check_action(action.game_hash)?;
+        Ok(self.apply_move(action))
+    }
+
+    pub fn apply_move(self, action: ExtraMoveAction) -> ActionResult<Build> {
         #[cfg(debug_assertions)]
         assert!(
             action.game == self,
@@ -871,110 +2540,151 @@ impl Game<Move> {
             action
         );
 
-        let mut state = Build {
-            player1_locs: self.state.player1_locs,
-            player2_locs: self.state.player2_locs,
-            active_loc: action.to,
-        };
-        let locs = match self.player {
-            Player::PlayerOne => &mut state.player1_locs,
-            Player::PlayerTwo => &mut state.player2_locs,
-        };
-        let source = locs
-            .iter_mut()
-            .find(|loc| **loc == action.from)
-            .expect("Invalid MoveAction");
-        *source = action.to;
+        let mut state = self.state;
+        let player = self.player;
+        *worker_at(&mut state.workers, |owner| owner == player, action.from) = action.to;
+        if action.from == state.active_loc {
+            state.active_loc = action.to;
+            state.moves += 1;
+            state.moved_up |= self.board.level_at(action.to) > self.board.level_at(action.from);
+        } else {
+            state.other_moved = true;
+        }
 
-        if self.board.level_at(action.to) == CoordLevel::Three {
+        if self.wins_by_moving(action.from, action.to) {
             ActionResult::Victory(Game {
                 state: Victory {
-                    player1_locs: state.player1_locs,
-                    player2_locs: state.player2_locs,
+                    workers: state.workers,
+                    reason: VictoryReason::ReachedLevelThree {
+                        from: action.from,
+                        to: action.to,
+                    },
                 },
                 board: self.board,
                 player: self.player,
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply + 1,
             })
         } else {
             ActionResult::Continue(Game {
                 state,
                 board: self.board,
                 player: self.player,
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply,
             })
         }
     }
-}
-
-// Building
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct Build {
-    player1_locs: [Point; 2],
-    player2_locs: [Point; 2],
+    /// The number of builds made so far this turn.
+    pub fn builds(&self) -> u8 {
+        self.state.builds
+    }
 
-    active_loc: Point,
-}
-impl GameState for Build {}
-impl NormalState for Build {
-    fn player_locs(&self, player: Player) -> [Point; 2] {
-        match player {
-            Player::PlayerOne => self.player1_locs,
-            Player::PlayerTwo => self.player2_locs,
+    /// Whether the god's restrictions on additional builds allow building on
+    /// the given space. The first build of a turn is never restricted.
+    fn allows_build(&self, loc: Point) -> bool {
+        match (self.god(self.player), self.state.builds) {
+            (_, 0) => true,
+            (God::Demeter, 1) => self.state.first_build != Some(loc),
+            (God::Hephaestus, 1) => {
+                self.state.first_build == Some(loc)
+                    && self.board.less_than_equals(loc, CoordLevel::Two)
+            }
+            (God::Poseidon, 1..=3) => true,
+            _ => false,
         }
     }
-}
-
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct BuildAction {
-    loc: Point,
-    #[cfg(debug_assertions)]
-    game: Game<Build>,
-}
 
-impl BuildAction {
-    pub fn loc(&self) -> Point {
-        self.loc
+    /// The first of Poseidon's workers that didn't move this turn and stands
+    /// on the ground, which may build again.
+    fn unmoved_loc(&self) -> Option<Point> {
+        self.state
+            .player_locs(self.player)
+            .iter()
+            .cloned()
+            .find(|loc| {
+                *loc != self.state.active_loc && self.board.level_at(*loc) == CoordLevel::Ground
+            })
     }
-}
 
-impl<'a> Pawn<'a, Build> {
-    pub fn can_build(&self, loc: Point) -> Option<BuildAction> {
-        if self.pos == self.game.state.active_loc
-            && self.pos.distance(loc) == 1
-            && self.game.composite_board().check(loc, CoordLevel::Three)
-        {
-            Some(BuildAction {
-                loc,
-                #[cfg(debug_assertions)]
-                game: *self.game,
-            })
-        } else {
-            None
+    /// Whether the player may build again after their next build, rather than
+    /// ending their turn.
+    pub fn can_build_again(&self) -> bool {
+        match (self.god(self.player), self.state.builds) {
+            (God::Demeter, 0) | (God::Hephaestus, 0) => true,
+            (God::Poseidon, 0) => self.unmoved_loc().is_some(),
+            (God::Poseidon, 1..=2) => true,
+            _ => false,
         }
     }
 
-    pub fn actions(&self) -> impl Iterator<Item = BuildAction> {
-        let is_active_pawn = *self == self.game.active_pawn();
-        let game = *self.game;
-        let composite = game.composite_board();
-        self.neighbors()
-            .filter(move |_| is_active_pawn)
-            .filter(move |loc| composite.check(*loc, CoordLevel::Three))
-            .map(move |loc| BuildAction {
-                loc,
-                #[cfg(debug_assertions)]
-                game,
-            })
+    /// Build as `build_again` does, unless the action was made for another
+    /// game.
+    pub fn try_build_again(self, action: BuildAction) -> Result<Game<Build>, StaleAction> {
+        self.check_action(action.game_hash)?;
+        Ok(self.build_again(action))
     }
-}
 
-impl Game<Build> {
-    pub fn active_pawn(&self) -> Pawn<Build> {
-        Pawn {
-            game: self,
-            pos: self.state.active_loc,
-            player: self.player,
+    /// Build, and then stay in the `Build` state for an optional additional
+    /// build. Only valid if `can_build_again()`.
+    pub fn build_again(self, action: BuildAction) -> Game<Build> {
+        #[cfg(debug_assertions)]
+        assert!(
+            action.game == self,
+            "Game {:?} received action {:?} associated with a different game!",
+            self,
+            action
+        );
+        assert!(self.can_build_again(), "No additional builds allowed!");
+
+        let mut game = self;
+        game.board.apply_build(action);
+        if game.state.builds == 0 {
+            game.state.first_build = Some(action.loc);
+            if game.god(game.player) == God::Poseidon {
+                // Poseidon's additional builds are made by an unmoved worker
+                game.state.active_loc = game.unmoved_loc().expect("No unmoved worker!");
+            }
         }
+        game.state.builds += 1;
+        game
+    }
+
+    /// Whether the player may end their turn without building again.
+    pub fn can_pass(&self) -> bool {
+        self.state.builds > 0
+    }
+
+    /// Decline an optional additional build and end the turn. Only valid if
+    /// `can_pass()`.
+    pub fn pass(self) -> ActionResult<Move> {
+        assert!(self.can_pass(), "The player has to build!");
+        self.end_turn(self.board, None)
+    }
+
+    /// Concede the game. With two players left the other one wins, and with
+    /// more the player is eliminated and the next one takes their turn.
+    pub fn resign(self) -> ActionResult<Move> {
+        let athena = self.state.athena;
+        self.concede(athena, VictoryReason::Resignation)
+    }
+
+    /// Lose on time. Handled like resigning, except for the reason given for
+    /// the victory.
+    pub fn time_out(self) -> ActionResult<Move> {
+        let athena = self.state.athena;
+        self.concede(athena, VictoryReason::Timeout)
+    }
+
+    /// Build as `apply` does, unless the action was made for another game.
+    pub fn try_apply(self, action: BuildAction) -> Result<ActionResult<Move>, StaleAction> {
+        self.check_action(action.game_hash)?;
+        Ok(self.apply(action))
     }
 
     pub fn apply(self, action: BuildAction) -> ActionResult<Move> {
@@ -987,65 +2697,129 @@ impl Game<Build> {
         );
 
         let mut board = self.board;
-        board.build(action.loc);
-        let new_game = Game {
+        board.apply_build(action);
+        self.end_turn(board, Some(action.loc))
+    }
+
+    fn end_turn(self, board: Board, build: Option<Point>) -> ActionResult<Move> {
+        let athena = if self.god(self.player) == God::Athena && self.state.moved_up {
+            Some(self.player)
+        } else {
+            self.state.athena
+        };
+        Game {
             state: Move {
-                player1_locs: self.state.player1_locs,
-                player2_locs: self.state.player2_locs,
+                workers: self.state.workers,
+                no_climbing: false,
+                pre_move: None,
+                athena,
             },
             board,
-            player: self.player.other(),
-        };
-
-        // Note that after a move, there is always at least one valid build
-        // location (the place the pawn moved from), so we just need to check
-        // moves and not builds to determine a stalemate.
-        let pawns = new_game.active_pawns();
-        if pawns[0].has_actions() || pawns[1].has_actions() {
-            ActionResult::Continue(new_game)
-        } else {
-            // New player can't move so the current player wins!
-            ActionResult::Victory(Game {
-                state: Victory {
-                    player1_locs: new_game.state.player1_locs,
-                    player2_locs: new_game.state.player2_locs,
-                },
-                board: new_game.board,
-                player: self.player,
-            })
+            player: self.player,
+            gods: self.gods,
+            players: self.players,
+            worker_count: self.worker_count,
+            ply: self.ply + 1,
         }
+        .hand_over(build)
     }
 }
 
 // Placement
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlaceAction<T: GameState> {
-    pos1: Point,
-    pos2: Point,
+    locs: WorkerLocs,
     game: Game<T>,
 }
 
 impl<T: GameState> PlaceAction<T> {
-    pub fn pos1(&self) -> Point {
-        self.pos1
+    pub fn locs(&self) -> WorkerLocs {
+        self.locs
     }
+}
 
-    pub fn pos2(&self) -> Point {
-        self.pos2
+impl<T: GameState> fmt::Display for PlaceAction<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let locs: Vec<String> = self.locs.iter().map(|loc| loc.to_string()).collect();
+        write!(f, "{}", locs.join(","))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Every set of `count` distinct squares, each set listed once in order.
+fn square_sets(count: usize) -> Vec<Vec<Point>> {
+    let squares: Vec<Point> = Point::all().collect();
+    let mut sets: Vec<Vec<Point>> = vec![vec![]];
+    for _ in 0..count {
+        sets = sets
+            .into_iter()
+            .flat_map(|set| {
+                let start = set.last().map_or(0, |last| {
+                    squares.iter().position(|sq| sq == last).unwrap() + 1
+                });
+                squares[start..].iter().map(move |sq| {
+                    let mut set = set.clone();
+                    set.push(*sq);
+                    set
+                })
+            })
+            .collect();
+    }
+    sets
+}
+
+/// Whether `locs` are the right number of distinct squares, none of them
+/// taken.
+fn valid_placement(locs: &[Point], count: usize, taken: &[Point]) -> bool {
+    locs.len() == count
+        && locs
+            .iter()
+            .enumerate()
+            .all(|(idx, loc)| !locs[idx + 1..].contains(loc) && !taken.contains(loc))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlaceOne {}
 impl GameState for PlaceOne {}
 
 impl Game<PlaceOne> {
-    pub fn can_place(&self, pos1: Point, pos2: Point) -> Option<PlaceAction<PlaceOne>> {
-        if pos1 != pos2 {
+    /// Give every player the given number of workers rather than two. Panics
+    /// unless the count is between one and `MAX_WORKERS`.
+    pub fn with_workers(self, count: usize) -> Game<PlaceOne> {
+        assert!(
+            (1..=MAX_WORKERS).contains(&count),
+            "Players need between 1 and {} workers!",
+            MAX_WORKERS
+        );
+        Game {
+            worker_count: count as u8,
+            ..self
+        }
+    }
+
+    /// Play with the building pieces in the box rather than an endless
+    /// supply, so builds become illegal once the pieces they need run out.
+    pub fn with_limited_components(self) -> Game<PlaceOne> {
+        Game {
+            board: self.board.with_supply(Supply::OFFICIAL),
+            ..self
+        }
+    }
+
+    /// Every legal placement, listing each set of squares once.
+    pub fn placements(&self) -> impl Iterator<Item = PlaceAction<PlaceOne>> + '_ {
+        square_sets(self.worker_count())
+            .into_iter()
+            .filter_map(move |locs| self.can_place(&locs))
+    }
+
+    /// Place one worker on each of the given squares.
+    pub fn can_place(&self, locs: &[Point]) -> Option<PlaceAction<PlaceOne>> {
+        if valid_placement(locs, self.worker_count(), &[]) {
             Some(PlaceAction {
-                pos1,
-                pos2,
+                locs: WorkerLocs::new(locs),
                 game: *self,
             })
         } else {
@@ -1053,6 +2827,17 @@ impl Game<PlaceOne> {
         }
     }
 
+    /// Place as `apply` does, unless the placement was made for another game.
+    pub fn try_apply(
+        self,
+        placement: PlaceAction<PlaceOne>,
+    ) -> Result<Game<PlaceTwo>, StaleAction> {
+        if placement.game != self {
+            return Err(StaleAction);
+        }
+        Ok(self.apply(placement))
+    }
+
     pub fn apply(self, placement: PlaceAction<PlaceOne>) -> Game<PlaceTwo> {
         debug_assert!(
             placement.game == self,
@@ -1063,36 +2848,80 @@ impl Game<PlaceOne> {
 
         Game {
             state: PlaceTwo {
-                player1_locs: [placement.pos1, placement.pos2],
+                workers: [Some(placement.locs), None, None],
             },
             board: self.board,
             player: Player::PlayerTwo,
+            gods: self.gods,
+            players: self.players,
+            worker_count: self.worker_count,
+            ply: self.ply + 1,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Placing the workers of any player after the first.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlaceTwo {
-    player1_locs: [Point; 2],
+    /// The workers placed so far.
+    workers: Workers,
 }
 impl GameState for PlaceTwo {}
 
-impl Game<PlaceTwo> {
-    pub fn player1_locs(&self) -> [Point; 2] {
-        self.state.player1_locs
+/// The game after a player places their workers: either the next player
+/// places theirs, or everyone has and the first turn begins.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PlaceResult {
+    Place(Game<PlaceTwo>),
+    Move(Game<Move>),
+}
+
+impl PlaceResult {
+    pub fn unwrap(self) -> Game<Move> {
+        self.expect("More players have to place their workers!")
     }
 
-    pub fn can_place(&self, pos1: Point, pos2: Point) -> Option<PlaceAction<PlaceTwo>> {
-        for pos in self.state.player1_locs.iter() {
-            if pos1 == *pos || pos2 == *pos {
-                return None;
-            }
+    pub fn expect(self, msg: &str) -> Game<Move> {
+        match self {
+            PlaceResult::Move(g) => g,
+            PlaceResult::Place(_) => panic!("{}", msg),
         }
+    }
+}
 
-        if pos1 != pos2 {
+impl Game<PlaceTwo> {
+    pub fn player1_locs(&self) -> WorkerLocs {
+        self.placed(Player::PlayerOne)
+            .expect("Player One has to place first!")
+    }
+
+    /// The workers a player has placed, if they have yet.
+    pub fn placed(&self, player: Player) -> Option<WorkerLocs> {
+        self.state.workers[player.index()]
+    }
+
+    /// Every legal placement, listing each set of squares once.
+    pub fn placements(&self) -> impl Iterator<Item = PlaceAction<PlaceTwo>> + '_ {
+        square_sets(self.worker_count())
+            .into_iter()
+            .filter_map(move |locs| self.can_place(&locs))
+    }
+
+    /// Place one worker on each of the given squares, which must not already
+    /// be taken.
+    pub fn can_place(&self, locs: &[Point]) -> Option<PlaceAction<PlaceTwo>> {
+        let taken: Vec<Point> = self
+            .state
+            .workers
+            .iter()
+            .flatten()
+            .flat_map(|locs| locs.to_vec())
+            .collect();
+        if valid_placement(locs, self.worker_count(), &taken) {
             Some(PlaceAction {
-                pos1,
-                pos2,
+                locs: WorkerLocs::new(locs),
                 game: *self,
             })
         } else {
@@ -1100,7 +2929,15 @@ impl Game<PlaceTwo> {
         }
     }
 
-    pub fn apply(self, placement: PlaceAction<PlaceTwo>) -> Game<Move> {
+    /// Place as `apply` does, unless the placement was made for another game.
+    pub fn try_apply(self, placement: PlaceAction<PlaceTwo>) -> Result<PlaceResult, StaleAction> {
+        if placement.game != self {
+            return Err(StaleAction);
+        }
+        Ok(self.apply(placement))
+    }
+
+    pub fn apply(self, placement: PlaceAction<PlaceTwo>) -> PlaceResult {
         debug_assert!(
             placement.game == self,
             "Game {:?} received action {:?} associated with a different game!",
@@ -1108,13 +2945,29 @@ impl Game<PlaceTwo> {
             placement
         );
 
-        Game {
-            state: Move {
-                player1_locs: self.state.player1_locs,
-                player2_locs: [placement.pos1, placement.pos2],
-            },
-            board: self.board,
-            player: Player::PlayerOne,
+        let mut workers = self.state.workers;
+        workers[self.player.index()] = Some(placement.locs);
+        match Player::iter().nth(self.player.index() + 1) {
+            Some(next) if next.index() < self.player_count() => PlaceResult::Place(Game {
+                state: PlaceTwo { workers },
+                player: *next,
+                ply: self.ply + 1,
+                ..self
+            }),
+            _ => PlaceResult::Move(Game {
+                state: Move {
+                    workers,
+                    no_climbing: false,
+                    pre_move: None,
+                    athena: None,
+                },
+                board: self.board,
+                player: Player::PlayerOne,
+                gods: self.gods,
+                players: self.players,
+                worker_count: self.worker_count,
+                ply: self.ply + 1,
+            }),
         }
     }
 }
@@ -1122,6 +2975,9 @@ impl Game<PlaceTwo> {
 #[cfg(test)]
 mod game_tests {
     use super::*;
+    use rand::rngs::SmallRng;
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
 
     #[test]
     fn place_one() {
@@ -1129,10 +2985,10 @@ mod game_tests {
         assert_eq!(Player::PlayerOne, g.player());
 
         let pt1 = Point::new(0.into(), 0.into());
-        assert_eq!(None, g.can_place(pt1, pt1));
+        assert_eq!(None, g.can_place(&[pt1, pt1]));
 
         let pt2 = Point::new(1.into(), 1.into());
-        assert_ne!(None, g.can_place(pt1, pt2));
+        assert_ne!(None, g.can_place(&[pt1, pt2]));
     }
 
     #[test]
@@ -1143,41 +2999,76 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(3.into(), 0.into());
 
-        let placement = g.can_place(pt1, pt2).expect("Invalid placement!");
+        let placement = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(placement);
         assert_eq!(Player::PlayerTwo, g.player());
 
-        assert_eq!(None, g.can_place(pt3, pt3));
-        assert_eq!(None, g.can_place(pt1, pt3));
-        assert_eq!(None, g.can_place(pt3, pt2));
-        assert_ne!(None, g.can_place(pt3, pt4));
+        assert_eq!(None, g.can_place(&[pt3, pt3]));
+        assert_eq!(None, g.can_place(&[pt1, pt3]));
+        assert_eq!(None, g.can_place(&[pt3, pt2]));
+        assert_ne!(None, g.can_place(&[pt3, pt4]));
+    }
+
+    #[test]
+    fn worker_counts() {
+        let pt1 = Point::new(0.into(), 0.into());
+        let pt2 = Point::new(1.into(), 1.into());
+        let pt3 = Point::new(2.into(), 2.into());
+
+        let g = new_game().with_workers(1);
+        assert_eq!(g.worker_count(), 1);
+        assert_eq!(None, g.can_place(&[pt1, pt2]));
+        let g = g.apply(g.can_place(&[pt1]).expect("Invalid placement!"));
+        assert_eq!(g.player1_locs().to_vec(), vec![pt1]);
+        assert_eq!(None, g.can_place(&[pt1]));
+        let g = match g.apply(g.can_place(&[pt2]).expect("Invalid placement!")) {
+            PlaceResult::Move(g) => g,
+            PlaceResult::Place(_) => panic!("Still placing!"),
+        };
+        assert_eq!(g.active_pawns().len(), 1);
+        assert_eq!(g.inactive_pawns().len(), 1);
+
+        let g = new_game().with_workers(3);
+        assert_eq!(None, g.can_place(&[pt1, pt2]));
+        assert_eq!(None, g.can_place(&[pt1, pt2, pt1]));
+        let placement = g.can_place(&[pt1, pt2, pt3]).expect("Invalid placement!");
+        assert_eq!(placement.to_string(), "a1,b2,c3");
+        let g = g.apply(placement);
+        assert_eq!(g.placed(Player::PlayerOne).unwrap().len(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_workers() {
+        new_game().with_workers(MAX_WORKERS + 1);
     }
 
     #[test]
     fn pawn_reporting() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         let g = new_game();
         let pt1 = Point::new(0.into(), 0.into());
-        let pt2 = Point::new(4.into(), 4.into());
-        let pt3 = Point::new(2.into(), 4.into());
-        let pt4 = Point::new(3.into(), 1.into());
+        let pt2 = Point::new((w - 1).into(), (h - 1).into());
+        let pt3 = Point::new(2.into(), (h - 1).into());
+        let pt4 = Point::new((w - 2).into(), 1.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
         assert_eq!(Player::PlayerOne, g.player());
 
-        let [pawn1, pawn2] = g.active_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn1.pos(), pt1);
         assert_eq!(pawn2.pos(), pt2);
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
 
-        let [pawn1, pawn2] = g.active_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn1.pos(), pt1);
         assert_eq!(pawn2.pos(), pt2);
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
 
@@ -1186,17 +3077,17 @@ mod game_tests {
         let g = g.apply(action).expect("Invalid victory!");
         assert_eq!(Player::PlayerOne, g.player());
 
-        let [pawn1, pawn2] = g.active_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn1.pos(), pt5);
         assert_eq!(pawn2.pos(), pt2);
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
 
-        let [pawn1, pawn2] = g.active_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn1.pos(), pt5);
         assert_eq!(pawn2.pos(), pt2);
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
 
@@ -1204,36 +3095,37 @@ mod game_tests {
         let g = g.apply(action).expect("Invalid victory!");
         assert_eq!(Player::PlayerTwo, g.player());
 
-        let [pawn1, pawn2] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn1.pos(), pt5);
         assert_eq!(pawn2.pos(), pt2);
-        let [pawn3, pawn4] = g.active_pawns();
+        let (pawn3, pawn4) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
 
-        let [pawn3, pawn4] = g.active_pawns();
+        let (pawn3, pawn4) = (g.active_pawns()[0], g.active_pawns()[1]);
         assert_eq!(pawn3.pos(), pt3);
         assert_eq!(pawn4.pos(), pt4);
-        let [pawn1, pawn2] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
         assert_eq!(pawn1.pos(), pt5);
         assert_eq!(pawn2.pos(), pt2);
     }
 
     #[test]
     fn neighbors() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         let g = new_game();
         let pt1 = Point::new(0.into(), 0.into());
-        let pt2 = Point::new(4.into(), 4.into());
-        let pt3 = Point::new(2.into(), 4.into());
-        let pt4 = Point::new(3.into(), 1.into());
+        let pt2 = Point::new((w - 1).into(), (h - 1).into());
+        let pt3 = Point::new(2.into(), (h - 1).into());
+        let pt4 = Point::new((w - 2).into(), 1.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
-        let [pawn1, pawn2] = g.active_pawns();
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
 
         let neighbors1 = [
             Point::new(1.into(), 0.into()),
@@ -1241,26 +3133,26 @@ mod game_tests {
             Point::new(1.into(), 1.into()),
         ];
         let neighbors2 = [
-            Point::new(3.into(), 3.into()),
-            Point::new(4.into(), 3.into()),
-            Point::new(3.into(), 4.into()),
+            Point::new((w - 2).into(), (h - 2).into()),
+            Point::new((w - 1).into(), (h - 2).into()),
+            Point::new((w - 2).into(), (h - 1).into()),
         ];
         let neighbors3 = [
-            Point::new(1.into(), 3.into()),
-            Point::new(2.into(), 3.into()),
-            Point::new(3.into(), 3.into()),
-            Point::new(1.into(), 4.into()),
-            Point::new(3.into(), 4.into()),
+            Point::new(1.into(), (h - 2).into()),
+            Point::new(2.into(), (h - 2).into()),
+            Point::new(3.into(), (h - 2).into()),
+            Point::new(1.into(), (h - 1).into()),
+            Point::new(3.into(), (h - 1).into()),
         ];
         let neighbors4 = [
-            Point::new(2.into(), 0.into()),
-            Point::new(3.into(), 0.into()),
-            Point::new(4.into(), 0.into()),
-            Point::new(2.into(), 1.into()),
-            Point::new(4.into(), 1.into()),
-            Point::new(2.into(), 2.into()),
-            Point::new(3.into(), 2.into()),
-            Point::new(4.into(), 2.into()),
+            Point::new((w - 3).into(), 0.into()),
+            Point::new((w - 2).into(), 0.into()),
+            Point::new((w - 1).into(), 0.into()),
+            Point::new((w - 3).into(), 1.into()),
+            Point::new((w - 1).into(), 1.into()),
+            Point::new((w - 3).into(), 2.into()),
+            Point::new((w - 2).into(), 2.into()),
+            Point::new((w - 1).into(), 2.into()),
         ];
 
         assert_eq!(pawn1.neighbors().collect::<Vec<Point>>(), neighbors1);
@@ -1271,36 +3163,43 @@ mod game_tests {
 
     #[test]
     fn move_actions() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         let g = new_game();
         let pt1 = Point::new(0.into(), 0.into());
-        let pt2 = Point::new(3.into(), 1.into());
-        let pt3 = Point::new(4.into(), 4.into());
-        let pt4 = Point::new(0.into(), 3.into());
+        let pt2 = Point::new((w - 2).into(), 1.into());
+        let pt3 = Point::new((w - 1).into(), (h - 1).into());
+        let pt4 = Point::new(0.into(), (h - 2).into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
-        let [pawn1, pawn2] = g.active_pawns();
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
 
         let moves1 = [
             MoveAction {
                 from: pt1,
                 to: Point::new(1.into(), 0.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt1,
                 to: Point::new(0.into(), 1.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt1,
                 to: Point::new(1.into(), 1.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
@@ -1308,49 +3207,65 @@ mod game_tests {
         let moves2 = [
             MoveAction {
                 from: pt2,
-                to: Point::new(2.into(), 0.into()),
+                to: Point::new((w - 3).into(), 0.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(3.into(), 0.into()),
+                to: Point::new((w - 2).into(), 0.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(4.into(), 0.into()),
+                to: Point::new((w - 1).into(), 0.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(2.into(), 1.into()),
+                to: Point::new((w - 3).into(), 1.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(4.into(), 1.into()),
+                to: Point::new((w - 1).into(), 1.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(2.into(), 2.into()),
+                to: Point::new((w - 3).into(), 2.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(3.into(), 2.into()),
+                to: Point::new((w - 2).into(), 2.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt2,
-                to: Point::new(4.into(), 2.into()),
+                to: Point::new((w - 1).into(), 2.into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
@@ -1368,25 +3283,31 @@ mod game_tests {
             .expect("Invalid build!");
         let g = g.apply(build).expect("Invalid victory!");
 
-        let [pawn1, pawn2] = g.inactive_pawns();
-        let [pawn3, pawn4] = g.active_pawns();
+        let (pawn1, pawn2) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
+        let (pawn3, pawn4) = (g.active_pawns()[0], g.active_pawns()[1]);
 
         let moves3 = [
             MoveAction {
                 from: pt3,
-                to: Point::new(3.into(), 3.into()),
+                to: Point::new((w - 2).into(), (h - 2).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt3,
-                to: Point::new(4.into(), 3.into()),
+                to: Point::new((w - 1).into(), (h - 2).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt3,
-                to: Point::new(3.into(), 4.into()),
+                to: Point::new((w - 2).into(), (h - 1).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
@@ -1394,31 +3315,41 @@ mod game_tests {
         let moves4 = [
             MoveAction {
                 from: pt4,
-                to: Point::new(0.into(), 2.into()),
+                to: Point::new(0.into(), (h - 3).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt4,
-                to: Point::new(1.into(), 2.into()),
+                to: Point::new(1.into(), (h - 3).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt4,
-                to: Point::new(1.into(), 3.into()),
+                to: Point::new(1.into(), (h - 2).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt4,
-                to: Point::new(0.into(), 4.into()),
+                to: Point::new(0.into(), (h - 1).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             MoveAction {
                 from: pt4,
-                to: Point::new(1.into(), 4.into()),
+                to: Point::new(1.into(), (h - 1).into()),
+                displaced: None,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
@@ -1432,48 +3363,59 @@ mod game_tests {
 
     #[test]
     fn build_actions() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
         let g = new_game();
         let pt1 = Point::new(0.into(), 0.into());
-        let pt2 = Point::new(3.into(), 1.into());
-        let pt3 = Point::new(4.into(), 4.into());
-        let pt4 = Point::new(2.into(), 4.into());
+        let pt2 = Point::new((w - 2).into(), 1.into());
+        let pt3 = Point::new((w - 1).into(), (h - 1).into());
+        let pt4 = Point::new(2.into(), (h - 1).into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         let pt1a = Point::new(0.into(), 1.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid move!");
         let g = g.apply(action).expect("Invalid victory!");
 
-        let [pawn1, pawn2] = g.active_pawns();
-        let [pawn3, pawn4] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
+        let (pawn3, pawn4) = (g.inactive_pawns()[0], g.inactive_pawns()[1]);
 
         let build1 = [
             BuildAction {
                 loc: Point::new(0.into(), 0.into()),
+                dome: false,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             BuildAction {
                 loc: Point::new(1.into(), 0.into()),
+                dome: false,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             BuildAction {
                 loc: Point::new(1.into(), 1.into()),
+                dome: false,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             BuildAction {
                 loc: Point::new(0.into(), 2.into()),
+                dome: false,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
             BuildAction {
                 loc: Point::new(1.into(), 2.into()),
+                dome: false,
+                game_hash: g.hash(),
                 #[cfg(debug_assertions)]
                 game: g,
             },
@@ -1493,13 +3435,13 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(1.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
-        let [pawn1, pawn2] = g.active_pawns();
-        let [pawn3, _] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
+        let pawn3 = g.inactive_pawns()[0];
 
         assert_eq!(None, pawn1.can_move(pt2));
         assert_eq!(None, pawn1.can_move(pt3));
@@ -1521,18 +3463,18 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(1.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         let pt1a = Point::new(1.into(), 0.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
-        let [pawn1, pawn2] = g.active_pawns();
-        let [pawn3, _] = g.inactive_pawns();
+        let (pawn1, pawn2) = (g.active_pawns()[0], g.active_pawns()[1]);
+        let pawn3 = g.inactive_pawns()[0];
 
         assert_eq!(pawn1, g.active_pawn());
 
@@ -1551,10 +3493,10 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(1.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0P1][0P3][0  ][0  ]
@@ -1563,7 +3505,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt1a = Point::new(1.into(), 0.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1578,7 +3520,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt3a = Point::new(2.into(), 0.into());
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt3a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1592,7 +3534,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         assert_eq!(None, pawn1.can_move(pt1));
 
         let pt1b = Point::new(0.into(), 0.into());
@@ -1609,7 +3551,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1624,7 +3566,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1c).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1638,7 +3580,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt1).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1652,7 +3594,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1666,7 +3608,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         assert_ne!(None, pawn3.can_move(pt1b));
         assert_eq!(None, pawn3.can_move(pt1a));
         assert_ne!(None, pawn3.can_move(pt3a));
@@ -1686,10 +3628,10 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(1.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0P1][0P3][0  ][0  ]
@@ -1698,7 +3640,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt1a = Point::new(1.into(), 0.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1713,7 +3655,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt3a = Point::new(2.into(), 0.into());
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt3a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1727,7 +3669,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt3).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1741,7 +3683,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1755,7 +3697,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt3a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1776,10 +3718,10 @@ mod game_tests {
         let pt3 = Point::new(2.into(), 1.into());
         let pt4 = Point::new(1.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0P1][0P3][0  ][0  ]
@@ -1788,7 +3730,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt1a = Point::new(1.into(), 0.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1803,7 +3745,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt3a = Point::new(2.into(), 0.into());
-        let [pawn3, _] = g.active_pawns();
+        let pawn3 = g.active_pawns()[0];
         let action = pawn3.can_move(pt3a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1817,7 +3759,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn2] = g.active_pawns();
+        let pawn2 = g.active_pawns()[1];
         let action = pawn2.can_move(pt3).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1831,7 +3773,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn4] = g.active_pawns();
+        let pawn4 = g.active_pawns()[1];
         let action = pawn4.can_move(pt2).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1845,7 +3787,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn2] = g.active_pawns();
+        let pawn2 = g.active_pawns()[1];
         let action = pawn2.can_move(pt1).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1859,7 +3801,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn4] = g.active_pawns();
+        let pawn4 = g.active_pawns()[1];
         let action = pawn4.can_move(pt4).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1873,7 +3815,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn2] = g.active_pawns();
+        let pawn2 = g.active_pawns()[1];
         let action = pawn2.can_move(pt3).expect("Invalid movement!");
         let g = g.apply(action);
 
@@ -1885,6 +3827,10 @@ mod game_tests {
 
         if let ActionResult::Victory(g) = g {
             assert_eq!(g.player(), Player::PlayerOne);
+            assert_eq!(
+                g.reason(),
+                VictoryReason::ReachedLevelThree { from: pt1, to: pt3 }
+            );
         } else {
             panic!("Victory not detected!");
         }
@@ -1898,10 +3844,10 @@ mod game_tests {
         let pt3 = Point::new(0.into(), 2.into());
         let pt4 = Point::new(2.into(), 2.into());
 
-        let action = g.can_place(pt1, pt2).expect("Invalid placement!");
-        let g = g.apply(action);
-        let action = g.can_place(pt3, pt4).expect("Invalid placement!");
+        let action = g.can_place(&[pt1, pt2]).expect("Invalid placement!");
         let g = g.apply(action);
+        let action = g.can_place(&[pt3, pt4]).expect("Invalid placement!");
+        let g = g.apply(action).unwrap();
 
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0P1][0  ][0  ][0  ]
@@ -1910,7 +3856,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt1a = Point::new(0.into(), 0.into());
-        let [pawn1, _] = g.active_pawns();
+        let pawn1 = g.active_pawns()[0];
         let action = pawn1.can_move(pt1a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1926,7 +3872,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt4a = Point::new(2.into(), 1.into());
-        let [_, pawn4] = g.active_pawns();
+        let pawn4 = g.active_pawns()[1];
         let action = pawn4.can_move(pt4a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1941,7 +3887,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
 
         let pt2a = Point::new(0.into(), 1.into());
-        let [_, pawn2] = g.active_pawns();
+        let pawn2 = g.active_pawns()[1];
         let action = pawn2.can_move(pt2a).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1955,7 +3901,7 @@ mod game_tests {
         // [0  ][0  ][0  ][0  ][0  ]
         // [0  ][0  ][0  ][0  ][0  ]
 
-        let [_, pawn4] = g.active_pawns();
+        let pawn4 = g.active_pawns()[1];
         let action = pawn4.can_move(pt2).expect("Invalid movement!");
         let g = g.apply(action).expect("Invalid victory!");
 
@@ -1971,8 +3917,341 @@ mod game_tests {
 
         if let ActionResult::Victory(g) = g {
             assert_eq!(g.player(), Player::PlayerTwo);
+            assert_eq!(
+                g.reason(),
+                VictoryReason::OpponentStalemated { build: Some(pt1) }
+            );
         } else {
             panic!("Victory not detected!");
         }
     }
+
+    #[test]
+    fn three_players() {
+        let g: AnyGame = new_three_player_game([God::Mortal, God::Pan, God::Mortal]).into();
+        let g = [
+            "a1,b1", "d1,c1", "a4,b4", "a1>a2^a1", "d1>d2^d1", "a4>a3^a4",
+        ]
+        .iter()
+        .fold(g, |g, turn| g.apply_turn(turn.parse().unwrap()).unwrap());
+        let g = match g {
+            AnyGame::Move(g) => g,
+            _ => panic!("Not moving!"),
+        };
+        assert_eq!(g.player(), Player::PlayerOne);
+        assert_eq!(g.player_count(), 3);
+        assert_eq!(g.next_player(), Player::PlayerTwo);
+        assert_eq!(g.god(Player::PlayerTwo), God::Pan);
+
+        // Player Two resigns, leaving the others to play on
+        let g = match g.resign() {
+            ActionResult::Continue(g) => g,
+            ActionResult::Victory(_) => panic!("Victory after one resignation!"),
+        };
+        assert_eq!(g.player(), Player::PlayerTwo);
+        assert!(!g.in_play(Player::PlayerOne));
+        assert_eq!(g.workers(Player::PlayerOne), None);
+        assert_eq!(g.next_player(), Player::PlayerThree);
+
+        match g.time_out() {
+            ActionResult::Victory(g) => assert_eq!(g.reason(), VictoryReason::Timeout),
+            ActionResult::Continue(_) => panic!("Victory not detected!"),
+        }
+        match g.resign() {
+            ActionResult::Victory(g) => {
+                assert_eq!(g.player(), Player::PlayerThree);
+                assert_eq!(g.reason(), VictoryReason::Resignation);
+            }
+            ActionResult::Continue(_) => panic!("Victory not detected!"),
+        }
+    }
+
+    #[test]
+    fn ply_counter() {
+        let g = new_three_player_game([God::Mortal, God::Artemis, God::Mortal]);
+        assert_eq!((g.ply(), g.turn_number()), (0, 1));
+        let g: AnyGame = g.into();
+        let g = ["a1,b1", "d1,c1", "a4,b4"]
+            .iter()
+            .fold(g, |g, turn| g.apply_turn(turn.parse().unwrap()).unwrap());
+        let g = match g {
+            AnyGame::Move(g) => g,
+            _ => panic!("Not moving!"),
+        };
+        assert_eq!((g.ply(), g.turn_number()), (3, 2));
+
+        // Only finished turns count
+        let pawn = g.active_pawns()[0];
+        let g = g
+            .apply(pawn.can_move(Point::new(0.into(), 1.into())).unwrap())
+            .unwrap();
+        assert_eq!(g.ply(), 3);
+        let g = g
+            .apply(
+                g.active_pawn()
+                    .can_build(Point::new(0.into(), 0.into()))
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!((g.ply(), g.player()), (4, Player::PlayerTwo));
+        let g = g.resign().unwrap();
+        assert_eq!((g.ply(), g.turn_number()), (5, 2));
+
+        let game = GameBuilder::new([God::Mortal; 2])
+            .workers(Player::PlayerOne, &[Point::new(0.into(), 0.into())])
+            .workers(Player::PlayerTwo, &[Point::new(3.into(), 3.into())])
+            .ply(6)
+            .build()
+            .unwrap();
+        assert_eq!((game.ply(), game.turn_number()), (6, 4));
+        let restarted = GameBuilder::new([God::Mortal; 2])
+            .workers(Player::PlayerOne, &[Point::new(0.into(), 0.into())])
+            .workers(Player::PlayerTwo, &[Point::new(3.into(), 3.into())])
+            .build()
+            .unwrap();
+        assert_ne!(game, restarted);
+        assert!(game.same_position(&restarted));
+    }
+
+    #[test]
+    fn stale_actions() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let g = new_game();
+        let placement = g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap();
+        let other = new_game_with_gods([God::Mortal, God::Pan]);
+        assert_eq!(other.try_apply(placement), Err(StaleAction));
+        let g = g.try_apply(placement).unwrap();
+        let g = g
+            .apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap())
+            .unwrap();
+
+        let pawn = g.active_pawns()[0];
+        let step = pawn.can_move(pt(2, 2)).unwrap();
+        let built = g.try_apply(step).unwrap().unwrap();
+        let build = built.active_pawn().can_build(pt(2, 1)).unwrap();
+        let moved = g.try_apply(pawn.can_move(pt(1, 2)).unwrap());
+        let moved = moved.unwrap().unwrap();
+        assert_eq!(moved.try_apply(build), Err(StaleAction));
+        assert!(built.try_apply(build).is_ok());
+
+        // The whole game has to match, not just the board
+        let any: AnyGame = moved.into();
+        assert_eq!(any.apply(AnyAction::Build(build)), None);
+    }
+
+    #[test]
+    fn worker_ids() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let g = new_game_with_gods([God::Apollo, God::Mortal]);
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        let g = g
+            .apply(g.can_place(&[pt(1, 2), pt(3, 1)]).unwrap())
+            .unwrap();
+        let p1b = WorkerId::new(Player::PlayerOne, 1);
+        let p2a = WorkerId::new(Player::PlayerTwo, 0);
+        assert_eq!(p1b.to_string(), "P1B");
+        assert_eq!(g.worker_on(pt(1, 2)), Some(p2a));
+        assert_eq!(g.worker_on(pt(2, 2)), None);
+
+        // Apollo swaps places with P2A, and both workers keep their names
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.id().to_string(), "P1A");
+        let g = g.apply(pawn.can_move(pt(1, 2)).unwrap()).unwrap();
+        assert_eq!(g.active_worker(), pawn.id());
+        assert_eq!(g.worker(p2a), Some(pt(1, 1)));
+        assert_eq!(g.worker(p1b), Some(pt(3, 3)));
+        assert_eq!(g.worker(WorkerId::new(Player::PlayerThree, 0)), None);
+    }
+
+    #[test]
+    fn illegal_actions() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::Capped);
+        board.set_level(pt(0, 1), CoordLevel::One);
+        board.set_level(pt(1, 1), CoordLevel::Three);
+        let g = GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(w - 1, h - 1)])
+            .workers(Player::PlayerTwo, &[pt(2, 2), pt(w - 1, 0)])
+            .build()
+            .unwrap();
+        let pawns = g.active_pawns();
+        let pawn = pawns[0];
+        assert_eq!(pawn.why_cannot_move(pt(0, 1)), None);
+        assert_eq!(pawn.why_cannot_move(pt(1, 0)), Some(IllegalAction::Capped));
+        assert_eq!(pawn.why_cannot_move(pt(1, 1)), Some(IllegalAction::TooHigh));
+        assert_eq!(pawn.why_cannot_move(pt(2, 2)), Some(IllegalAction::TooFar));
+        let other = g.inactive_pawns()[0];
+        assert_eq!(
+            other.why_cannot_move(pt(2, 1)),
+            Some(IllegalAction::NotYourTurn)
+        );
+        assert_eq!(
+            pawns[1].why_cannot_move(pt(w - 1, h - 1)),
+            Some(IllegalAction::TooFar)
+        );
+
+        let g = g.apply(pawn.can_move(pt(0, 1)).unwrap()).unwrap();
+        let pawn = g.active_pawn();
+        assert_eq!(pawn.why_cannot_build(pt(0, 0)), None);
+        assert_eq!(
+            pawn.why_cannot_build(pt(0, 1)),
+            Some(IllegalAction::Occupied)
+        );
+        assert_eq!(pawn.why_cannot_build(pt(1, 0)), Some(IllegalAction::Capped));
+        assert_eq!(pawn.why_cannot_build(pt(0, 3)), Some(IllegalAction::TooFar));
+        assert_eq!(
+            g.inactive_pawns()[0].why_cannot_build(pt(2, 3)),
+            Some(IllegalAction::NotYourTurn)
+        );
+        assert_eq!(
+            IllegalAction::TooHigh.to_string(),
+            "that space is too high to move up to"
+        );
+    }
+
+    #[test]
+    fn three_player_stalemate() {
+        // Player Two is walled in, so loses their workers instead of the game
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        for &(x, y) in &[(2, 0), (0, 1), (1, 1), (2, 1)] {
+            board.set_level(pt(x, y), CoordLevel::Capped);
+        }
+        let g = GameBuilder::three_player([God::Mortal; 3])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, h - 1), pt(1, h - 1)])
+            .workers(Player::PlayerTwo, &[pt(0, 0), pt(1, 0)])
+            .workers(Player::PlayerThree, &[pt(w - 2, h - 1), pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(0, h - 2)).unwrap())
+            .unwrap();
+        let g = g
+            .apply(g.active_pawn().can_build(pt(0, h - 1)).unwrap())
+            .unwrap();
+        assert_eq!(g.player(), Player::PlayerThree);
+        assert_eq!(g.workers(Player::PlayerTwo), None);
+        assert_eq!(g.next_player(), Player::PlayerOne);
+    }
+
+    #[test]
+    fn move_generation() {
+        // Play random games out, checking the moves read off the bitboard
+        // against every square on the board
+        let mut rng = SmallRng::seed_from_u64(0);
+        let (mx, my) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        for count in (1..=MAX_WORKERS).flat_map(|count| vec![count; 10]) {
+            let g = new_game().with_workers(count);
+            let g = g.apply(g.placements().choose(&mut rng).unwrap());
+            let mut g = match g.apply(g.placements().choose(&mut rng).unwrap()) {
+                PlaceResult::Move(g) => g,
+                PlaceResult::Place(_) => panic!("Still placing!"),
+            };
+            assert_eq!(g.active_pawns().len(), count);
+            loop {
+                let mut moves = vec![];
+                for pawn in g.active_pawns().iter() {
+                    let actions: Vec<_> = pawn.actions().collect();
+                    let expected: Vec<_> = (0..=mx)
+                        .flat_map(|x| (0..=my).map(move |y| pt(x, y)))
+                        .filter_map(|to| pawn.can_move(to))
+                        .collect();
+                    assert_eq!(actions.len(), expected.len());
+                    assert!(actions.iter().all(|action| expected.contains(action)));
+                    assert_eq!(pawn.has_actions(), !actions.is_empty());
+                    moves.extend(actions);
+                }
+
+                let g2 = match g.apply(moves.into_iter().choose(&mut rng).unwrap()) {
+                    ActionResult::Continue(g2) => g2,
+                    ActionResult::Victory(_) => break,
+                };
+                let build = g2.active_pawn().actions().choose(&mut rng).unwrap();
+                g = match g2.apply(build) {
+                    ActionResult::Continue(g) => g,
+                    ActionResult::Victory(_) => break,
+                };
+            }
+        }
+    }
+
+    #[test]
+    fn limited_components() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let g = new_game().with_limited_components();
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        let g = match g.apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap()) {
+            PlaceResult::Move(g) => g,
+            PlaceResult::Place(_) => panic!("Still placing!"),
+        };
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap();
+        let g = g
+            .apply(g.active_pawn().can_build(pt(1, 1)).unwrap())
+            .unwrap();
+        assert_eq!(g.board().supply(), Some(Supply::new([21, 18, 14], 18)));
+
+        // With no blocks for the first level, the only square to build on is
+        // a1, so moves away from it are not allowed
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let mut board = Board::new();
+        board.set_level(pt(0, 0), CoordLevel::One);
+        let g = GameBuilder::new([God::Mortal; 2])
+            .board(board.with_supply(Supply::new([0, 1, 0], 0)))
+            .workers(Player::PlayerOne, &[pt(0, 2), pt(w - 1, 0)])
+            .workers(Player::PlayerTwo, &[pt(2, h - 1), pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        let pawns = g.active_pawns();
+        let moves: Vec<_> = pawns.iter().flat_map(|pawn| pawn.actions()).collect();
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&pawns[0].can_move(pt(0, 1)).unwrap()));
+        assert!(moves.contains(&pawns[0].can_move(pt(1, 1)).unwrap()));
+        assert_eq!(pawns[0].can_move(pt(1, 2)), None);
+        assert!(!pawns[1].has_actions());
+
+        let built = g.apply(pawns[0].can_move(pt(0, 1)).unwrap()).unwrap();
+        let pawn = built.active_pawn();
+        assert_eq!(pawn.can_build(pt(1, 1)), None);
+        let (result, undo) = built.apply_with_undo(pawn.can_build(pt(0, 0)).unwrap());
+        assert_eq!(result.unapply(undo), built);
+
+        // Player Two has nothing left to build with, so loses
+        match result {
+            ActionResult::Victory(g) => {
+                assert_eq!(g.player(), Player::PlayerOne);
+                assert_eq!(g.board().supply(), Some(Supply::new([0, 0, 0], 0)));
+            }
+            ActionResult::Continue(_) => panic!("Victory not detected!"),
+        }
+
+        // Atlas builds domes from the domes
+        let g = GameBuilder::new([God::Atlas, God::Mortal])
+            .board(Board::new().with_supply(Supply::new([0, 0, 0], 1)))
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(w - 1, 0)])
+            .workers(Player::PlayerTwo, &[pt(0, h - 1), pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        let built = g
+            .apply(g.active_pawns()[0].can_move(pt(0, 1)).unwrap())
+            .unwrap();
+        let pawn = built.active_pawn();
+        assert_eq!(pawn.can_build(pt(0, 0)), None);
+        assert_eq!(pawn.actions().count(), 5);
+        let dome = pawn.can_build_dome(pt(0, 0)).unwrap();
+        match built.apply(dome) {
+            ActionResult::Victory(g) => {
+                assert_eq!(g.board().level_at(pt(0, 0)), CoordLevel::Capped);
+                assert_eq!(g.board().supply().unwrap().left(CoordLevel::Capped), 0);
+            }
+            ActionResult::Continue(_) => panic!("Victory not detected!"),
+        }
+    }
 }
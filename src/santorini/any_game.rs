@@ -0,0 +1,281 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::santorini::{
+    ActionResult, Board, Build, BuildAction, ExtraMoveAction, Game, GameState, Move, MoveAction,
+    NormalState, PlaceAction, PlaceOne, PlaceResult, PlaceTwo, Player, Point, PreMoveAction, Turn,
+    Victory,
+};
+
+/// A game in any state.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AnyGame {
+    PlaceOne(Game<PlaceOne>),
+    PlaceTwo(Game<PlaceTwo>),
+    Move(Game<Move>),
+    Build(Game<Build>),
+    Victory(Game<Victory>),
+}
+
+macro_rules! any_game_from {
+    ($state:ident) => {
+        impl From<Game<$state>> for AnyGame {
+            fn from(game: Game<$state>) -> AnyGame {
+                AnyGame::$state(game)
+            }
+        }
+    };
+}
+
+any_game_from!(PlaceOne);
+any_game_from!(PlaceTwo);
+any_game_from!(Move);
+any_game_from!(Build);
+any_game_from!(Victory);
+
+impl<T> From<ActionResult<T>> for AnyGame
+where
+    T: GameState,
+    AnyGame: From<Game<T>>,
+{
+    fn from(result: ActionResult<T>) -> AnyGame {
+        match result {
+            ActionResult::Continue(game) => game.into(),
+            ActionResult::Victory(game) => game.into(),
+        }
+    }
+}
+
+impl From<PlaceResult> for AnyGame {
+    fn from(result: PlaceResult) -> AnyGame {
+        match result {
+            PlaceResult::Place(game) => game.into(),
+            PlaceResult::Move(game) => game.into(),
+        }
+    }
+}
+
+/// A single action in any state of a game.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AnyAction {
+    PlaceOne(PlaceAction<PlaceOne>),
+    PlaceTwo(PlaceAction<PlaceTwo>),
+    PreMove(PreMoveAction),
+    Move(MoveAction),
+    ExtraMove(ExtraMoveAction),
+    /// A build, after which the player builds again if their god allows it.
+    Build(BuildAction),
+    /// Declining an optional additional build.
+    Pass,
+}
+
+fn pawn_locs<S: GameState + NormalState>(game: &Game<S>) -> Vec<Point> {
+    game.state
+        .workers()
+        .iter()
+        .flatten()
+        .flat_map(|locs| locs.to_vec())
+        .collect()
+}
+
+impl AnyGame {
+    pub fn board(&self) -> Board {
+        match self {
+            AnyGame::PlaceOne(game) => game.board(),
+            AnyGame::PlaceTwo(game) => game.board(),
+            AnyGame::Move(game) => game.board(),
+            AnyGame::Build(game) => game.board(),
+            AnyGame::Victory(game) => game.board(),
+        }
+    }
+
+    /// The player to act, or the winner once the game is over.
+    pub fn player(&self) -> Player {
+        match self {
+            AnyGame::PlaceOne(game) => game.player(),
+            AnyGame::PlaceTwo(game) => game.player(),
+            AnyGame::Move(game) => game.player(),
+            AnyGame::Build(game) => game.player(),
+            AnyGame::Victory(game) => game.player(),
+        }
+    }
+
+    /// The number of players the game started with.
+    pub fn player_count(&self) -> usize {
+        match self {
+            AnyGame::PlaceOne(game) => game.player_count(),
+            AnyGame::PlaceTwo(game) => game.player_count(),
+            AnyGame::Move(game) => game.player_count(),
+            AnyGame::Build(game) => game.player_count(),
+            AnyGame::Victory(game) => game.player_count(),
+        }
+    }
+
+    /// The locations of every pawn placed so far.
+    pub fn pawn_locs(&self) -> Vec<Point> {
+        match self {
+            AnyGame::PlaceOne(_) => vec![],
+            AnyGame::PlaceTwo(game) => game
+                .state
+                .workers
+                .iter()
+                .flatten()
+                .flat_map(|locs| locs.to_vec())
+                .collect(),
+            AnyGame::Move(game) => pawn_locs(game),
+            AnyGame::Build(game) => pawn_locs(game),
+            AnyGame::Victory(game) => pawn_locs(game),
+        }
+    }
+
+    /// Apply a complete turn, returning None if it is not legal in this state.
+    pub fn apply_turn(self, turn: Turn) -> Option<AnyGame> {
+        match (self, turn) {
+            (AnyGame::PlaceOne(game), _) => Some(game.apply_turn(turn)?.into()),
+            (AnyGame::PlaceTwo(game), _) => Some(game.apply_turn(turn)?.into()),
+            (AnyGame::Move(game), _) => Some(game.apply_turn(turn)?.into()),
+            (AnyGame::Build(game), Turn::Resign) => Some(game.resign().into()),
+            _ => None,
+        }
+    }
+    /// The complete turn leading from this state to `next`, or None if no
+    /// single turn does. Only the turns `legal_turns` considers are found.
+    pub fn turn_to(self, next: AnyGame) -> Option<Turn> {
+        match self {
+            AnyGame::PlaceOne(game) => game
+                .placements()
+                .find(|placement| AnyGame::from(game.apply(*placement)) == next)
+                .map(|placement| Turn::Place(placement.locs())),
+            AnyGame::PlaceTwo(game) => game
+                .placements()
+                .find(|placement| AnyGame::from(game.apply(*placement)) == next)
+                .map(|placement| Turn::Place(placement.locs())),
+            AnyGame::Move(game) if AnyGame::from(game.resign()) == next => Some(Turn::Resign),
+            AnyGame::Move(game) => game
+                .legal_turns()
+                .find(|(_, result)| AnyGame::from(*result) == next)
+                .map(|(actions, _)| actions.into()),
+            AnyGame::Build(_) | AnyGame::Victory(_) => None,
+        }
+    }
+
+    /// Every legal action in this state, none once the game is over.
+    pub fn actions(&self) -> Vec<AnyAction> {
+        match self {
+            AnyGame::PlaceOne(game) => game.placements().map(AnyAction::PlaceOne).collect(),
+            AnyGame::PlaceTwo(game) => game.placements().map(AnyAction::PlaceTwo).collect(),
+            AnyGame::Move(game) => game
+                .active_pawns()
+                .iter()
+                .flat_map(|pawn| {
+                    pawn.pre_moves()
+                        .map(AnyAction::PreMove)
+                        .chain(pawn.actions().map(AnyAction::Move))
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            AnyGame::Build(game) => {
+                let pawn = game.active_pawn();
+                let mut actions: Vec<_> = pawn.moves().map(AnyAction::ExtraMove).collect();
+                actions.extend(pawn.actions().map(AnyAction::Build));
+                if game.can_pass() {
+                    actions.push(AnyAction::Pass);
+                }
+                actions
+            }
+            AnyGame::Victory(_) => vec![],
+        }
+    }
+
+    /// Apply an action, returning None if it is not an action of this state
+    /// or was made for another game.
+    pub fn apply(self, action: AnyAction) -> Option<AnyGame> {
+        let game = match (self, action) {
+            (AnyGame::PlaceOne(game), AnyAction::PlaceOne(action)) => {
+                game.try_apply(action).ok()?.into()
+            }
+            (AnyGame::PlaceTwo(game), AnyAction::PlaceTwo(action)) => {
+                game.try_apply(action).ok()?.into()
+            }
+            (AnyGame::Move(game), AnyAction::PreMove(action)) => {
+                game.try_apply_pre_move(action).ok()?.into()
+            }
+            (AnyGame::Move(game), AnyAction::Move(action)) => game.try_apply(action).ok()?.into(),
+            (AnyGame::Build(game), AnyAction::ExtraMove(action)) => {
+                game.try_apply_move(action).ok()?.into()
+            }
+            (AnyGame::Build(game), AnyAction::Build(action)) if game.can_build_again() => {
+                game.try_build_again(action).ok()?.into()
+            }
+            (AnyGame::Build(game), AnyAction::Build(action)) => game.try_apply(action).ok()?.into(),
+            (AnyGame::Build(game), AnyAction::Pass) if game.can_pass() => game.pass().into(),
+            _ => return None,
+        };
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod any_game_tests {
+    use rand::rngs::SmallRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::santorini::{self, God, BOARD_SQUARES};
+
+    #[test]
+    fn placements() {
+        let game: AnyGame = santorini::new_game().into();
+        let actions = game.actions();
+        let pairs = |squares: usize| squares * (squares - 1) / 2;
+        assert_eq!(actions.len(), pairs(BOARD_SQUARES));
+
+        let game = game.apply(actions[0]).unwrap();
+        assert_eq!(game.actions().len(), pairs(BOARD_SQUARES - 2));
+        assert_eq!(game.apply(actions[0]), None);
+
+        let game: AnyGame = santorini::new_game().with_workers(1).into();
+        assert_eq!(game.actions().len(), BOARD_SQUARES);
+    }
+
+    #[test]
+    fn play_out() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for gods in [[God::Mortal, God::Prometheus], [God::Artemis, God::Demeter]].iter() {
+            let mut game: AnyGame = santorini::new_game_with_gods(*gods).into();
+            loop {
+                let actions = game.actions();
+                if let AnyGame::Victory(_) = game {
+                    assert!(actions.is_empty());
+                    break;
+                }
+                let action = *actions.choose(&mut rng).expect("No legal actions!");
+                game = game.apply(action).expect("Illegal action!");
+            }
+        }
+    }
+
+    #[test]
+    fn turns_between_states() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        for gods in [[God::Mortal, God::Prometheus], [God::Artemis, God::Demeter]].iter() {
+            let mut start: AnyGame = santorini::new_game_with_gods(*gods).into();
+            let mut game = start;
+            while let Some(action) = game.actions().choose(&mut rng) {
+                game = game.apply(*action).expect("Illegal action!");
+                let over = matches!(game, AnyGame::Victory(_));
+                if over || game.player() != start.player() {
+                    let turn = start.turn_to(game).expect("No turn found!");
+                    assert_eq!(start.apply_turn(turn), Some(game));
+                    start = game;
+                }
+            }
+        }
+
+        let game: AnyGame = santorini::new_game().into();
+        assert_eq!(game.turn_to(game), None);
+    }
+}
@@ -0,0 +1,454 @@
+use thiserror::Error;
+
+use crate::santorini::{
+    AnyGame, Board, CoordLevel, Game, God, Move, Phase, PlaceOne, PlaceTwo, Player, Point,
+    WorkerLocs, Workers, BOARD_HEIGHT, BOARD_WIDTH, MAX_PLAYERS, MAX_WORKERS,
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    #[error("invalid height {height} at {loc}")]
+    InvalidHeight { loc: Point, height: u8 },
+    #[error("{0:?} is not in the game")]
+    NoSuchPlayer(Player),
+    #[error("{0:?} to move has no workers")]
+    NoWorkers(Player),
+    #[error("not enough players have workers")]
+    TooFewPlayers,
+    #[error("more than one worker on {0}")]
+    Overlap(Point),
+    #[error("worker on {0} stands too high")]
+    TooHigh(Point),
+    #[error("worker on {0} stands on a dome")]
+    OnDome(Point),
+    #[error("workers are placed in turn order, starting with Player One")]
+    PlacementOrder,
+    #[error("positions can't be set up in the {0:?} phase")]
+    UnsupportedPhase(Phase),
+    #[error(
+        "players need between 1 and {} workers, all the same number",
+        MAX_WORKERS
+    )]
+    WorkerCount,
+}
+
+impl Board {
+    /// A board with the given heights, listed row by row from the top with 4
+    /// for a dome.
+    pub fn from_heights(
+        heights: [[u8; BOARD_WIDTH.0 as usize]; BOARD_HEIGHT.0 as usize],
+    ) -> Result<Board, PositionError> {
+        let mut board = Board::new();
+        for (y, row) in heights.iter().enumerate() {
+            for (x, height) in row.iter().enumerate() {
+                let loc = Point::new((x as i8).into(), (y as i8).into());
+                if *height > 4 {
+                    return Err(PositionError::InvalidHeight {
+                        loc,
+                        height: *height,
+                    });
+                }
+                board.set_level(loc, (*height as i8).into());
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Sets up a game in the middle of play, at the start of a player's turn.
+///
+/// Players start without workers and Player One to move on an empty board.
+/// Players left without workers have been eliminated, which is only possible
+/// in three player games.
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+    board: Board,
+    worker_locs: [Vec<Point>; MAX_PLAYERS],
+    player: Player,
+    gods: [God; MAX_PLAYERS],
+    players: u8,
+    ply: u16,
+}
+
+impl GameBuilder {
+    /// Set up a game where Player One and Player Two have the given gods.
+    pub fn new(gods: [God; 2]) -> Self {
+        GameBuilder {
+            board: Board::new(),
+            worker_locs: Default::default(),
+            player: Player::PlayerOne,
+            gods: [gods[0], gods[1], God::Mortal],
+            players: 2,
+            ply: 0,
+        }
+    }
+
+    /// Set up a three player game where the players have the given gods.
+    pub fn three_player(gods: [God; 3]) -> Self {
+        GameBuilder {
+            players: 3,
+            gods,
+            ..GameBuilder::new([God::Mortal; 2])
+        }
+    }
+
+    pub fn board(mut self, board: Board) -> Self {
+        self.board = board;
+        self
+    }
+
+    /// Place the player's workers. Every player must have the same number
+    /// of workers, between one and `MAX_WORKERS`.
+    pub fn workers(mut self, player: Player, locs: &[Point]) -> Self {
+        self.worker_locs[player.index()] = locs.to_vec();
+        self
+    }
+
+    /// The player whose turn it is.
+    pub fn player(mut self, player: Player) -> Self {
+        self.player = player;
+        self
+    }
+
+    /// The number of plies played before the position was reached.
+    pub fn ply(mut self, ply: u16) -> Self {
+        self.ply = ply;
+        self
+    }
+
+    /// Check the position makes sense and assemble the game.
+    pub fn build(self) -> Result<Game<Move>, PositionError> {
+        let placed: Vec<&Vec<Point>> = self
+            .worker_locs
+            .iter()
+            .filter(|locs| !locs.is_empty())
+            .collect();
+        let worker_count = placed.first().map_or(2, |locs| locs.len());
+        if worker_count > MAX_WORKERS || placed.iter().any(|locs| locs.len() != worker_count) {
+            return Err(PositionError::WorkerCount);
+        }
+        let mut workers: Workers = [None; MAX_PLAYERS];
+        for (locs, placed) in workers.iter_mut().zip(self.worker_locs.iter()) {
+            if !placed.is_empty() {
+                *locs = Some(WorkerLocs::new(placed));
+            }
+        }
+
+        for player in Player::iter().skip(self.players as usize) {
+            if workers[player.index()].is_some() || self.player == *player {
+                return Err(PositionError::NoSuchPlayer(*player));
+            }
+        }
+        if workers[self.player.index()].is_none() {
+            return Err(PositionError::NoWorkers(self.player));
+        }
+        if workers.iter().flatten().count() < 2 {
+            return Err(PositionError::TooFewPlayers);
+        }
+
+        let all: Vec<Point> = workers
+            .iter()
+            .flatten()
+            .flat_map(|locs| locs.to_vec())
+            .collect();
+        for (idx, loc) in all.iter().enumerate() {
+            if all[idx + 1..].contains(loc) {
+                return Err(PositionError::Overlap(*loc));
+            }
+            // Workers on the third level would already have won
+            match self.board.level_at(*loc) {
+                CoordLevel::Capped => return Err(PositionError::OnDome(*loc)),
+                CoordLevel::Three => return Err(PositionError::TooHigh(*loc)),
+                _ => (),
+            }
+        }
+
+        Ok(Game {
+            state: Move {
+                workers,
+                no_climbing: false,
+                pre_move: None,
+                athena: None,
+            },
+            board: self.board,
+            player: self.player,
+            gods: self.gods,
+            players: self.players,
+            worker_count: worker_count as u8,
+            ply: self.ply,
+        })
+    }
+}
+
+impl Game<Move> {
+    /// Set up a two player game between mortals from its parts, checking
+    /// they describe a position that could come up in play. Players who have
+    /// yet to place their workers have none.
+    ///
+    /// Only positions where a player is about to place their workers or to
+    /// start their turn can be set up this way. The notation in
+    /// `santorini::notation` covers turns in progress and finished games.
+    pub fn try_from_parts(
+        board: Board,
+        player1_locs: &[Point],
+        player2_locs: &[Point],
+        player: Player,
+        phase: Phase,
+    ) -> Result<AnyGame, PositionError> {
+        if phase != Phase::Place {
+            let builder = GameBuilder::new([God::Mortal; 2])
+                .board(board)
+                .workers(Player::PlayerOne, player1_locs)
+                .workers(Player::PlayerTwo, player2_locs)
+                .player(player);
+            return match phase {
+                Phase::Move => Ok(builder.build()?.into()),
+                _ => Err(PositionError::UnsupportedPhase(phase)),
+            };
+        }
+
+        let placed = match (player, player1_locs.len(), player2_locs.len()) {
+            (Player::PlayerOne, 0, 0) => {
+                return Ok(Game {
+                    state: PlaceOne {},
+                    board,
+                    player,
+                    gods: [God::Mortal; MAX_PLAYERS],
+                    players: 2,
+                    worker_count: 2,
+                    ply: 0,
+                }
+                .into())
+            }
+            (Player::PlayerTwo, count, 0) if count > 0 => player1_locs,
+            (Player::PlayerThree, _, _) => return Err(PositionError::NoSuchPlayer(player)),
+            _ => return Err(PositionError::PlacementOrder),
+        };
+        if placed.len() > MAX_WORKERS {
+            return Err(PositionError::WorkerCount);
+        }
+        for (idx, loc) in placed.iter().enumerate() {
+            if placed[idx + 1..].contains(loc) {
+                return Err(PositionError::Overlap(*loc));
+            }
+            if board.level_at(*loc) == CoordLevel::Capped {
+                return Err(PositionError::OnDome(*loc));
+            }
+        }
+        Ok(Game {
+            state: PlaceTwo {
+                workers: [Some(WorkerLocs::new(placed)), None, None],
+            },
+            board,
+            player,
+            gods: [God::Mortal; MAX_PLAYERS],
+            players: 2,
+            worker_count: placed.len() as u8,
+            ply: 1,
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+    use crate::santorini::notation;
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// Heights with every level, and a dome in the bottom left corner.
+    fn heights() -> [[u8; BOARD_WIDTH.0 as usize]; BOARD_HEIGHT.0 as usize] {
+        let mut heights = [[0; BOARD_WIDTH.0 as usize]; BOARD_HEIGHT.0 as usize];
+        heights[0][1] = 1;
+        heights[1][2] = 2;
+        heights[3][3] = 3;
+        heights[BOARD_HEIGHT.0 as usize - 1][0] = 4;
+        heights
+    }
+
+    #[test]
+    fn from_heights() {
+        let bottom = BOARD_HEIGHT.0 - 1;
+        let board = Board::from_heights(heights()).unwrap();
+        assert_eq!(board.level_at(pt(1, 0)), CoordLevel::One);
+        assert_eq!(board.level_at(pt(2, 1)), CoordLevel::Two);
+        assert_eq!(board.level_at(pt(3, 3)), CoordLevel::Three);
+        assert_eq!(board.level_at(pt(0, bottom)), CoordLevel::Capped);
+        assert_eq!(
+            Point::all()
+                .filter(|loc| board.level_at(*loc) != CoordLevel::Ground)
+                .count(),
+            4
+        );
+
+        let mut heights = heights();
+        heights[2][1] = 5;
+        assert_eq!(
+            Board::from_heights(heights),
+            Err(PositionError::InvalidHeight {
+                loc: pt(1, 2),
+                height: 5
+            })
+        );
+    }
+
+    #[test]
+    fn build() {
+        let board = Board::from_heights(heights()).unwrap();
+        let game = GameBuilder::new([God::Mortal, God::Pan])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(1, 0), pt(2, 1)])
+            .workers(Player::PlayerTwo, &[pt(1, 3), pt(3, 1)])
+            .player(Player::PlayerTwo)
+            .build()
+            .unwrap();
+        assert_eq!(
+            notation::encode(game),
+            format!(
+                "{} b1,c2 b4,d2 2 m Mortal,Pan",
+                notation::encode_board(&board)
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_positions() {
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let mut board = Board::new();
+        board.set_level(pt(2, 1), CoordLevel::Three);
+        let builder = GameBuilder::new([God::Mortal; 2])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(1, 1)]);
+        assert_eq!(
+            builder.clone().build().unwrap_err(),
+            PositionError::TooFewPlayers
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .workers(Player::PlayerTwo, &[pt(1, 1), pt(3, 3)])
+                .build()
+                .unwrap_err(),
+            PositionError::Overlap(pt(1, 1))
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .workers(Player::PlayerTwo, &[pt(2, 1), pt(3, 3)])
+                .build()
+                .unwrap_err(),
+            PositionError::TooHigh(pt(2, 1))
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .workers(Player::PlayerTwo, &[pt(2, 2), pt(3, 3), pt(0, 3)])
+                .build()
+                .unwrap_err(),
+            PositionError::WorkerCount
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .workers(Player::PlayerThree, &[pt(2, 2), pt(3, 3)])
+                .build()
+                .unwrap_err(),
+            PositionError::NoSuchPlayer(Player::PlayerThree)
+        );
+
+        // A three player game may go on without an eliminated player
+        let game = GameBuilder::three_player([God::Mortal; 3])
+            .workers(Player::PlayerOne, &[pt(0, 0), pt(1, 1)])
+            .workers(Player::PlayerThree, &[pt(2, 2), pt(3, 3)])
+            .player(Player::PlayerThree)
+            .build()
+            .unwrap();
+        assert!(!game.in_play(Player::PlayerTwo));
+        assert_eq!(game.next_player(), Player::PlayerOne);
+
+        let game = GameBuilder::new([God::Mortal; 2])
+            .workers(Player::PlayerOne, &[pt(0, 0)])
+            .workers(Player::PlayerTwo, &[pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        assert_eq!(game.worker_count(), 1);
+        assert_eq!(
+            notation::encode(game),
+            format!(
+                "{} a1 {} 1 m Mortal,Mortal",
+                notation::encode_board(&Board::new()),
+                notation::encode_point(pt(w - 1, h - 1))
+            )
+        );
+    }
+
+    #[test]
+    fn try_from_parts() {
+        let h = BOARD_HEIGHT.0;
+        let mut board = Board::new();
+        board.set_level(pt(2, 1), CoordLevel::Three);
+        board.set_level(pt(3, 2), CoordLevel::Capped);
+        let squares = notation::encode_board(&board);
+        let game = |p1: &[Point], p2: &[Point], player, phase| {
+            Game::try_from_parts(board, p1, p2, player, phase)
+        };
+        let (p1, p2) = ([pt(0, 0), pt(1, 1)], [pt(3, 3), pt(0, h - 1)]);
+
+        let g = game(&p1, &p2, Player::PlayerTwo, Phase::Move).unwrap();
+        assert_eq!(
+            notation::encode(g),
+            format!(
+                "{} a1,b2 d4,{} 2 m Mortal,Mortal",
+                squares,
+                notation::encode_point(pt(0, h - 1))
+            )
+        );
+        let g = game(&[], &[], Player::PlayerOne, Phase::Place).unwrap();
+        assert_eq!(
+            notation::encode(g),
+            format!("{} - - 1 p Mortal,Mortal", squares)
+        );
+        let g = game(&[pt(0, 0)], &[], Player::PlayerTwo, Phase::Place).unwrap();
+        assert_eq!(
+            notation::encode(g),
+            format!("{} a1 - 2 p Mortal,Mortal workers=1", squares)
+        );
+
+        let cases = [
+            (
+                game(&p1, &[pt(3, 3), pt(1, 1)], Player::PlayerOne, Phase::Move),
+                PositionError::Overlap(pt(1, 1)),
+            ),
+            (
+                game(&p1, &[pt(3, 3), pt(3, 2)], Player::PlayerOne, Phase::Move),
+                PositionError::OnDome(pt(3, 2)),
+            ),
+            (
+                game(&p1, &[pt(3, 3), pt(2, 1)], Player::PlayerOne, Phase::Move),
+                PositionError::TooHigh(pt(2, 1)),
+            ),
+            (
+                game(&[pt(3, 2)], &[], Player::PlayerTwo, Phase::Place),
+                PositionError::OnDome(pt(3, 2)),
+            ),
+            (
+                game(&p1, &[], Player::PlayerOne, Phase::Place),
+                PositionError::PlacementOrder,
+            ),
+            (
+                game(&p1, &p2, Player::PlayerTwo, Phase::Place),
+                PositionError::PlacementOrder,
+            ),
+            (
+                game(&p1, &p2, Player::PlayerOne, Phase::Build),
+                PositionError::UnsupportedPhase(Phase::Build),
+            ),
+        ];
+        for (result, err) in cases.iter() {
+            assert_eq!(result.as_ref().unwrap_err(), err);
+        }
+    }
+}
@@ -0,0 +1,1022 @@
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::santorini::{Board, Coord, CoordLevel, Point};
+
+/// A way to win the game by moving a worker from one space to another,
+/// checked against the board before the move.
+pub(crate) type VictoryCondition = fn(&Board, Point, Point) -> bool;
+
+/// Only moving up onto the third level wins, so a worker that was forced onto
+/// it has to step down and back up again.
+fn moved_up_to_third_level(board: &Board, from: Point, to: Point) -> bool {
+    board.level_at(to) == CoordLevel::Three && board.level_at(from) != CoordLevel::Three
+}
+
+fn moved_down_two_levels(board: &Board, from: Point, to: Point) -> bool {
+    i8::from(board.level_at(from)) - i8::from(board.level_at(to)) >= 2
+}
+
+/// A god power granted to a player for the whole game. Players without one
+/// are `Mortal` and follow the standard rules.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum God {
+    Mortal,
+    /// May move into an opponent worker's space, forcing their worker into
+    /// the space yours just vacated.
+    Apollo,
+    /// May move into an opponent worker's space if their worker can be
+    /// pushed one space straight back into an unoccupied, undomed space.
+    Minotaur,
+    /// May move one additional time, but not back to the space it started
+    /// on.
+    Artemis,
+    /// If the worker that moved did not move up or down, both workers may
+    /// keep moving across spaces on their own levels, and then either one
+    /// builds.
+    Hermes,
+    /// May build one additional time, but not on the same space.
+    Demeter,
+    /// May build one additional block, but not a dome, on top of the first
+    /// block.
+    Hephaestus,
+    /// If the unmoved worker is on the ground level, it may build up to
+    /// three times after the moved worker builds.
+    Poseidon,
+    /// May build a dome at any level.
+    Atlas,
+    /// If one of her workers moved up this turn, opponent workers may not
+    /// move up on their next turn.
+    Athena,
+    /// Also wins by moving down two or more levels.
+    Pan,
+    /// May build before moving, as long as the worker then doesn't move up.
+    Prometheus,
+
+    // The second wave of gods, listed in `God::EXPANSION`.
+    /// May move a neighboring opponent worker to the space directly on the
+    /// other side of his worker before moving, if that space is free.
+    Charon,
+    /// Each time a worker moves into a perimeter space, it may move again.
+    Triton,
+    /// May build a block under his own worker.
+    Zeus,
+}
+
+impl God {
+    /// Mortal and the gods from the base game, in the order menus list them.
+    pub const BASE: &'static [God] = &[
+        God::Mortal,
+        God::Apollo,
+        God::Minotaur,
+        God::Artemis,
+        God::Hermes,
+        God::Demeter,
+        God::Hephaestus,
+        God::Poseidon,
+        God::Atlas,
+        God::Athena,
+        God::Pan,
+        God::Prometheus,
+    ];
+
+    /// The second wave of gods. Gods relying on hidden information are left
+    /// out.
+    pub const EXPANSION: &'static [God] = &[God::Charon, God::Triton, God::Zeus];
+
+    /// Every available god, base game first.
+    pub fn all() -> impl Iterator<Item = God> {
+        God::BASE.iter().chain(God::EXPANSION).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            God::Mortal => "Mortal",
+            God::Apollo => "Apollo",
+            God::Minotaur => "Minotaur",
+            God::Artemis => "Artemis",
+            God::Hermes => "Hermes",
+            God::Demeter => "Demeter",
+            God::Hephaestus => "Hephaestus",
+            God::Poseidon => "Poseidon",
+            God::Atlas => "Atlas",
+            God::Athena => "Athena",
+            God::Pan => "Pan",
+            God::Prometheus => "Prometheus",
+            God::Charon => "Charon",
+            God::Triton => "Triton",
+            God::Zeus => "Zeus",
+        }
+    }
+
+    /// Every way a player with this god wins by moving.
+    pub(crate) fn victory_conditions(&self) -> &'static [VictoryCondition] {
+        match self {
+            God::Pan => &[moved_up_to_third_level, moved_down_two_levels],
+            _ => &[moved_up_to_third_level],
+        }
+    }
+
+    /// Whether workers of this god may ever move into an occupied space.
+    pub(crate) fn displaces(&self) -> bool {
+        matches!(self, God::Apollo | God::Minotaur)
+    }
+
+    /// Whether workers of this god may move again before building.
+    pub(crate) fn moves_again(&self) -> bool {
+        matches!(self, God::Artemis | God::Hermes | God::Triton)
+    }
+
+    /// Where an opponent worker on `to` is forced to when a worker of this god
+    /// moves there from `from`. The caller is responsible for checking the
+    /// destination is free.
+    pub(crate) fn displace(&self, from: Point, to: Point) -> Option<Point> {
+        match self {
+            God::Apollo => Some(from),
+            God::Minotaur => Point::new_(
+                Coord(2 * to.x().0 - from.x().0),
+                Coord(2 * to.y().0 - from.y().0),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for God {
+    type Err = String;
+
+    /// Look a god up by name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        God::all()
+            .find(|god| god.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown god: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod god_tests {
+    use super::*;
+
+    #[test]
+    fn displace() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        assert_eq!(God::Mortal.displace(pt(1, 1), pt(2, 2)), None);
+        assert_eq!(God::Apollo.displace(pt(1, 1), pt(2, 2)), Some(pt(1, 1)));
+        assert_eq!(God::Minotaur.displace(pt(1, 1), pt(2, 2)), Some(pt(3, 3)));
+        assert_eq!(God::Minotaur.displace(pt(2, 1), pt(2, 0)), None);
+        assert_eq!(God::Minotaur.displace(pt(1, 2), pt(0, 2)), None);
+    }
+
+    #[test]
+    fn victory_conditions() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(0, 0), CoordLevel::Two);
+        board.set_level(pt(1, 1), CoordLevel::Three);
+        board.set_level(pt(2, 2), CoordLevel::One);
+        let wins = |god: God, from, to| {
+            god.victory_conditions()
+                .iter()
+                .any(|condition| condition(&board, from, to))
+        };
+
+        assert!(wins(God::Mortal, pt(0, 0), pt(1, 1)));
+        assert!(!wins(God::Mortal, pt(1, 1), pt(0, 1)));
+        assert!(wins(God::Pan, pt(1, 1), pt(0, 1)));
+        assert!(wins(God::Pan, pt(0, 0), pt(0, 1)));
+        assert!(!wins(God::Pan, pt(1, 1), pt(0, 0)));
+        assert!(!wins(God::Pan, pt(0, 0), pt(2, 2)));
+    }
+
+    #[test]
+    fn registry() {
+        assert_eq!(God::all().count(), God::BASE.len() + God::EXPANSION.len());
+        for god in God::all() {
+            assert_eq!(god.name().parse(), Ok(god));
+        }
+        assert_eq!("zeus".parse(), Ok(God::Zeus));
+        assert!("Hecate".parse::<God>().is_err());
+    }
+}
+
+/// Positions shared by the tests of the gods' powers.
+#[cfg(test)]
+mod fixtures {
+    use crate::santorini::{Board, Game, God, Move, Player, Point, BOARD_HEIGHT, BOARD_WIDTH};
+
+    pub fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// The last column and row, which move with the board size.
+    pub fn edge() -> (i8, i8) {
+        (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1)
+    }
+
+    /// Player One, with the god given, to move their workers on (1, 1) and
+    /// the bottom left corner, against a mortal Player Two's in the bottom
+    /// and top right corners.
+    pub fn game(god: God, board: Board) -> Game<Move> {
+        let (right, bottom) = edge();
+        Game::from_parts(
+            board,
+            [pt(1, 1), pt(0, bottom)],
+            [pt(right, bottom), pt(right, 0)],
+            Player::PlayerOne,
+            [god, God::Mortal],
+        )
+    }
+
+    /// As `game`, with Player Two's first worker on (2, 2) instead, next to
+    /// Player One's.
+    pub fn beside(god: God, board: Board) -> Game<Move> {
+        let (right, bottom) = edge();
+        Game::from_parts(
+            board,
+            [pt(1, 1), pt(0, bottom)],
+            [pt(2, 2), pt(right, 0)],
+            Player::PlayerOne,
+            [god, God::Mortal],
+        )
+    }
+}
+
+#[cfg(test)]
+mod displacement_tests {
+    use super::fixtures::{beside, edge, pt};
+    use crate::santorini::{ActionResult, Board, CoordLevel, Game, God, Player, Point};
+
+    #[test]
+    fn mortal_cannot_displace() {
+        let g = beside(God::Mortal, Board::new());
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.can_move(pt(2, 2)), None);
+        assert!(pawn.actions().all(|action| action.to() != pt(2, 2)));
+    }
+
+    #[test]
+    fn apollo_swaps() {
+        let (right, _) = edge();
+        let g = beside(God::Apollo, Board::new());
+        let pawn = g.active_pawns()[0];
+        let action = pawn.can_move(pt(2, 2)).expect("Apollo should swap!");
+        assert_eq!(action.displaced(), Some(pt(1, 1)));
+        assert!(pawn.actions().any(|action| action.to() == pt(2, 2)));
+
+        let g = g.apply(action).unwrap();
+        assert_eq!(g.active_pawn().pos(), pt(2, 2));
+        let others: Vec<Point> = g.inactive_pawns().iter().map(|pawn| pawn.pos()).collect();
+        assert_eq!(others, vec![pt(1, 1), pt(right, 0)]);
+    }
+
+    #[test]
+    fn minotaur_pushes() {
+        let (right, bottom) = edge();
+        let g = beside(God::Minotaur, Board::new());
+        let action = g.active_pawns()[0].can_move(pt(2, 2)).unwrap();
+        assert_eq!(action.displaced(), Some(pt(3, 3)));
+        let g = g.apply(action).unwrap();
+        let others: Vec<Point> = g.inactive_pawns().iter().map(|pawn| pawn.pos()).collect();
+        assert_eq!(others, vec![pt(3, 3), pt(right, 0)]);
+
+        // Blocked by a dome
+        let mut board = Board::new();
+        board.set_level(pt(3, 3), CoordLevel::Capped);
+        let g = beside(God::Minotaur, board);
+        assert_eq!(g.active_pawns()[0].can_move(pt(2, 2)), None);
+
+        // Blocked by the edge of the board
+        let g = Game::from_parts(
+            Board::new(),
+            [pt(1, 1), pt(0, bottom)],
+            [pt(2, 2), pt(1, 0)],
+            Player::PlayerOne,
+            [God::Minotaur, God::Mortal],
+        );
+        assert_eq!(g.active_pawns()[0].can_move(pt(1, 0)), None);
+        assert!(g.active_pawns()[0].can_move(pt(2, 2)).is_some());
+    }
+
+    #[test]
+    fn displacement_avoids_stalemate() {
+        // Player One's worker in the corner is walled in by domes apart from
+        // the space held by Player Two's worker
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::Capped);
+        board.set_level(pt(0, 1), CoordLevel::Capped);
+        let parts = |god| {
+            Game::from_parts(
+                board,
+                [pt(0, 0), pt(0, bottom)],
+                [pt(1, 1), pt(right, 0)],
+                Player::PlayerOne,
+                [god, God::Mortal],
+            )
+        };
+
+        assert!(!parts(God::Mortal).active_pawns()[0].has_actions());
+        let g = parts(God::Apollo);
+        let pawn = g.active_pawns()[0];
+        assert!(pawn.has_actions());
+        let actions: Vec<Point> = pawn.actions().map(|action| action.to()).collect();
+        assert_eq!(actions, vec![pt(1, 1)]);
+    }
+
+    #[test]
+    fn swapping_needs_somewhere_to_build() {
+        // As above, but with Player Two's worker walled in too, so swapping
+        // leaves Apollo nowhere to build
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        for loc in [(1, 0), (0, 1), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2)].iter() {
+            board.set_level(pt(loc.0, loc.1), CoordLevel::Capped);
+        }
+        let g = Game::from_parts(
+            board,
+            [pt(0, 0), pt(0, bottom)],
+            [pt(1, 1), pt(right, 0)],
+            Player::PlayerOne,
+            [God::Apollo, God::Mortal],
+        );
+        let pawn = g.active_pawns()[0];
+        assert!(!pawn.has_actions());
+        assert_eq!(pawn.actions().count(), 0);
+        assert_eq!(pawn.can_move(pt(1, 1)), None);
+    }
+
+    #[test]
+    fn displaced_worker_must_climb_to_win() {
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::Two);
+        board.set_level(pt(2, 2), CoordLevel::Three);
+        board.set_level(pt(3, 3), CoordLevel::Three);
+        let g = Game::from_parts(
+            board,
+            [pt(1, 1), pt(0, bottom)],
+            [pt(2, 2), pt(right, 0)],
+            Player::PlayerOne,
+            [God::Minotaur, God::Mortal],
+        );
+
+        // Climbing from the second level wins, even when pushing a worker
+        let action = g.active_pawns()[0].can_move(pt(2, 2)).unwrap();
+        assert!(matches!(g.apply(action), ActionResult::Victory(_)));
+
+        // The pushed worker stands on the third level, but stepping across
+        // to (3, 3) doesn't win
+        let g = Game::from_parts(
+            board,
+            [pt(0, 0), pt(0, bottom)],
+            [pt(2, 2), pt(right, 0)],
+            Player::PlayerTwo,
+            [God::Mortal, God::Mortal],
+        );
+        let action = g.active_pawns()[0].can_move(pt(3, 3)).unwrap();
+        assert!(matches!(g.apply(action), ActionResult::Continue(_)));
+    }
+}
+
+#[cfg(test)]
+mod extra_move_tests {
+    use super::fixtures::{edge, game, pt};
+    use crate::santorini::{ActionResult, AnyGame, Board, CoordLevel, God, Point, Turn};
+
+    #[test]
+    fn mortal_builds_immediately() {
+        let g = game(God::Mortal, Board::new());
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap();
+        assert!(!g.can_move_again());
+        assert_eq!(g.active_pawn().moves().count(), 0);
+    }
+
+    #[test]
+    fn artemis_moves_twice() {
+        let g = game(God::Artemis, Board::new());
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap();
+        assert!(g.can_move_again());
+        assert_eq!(g.active_pawn().can_move(pt(1, 1)), None);
+        // Any free space around it but the one it came from, which leaves
+        // out Player Two's corner on the 4x4 board too
+        let free = Point::all()
+            .filter(|&loc| loc.distance(pt(2, 2)) == 1 && loc != pt(1, 1))
+            .filter(|&loc| g.worker_on(loc).is_none())
+            .count();
+        assert_eq!(g.active_pawn().moves().count(), free);
+
+        let g = g
+            .apply_move(g.active_pawn().can_move(pt(3, 2)).unwrap())
+            .unwrap();
+        assert_eq!(g.active_pawn().pos(), pt(3, 2));
+        assert!(!g.can_move_again());
+        assert!(g.active_pawn().can_build(pt(2, 2)).is_some());
+    }
+
+    #[test]
+    fn artemis_wins_on_second_move() {
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::One);
+        board.set_level(pt(2, 2), CoordLevel::Two);
+        board.set_level(pt(3, 2), CoordLevel::Three);
+        let g = game(God::Artemis, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap();
+        let action = g.active_pawn().can_move(pt(3, 2)).unwrap();
+        assert!(matches!(g.apply_move(action), ActionResult::Victory(_)));
+    }
+
+    #[test]
+    fn hermes_crosses_level() {
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        for x in 0..=right {
+            board.set_level(pt(x, 2), CoordLevel::One);
+        }
+        let g = game(God::Hermes, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 1)).unwrap())
+            .unwrap();
+        let moves: Vec<Point> = g.active_pawn().moves().map(|mv| mv.to()).collect();
+        // Any free space on the ground in the first two rows, including the
+        // one it started on
+        assert_eq!(moves.len(), 2 * right as usize);
+        assert!(moves.contains(&pt(1, 1)));
+        assert!(!moves.contains(&pt(2, 2)));
+
+        // The other worker may move along its own level too, once, anywhere
+        // below the raised row
+        let pawns = g.active_pawns();
+        let other = pawns
+            .iter()
+            .find(|pawn| pawn.pos() == pt(0, bottom))
+            .unwrap();
+        let below = (bottom - 2) as usize * (right + 1) as usize;
+        assert_eq!(other.moves().count(), below - 2);
+        assert_eq!(other.can_move(pt(1, 2)), None);
+        let g = g
+            .apply_move(other.can_move(pt(1, bottom)).unwrap())
+            .unwrap();
+        let pawns = g.active_pawns();
+        let other = pawns
+            .iter()
+            .find(|pawn| pawn.pos() == pt(1, bottom))
+            .unwrap();
+        assert_eq!(other.moves().count(), 0);
+        assert!(g.can_move_again());
+
+        // Either worker may then build, on any free space next to them
+        assert!(g.can_build(pt(1, 2)).is_some());
+        assert!(g.can_build(pt(2, bottom - 1)).is_some());
+        assert_eq!(g.can_build(pt(0, 0)), None);
+        let free = Point::all()
+            .filter(|&loc| loc.distance(pt(2, 1)) == 1 || loc.distance(pt(1, bottom)) == 1)
+            .filter(|&loc| g.worker_on(loc).is_none())
+            .count();
+        assert_eq!(g.build_actions().count(), free);
+
+        // Climbing forfeits the extra moves
+        let g = game(God::Hermes, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(1, 2)).unwrap())
+            .unwrap();
+        assert!(!g.can_move_again());
+    }
+
+    #[test]
+    fn turn_with_extra_move() {
+        let g: AnyGame = game(God::Artemis, Board::new()).into();
+        let turn = Turn::MoveBuild {
+            force: None,
+            pre_build: None,
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: Some(pt(3, 2)),
+            other_move: None,
+            build: Some(pt(2, 2)),
+            dome: false,
+            extra_builds: [None; 3],
+        };
+        let after = g.apply_turn(turn).unwrap();
+        assert!(after.pawn_locs().contains(&pt(3, 2)));
+        assert_eq!(after.board().level_at(pt(2, 2)), CoordLevel::One);
+
+        let g: AnyGame = game(God::Mortal, Board::new()).into();
+        assert_eq!(g.apply_turn(turn), None);
+    }
+}
+
+#[cfg(test)]
+mod extra_build_tests {
+    use super::fixtures::{self, edge, pt};
+    use crate::santorini::{AnyGame, Board, Build, CoordLevel, Game, God, Player, Point, Turn};
+
+    /// Move the worker on (1, 1) to (2, 2), ready to build.
+    fn game(god: God, board: Board) -> Game<Build> {
+        let g = fixtures::game(god, board);
+        g.apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn mortal_builds_once() {
+        let g = game(God::Mortal, Board::new());
+        assert!(!g.can_build_again());
+        assert!(!g.can_pass());
+    }
+
+    #[test]
+    fn demeter_builds_elsewhere() {
+        let g = game(God::Demeter, Board::new());
+        assert!(g.can_build_again());
+        let g = g.build_again(g.active_pawn().can_build(pt(3, 2)).unwrap());
+        assert_eq!(g.builds(), 1);
+        assert!(g.can_pass());
+        assert!(!g.can_build_again());
+        assert_eq!(g.active_pawn().can_build(pt(3, 2)), None);
+        let free = Point::all()
+            .filter(|&loc| loc.distance(pt(2, 2)) == 1 && loc != pt(3, 2))
+            .filter(|&loc| g.worker_on(loc).is_none())
+            .count();
+        assert_eq!(g.active_pawn().actions().count(), free);
+
+        let g = g
+            .apply(g.active_pawn().can_build(pt(1, 1)).unwrap())
+            .unwrap();
+        assert_eq!(g.board().level_at(pt(3, 2)), CoordLevel::One);
+        assert_eq!(g.board().level_at(pt(1, 1)), CoordLevel::One);
+    }
+
+    #[test]
+    fn declining_ends_the_turn() {
+        let g = game(God::Demeter, Board::new());
+        let built = g.build_again(g.active_pawn().can_build(pt(3, 2)).unwrap());
+        let passed = built.pass().unwrap();
+        let direct = g
+            .apply(g.active_pawn().can_build(pt(3, 2)).unwrap())
+            .unwrap();
+        assert_eq!(passed, direct);
+        assert_eq!(passed.player(), Player::PlayerTwo);
+    }
+
+    #[test]
+    fn hephaestus_builds_on_top() {
+        let g = game(God::Hephaestus, Board::new());
+        let g = g.build_again(g.active_pawn().can_build(pt(3, 2)).unwrap());
+        let builds: Vec<Point> = g.active_pawn().actions().map(|build| build.loc()).collect();
+        assert_eq!(builds, vec![pt(3, 2)]);
+
+        // The second block can't be a dome
+        let mut board = Board::new();
+        board.set_level(pt(3, 2), CoordLevel::Two);
+        let g = game(God::Hephaestus, board);
+        let g = g.build_again(g.active_pawn().can_build(pt(3, 2)).unwrap());
+        assert_eq!(g.active_pawn().actions().count(), 0);
+        assert!(g.can_pass());
+    }
+
+    #[test]
+    fn poseidon_builds_with_unmoved_worker() {
+        let (_, bottom) = edge();
+        let g = game(God::Poseidon, Board::new());
+        assert!(g.can_build_again());
+        let mut g = g.build_again(g.active_pawn().can_build(pt(2, 3)).unwrap());
+        assert_eq!(g.active_pawn().pos(), pt(0, bottom));
+        for _ in 0..2 {
+            assert!(g.can_build_again());
+            g = g.build_again(g.active_pawn().can_build(pt(0, bottom - 1)).unwrap());
+        }
+        assert!(!g.can_build_again());
+        let g = g
+            .apply(g.active_pawn().can_build(pt(1, bottom)).unwrap())
+            .unwrap();
+        assert_eq!(g.board().level_at(pt(0, bottom - 1)), CoordLevel::Two);
+        assert_eq!(g.board().level_at(pt(1, bottom)), CoordLevel::One);
+
+        // Only while the unmoved worker is on the ground
+        let mut board = Board::new();
+        board.set_level(pt(0, bottom), CoordLevel::One);
+        assert!(!game(God::Poseidon, board).can_build_again());
+    }
+
+    #[test]
+    fn turn_with_extra_builds() {
+        let (_, bottom) = edge();
+        let g: AnyGame = fixtures::game(God::Poseidon, Board::new()).into();
+        let turn = Turn::MoveBuild {
+            force: None,
+            pre_build: None,
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: None,
+            other_move: None,
+            build: Some(pt(2, 3)),
+            dome: false,
+            extra_builds: [Some(pt(0, bottom - 1)), Some(pt(0, bottom - 1)), None],
+        };
+        let after = g.apply_turn(turn).unwrap();
+        assert_eq!(after.board().level_at(pt(0, bottom - 1)), CoordLevel::Two);
+
+        // Poseidon's additional builds have to be next to the unmoved worker
+        let turn = Turn::MoveBuild {
+            force: None,
+            pre_build: None,
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: None,
+            other_move: None,
+            build: Some(pt(2, 3)),
+            dome: false,
+            extra_builds: [Some(pt(3, 2)), None, None],
+        };
+        assert_eq!(g.apply_turn(turn), None);
+    }
+}
+
+#[cfg(test)]
+mod dome_tests {
+    use super::fixtures::{self, pt};
+    use crate::santorini::{AnyGame, Board, CoordLevel, God, Point, Turn};
+
+    fn game(god: God, board: Board) -> AnyGame {
+        fixtures::game(god, board).into()
+    }
+
+    fn turn(build: Point, dome: bool) -> Turn {
+        Turn::MoveBuild {
+            force: None,
+            pre_build: None,
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: None,
+            other_move: None,
+            build: Some(build),
+            dome,
+            extra_builds: [None; 3],
+        }
+    }
+
+    #[test]
+    fn atlas_domes_any_level() {
+        let mut board = Board::new();
+        board.set_level(pt(3, 2), CoordLevel::Three);
+        let g = fixtures::game(God::Atlas, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap())
+            .unwrap();
+        let pawn = g.active_pawn();
+        let action = pawn.can_build_dome(pt(2, 3)).unwrap();
+        assert!(action.dome());
+        assert!(!pawn.can_build(pt(2, 3)).unwrap().dome());
+        // A dome on the third level is just an ordinary build
+        assert_eq!(pawn.can_build_dome(pt(3, 2)), None);
+        let free = pawn
+            .neighbors()
+            .filter(|&loc| g.worker_on(loc).is_none())
+            .count();
+        assert_eq!(pawn.actions().count(), free + free - 1);
+        assert_eq!(
+            pawn.actions().filter(|build| build.dome()).count(),
+            free - 1
+        );
+
+        let g = g.apply(action).unwrap();
+        assert_eq!(g.board().level_at(pt(2, 3)), CoordLevel::Capped);
+    }
+
+    #[test]
+    fn only_atlas_domes() {
+        let g = game(God::Mortal, Board::new());
+        assert_eq!(g.apply_turn(turn(pt(2, 3), true)), None);
+        assert!(g.apply_turn(turn(pt(2, 3), false)).is_some());
+
+        let g = game(God::Atlas, Board::new());
+        let after = g.apply_turn(turn(pt(2, 3), true)).unwrap();
+        assert_eq!(after.board().level_at(pt(2, 3)), CoordLevel::Capped);
+    }
+}
+
+#[cfg(test)]
+mod restriction_tests {
+    use super::fixtures::{edge, game, pt};
+    use crate::santorini::{ActionResult, Board, CoordLevel, Game, God, Move, Player, Point};
+
+    /// Player One moves from (1, 1) to `to` and builds where it came from,
+    /// leaving Player Two to move.
+    fn play_on(board: Board, god: God, to: Point) -> ActionResult<Move> {
+        let g = game(god, board);
+        let g = g.apply(g.active_pawns()[0].can_move(to).unwrap()).unwrap();
+        g.apply(g.active_pawn().can_build(pt(1, 1)).unwrap())
+    }
+
+    /// As `play_on`, with (2, 2) raised, along with the spaces above and
+    /// diagonally up from Player Two's worker in the bottom right corner.
+    fn play(god: God, to: Point) -> Game<Move> {
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        board.set_level(pt(2, 2), CoordLevel::One);
+        board.set_level(pt(right - 1, bottom - 1), CoordLevel::One);
+        board.set_level(pt(right, bottom - 1), CoordLevel::One);
+        play_on(board, god, to).unwrap()
+    }
+
+    #[test]
+    fn athena_blocks_climbing() {
+        let (right, bottom) = edge();
+        let g = play(God::Athena, pt(2, 2));
+        assert!(g.climbing_blocked());
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.pos(), pt(right, bottom));
+        assert_eq!(pawn.can_move(pt(right - 1, bottom - 1)), None);
+        assert!(pawn.can_move(pt(right - 1, bottom)).is_some());
+        let moves: Vec<Point> = pawn.actions().map(|mv| mv.to()).collect();
+        assert_eq!(moves, vec![pt(right - 1, bottom)]);
+    }
+
+    #[test]
+    fn athena_only_blocks_after_climbing() {
+        let (right, bottom) = edge();
+        let g = play(God::Athena, pt(2, 1));
+        assert!(!g.climbing_blocked());
+        let pawn = g.active_pawns()[0];
+        assert!(pawn.can_move(pt(right - 1, bottom - 1)).is_some());
+
+        let g = play(God::Mortal, pt(2, 2));
+        assert!(!g.climbing_blocked());
+    }
+
+    #[test]
+    fn blocked_climbing_can_stalemate() {
+        // Every space Player Two's workers could move to is up a level
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        for &(x, y) in &[
+            (2, 2),
+            (right - 1, bottom - 1),
+            (right, bottom - 1),
+            (right - 1, bottom),
+            (right - 1, 0),
+            (right - 1, 1),
+            (right, 1),
+        ] {
+            board.set_level(pt(x, y), CoordLevel::One);
+        }
+        match play_on(board, God::Athena, pt(2, 2)) {
+            ActionResult::Victory(g) => assert_eq!(g.player(), Player::PlayerOne),
+            ActionResult::Continue(_) => panic!("Stalemate not detected!"),
+        }
+        assert!(matches!(
+            play_on(board, God::Mortal, pt(2, 2)),
+            ActionResult::Continue(_)
+        ));
+    }
+
+    #[test]
+    fn pan_wins_by_moving_down() {
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::Two);
+        for &god in &[God::Pan, God::Mortal] {
+            let g = game(god, board);
+            let result = g.apply(g.active_pawns()[0].can_move(pt(2, 2)).unwrap());
+            assert_eq!(matches!(result, ActionResult::Victory(_)), god == God::Pan);
+        }
+    }
+}
+
+#[cfg(test)]
+mod pre_build_tests {
+    use super::fixtures::{game, pt};
+    use crate::santorini::{AnyGame, Board, CoordLevel, God, Turn};
+
+    #[test]
+    fn prometheus_builds_first() {
+        let mut board = Board::new();
+        board.set_level(pt(2, 2), CoordLevel::One);
+        let g = game(God::Prometheus, board);
+        let pawn = g.active_pawns()[0];
+        let action = pawn.can_build_first(pt(1, 2)).unwrap();
+        assert_eq!(pawn.pre_builds().count(), 8);
+
+        let g = g.apply_pre_build(action);
+        assert_eq!(g.board().level_at(pt(1, 2)), CoordLevel::One);
+        assert_eq!(g.acted_before_moving(), Some(pt(1, 1)));
+        assert!(g.climbing_blocked());
+
+        // Only the worker that built may move, and not up
+        let pawns = g.active_pawns();
+        assert!(pawns[1].actions().next().is_none());
+        assert_eq!(pawns[0].can_move(pt(2, 2)), None);
+        assert_eq!(pawns[0].can_move(pt(1, 2)), None);
+        assert!(pawns[0].can_move(pt(2, 1)).is_some());
+        assert_eq!(pawns[0].can_build_first(pt(2, 1)), None);
+
+        let g = g.apply(pawns[0].can_move(pt(2, 1)).unwrap()).unwrap();
+        let g = g
+            .apply(g.active_pawn().can_build(pt(1, 1)).unwrap())
+            .unwrap();
+        assert!(!g.climbing_blocked());
+        assert_eq!(g.acted_before_moving(), None);
+    }
+
+    #[test]
+    fn pre_build_must_leave_a_move() {
+        // The worker's only level neighbor is (0, 0)
+        let mut board = Board::new();
+        for &(x, y) in &[(0, 1), (0, 2), (1, 0), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            board.set_level(pt(x, y), CoordLevel::One);
+        }
+        let g = game(God::Prometheus, board);
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.can_build_first(pt(0, 0)), None);
+        assert!(pawn.can_build_first(pt(2, 2)).is_some());
+    }
+
+    #[test]
+    fn mortals_cannot_build_first() {
+        let g = game(God::Mortal, Board::new());
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.can_build_first(pt(1, 2)), None);
+        assert_eq!(pawn.pre_builds().count(), 0);
+    }
+
+    #[test]
+    fn turn_with_pre_build() {
+        let turn = Turn::MoveBuild {
+            force: None,
+            pre_build: Some(pt(1, 2)),
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: None,
+            other_move: None,
+            build: Some(pt(1, 1)),
+            dome: false,
+            extra_builds: [None; 3],
+        };
+        let g: AnyGame = game(God::Prometheus, Board::new()).into();
+        let after = g.apply_turn(turn).unwrap();
+        assert_eq!(after.board().level_at(pt(1, 2)), CoordLevel::One);
+        assert_eq!(after.board().level_at(pt(1, 1)), CoordLevel::One);
+        assert!(after.pawn_locs().contains(&pt(2, 2)));
+
+        let g: AnyGame = game(God::Mortal, Board::new()).into();
+        assert_eq!(g.apply_turn(turn), None);
+    }
+}
+
+#[cfg(test)]
+mod expansion_tests {
+    use super::fixtures::{beside, edge, game, pt};
+    use crate::santorini::{
+        ActionResult, AnyGame, Board, CoordLevel, Game, God, Player, Point, Turn,
+    };
+
+    #[test]
+    fn charon_forces() {
+        let (right, _) = edge();
+        let g = beside(God::Charon, Board::new());
+        let pawn = g.active_pawns()[0];
+        let action = pawn.can_force(pt(2, 2)).unwrap();
+        assert_eq!(action.to(), pt(0, 0));
+        assert_eq!(pawn.forces().count(), 1);
+        assert_eq!(pawn.can_force(pt(right, 0)), None);
+
+        let g = g.apply_force(action);
+        let others: Vec<Point> = g.inactive_pawns().iter().map(|pawn| pawn.pos()).collect();
+        assert_eq!(others, vec![pt(0, 0), pt(right, 0)]);
+        assert_eq!(g.acted_before_moving(), Some(pt(1, 1)));
+        assert!(!g.climbing_blocked());
+
+        // Only the worker that forced may move, and only once
+        let pawns = g.active_pawns();
+        assert!(pawns[1].actions().next().is_none());
+        assert!(pawns[0].can_move(pt(2, 2)).is_some());
+        assert_eq!(pawns[0].forces().count(), 0);
+    }
+
+    #[test]
+    fn charon_needs_a_free_space() {
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        board.set_level(pt(0, 0), CoordLevel::Capped);
+        let g = beside(God::Charon, board);
+        assert_eq!(g.active_pawns()[0].can_force(pt(2, 2)), None);
+
+        // The space on the other side is off the board
+        let g = Game::from_parts(
+            Board::new(),
+            [pt(0, 2), pt(0, bottom)],
+            [pt(1, 2), pt(right, 0)],
+            Player::PlayerOne,
+            [God::Charon, God::Mortal],
+        );
+        assert_eq!(g.active_pawns()[0].can_force(pt(1, 2)), None);
+
+        let g = beside(God::Mortal, Board::new());
+        assert_eq!(g.active_pawns()[0].can_force(pt(2, 2)), None);
+    }
+
+    #[test]
+    fn turn_with_force() {
+        let turn = Turn::MoveBuild {
+            force: Some(pt(2, 2)),
+            pre_build: None,
+            from: pt(1, 1),
+            to: pt(2, 2),
+            extra_move: None,
+            other_move: None,
+            build: Some(pt(1, 1)),
+            dome: false,
+            extra_builds: [None; 3],
+        };
+        let g: AnyGame = beside(God::Charon, Board::new()).into();
+        let after = g.apply_turn(turn).unwrap();
+        assert!(after.pawn_locs().contains(&pt(0, 0)));
+        assert!(after.pawn_locs().contains(&pt(2, 2)));
+
+        let g: AnyGame = beside(God::Mortal, Board::new()).into();
+        assert_eq!(g.apply_turn(turn), None);
+    }
+
+    #[test]
+    fn triton_moves_along_the_perimeter() {
+        let (right, bottom) = edge();
+        let mut board = Board::new();
+        board.set_level(pt(2, 0), CoordLevel::One);
+        board.set_level(pt(2, 1), CoordLevel::Two);
+        let g = game(God::Triton, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(1, 0)).unwrap())
+            .unwrap();
+        assert!(g.can_move_again());
+        let moves: Vec<Point> = g.active_pawn().moves().map(|mv| mv.to()).collect();
+        // Every free space next to the perimeter, which leaves out the
+        // center of the 5x5 board, and the four in the middle of the 6x6 one
+        let near_perimeter = |loc: Point| {
+            let (x, y) = (loc.x().0, loc.y().0);
+            x <= 1 || y <= 1 || x >= right - 1 || y >= bottom - 1
+        };
+        let free = Point::all()
+            .filter(|&loc| near_perimeter(loc) && g.worker_on(loc).is_none())
+            .count();
+        assert_eq!(moves.len(), free);
+        assert!(moves.contains(&pt(2, 1)));
+        assert!(moves.contains(&pt(right, 2)));
+        assert!(moves.contains(&pt(1, 1)));
+        assert!(moves.iter().all(|&loc| near_perimeter(loc)));
+
+        let g = beside(God::Triton, Board::new());
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(2, 1)).unwrap())
+            .unwrap();
+        assert!(!g.can_move_again());
+    }
+
+    #[test]
+    fn zeus_builds_under_himself() {
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::One);
+        board.set_level(pt(1, 2), CoordLevel::Two);
+        board.set_level(pt(0, 1), CoordLevel::Three);
+        for &(god, allowed) in &[(God::Zeus, true), (God::Mortal, false)] {
+            let g = beside(god, board);
+            let g = g
+                .apply(g.active_pawns()[0].can_move(pt(1, 2)).unwrap())
+                .unwrap();
+            let pawn = g.active_pawn();
+            assert_eq!(pawn.can_build(pt(1, 2)).is_some(), allowed);
+            assert_eq!(pawn.actions().any(|build| build.loc() == pt(1, 2)), allowed);
+        }
+
+        // Being raised onto the third level doesn't win
+        let g = beside(God::Zeus, board);
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(1, 2)).unwrap())
+            .unwrap();
+        let g = match g.apply(g.active_pawn().can_build(pt(1, 2)).unwrap()) {
+            ActionResult::Continue(g) => g,
+            ActionResult::Victory(_) => panic!("Building under a worker won!"),
+        };
+        assert_eq!(g.board().level_at(pt(1, 2)), CoordLevel::Three);
+        assert_eq!(g.active_pawns()[0].pos(), pt(2, 2));
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(3, 3)).unwrap())
+            .unwrap();
+        let g = g
+            .apply(g.active_pawn().can_build(pt(2, 3)).unwrap())
+            .unwrap();
+
+        // Nor does moving across the third level
+        let pawn = g.active_pawns()[0];
+        assert_eq!(pawn.pos(), pt(1, 2));
+        let g = g.apply(pawn.can_move(pt(0, 1)).unwrap());
+        assert!(matches!(g, ActionResult::Continue(_)));
+    }
+}
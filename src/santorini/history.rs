@@ -0,0 +1,298 @@
+use crate::santorini::{AnyGame, GameRecord, Turn};
+
+/// How many turns are played between stored snapshots. Reconstructing any
+/// state requires replaying at most this many turns.
+const SNAPSHOT_INTERVAL: usize = 16;
+
+/// The sequence of turns played in a game, with random access to the state
+/// after any number of plies and the ability to take turns back.
+///
+/// A ply is a single turn by one player, including worker placement. Ply 0 is
+/// the initial state and ply `len()` is the current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameHistory {
+    record: GameRecord,
+    // snapshots[i] is the state after i * SNAPSHOT_INTERVAL plies
+    snapshots: Vec<AnyGame>,
+    // Turns taken back by `undo`, most recently undone last
+    undone: Vec<Turn>,
+}
+
+impl GameHistory {
+    pub fn new(initial: AnyGame) -> Self {
+        GameHistory {
+            record: GameRecord::new(initial),
+            snapshots: vec![initial],
+            undone: Vec::new(),
+        }
+    }
+
+    /// The number of plies played.
+    pub fn len(&self) -> usize {
+        self.record.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.record.is_empty()
+    }
+
+    pub fn initial(&self) -> AnyGame {
+        self.record.initial()
+    }
+
+    pub fn current(&self) -> AnyGame {
+        self.record.current()
+    }
+
+    pub fn turns(&self) -> &[Turn] {
+        self.record.turns()
+    }
+
+    /// The transcript of the turns played, without any undone turns.
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// Play a turn from the current state. Returns the new state, or None if
+    /// the turn is not legal, in which case the history is unchanged.
+    ///
+    /// Playing a turn discards any turns that could be redone.
+    pub fn push(&mut self, turn: Turn) -> Option<AnyGame> {
+        let next = self.record.push(turn)?;
+        self.undone.clear();
+        self.snapshot(next);
+        Some(next)
+    }
+
+    fn snapshot(&mut self, next: AnyGame) {
+        if self.record.len() == self.snapshots.len() * SNAPSHOT_INTERVAL {
+            self.snapshots.push(next);
+        }
+    }
+
+    /// Take back the last turn played. Returns the state it was played from,
+    /// or None if no turns have been played.
+    pub fn undo(&mut self) -> Option<AnyGame> {
+        let ply = self.record.len().checked_sub(1)?;
+        let previous = self.state_at(ply)?;
+        let turn = self.record.pop_to(previous)?;
+        self.snapshots.truncate(ply / SNAPSHOT_INTERVAL + 1);
+        self.undone.push(turn);
+        Some(previous)
+    }
+
+    /// Play the last turn taken back again. Returns the new state, or None if
+    /// there is nothing to redo.
+    pub fn redo(&mut self) -> Option<AnyGame> {
+        let turn = self.undone.pop()?;
+        let next = self
+            .record
+            .push(turn)
+            .expect("History contains an illegal turn!");
+        self.snapshot(next);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.record.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// The state after the given number of plies, or None if fewer plies
+    /// have been played.
+    pub fn state_at(&self, ply: usize) -> Option<AnyGame> {
+        if ply > self.len() {
+            return None;
+        }
+        if ply == self.len() {
+            return Some(self.current());
+        }
+
+        let snapshot = ply / SNAPSHOT_INTERVAL;
+        let start = snapshot * SNAPSHOT_INTERVAL;
+        let state =
+            self.turns()[start..ply]
+                .iter()
+                .fold(self.snapshots[snapshot], |state, turn| {
+                    state
+                        .apply_turn(*turn)
+                        .expect("History contains an illegal turn!")
+                });
+        Some(state)
+    }
+
+    /// Iterate over `(ply, turn, state)` for every turn played, where `state`
+    /// is the state the turn was played from.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Turn, AnyGame)> + '_ {
+        self.record.iter()
+    }
+}
+
+impl From<GameRecord> for GameHistory {
+    fn from(record: GameRecord) -> Self {
+        let mut history = GameHistory::new(record.initial());
+        for turn in record.turns() {
+            history
+                .push(*turn)
+                .expect("Record contains an illegal turn!");
+        }
+        history
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use crate::santorini::{self, CoordLevel, Point, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// Walk one worker per player back and forth along the edge rows,
+    /// building on the next row in, ahead of the worker where it can. The
+    /// other workers wait at the far end of the edge rows.
+    fn long_history(turns: usize) -> GameHistory {
+        let (width, height) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let mut history = GameHistory::new(santorini::new_game().into());
+        history
+            .push(Turn::Place([pt(0, 0), pt(width - 1, 0)].into()))
+            .unwrap();
+        history
+            .push(Turn::Place(
+                [pt(0, height - 1), pt(width - 1, height - 1)].into(),
+            ))
+            .unwrap();
+
+        let mut walkers = [(0, 1, 0, 1), (0, 1, height - 1, height - 2)];
+        for idx in 0..turns {
+            let (x, dir, row, build_row) = &mut walkers[idx % 2];
+            if *x + *dir < 0 || *x + *dir > width - 2 {
+                *dir = -*dir;
+            }
+            let from = pt(*x, *row);
+            *x += *dir;
+            let to = pt(*x, *row);
+
+            let board = history.current().board();
+            let build = (*x - 1..=*x + 1)
+                .filter_map(|bx| Point::new_(bx.into(), (*build_row).into()))
+                .filter(|loc| board.level_at(*loc) != CoordLevel::Capped)
+                .min_by_key(|loc| (board.level_at(*loc), -*dir * *loc.x()))
+                .expect("Nowhere to build");
+            history
+                .push(Turn::MoveBuild {
+                    force: None,
+                    pre_build: None,
+                    from,
+                    to,
+                    extra_move: None,
+                    other_move: None,
+                    build: Some(build),
+                    dome: false,
+                    extra_builds: [None; 3],
+                })
+                .expect("Illegal turn");
+        }
+
+        history
+    }
+
+    #[test]
+    fn state_at() {
+        let history = long_history(6);
+        assert_eq!(history.len(), 8);
+        assert_eq!(history.state_at(0), Some(history.initial()));
+        assert_eq!(history.state_at(8), Some(history.current()));
+        assert_eq!(history.state_at(9), None);
+
+        let mut state = history.initial();
+        for ply in 0..history.len() {
+            assert_eq!(history.state_at(ply), Some(state));
+            state = state.apply_turn(history.turns()[ply]).unwrap();
+        }
+    }
+
+    #[test]
+    fn snapshots() {
+        let history = long_history(32);
+        assert_eq!(history.len(), 34);
+        assert_eq!(history.snapshots.len(), 3);
+
+        let states: Vec<_> = history.iter().map(|(_, _, state)| state).collect();
+        for (ply, state) in states.iter().enumerate() {
+            assert_eq!(history.state_at(ply), Some(*state));
+        }
+    }
+
+    #[test]
+    fn illegal_turns() {
+        let mut history = long_history(0);
+        let before = history.clone();
+        assert_eq!(history.push(Turn::Place([pt(2, 2), pt(3, 3)].into())), None);
+        assert_eq!(
+            history.push(Turn::MoveBuild {
+                force: None,
+                pre_build: None,
+                from: pt(0, 0),
+                to: pt(2, 2),
+                extra_move: None,
+                other_move: None,
+                build: Some(pt(0, 0)),
+                dome: false,
+                extra_builds: [None; 3],
+            }),
+            None
+        );
+        assert_eq!(history, before);
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = long_history(16);
+        let full = history.clone();
+        assert!(!history.can_redo());
+
+        for ply in (0..history.len()).rev() {
+            assert_eq!(history.undo(), full.state_at(ply));
+            assert_eq!(history.current(), full.state_at(ply).unwrap());
+        }
+        assert!(matches!(history.current(), AnyGame::PlaceOne(_)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.snapshots.len(), 1);
+
+        while history.redo().is_some() {}
+        assert_eq!(history.turns(), full.turns());
+        assert_eq!(history.current(), full.current());
+        assert_eq!(history.snapshots, full.snapshots);
+
+        // Playing a turn discards the undone ones, even the same turn
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert!(history.can_redo());
+        let turn = full.turns()[full.len() - 2];
+        history.push(turn).unwrap();
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn from_record() {
+        let history = long_history(20);
+        let rebuilt = GameHistory::from(history.record().clone());
+        assert_eq!(rebuilt, history);
+    }
+
+    #[test]
+    fn iterate() {
+        let history = long_history(2);
+        let entries: Vec<_> = history.iter().collect();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0], (0, history.turns()[0], history.initial()));
+        assert!(matches!(entries[2].2, AnyGame::Move(_)));
+        assert_eq!(entries[3].0, 3);
+    }
+}
@@ -0,0 +1,259 @@
+use std::iter;
+
+use crate::santorini::{
+    ActionResult, Build, BuildAction, ExtraMoveAction, Game, Move, MoveAction, PreMoveAction,
+};
+
+/// The actions making up a complete turn: an optional power used before
+/// moving, the move, an optional extra move, an optional move by Hermes' other
+/// worker and the build, which is omitted when a move wins, followed by an
+/// optional extra build.
+///
+/// Only one extra build is considered, which covers Demeter and Hephaestus
+/// but not all of Poseidon's options, and Hermes' other worker only moves
+/// after the first one is done.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TurnActions {
+    pub pre_move: Option<PreMoveAction>,
+    pub mv: MoveAction,
+    pub extra_move: Option<ExtraMoveAction>,
+    pub other_move: Option<ExtraMoveAction>,
+    pub build: Option<BuildAction>,
+    pub extra_build: Option<BuildAction>,
+}
+
+/// The ways to finish a turn by building, with at most one extra build.
+fn builds(moves: TurnActions, game: Game<Build>) -> Vec<(TurnActions, ActionResult<Move>)> {
+    let turn = |build, extra_build| TurnActions {
+        build: Some(build),
+        extra_build,
+        ..moves
+    };
+
+    let mut turns = vec![];
+    for build in game.build_actions() {
+        turns.push((turn(build, None), game.apply(build)));
+        if game.can_build_again() {
+            let game = game.build_again(build);
+            for second in game.active_pawn().actions() {
+                turns.push((turn(build, Some(second)), game.apply(second)));
+            }
+        }
+    }
+    turns
+}
+
+/// The ways to finish a turn once the workers are done moving: building,
+/// unless the last move won.
+fn finish(
+    moves: TurnActions,
+    result: ActionResult<Build>,
+) -> Vec<(TurnActions, ActionResult<Move>)> {
+    match result {
+        ActionResult::Victory(game) => vec![(moves, ActionResult::Victory(game))],
+        ActionResult::Continue(game) => builds(moves, game),
+    }
+}
+
+/// The ways to finish a turn after moving: building straight away, or after
+/// any extra move the god allows, each followed by any move of Hermes' other
+/// worker.
+fn after_move(
+    pre_move: Option<PreMoveAction>,
+    mv: MoveAction,
+    game: Game<Build>,
+) -> Vec<(TurnActions, ActionResult<Move>)> {
+    let moves = TurnActions {
+        pre_move,
+        mv,
+        extra_move: None,
+        other_move: None,
+        build: None,
+        extra_build: None,
+    };
+
+    let mut turns = vec![];
+    let extra_moves = iter::once(None).chain(game.active_pawn().moves().map(Some));
+    for extra_move in extra_moves {
+        let moves = TurnActions {
+            extra_move,
+            ..moves
+        };
+        let result = match extra_move {
+            Some(action) => game.apply_move(action),
+            None => ActionResult::Continue(game),
+        };
+        turns.extend(finish(moves, result));
+
+        if let ActionResult::Continue(game) = result {
+            let active = game.active_pawn().pos();
+            let pawns = game.active_pawns();
+            let others = pawns.iter().filter(|pawn| pawn.pos() != active);
+            for other_move in others.flat_map(|pawn| pawn.moves()) {
+                let moves = TurnActions {
+                    other_move: Some(other_move),
+                    ..moves
+                };
+                turns.extend(finish(moves, game.apply_move(other_move)));
+            }
+        }
+    }
+    turns
+}
+
+impl Game<Move> {
+    /// Every complete turn the player to move can take, along with the state
+    /// it leads to.
+    pub fn legal_turns(&self) -> impl Iterator<Item = (TurnActions, ActionResult<Move>)> {
+        let mut starts = vec![(None, *self)];
+        for pawn in self.active_pawns().iter() {
+            for pre_move in pawn.pre_moves() {
+                starts.push((Some(pre_move), self.apply_pre_move(pre_move)));
+            }
+        }
+
+        starts
+            .into_iter()
+            .flat_map(|(pre_move, game)| {
+                game.active_pawns()
+                    .iter()
+                    .flat_map(|pawn| pawn.actions())
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(move |mv| (pre_move, game, mv))
+            })
+            .flat_map(|(pre_move, game, mv)| match game.apply(mv) {
+                ActionResult::Victory(game) => vec![(
+                    TurnActions {
+                        pre_move,
+                        mv,
+                        extra_move: None,
+                        other_move: None,
+                        build: None,
+                        extra_build: None,
+                    },
+                    ActionResult::Victory(game),
+                )],
+                ActionResult::Continue(game) => after_move(pre_move, mv, game),
+            })
+    }
+}
+
+/// Count the sequences of `depth` complete turns that can be played from a
+/// game, stopping early at any that are won. This is the usual check of a move
+/// generator against known values, though it only counts the turns
+/// `legal_turns` considers.
+pub fn perft(game: Game<Move>, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    game.legal_turns()
+        .map(|(_, result)| match result {
+            ActionResult::Continue(game) => perft(game, depth - 1),
+            ActionResult::Victory(_) if depth == 1 => 1,
+            ActionResult::Victory(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod legal_tests {
+    use crate::santorini::{
+        notation, perft, ActionResult, AnyGame, Board, CoordLevel, Game, Move, Point,
+    };
+
+    fn parse(game: &str) -> Game<Move> {
+        match notation::parse(game).unwrap() {
+            AnyGame::Move(game) => game,
+            _ => panic!("Not moving!"),
+        }
+    }
+
+    /// Parse a game on an empty board from the rest of its notation.
+    fn on_empty_board(rest: &str) -> Game<Move> {
+        parse(&format!(
+            "{} {}",
+            notation::encode_board(&Board::new()),
+            rest
+        ))
+    }
+
+    #[test]
+    fn one_turn_per_move_and_build() {
+        let game = on_empty_board("b2,d4 b4,d2 1 m Mortal,Mortal");
+        let mut expected = 0;
+        for pawn in game.active_pawns().iter() {
+            for mv in pawn.actions() {
+                expected += game.apply(mv).unwrap().active_pawn().actions().count();
+            }
+        }
+
+        let turns: Vec<_> = game.legal_turns().collect();
+        assert_eq!(turns.len(), expected);
+        for (turn, result) in turns {
+            assert_eq!(turn.pre_move, None);
+            assert_eq!(turn.extra_move, None);
+            assert_eq!(turn.extra_build, None);
+            let build = game.apply(turn.mv).unwrap();
+            assert_eq!(build.apply(turn.build.unwrap()), result);
+        }
+    }
+
+    #[test]
+    fn winning_moves_skip_the_build() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::Two);
+        board.set_level(pt(2, 1), CoordLevel::Three);
+        let game = parse(&format!(
+            "{} b2,d4 b4,d2 1 m Mortal,Mortal",
+            notation::encode_board(&board)
+        ));
+        let wins: Vec<_> = game
+            .legal_turns()
+            .filter(|(_, result)| matches!(result, ActionResult::Victory(_)))
+            .collect();
+        assert_eq!(wins.len(), 1);
+        assert_eq!(wins[0].0.build, None);
+        assert_eq!(notation::encode_point(wins[0].0.mv.to()), "c2");
+    }
+
+    #[test]
+    fn extra_builds() {
+        let game = on_empty_board("b2,d4 b4,d2 1 m Demeter,Mortal");
+        let turns: Vec<_> = game.legal_turns().collect();
+        assert!(turns.iter().any(|(turn, _)| turn.extra_build.is_some()));
+        for (turn, _) in turns {
+            if let Some(extra_build) = turn.extra_build {
+                // Demeter may not build twice on the same space
+                assert_ne!(extra_build.loc(), turn.build.unwrap().loc());
+            }
+        }
+    }
+
+    #[test]
+    // The known values were counted on the standard board
+    #[cfg_attr(any(board_size = "4", board_size = "6"), ignore)]
+    fn perft_known_values() {
+        let board = "00000/00000/00000/00000/00000 b2,d4 b4,d2 1 m";
+        let cases = [
+            (format!("{} Mortal,Mortal", board), 80, 6176),
+            (format!("{} Apollo,Minotaur", board), 80, 6372),
+            (
+                "01230/02300/00000/00000/00000 b2,d4 b4,d2 1 m Mortal,Mortal".to_string(),
+                74,
+                4480,
+            ),
+        ];
+        for (game, one, two) in cases.iter() {
+            let game = parse(game);
+            assert_eq!(perft(game, 0), 1);
+            assert_eq!(perft(game, 1), *one);
+            assert_eq!(perft(game, 2), *two);
+        }
+
+        let game = parse(&format!("{} Demeter,Prometheus", board));
+        assert_eq!(perft(game, 1), 428);
+    }
+}
@@ -0,0 +1,831 @@
+//! A compact text notation for games in any state.
+//!
+//! A game is written as
+//!
+//! ```text
+//! <heights> <p1 workers> <p2 workers> [<p3 workers>] <player> <phase> <gods> [details]
+//! ```
+//!
+//! The heights are given row by row separated by `/`, with 4 for a dome.
+//! Workers are written as `b2,d4`, or `-` if they have not been placed yet or
+//! their player has been eliminated, and a third player's workers are only
+//! written in three player games. Every player has the same number of
+//! workers, from one to three. The player is the one to act, or the winner
+//! once the game is over, and the phase is one of `p` (placing workers), `m`
+//! (moving), `b` (building) and `v` (victory). The gods are named in turn
+//! order, separated by `,`.
+//!
+//! The details describe the rest of the turn in progress:
+//!
+//! - When placing, `workers=<n>` if players have other than two workers each.
+//! - When moving, `nc` if the player may not move up and `pre=<square>` for the
+//!   worker that used its god's power before moving.
+//! - When building, `at=<square>` for the worker to build with and
+//!   `from=<square>` for where the moving worker started the turn, followed by
+//!   `moves=<n>`, `builds=<n>`, `up` if the worker moved up, `other` if
+//!   Hermes' other worker moved, and `first=<square>` for the turn's first
+//!   build.
+//! - In either, `athena=<player>` for an Athena player keeping the others
+//!   from moving up until their next turn, once more than one opponent has to
+//!   be kept track of.
+//! - Once the game is over, `won=<reason>` for how it was won: `move` with
+//!   `from=<square>` and `to=<square>` for the winning move, `stalemate` with
+//!   `build=<square>` for the build that left the last opponent unable to
+//!   move, if there was one, `resign` or `timeout`.
+//! - After placing, `ply=<n>` for the number of plies played so far, if any.
+//! - In any phase, `supply=<l1>,<l2>,<l3>,<domes>` for the building pieces
+//!   left when they are limited.
+//!
+//! For example, a fresh game is `00000/00000/00000/00000/00000 - - 1 p
+//! Mortal,Mortal`.
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::santorini::{
+    AnyGame, Board, Build, Coord, CoordLevel, Game, God, Move, NormalState, PlaceOne, PlaceTwo,
+    Player, Point, Supply, Victory, VictoryReason, WorkerLocs, BOARD_HEIGHT, BOARD_WIDTH,
+    MAX_PLAYERS, MAX_WORKERS,
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct NotationError(String);
+
+impl NotationError {
+    pub(crate) fn new(msg: String) -> Self {
+        NotationError(msg)
+    }
+}
+
+fn invalid<T>(msg: &str) -> Result<T, NotationError> {
+    Err(NotationError(msg.to_string()))
+}
+
+/// Name a square by file (a-e, left to right) and rank (1-5, top to bottom).
+pub fn encode_point(pt: Point) -> String {
+    format!("{}{}", (b'a' + *pt.x() as u8) as char, *pt.y() + 1)
+}
+
+/// Parse a square named by `encode_point`.
+pub fn parse_point(s: &str) -> Option<Point> {
+    let mut chars = s.chars();
+    let file = chars.next()?;
+    let rank = chars.as_str().parse::<i8>().ok()?;
+    if !file.is_ascii_lowercase() {
+        return None;
+    }
+    Point::new_(Coord(file as i8 - b'a' as i8), Coord(rank - 1))
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", encode_point(*self))
+    }
+}
+
+impl FromStr for Point {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_point(s).ok_or_else(|| NotationError(format!("invalid square: {}", s)))
+    }
+}
+
+/// Write the heights of the board row by row, separated by `/`.
+pub fn encode_board(board: &Board) -> String {
+    let rows: Vec<String> = (0..BOARD_HEIGHT.0)
+        .map(|y| {
+            (0..BOARD_WIDTH.0)
+                .map(|x| i8::from(board.level_at(Point::new(x.into(), y.into()))).to_string())
+                .collect()
+        })
+        .collect();
+    rows.join("/")
+}
+
+/// Parse the heights written by `encode_board`.
+pub fn parse_board(s: &str) -> Result<Board, NotationError> {
+    let rows: Vec<&str> = s.split('/').collect();
+    if rows.len() != BOARD_HEIGHT.0 as usize {
+        return invalid("wrong number of rows");
+    }
+    let mut board = Board::new();
+    for (y, row) in rows.iter().enumerate() {
+        if row.len() != BOARD_WIDTH.0 as usize {
+            return invalid("wrong number of columns");
+        }
+        for (x, height) in row.chars().enumerate() {
+            let level = match height.to_digit(10) {
+                Some(level) if level <= 4 => CoordLevel::from(level as i8),
+                _ => return invalid("heights must be between 0 and 4"),
+            };
+            board.set_level(Point::new((x as i8).into(), (y as i8).into()), level);
+        }
+    }
+    Ok(board)
+}
+
+/// Write the blocks left for each level followed by the domes left, separated
+/// by `,`.
+pub fn encode_supply(supply: Supply) -> String {
+    let levels = [
+        CoordLevel::One,
+        CoordLevel::Two,
+        CoordLevel::Three,
+        CoordLevel::Capped,
+    ];
+    let counts: Vec<String> = levels
+        .iter()
+        .map(|level| supply.left(*level).to_string())
+        .collect();
+    counts.join(",")
+}
+
+/// Parse the pieces written by `encode_supply`.
+pub fn parse_supply(s: &str) -> Result<Supply, NotationError> {
+    let counts = match s.split(',').map(str::parse).collect::<Result<Vec<u8>, _>>() {
+        Ok(counts) => counts,
+        Err(_) => return invalid("invalid piece count"),
+    };
+    match counts[..] {
+        [one, two, three, domes] => Ok(Supply::new([one, two, three], domes)),
+        _ => invalid("expected counts for three levels and domes"),
+    }
+}
+
+/// Write a player's workers, or `-` if they have not been placed.
+pub fn encode_workers(workers: Option<WorkerLocs>) -> String {
+    match workers {
+        Some(locs) => {
+            let locs: Vec<String> = locs.iter().map(|loc| encode_point(*loc)).collect();
+            locs.join(",")
+        }
+        None => "-".to_string(),
+    }
+}
+
+/// Parse the workers written by `encode_workers`.
+pub fn parse_workers(s: &str) -> Result<Option<WorkerLocs>, NotationError> {
+    if s == "-" {
+        return Ok(None);
+    }
+    let pts = match s.split(',').map(parse_point).collect::<Option<Vec<_>>>() {
+        Some(pts) => pts,
+        None => return invalid("invalid worker location"),
+    };
+    if pts.len() > MAX_WORKERS {
+        return Err(NotationError(format!(
+            "players have at most {} workers",
+            MAX_WORKERS
+        )));
+    }
+    for (idx, pt) in pts.iter().enumerate() {
+        if pts[idx + 1..].contains(pt) {
+            return invalid("workers overlap");
+        }
+    }
+    Ok(Some(WorkerLocs::new(&pts)))
+}
+
+/// Check that the given workers stand on distinct spaces no higher than
+/// `highest`.
+pub fn check_workers(
+    board: &Board,
+    workers: &[WorkerLocs],
+    highest: CoordLevel,
+) -> Result<(), NotationError> {
+    let all: Vec<Point> = workers.iter().flat_map(|locs| locs.to_vec()).collect();
+    for (idx, pt) in all.iter().enumerate() {
+        if all[idx + 1..].contains(pt) {
+            return invalid("workers overlap");
+        }
+        if board.level_at(*pt) > highest {
+            return invalid("workers stand too high");
+        }
+    }
+    Ok(())
+}
+
+fn encode_player(player: Player) -> String {
+    (player.index() + 1).to_string()
+}
+
+/// Write a game in any state. See the module documentation for the format.
+pub fn encode<G: Into<AnyGame>>(game: G) -> String {
+    let game = game.into();
+    let placing = |count: usize| {
+        if count == 2 {
+            vec![]
+        } else {
+            vec![format!("workers={}", count)]
+        }
+    };
+    let (workers, phase, details) = match game {
+        AnyGame::PlaceOne(game) => ([None; MAX_PLAYERS], "p", placing(game.worker_count())),
+        AnyGame::PlaceTwo(game) => (game.state.workers, "p", placing(game.worker_count())),
+        AnyGame::Move(game) => {
+            let state = game.state;
+            let mut details = vec![];
+            if state.no_climbing {
+                details.push("nc".to_string());
+            }
+            if let Some(pre) = state.pre_move {
+                details.push(format!("pre={}", encode_point(pre)));
+            }
+            if let Some(athena) = state.athena {
+                details.push(format!("athena={}", encode_player(athena)));
+            }
+            (state.workers, "m", details)
+        }
+        AnyGame::Build(game) => {
+            let state = game.state;
+            let mut details = vec![
+                format!("at={}", encode_point(state.active_loc)),
+                format!("from={}", encode_point(state.start)),
+                format!("moves={}", state.moves),
+                format!("builds={}", state.builds),
+            ];
+            if state.moved_up {
+                details.push("up".to_string());
+            }
+            if state.other_moved {
+                details.push("other".to_string());
+            }
+            if let Some(first) = state.first_build {
+                details.push(format!("first={}", encode_point(first)));
+            }
+            if let Some(athena) = state.athena {
+                details.push(format!("athena={}", encode_player(athena)));
+            }
+            (state.workers, "b", details)
+        }
+        AnyGame::Victory(game) => {
+            let details = match game.state.reason {
+                VictoryReason::ReachedLevelThree { from, to } => vec![
+                    "won=move".to_string(),
+                    format!("from={}", encode_point(from)),
+                    format!("to={}", encode_point(to)),
+                ],
+                VictoryReason::OpponentStalemated { build } => {
+                    let mut details = vec!["won=stalemate".to_string()];
+                    if let Some(build) = build {
+                        details.push(format!("build={}", encode_point(build)));
+                    }
+                    details
+                }
+                VictoryReason::Resignation => vec!["won=resign".to_string()],
+                VictoryReason::Timeout => vec!["won=timeout".to_string()],
+            };
+            (game.state.workers, "v", details)
+        }
+    };
+    let (gods, players, ply) = match game {
+        AnyGame::PlaceOne(game) => (game.gods, game.players, game.ply),
+        AnyGame::PlaceTwo(game) => (game.gods, game.players, game.ply),
+        AnyGame::Move(game) => (game.gods, game.players, game.ply),
+        AnyGame::Build(game) => (game.gods, game.players, game.ply),
+        AnyGame::Victory(game) => (game.gods, game.players, game.ply),
+    };
+    let players = players as usize;
+
+    let mut details = details;
+    if ply > 0 && phase != "p" {
+        details.push(format!("ply={}", ply));
+    }
+    if let Some(supply) = game.board().supply() {
+        details.push(format!("supply={}", encode_supply(supply)));
+    }
+
+    let mut fields = vec![encode_board(&game.board())];
+    fields.extend(workers[..players].iter().map(|locs| encode_workers(*locs)));
+    fields.push(encode_player(game.player()));
+    fields.push(phase.to_string());
+    let names: Vec<&str> = gods[..players].iter().map(|god| god.name()).collect();
+    fields.push(names.join(","));
+    fields.extend(details);
+    fields.join(" ")
+}
+
+/// The `key=value` and flag details following the fixed fields.
+struct Details<'a> {
+    fields: Vec<&'a str>,
+}
+
+impl<'a> Details<'a> {
+    fn flag(&mut self, name: &str) -> bool {
+        let len = self.fields.len();
+        self.fields.retain(|field| *field != name);
+        self.fields.len() != len
+    }
+
+    fn value(&mut self, key: &str) -> Option<&'a str> {
+        let value = |field: &&'a str| field.strip_prefix(key)?.strip_prefix('=');
+        let idx = self
+            .fields
+            .iter()
+            .position(|field| value(field).is_some())?;
+        value(&self.fields.remove(idx))
+    }
+
+    fn point(&mut self, key: &str) -> Result<Option<Point>, NotationError> {
+        match self.value(key) {
+            Some(value) => match parse_point(value) {
+                Some(pt) => Ok(Some(pt)),
+                None => invalid("invalid square in details"),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn player(&mut self, key: &str) -> Result<Option<Player>, NotationError> {
+        match self.value(key) {
+            Some(value) => match parse_player(value) {
+                Some(player) => Ok(Some(player)),
+                None => invalid("invalid player in details"),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn count<T: FromStr>(&mut self, key: &str, default: T) -> Result<T, NotationError> {
+        match self.value(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| NotationError("invalid count in details".to_string())),
+            None => Ok(default),
+        }
+    }
+
+    fn finish(self) -> Result<(), NotationError> {
+        match self.fields.first() {
+            Some(field) => Err(NotationError(format!("unexpected detail: {}", field))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_player(s: &str) -> Option<Player> {
+    match s {
+        "1" => Some(Player::PlayerOne),
+        "2" => Some(Player::PlayerTwo),
+        "3" => Some(Player::PlayerThree),
+        _ => None,
+    }
+}
+
+/// Parse a game written by `encode`.
+pub fn parse(s: &str) -> Result<AnyGame, NotationError> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    // Three player games have a third set of workers before the player to act
+    let players = match fields.get(3) {
+        Some(field) if parse_player(field).is_none() => 3,
+        _ => 2,
+    };
+    if fields.len() < players + 4 {
+        return Err(NotationError(format!(
+            "expected at least {} fields",
+            players + 4
+        )));
+    }
+
+    let mut board = parse_board(fields[0])?;
+    let mut workers = [None; MAX_PLAYERS];
+    for (locs, field) in workers.iter_mut().zip(fields[1..=players].iter()) {
+        *locs = parse_workers(field)?;
+    }
+    let player = match parse_player(fields[players + 1]) {
+        Some(player) if player.index() < players => player,
+        _ => {
+            return Err(NotationError(format!(
+                "player must be between 1 and {}",
+                players
+            )))
+        }
+    };
+    let mut gods = [God::Mortal; MAX_PLAYERS];
+    match fields[players + 3]
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<God>, _>>()
+    {
+        Ok(names) if names.len() == players => gods[..players].copy_from_slice(&names),
+        Ok(_) => return Err(NotationError(format!("expected {} gods", players))),
+        Err(err) => return Err(NotationError(err)),
+    };
+    let mut details = Details {
+        fields: fields[players + 4..].to_vec(),
+    };
+    if let Some(supply) = details.value("supply") {
+        board = board.with_supply(parse_supply(supply)?);
+    }
+
+    let placed: Vec<WorkerLocs> = workers.iter().flatten().cloned().collect();
+    let players = players as u8;
+    let phase = fields[players as usize + 2];
+    // Workers yet to be placed are counted in the details, and everyone else's
+    // workers are counted directly
+    let worker_count = match phase {
+        "p" => details.count("workers", 2)?,
+        _ => placed.first().map_or(0, |locs| locs.len() as u8),
+    };
+    // Every player before the one to act has placed their workers
+    let ply = match phase {
+        "p" => player.index() as u16,
+        _ => details.count("ply", 0)?,
+    };
+    if phase == "p" && !(1..=MAX_WORKERS as u8).contains(&worker_count) {
+        return Err(NotationError(format!(
+            "players need between 1 and {} workers",
+            MAX_WORKERS
+        )));
+    }
+    if placed
+        .iter()
+        .any(|locs| locs.len() != worker_count as usize)
+    {
+        return invalid("every player needs the same number of workers");
+    }
+    let game = match phase {
+        "p" => {
+            // Players place in turn, so exactly the ones before the player to
+            // act have placed
+            let in_turn = Player::iter()
+                .zip(workers.iter())
+                .all(|(seat, locs)| locs.is_some() == (seat.index() < player.index()));
+            if !in_turn {
+                return invalid("inconsistent placement");
+            }
+            check_workers(&board, &placed, CoordLevel::Capped)?;
+            if player == Player::PlayerOne {
+                AnyGame::PlaceOne(Game {
+                    state: PlaceOne {},
+                    board,
+                    player,
+                    gods,
+                    players,
+                    worker_count,
+                    ply,
+                })
+            } else {
+                AnyGame::PlaceTwo(Game {
+                    state: PlaceTwo { workers },
+                    board,
+                    player,
+                    gods,
+                    players,
+                    worker_count,
+                    ply,
+                })
+            }
+        }
+        "m" | "b" | "v" if placed.len() < 2 || workers[player.index()].is_none() => {
+            return invalid("the player and at least one opponent need workers")
+        }
+        "m" => {
+            check_workers(&board, &placed, CoordLevel::Two)?;
+            let pre_move = details.point("pre")?;
+            let game = Game {
+                state: Move {
+                    workers,
+                    no_climbing: details.flag("nc"),
+                    pre_move,
+                    athena: details.player("athena")?,
+                },
+                board,
+                player,
+                gods,
+                players,
+                worker_count,
+                ply,
+            };
+            if let Some(pre) = pre_move {
+                if !game.state.player_locs(player).contains(&pre) {
+                    return invalid("the worker acting before moving must belong to the player");
+                }
+            }
+            AnyGame::Move(game)
+        }
+        "b" => {
+            check_workers(&board, &placed, CoordLevel::Two)?;
+            let (active_loc, start) = match (details.point("at")?, details.point("from")?) {
+                (Some(at), Some(from)) => (at, from),
+                _ => return invalid("building requires at= and from="),
+            };
+            let game = Game {
+                state: Build {
+                    workers,
+                    active_loc,
+                    start,
+                    moves: details.count("moves", 1)?,
+                    moved_up: details.flag("up"),
+                    other_moved: details.flag("other"),
+                    builds: details.count("builds", 0)?,
+                    first_build: details.point("first")?,
+                    athena: details.player("athena")?,
+                },
+                board,
+                player,
+                gods,
+                players,
+                worker_count,
+                ply,
+            };
+            if !game.state.player_locs(player).contains(&active_loc) {
+                return invalid("the building worker must belong to the player");
+            }
+            AnyGame::Build(game)
+        }
+        "v" => {
+            check_workers(&board, &placed, CoordLevel::Three)?;
+            let reason = match details.value("won") {
+                Some("move") => match (details.point("from")?, details.point("to")?) {
+                    (Some(from), Some(to)) => VictoryReason::ReachedLevelThree { from, to },
+                    _ => return invalid("winning by moving requires from= and to="),
+                },
+                Some("stalemate") => VictoryReason::OpponentStalemated {
+                    build: details.point("build")?,
+                },
+                Some("resign") => VictoryReason::Resignation,
+                Some("timeout") => VictoryReason::Timeout,
+                Some(_) => return invalid("invalid victory reason"),
+                None => return invalid("victory requires won="),
+            };
+            AnyGame::Victory(Game {
+                state: Victory { workers, reason },
+                board,
+                player,
+                gods,
+                players,
+                worker_count,
+                ply,
+            })
+        }
+        _ => return invalid("phase must be p, m, b or v"),
+    };
+
+    details.finish()?;
+    Ok(game)
+}
+
+/// Squares and boards are serialized in this notation, so that their internal
+/// representation never has to be trusted when reading them back.
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::{
+        encode_board, encode_point, encode_supply, parse_board, parse_point, parse_supply,
+    };
+    use crate::santorini::{Board, Point};
+
+    impl Serialize for Point {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&encode_point(*self))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Point {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            parse_point(&s).ok_or_else(|| de::Error::custom(format!("invalid square: {}", s)))
+        }
+    }
+
+    /// Boards with limited building pieces follow the heights with the
+    /// pieces left, separated by a space.
+    impl Serialize for Board {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self.supply() {
+                Some(supply) => serializer.serialize_str(&format!(
+                    "{} {}",
+                    encode_board(self),
+                    encode_supply(supply)
+                )),
+                None => serializer.serialize_str(&encode_board(self)),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Board {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            match s.split_once(' ') {
+                Some((heights, supply)) => {
+                    let board = parse_board(heights).map_err(de::Error::custom)?;
+                    let supply = parse_supply(supply).map_err(de::Error::custom)?;
+                    Ok(board.with_supply(supply))
+                }
+                None => parse_board(&s).map_err(de::Error::custom),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod notation_tests {
+    use super::*;
+    use crate::santorini::{self, Turn};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    fn round_trip(game: AnyGame) {
+        let encoded = encode(game);
+        assert_eq!(parse(&encoded), Ok(game), "{}", encoded);
+    }
+
+    /// The heights of an empty board, which depend on its size.
+    fn empty() -> String {
+        encode_board(&Board::new())
+    }
+
+    #[test]
+    fn placement() {
+        let g = santorini::new_game();
+        assert_eq!(encode(g), format!("{} - - 1 p Mortal,Mortal", empty()));
+        round_trip(g.into());
+
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        assert_eq!(encode(g), format!("{} b2,d4 - 2 p Mortal,Mortal", empty()));
+        round_trip(g.into());
+    }
+
+    #[test]
+    fn worker_counts() {
+        let g = santorini::new_game().with_workers(3);
+        assert_eq!(
+            encode(g),
+            format!("{} - - 1 p Mortal,Mortal workers=3", empty())
+        );
+        round_trip(g.into());
+
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3), pt(0, 3)]).unwrap());
+        assert_eq!(
+            encode(g),
+            format!("{} b2,d4,a4 - 2 p Mortal,Mortal workers=3", empty())
+        );
+        round_trip(g.into());
+
+        let g: AnyGame = g
+            .apply(g.can_place(&[pt(1, 3), pt(3, 1), pt(2, 0)]).unwrap())
+            .unwrap()
+            .into();
+        assert_eq!(
+            encode(g),
+            format!("{} b2,d4,a4 b4,d2,c1 1 m Mortal,Mortal ply=2", empty())
+        );
+        round_trip(g);
+
+        let g = parse(&format!("{} c3 - 2 p Mortal,Mortal workers=1", empty())).unwrap();
+        let g = g.apply_turn("a1".parse().unwrap()).unwrap();
+        assert_eq!(
+            encode(g),
+            format!("{} c3 a1 1 m Mortal,Mortal ply=2", empty())
+        );
+        round_trip(g);
+    }
+
+    #[test]
+    fn supply() {
+        let g = santorini::new_game().with_limited_components();
+        assert_eq!(
+            encode(g),
+            format!("{} - - 1 p Mortal,Mortal supply=22,18,14,18", empty())
+        );
+        round_trip(g.into());
+
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::One);
+        let case = format!(
+            "{} a1 d4 1 m Mortal,Mortal supply=3,0,2,1",
+            encode_board(&board)
+        );
+        let g = parse(&case).unwrap();
+        assert_eq!(g.board().supply(), Some(Supply::new([3, 0, 2], 1)));
+        round_trip(g);
+
+        assert!(parse(&format!("{} - - 1 p Mortal,Mortal supply=1,2,3", empty())).is_err());
+        assert!(parse(&format!("{} - - 1 p Mortal,Mortal supply=a,2,3,4", empty())).is_err());
+    }
+
+    #[test]
+    fn every_phase() {
+        let g = santorini::new_game_with_gods([God::Prometheus, God::Athena]);
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        let g = g
+            .apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap())
+            .unwrap();
+        round_trip(g.into());
+
+        let pawn = g.active_pawns()[0];
+        let g = g.apply_pre_build(pawn.can_build_first(pt(2, 2)).unwrap());
+        assert!(encode(g).ends_with(" m Prometheus,Athena nc pre=b2 ply=2"));
+        round_trip(g.into());
+
+        let g = g
+            .apply(g.active_pawns()[0].can_move(pt(1, 2)).unwrap())
+            .unwrap();
+        assert!(encode(g).ends_with(" b Prometheus,Athena at=b3 from=b2 moves=1 builds=0 ply=2"));
+        round_trip(g.into());
+
+        let g = AnyGame::from(g).apply_turn(Turn::Resign).unwrap();
+        assert!(matches!(g, AnyGame::Victory(_)));
+        assert!(encode(g).ends_with(" v Prometheus,Athena won=resign ply=3"));
+        round_trip(g);
+
+        let mut board = Board::new();
+        board.set_level(pt(2, 3), CoordLevel::Three);
+        let board = encode_board(&board);
+        let cases = [
+            "a1,c4 d1,d3 1 v Mortal,Mortal won=move from=c3 to=c4",
+            "a1,c4 d1,d3 1 v Mortal,Mortal won=stalemate build=b2",
+            "a1,c4 d1,d3 1 v Mortal,Mortal won=stalemate",
+            "a1,c4 d1,d3 2 v Mortal,Mortal won=timeout",
+        ];
+        for case in cases.iter() {
+            let case = format!("{} {}", board, case);
+            assert_eq!(encode(parse(&case).unwrap()), case);
+        }
+    }
+
+    #[test]
+    fn invalid_notation() {
+        let board = empty();
+        let cases = [
+            format!("{} - - 1 p", board),
+            format!("{} - - 2 p Mortal,Mortal", board),
+            format!("{} b2,d4 - 1 m Mortal,Mortal", board),
+            format!("{} b2,d4 b4,d2 1 x Mortal,Mortal", board),
+            format!("{} b2,d4 b4,d2 1 m Mortal,Hecate", board),
+            format!("{} b2,d4 b4,b2 1 m Mortal,Mortal", board),
+            format!("{} b2,d4 b4,d2 1 m Mortal,Mortal pre=b4", board),
+            format!("{} b2,d4 b4,d2 1 m Mortal,Mortal shrug", board),
+            format!("{} b2,d4 b4,d2 1 b Mortal,Mortal at=b2", board),
+            format!("{} b2,d4 b4,d2 1 b Mortal,Mortal at=b4 from=b3", board),
+            format!("{} - - 1 p Mortal,Mortal nc", board),
+            format!("{} - - 1 p Mortal,Mortal ply=1", board),
+            format!("{} b2,d4 b4,d2 1 m Mortal,Mortal ply=-1", board),
+            format!("{} b2,d4 b4,d2 1 v Mortal,Mortal", board),
+            format!("{} b2,d4 b4,d2 1 v Mortal,Mortal won=move from=b3", board),
+            format!("{} b2,d4 b4,d2 1 v Mortal,Mortal won=shrug", board),
+            format!("{} - - 1 p Mortal,Mortal workers=4", board),
+            format!("{} b2,d4 - 2 p Mortal,Mortal workers=3", board),
+            format!("{} b2,d4,c3 b4 1 m Mortal,Mortal", board),
+            format!("{} a1,b2,c3,d4 b4 1 m Mortal,Mortal", board),
+        ];
+        for case in cases.iter() {
+            assert!(parse(case).is_err(), "{}", case);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let g = santorini::new_game_with_gods([God::Apollo, God::Zeus]);
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        let g: AnyGame = g.apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap()).into();
+
+        let json = serde_json::to_string(&g).unwrap();
+        assert!(json.contains("\"b2\""), "{}", json);
+        assert!(json.contains(&format!("\"{}\"", empty())), "{}", json);
+        assert_eq!(serde_json::from_str::<AnyGame>(&json).unwrap(), g);
+
+        let turn = Turn::Place([pt(0, 0), pt(3, 3)].into());
+        let json = serde_json::to_string(&turn).unwrap();
+        assert_eq!(serde_json::from_str::<Turn>(&json).unwrap(), turn);
+
+        assert!(serde_json::from_str::<Point>("\"g1\"").is_err());
+        assert!(serde_json::from_str::<Board>("\"00000\"").is_err());
+    }
+
+    #[test]
+    fn three_players() {
+        let mut raised = Board::new();
+        raised.set_level(pt(1, 0), CoordLevel::One);
+        let cases = [
+            format!("{} - - - 1 p Mortal,Athena,Pan", empty()),
+            format!("{} b2,d4 - - 2 p Mortal,Athena,Pan", empty()),
+            format!(
+                "{} b2,d4 b4,d2 a1,c3 2 m Athena,Mortal,Pan nc athena=1",
+                encode_board(&raised)
+            ),
+            format!("{} b2,d4 - a1,c3 3 m Mortal,Athena,Pan", empty()),
+            format!(
+                "{} b2,d4 b4,d2 a1,c3 3 v Mortal,Athena,Pan won=resign",
+                empty()
+            ),
+        ];
+        for case in cases.iter() {
+            let game = parse(case).unwrap();
+            assert_eq!(encode(game), *case);
+        }
+
+        // Workers are placed in turn order
+        let case = format!("{} - b2,d4 - 3 p Mortal,Mortal,Mortal", empty());
+        assert!(parse(&case).is_err());
+    }
+}
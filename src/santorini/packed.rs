@@ -0,0 +1,311 @@
+//! A compact encoding of positions into a single `u128`.
+//!
+//! The phase, the player, the height of every square and each player's
+//! workers are packed as the digits of one mixed radix number. Each player's
+//! workers are stored as a set, so positions that only differ in the order
+//! their workers are listed pack the same, which is what a transposition
+//! table wants.
+
+use crate::santorini::{
+    AnyGame, Board, CoordLevel, NormalState, Player, Point, WorkerLocs, Workers, BOARD_SQUARES,
+    BOARD_WIDTH, MAX_PLAYERS, MAX_WORKERS,
+};
+
+/// The stage a game is in, as given by the state of an `AnyGame`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Phase {
+    Place,
+    Move,
+    Build,
+    Victory,
+}
+
+const PHASES: [Phase; 4] = [Phase::Place, Phase::Move, Phase::Build, Phase::Victory];
+/// Ground to dome.
+const LEVELS: u128 = 5;
+
+const fn binomial(n: usize, k: usize) -> u128 {
+    if k > n {
+        return 0;
+    }
+    let mut result = 1;
+    let mut idx = 0;
+    while idx < k {
+        result = result * (n - idx) as u128 / (idx + 1) as u128;
+        idx += 1;
+    }
+    result
+}
+
+/// The number of sets of workers before those with `count` workers, with no
+/// workers first.
+const fn seat_offset(count: usize) -> u128 {
+    let mut offset = 1;
+    let mut size = 1;
+    while size < count {
+        offset += binomial(BOARD_SQUARES, size);
+        size += 1;
+    }
+    offset
+}
+
+/// The number of ways a seat can be filled, including being empty.
+const SEAT_CODES: u128 = seat_offset(MAX_WORKERS + 1);
+
+/// Whether every digit fits in a `u128` together.
+const fn fits() -> bool {
+    let mut total: u128 = (PHASES.len() * MAX_PLAYERS) as u128;
+    let mut idx = 0;
+    while idx < BOARD_SQUARES + MAX_PLAYERS {
+        let radix = if idx < BOARD_SQUARES {
+            LEVELS
+        } else {
+            SEAT_CODES
+        };
+        total = match total.checked_mul(radix) {
+            Some(total) => total,
+            None => return false,
+        };
+        idx += 1;
+    }
+    true
+}
+
+const _: () = assert!(fits(), "Positions don't fit in a u128!");
+
+fn square(pt: Point) -> usize {
+    (*pt.y() * BOARD_WIDTH.0 + *pt.x()) as usize
+}
+
+fn point(square: usize) -> Point {
+    let width = BOARD_WIDTH.0 as usize;
+    Point::new(
+        ((square % width) as i8).into(),
+        ((square / width) as i8).into(),
+    )
+}
+
+/// The index of a set of workers among every possible one.
+fn seat_code(locs: Option<WorkerLocs>) -> u128 {
+    let locs = match locs {
+        Some(locs) => locs,
+        None => return 0,
+    };
+    let mut squares: Vec<usize> = locs.iter().map(|loc| square(*loc)).collect();
+    squares.sort_unstable();
+    // Rank the set in the combinatorial number system
+    let rank: u128 = squares
+        .iter()
+        .enumerate()
+        .map(|(idx, square)| binomial(*square, idx + 1))
+        .sum();
+    seat_offset(squares.len()) + rank
+}
+
+fn seat_locs(code: u128) -> Option<WorkerLocs> {
+    if code == 0 {
+        return None;
+    }
+    let count = (1..=MAX_WORKERS)
+        .rev()
+        .find(|count| seat_offset(*count) <= code)
+        .expect("Invalid seat code!");
+    let mut rank = code - seat_offset(count);
+    let mut squares = vec![0; count];
+    for idx in (0..count).rev() {
+        let square = (0..BOARD_SQUARES)
+            .rev()
+            .find(|square| binomial(*square, idx + 1) <= rank)
+            .expect("Invalid seat code!");
+        rank -= binomial(square, idx + 1);
+        squares[idx] = square;
+    }
+    let locs: Vec<Point> = squares.into_iter().map(point).collect();
+    Some(WorkerLocs::new(&locs))
+}
+
+/// The parts of a game packed by `encode`: everything but the gods, the
+/// details of the turn in progress and the building pieces left, so positions
+/// decode with an endless supply.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Position {
+    pub board: Board,
+    pub workers: Workers,
+    /// The player to act, or the winner once the game is over.
+    pub player: Player,
+    pub phase: Phase,
+}
+
+impl Position {
+    pub fn encode(&self) -> u128 {
+        let mut digits: Vec<(u128, u128)> = vec![
+            (
+                PHASES.len() as u128,
+                PHASES
+                    .iter()
+                    .position(|phase| *phase == self.phase)
+                    .unwrap() as u128,
+            ),
+            (MAX_PLAYERS as u128, self.player.index() as u128),
+        ];
+        digits.extend((0..BOARD_SQUARES).map(|idx| {
+            let level = self.board.level_at(point(idx));
+            (LEVELS, i8::from(level) as u128)
+        }));
+        digits.extend(
+            self.workers
+                .iter()
+                .map(|locs| (SEAT_CODES, seat_code(*locs))),
+        );
+
+        // The first digit ends up least significant
+        digits
+            .iter()
+            .rev()
+            .fold(0, |packed, (radix, digit)| packed * radix + digit)
+    }
+
+    /// Unpack a position packed by `encode`. Returns None if the number is
+    /// not a valid position.
+    pub fn decode(packed: u128) -> Option<Position> {
+        let mut packed = packed;
+        let mut digit = |radix: u128| {
+            let digit = packed % radix;
+            packed /= radix;
+            digit
+        };
+
+        let phase = PHASES[digit(PHASES.len() as u128) as usize];
+        let player = *Player::iter().nth(digit(MAX_PLAYERS as u128) as usize)?;
+        let mut board = Board::new();
+        for idx in 0..BOARD_SQUARES {
+            board.set_level(point(idx), CoordLevel::from(digit(LEVELS) as i8));
+        }
+        let mut workers = [None; MAX_PLAYERS];
+        for locs in workers.iter_mut() {
+            *locs = seat_locs(digit(SEAT_CODES));
+        }
+        if packed != 0 {
+            return None;
+        }
+
+        let all: Vec<Point> = workers
+            .iter()
+            .flatten()
+            .flat_map(|locs| locs.to_vec())
+            .collect();
+        for (idx, loc) in all.iter().enumerate() {
+            if all[idx + 1..].contains(loc) {
+                return None;
+            }
+        }
+
+        Some(Position {
+            board,
+            workers,
+            player,
+            phase,
+        })
+    }
+}
+
+impl AnyGame {
+    pub fn position(&self) -> Position {
+        let (workers, phase) = match self {
+            AnyGame::PlaceOne(_) => ([None; MAX_PLAYERS], Phase::Place),
+            AnyGame::PlaceTwo(game) => (game.state.workers, Phase::Place),
+            AnyGame::Move(game) => (game.state.workers(), Phase::Move),
+            AnyGame::Build(game) => (game.state.workers(), Phase::Build),
+            AnyGame::Victory(game) => (game.state.workers(), Phase::Victory),
+        };
+        Position {
+            board: self.board(),
+            workers,
+            player: self.player(),
+            phase,
+        }
+    }
+}
+
+#[cfg(test)]
+mod packed_tests {
+    use rand::rngs::SmallRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::santorini::{self, GameBuilder, God};
+
+    #[test]
+    fn round_trip() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let games: [AnyGame; 3] = [
+            santorini::new_game_with_gods([God::Mortal, God::Apollo]).into(),
+            santorini::new_three_player_game([God::Mortal; 3]).into(),
+            santorini::new_game().with_workers(3).into(),
+        ];
+        for game in games.iter() {
+            let mut game = *game;
+            loop {
+                let mut position = game.position();
+                let packed = position.encode();
+                for locs in position.workers.iter_mut().flatten() {
+                    locs.sort_unstable_by_key(|loc| square(*loc));
+                }
+                assert_eq!(Position::decode(packed), Some(position));
+
+                let actions = game.actions();
+                match actions.choose(&mut rng) {
+                    Some(action) => game = game.apply(*action).unwrap(),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn worker_order() {
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        board.set_level(pt(1, 1), CoordLevel::One);
+        board.set_level(pt(2, 2), CoordLevel::Two);
+        board.set_level(pt(3, 3), CoordLevel::Three);
+        let game = |p1: &[Point]| -> AnyGame {
+            GameBuilder::new([God::Mortal; 2])
+                .board(board)
+                .workers(Player::PlayerOne, p1)
+                .workers(Player::PlayerTwo, &[pt(BOARD_WIDTH.0 - 1, 0), pt(1, 3)])
+                .player(Player::PlayerTwo)
+                .build()
+                .unwrap()
+                .into()
+        };
+        let a = game(&[pt(0, 0), pt(2, 2)]);
+        let b = game(&[pt(2, 2), pt(0, 0)]);
+        assert_eq!(a.position().encode(), b.position().encode());
+
+        let decoded = Position::decode(b.position().encode()).unwrap();
+        assert_eq!(decoded.workers, a.position().workers);
+        assert_eq!(decoded.board, a.board());
+        assert_eq!(decoded.player, Player::PlayerTwo);
+        assert_eq!(decoded.phase, Phase::Move);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Position::decode(u128::MAX), None);
+
+        // Two players' workers on the same square
+        let position = Position {
+            board: Board::new(),
+            workers: [
+                Some([point(0), point(1)].into()),
+                Some([point(1), point(2)].into()),
+                None,
+            ],
+            player: Player::PlayerOne,
+            phase: Phase::Move,
+        };
+        assert_eq!(Position::decode(position.encode()), None);
+    }
+}
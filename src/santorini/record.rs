@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::santorini::notation::{self, NotationError};
+use crate::santorini::{AnyGame, Turn};
+
+/// A transcript of a game: the state it started from and every turn played
+/// since, which is enough to replay it.
+///
+/// A record is written as the starting state in `santorini::notation`,
+/// followed by one turn per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    initial: AnyGame,
+    turns: Vec<Turn>,
+    current: AnyGame,
+}
+
+impl GameRecord {
+    pub fn new(initial: AnyGame) -> Self {
+        GameRecord {
+            initial,
+            turns: Vec::new(),
+            current: initial,
+        }
+    }
+
+    /// The number of plies played.
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn initial(&self) -> AnyGame {
+        self.initial
+    }
+
+    pub fn current(&self) -> AnyGame {
+        self.current
+    }
+
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Play a turn from the current state. Returns the new state, or None if
+    /// the turn is not legal, in which case the record is unchanged.
+    pub fn push(&mut self, turn: Turn) -> Option<AnyGame> {
+        let next = self.current.apply_turn(turn)?;
+        self.turns.push(turn);
+        self.current = next;
+        Some(next)
+    }
+
+    /// Remove the last turn, given the state it was played from.
+    pub(super) fn pop_to(&mut self, previous: AnyGame) -> Option<Turn> {
+        let turn = self.turns.pop()?;
+        self.current = previous;
+        Some(turn)
+    }
+
+    /// The state after the given number of plies, replayed from the start, or
+    /// None if fewer plies have been played.
+    pub fn state_at(&self, ply: usize) -> Option<AnyGame> {
+        if ply > self.turns.len() {
+            return None;
+        }
+        if ply == self.turns.len() {
+            return Some(self.current);
+        }
+        Some(self.iter().nth(ply)?.2)
+    }
+
+    /// Iterate over `(ply, turn, state)` for every turn played, where `state`
+    /// is the state the turn was played from.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Turn, AnyGame)> + '_ {
+        self.turns
+            .iter()
+            .scan(self.initial, |state, turn| {
+                let before = *state;
+                *state = before
+                    .apply_turn(*turn)
+                    .expect("Record contains an illegal turn!");
+                Some((*turn, before))
+            })
+            .enumerate()
+            .map(|(ply, (turn, state))| (ply, turn, state))
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", notation::encode(self.initial))?;
+        for turn in self.turns.iter() {
+            writeln!(f, "{}", turn)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = NotationError;
+
+    /// Read a record written by `Display`, checking every turn is legal.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        let initial = lines
+            .next()
+            .ok_or_else(|| NotationError::new("empty record".to_string()))?;
+        let mut record = GameRecord::new(notation::parse(initial)?);
+        for line in lines {
+            let turn = line.parse()?;
+            if record.push(turn).is_none() {
+                return Err(NotationError::new(format!(
+                    "illegal turn at ply {}: {}",
+                    record.len(),
+                    line
+                )));
+            }
+        }
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod record_tests {
+    use super::*;
+    use crate::santorini;
+
+    fn opening() -> GameRecord {
+        let mut record = GameRecord::new(santorini::new_game().into());
+        for turn in ["b2,d4", "b4,d2", "b2>c3^b2", "b4>a4^a3"].iter() {
+            record.push(turn.parse().unwrap()).unwrap();
+        }
+        record
+    }
+
+    #[test]
+    fn replay() {
+        let record = opening();
+        assert_eq!(record.len(), 4);
+        assert_eq!(record.state_at(0), Some(record.initial()));
+        assert_eq!(record.state_at(4), Some(record.current()));
+        assert_eq!(record.state_at(5), None);
+
+        let states: Vec<_> = record.iter().map(|(_, _, state)| state).collect();
+        for (ply, state) in states.iter().enumerate() {
+            assert_eq!(record.state_at(ply), Some(*state));
+            assert_eq!(
+                state.apply_turn(record.turns()[ply]),
+                record.state_at(ply + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let record = opening();
+        let text = record.to_string();
+        assert_eq!(text.lines().count(), 5);
+        assert_eq!(text.lines().nth(3), Some("b2>c3^b2"));
+        assert_eq!(text.parse(), Ok(record));
+    }
+
+    #[test]
+    fn illegal_records() {
+        assert!("".parse::<GameRecord>().is_err());
+
+        let mut text = opening().to_string();
+        text.push_str("c3>c3^b2\n");
+        let err = text.parse::<GameRecord>().unwrap_err();
+        assert_eq!(err.to_string(), "illegal turn at ply 4: c3>c3^b2");
+    }
+}
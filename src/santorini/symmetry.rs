@@ -0,0 +1,366 @@
+use crate::santorini::{
+    AnyGame, Board, Build, Coord, Game, GameState, Move, PlaceOne, PlaceTwo, Point, Turn, Victory,
+    VictoryReason, WorkerLocs, Workers, BOARD_HEIGHT, BOARD_SQUARES, BOARD_WIDTH,
+};
+
+/// One of the 8 symmetries of the board: an optional reflection in the main
+/// diagonal, followed by optional reflections left to right and top to
+/// bottom.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Symmetry(u8);
+
+const TRANSPOSE: u8 = 0b100;
+const FLIP_X: u8 = 0b010;
+const FLIP_Y: u8 = 0b001;
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry(0);
+
+    /// Every symmetry of the board, starting with the identity.
+    pub fn all() -> impl Iterator<Item = Symmetry> {
+        (0..8).map(Symmetry)
+    }
+
+    pub fn apply(&self, pt: Point) -> Point {
+        let (x, y) = (*pt.x(), *pt.y());
+        let (mx, my) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        let (x, y) = if self.0 & TRANSPOSE != 0 {
+            (y, x)
+        } else {
+            (x, y)
+        };
+        let x = if self.0 & FLIP_X != 0 { mx - x } else { x };
+        let y = if self.0 & FLIP_Y != 0 { my - y } else { y };
+        Point::new(Coord(x), Coord(y))
+    }
+
+    /// The symmetry undoing this one.
+    pub fn inverse(&self) -> Symmetry {
+        if self.0 & TRANSPOSE != 0 {
+            // The flips swap axes when moved before the transposition
+            let flips = ((self.0 & FLIP_X) >> 1) | ((self.0 & FLIP_Y) << 1);
+            Symmetry(TRANSPOSE | flips)
+        } else {
+            *self
+        }
+    }
+}
+
+/// Something that can be seen through a symmetry of the board.
+///
+/// The workers of each player are also put in order, so that symmetric
+/// states compare equal however their workers were listed.
+pub trait Symmetric: Ord + Sized {
+    fn transform(&self, symmetry: Symmetry) -> Self;
+}
+
+fn workers(workers: Workers, symmetry: Symmetry) -> Workers {
+    let mut workers = workers;
+    for locs in workers.iter_mut().flatten() {
+        for loc in locs.iter_mut() {
+            *loc = symmetry.apply(*loc);
+        }
+        locs.sort_unstable();
+    }
+    workers
+}
+
+impl Board {
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let mut board = Board {
+            supply: self.supply,
+            ..Board::new()
+        };
+        for pt in Point::all() {
+            board.set_level(symmetry.apply(pt), self.level_at(pt));
+        }
+        board
+    }
+
+    /// The heights of the squares in row-major order.
+    fn heights(&self) -> [i8; BOARD_SQUARES] {
+        let mut heights = [0; BOARD_SQUARES];
+        for (height, pt) in heights.iter_mut().zip(Point::all()) {
+            *height = self.level_at(pt).into();
+        }
+        heights
+    }
+}
+
+impl Turn {
+    /// The turn seen through a symmetry of the board, as played in the
+    /// transformed game.
+    pub fn transform(&self, symmetry: Symmetry) -> Turn {
+        let apply = |loc: Point| symmetry.apply(loc);
+        match *self {
+            Turn::Place(locs) => {
+                let locs: Vec<Point> = locs.iter().map(|loc| apply(*loc)).collect();
+                Turn::Place(WorkerLocs::new(&locs))
+            }
+            Turn::MoveBuild {
+                force,
+                pre_build,
+                from,
+                to,
+                extra_move,
+                other_move,
+                build,
+                dome,
+                extra_builds,
+            } => Turn::MoveBuild {
+                force: force.map(apply),
+                pre_build: pre_build.map(apply),
+                from: apply(from),
+                to: apply(to),
+                extra_move: extra_move.map(apply),
+                other_move: other_move.map(|(from, to)| (apply(from), apply(to))),
+                build: build.map(apply),
+                dome,
+                extra_builds: [
+                    extra_builds[0].map(apply),
+                    extra_builds[1].map(apply),
+                    extra_builds[2].map(apply),
+                ],
+            },
+            Turn::Resign => Turn::Resign,
+        }
+    }
+}
+
+impl Symmetric for PlaceOne {
+    fn transform(&self, _: Symmetry) -> Self {
+        *self
+    }
+}
+
+impl Symmetric for PlaceTwo {
+    fn transform(&self, symmetry: Symmetry) -> Self {
+        PlaceTwo {
+            workers: workers(self.workers, symmetry),
+        }
+    }
+}
+
+impl Symmetric for Move {
+    fn transform(&self, symmetry: Symmetry) -> Self {
+        Move {
+            workers: workers(self.workers, symmetry),
+            pre_move: self.pre_move.map(|loc| symmetry.apply(loc)),
+            ..*self
+        }
+    }
+}
+
+impl Symmetric for Build {
+    fn transform(&self, symmetry: Symmetry) -> Self {
+        Build {
+            workers: workers(self.workers, symmetry),
+            active_loc: symmetry.apply(self.active_loc),
+            start: symmetry.apply(self.start),
+            first_build: self.first_build.map(|loc| symmetry.apply(loc)),
+            ..*self
+        }
+    }
+}
+
+impl Symmetric for Victory {
+    fn transform(&self, symmetry: Symmetry) -> Self {
+        let reason = match self.reason {
+            VictoryReason::ReachedLevelThree { from, to } => VictoryReason::ReachedLevelThree {
+                from: symmetry.apply(from),
+                to: symmetry.apply(to),
+            },
+            VictoryReason::OpponentStalemated { build } => VictoryReason::OpponentStalemated {
+                build: build.map(|loc| symmetry.apply(loc)),
+            },
+            reason => reason,
+        };
+        Victory {
+            workers: workers(self.workers, symmetry),
+            reason,
+        }
+    }
+}
+
+impl<S: GameState + Symmetric> Game<S> {
+    /// The game seen through a symmetry of the board.
+    pub fn transform(&self, symmetry: Symmetry) -> Game<S> {
+        Game {
+            state: self.state.transform(symmetry),
+            board: self.board.transform(symmetry),
+            ..*self
+        }
+    }
+
+    /// The symmetry that takes this game to its canonical form.
+    pub fn canonical_symmetry(&self) -> Symmetry {
+        Symmetry::all()
+            .min_by_key(|symmetry| {
+                let game = self.transform(*symmetry);
+                (game.board.heights(), game.state)
+            })
+            .expect("No symmetries!")
+    }
+
+    /// The lexicographically smallest of the games symmetric to this one,
+    /// comparing the heights row by row and then the workers. Symmetric games
+    /// share the same canonical form.
+    pub fn canonicalize(&self) -> Game<S> {
+        self.transform(self.canonical_symmetry())
+    }
+}
+
+impl AnyGame {
+    pub fn transform(&self, symmetry: Symmetry) -> AnyGame {
+        match self {
+            AnyGame::PlaceOne(game) => game.transform(symmetry).into(),
+            AnyGame::PlaceTwo(game) => game.transform(symmetry).into(),
+            AnyGame::Move(game) => game.transform(symmetry).into(),
+            AnyGame::Build(game) => game.transform(symmetry).into(),
+            AnyGame::Victory(game) => game.transform(symmetry).into(),
+        }
+    }
+
+    pub fn canonical_symmetry(&self) -> Symmetry {
+        match self {
+            AnyGame::PlaceOne(game) => game.canonical_symmetry(),
+            AnyGame::PlaceTwo(game) => game.canonical_symmetry(),
+            AnyGame::Move(game) => game.canonical_symmetry(),
+            AnyGame::Build(game) => game.canonical_symmetry(),
+            AnyGame::Victory(game) => game.canonical_symmetry(),
+        }
+    }
+
+    pub fn canonicalize(&self) -> AnyGame {
+        self.transform(self.canonical_symmetry())
+    }
+}
+
+#[cfg(test)]
+mod symmetry_tests {
+    use super::*;
+    use crate::santorini::{notation, CoordLevel};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    /// The top and bottom right corners, named for the notation.
+    fn corners() -> (String, String) {
+        let (mx, my) = (BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1);
+        (
+            notation::encode_point(pt(mx, 0)),
+            notation::encode_point(pt(mx, my)),
+        )
+    }
+
+    /// Heights with no symmetries of their own: the first and second levels
+    /// stepping down from the top edge, and the third in the bottom right
+    /// corner.
+    fn heights() -> String {
+        let mut board = Board::new();
+        board.set_level(pt(1, 0), CoordLevel::One);
+        board.set_level(pt(2, 1), CoordLevel::Two);
+        board.set_level(pt(BOARD_WIDTH.0 - 1, BOARD_HEIGHT.0 - 1), CoordLevel::Three);
+        notation::encode_board(&board)
+    }
+
+    #[test]
+    fn inverse() {
+        for symmetry in Symmetry::all() {
+            for x in 0..BOARD_WIDTH.0 {
+                for y in 0..BOARD_HEIGHT.0 {
+                    let p = pt(x, y);
+                    assert_eq!(symmetry.inverse().apply(symmetry.apply(p)), p);
+                }
+            }
+        }
+        assert_eq!(Symmetry::IDENTITY.apply(pt(1, 3)), pt(1, 3));
+        assert_eq!(
+            Symmetry(TRANSPOSE | FLIP_X).apply(pt(1, 3)),
+            pt(BOARD_WIDTH.0 - 4, 1)
+        );
+    }
+
+    #[test]
+    fn symmetric_games_share_a_canonical_form() {
+        let (top, _) = corners();
+        let game = notation::parse(&format!(
+            "{} a1,c3 {},b4 1 m Mortal,Athena nc",
+            heights(),
+            top
+        ))
+        .unwrap();
+        let canonical = game.canonicalize();
+        for symmetry in Symmetry::all() {
+            let other = game.transform(symmetry);
+            assert_eq!(other.canonicalize(), canonical);
+            assert_eq!(
+                other.transform(other.canonical_symmetry()),
+                other.canonicalize()
+            );
+        }
+        assert_eq!(canonical.canonicalize(), canonical);
+        assert_eq!(canonical.canonical_symmetry(), Symmetry::IDENTITY);
+
+        // The heights read row by row are smallest with the first level at
+        // the end of the second row and the third in the bottom left corner
+        assert_eq!(game.canonical_symmetry(), Symmetry(TRANSPOSE | FLIP_X));
+        let board = canonical.board();
+        assert_eq!(board.level_at(pt(BOARD_WIDTH.0 - 1, 1)), CoordLevel::One);
+        assert_eq!(board.level_at(pt(0, BOARD_HEIGHT.0 - 1)), CoordLevel::Three);
+
+        let other = notation::parse(&format!(
+            "{} a1,c3 {},b3 1 m Mortal,Athena nc",
+            heights(),
+            top
+        ))
+        .unwrap();
+        assert_ne!(other.canonicalize(), canonical);
+    }
+
+    #[test]
+    fn every_phase() {
+        let (board, (top, bottom)) = (heights(), corners());
+        let games = [
+            format!("{} - - 1 p Mortal,Mortal", board),
+            format!("{} a1,c3 - 2 p Mortal,Mortal", board),
+            format!(
+                "{} a1,c3 {},b4 1 b Demeter,Mortal at=c3 from=b2 moves=1 builds=1 first=c2",
+                board, top
+            ),
+            format!(
+                "{} a1,c3 {},{} 2 v Mortal,Mortal won=move from=b2 to=c3",
+                board, top, bottom
+            ),
+        ];
+        for game in games.iter() {
+            let game = notation::parse(game).unwrap();
+            let canonical = game.canonicalize();
+            for symmetry in Symmetry::all() {
+                assert_eq!(game.transform(symmetry).canonicalize(), canonical);
+            }
+        }
+    }
+
+    #[test]
+    fn turns() {
+        let (top, _) = corners();
+        let game =
+            notation::parse(&format!("{} a1,c3 {},b4 1 m Mortal,Mortal", heights(), top)).unwrap();
+        let turn: Turn = "c3>b2^c2".parse().unwrap();
+        let after = game.apply_turn(turn).unwrap();
+        for symmetry in Symmetry::all() {
+            let moved = game
+                .transform(symmetry)
+                .apply_turn(turn.transform(symmetry))
+                .unwrap();
+            assert_eq!(moved.canonicalize(), after.canonicalize());
+            assert_eq!(turn.transform(symmetry).transform(symmetry.inverse()), turn);
+        }
+        assert_eq!(
+            Turn::Place([pt(0, 0), pt(1, 3)].into()).transform(Symmetry(TRANSPOSE)),
+            Turn::Place([pt(0, 0), pt(3, 1)].into())
+        );
+    }
+}
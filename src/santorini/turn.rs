@@ -0,0 +1,482 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::santorini::notation::NotationError;
+use crate::santorini::{
+    ActionResult, Game, Move, PlaceOne, PlaceResult, PlaceTwo, Point, PreMoveAction, TurnActions,
+    WorkerLocs, MAX_WORKERS,
+};
+
+/// A complete turn for one player, described independently of any particular
+/// game so it can be stored and replayed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Turn {
+    /// Place every worker.
+    Place(WorkerLocs),
+    /// Move a worker and then build with it. `force` is the opponent worker
+    /// Charon forced before moving, and `pre_build` is where the worker built
+    /// before moving, for gods that allow it. `extra_move` is where the
+    /// worker moved on to afterwards, for gods that may move again,
+    /// `other_move` is where Hermes' other worker moved from and to, and
+    /// `extra_builds` are any further builds the god allows, in order. Hermes
+    /// may build with either worker. The build is omitted when a move wins
+    /// the game, and is a dome regardless of height if `dome` is set.
+    MoveBuild {
+        force: Option<Point>,
+        pre_build: Option<Point>,
+        from: Point,
+        to: Point,
+        extra_move: Option<Point>,
+        other_move: Option<(Point, Point)>,
+        build: Option<Point>,
+        dome: bool,
+        extra_builds: [Option<Point>; 3],
+    },
+    /// Concede the game.
+    Resign,
+}
+
+impl Game<PlaceOne> {
+    /// Place Player One's workers, returning None unless `turn` is a legal
+    /// placement.
+    pub fn apply_turn(self, turn: Turn) -> Option<Game<PlaceTwo>> {
+        match turn {
+            Turn::Place(locs) => Some(self.apply(self.can_place(&locs)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Game<PlaceTwo> {
+    /// Place the next player's workers, returning None unless `turn` is a
+    /// legal placement.
+    pub fn apply_turn(self, turn: Turn) -> Option<PlaceResult> {
+        match turn {
+            Turn::Place(locs) => Some(self.apply(self.can_place(&locs)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Game<Move> {
+    /// Validate and play a complete turn, returning None if any part of it is
+    /// not legal.
+    pub fn apply_turn(self, turn: Turn) -> Option<ActionResult<Move>> {
+        match turn {
+            Turn::MoveBuild {
+                force,
+                pre_build,
+                from,
+                to,
+                extra_move,
+                other_move,
+                build,
+                dome,
+                extra_builds,
+            } => {
+                let game = match force {
+                    Some(opponent) => {
+                        let pawns = self.active_pawns();
+                        let pawn = pawns.iter().find(|pawn| pawn.pos() == from)?;
+                        self.apply_force(pawn.can_force(opponent)?)
+                    }
+                    None => self,
+                };
+                let game = match pre_build {
+                    Some(loc) => {
+                        let pawns = game.active_pawns();
+                        let pawn = pawns.iter().find(|pawn| pawn.pos() == from)?;
+                        game.apply_pre_build(pawn.can_build_first(loc)?)
+                    }
+                    None => game,
+                };
+                let pawns = game.active_pawns();
+                let mv = pawns.iter().find(|pawn| pawn.pos() == from)?.can_move(to)?;
+                let result = match (game.apply(mv), extra_move) {
+                    (ActionResult::Continue(game), Some(extra_move)) => {
+                        game.apply_move(game.active_pawn().can_move(extra_move)?)
+                    }
+                    (result, None) => result,
+                    _ => return None,
+                };
+                let result = match (result, other_move) {
+                    (ActionResult::Continue(game), Some((from, to))) => {
+                        let pawns = game.active_pawns();
+                        let pawn = pawns.iter().find(|pawn| pawn.pos() == from)?;
+                        if pawn.pos() == game.active_pawn().pos() {
+                            return None;
+                        }
+                        game.apply_move(pawn.can_move(to)?)
+                    }
+                    (result, None) => result,
+                    _ => return None,
+                };
+                match (result, build) {
+                    (ActionResult::Victory(game), None) => Some(ActionResult::Victory(game)),
+                    (ActionResult::Continue(mut game), Some(build)) => {
+                        let mut build = if dome {
+                            game.can_build_dome(build)?
+                        } else {
+                            game.can_build(build)?
+                        };
+                        for extra_build in extra_builds.iter().flatten() {
+                            if !game.can_build_again() {
+                                return None;
+                            }
+                            game = game.build_again(build);
+                            build = game.active_pawn().can_build(*extra_build)?;
+                        }
+                        Some(game.apply(build))
+                    }
+                    _ => None,
+                }
+            }
+            Turn::Resign => Some(self.resign()),
+            Turn::Place(_) => None,
+        }
+    }
+}
+
+impl From<TurnActions> for Turn {
+    fn from(actions: TurnActions) -> Turn {
+        let (force, pre_build) = match actions.pre_move {
+            Some(PreMoveAction::Force(action)) => (Some(action.opponent()), None),
+            Some(PreMoveAction::Build(action)) => (None, Some(action.loc())),
+            None => (None, None),
+        };
+        Turn::MoveBuild {
+            force,
+            pre_build,
+            from: actions.mv.from(),
+            to: actions.mv.to(),
+            extra_move: actions.extra_move.map(|action| action.to()),
+            other_move: actions
+                .other_move
+                .map(|action| (action.from(), action.to())),
+            build: actions.build.map(|action| action.loc()),
+            dome: actions.build.is_some_and(|action| action.dome()),
+            extra_builds: [actions.extra_build.map(|action| action.loc()), None, None],
+        }
+    }
+}
+
+/// Turns are written as `<from>><to>^<build>`, for example `b2>b3^c3`.
+///
+/// A force before moving follows the worker as `*<opponent>` and a build
+/// before moving as `^<square>`, while an extra move follows the move as
+/// `><square>` and a move by Hermes' other worker as `&<from>><to>`. A dome
+/// built regardless of height is marked with `!`, and any extra builds follow
+/// the build, each as `^<square>`. Placements list the squares separated by
+/// `,`, such as `b2,d4`, and resigning is `resign`.
+///
+/// For example, `b2^c2>b3^c3` is a build before moving, `b2>b3^c3^c4` a
+/// build followed by an extra build and `b2>c2>d2&a4>a3^b4` a Hermes turn
+/// moving both workers.
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Turn::Place(locs) => {
+                let locs: Vec<String> = locs.iter().map(|loc| loc.to_string()).collect();
+                write!(f, "{}", locs.join(","))
+            }
+            Turn::MoveBuild {
+                force,
+                pre_build,
+                from,
+                to,
+                extra_move,
+                other_move,
+                build,
+                dome,
+                extra_builds,
+            } => {
+                write!(f, "{}", from)?;
+                if let Some(opponent) = force {
+                    write!(f, "*{}", opponent)?;
+                }
+                if let Some(loc) = pre_build {
+                    write!(f, "^{}", loc)?;
+                }
+                write!(f, ">{}", to)?;
+                if let Some(loc) = extra_move {
+                    write!(f, ">{}", loc)?;
+                }
+                if let Some((from, to)) = other_move {
+                    write!(f, "&{}>{}", from, to)?;
+                }
+                if let Some(loc) = build {
+                    write!(f, "^{}", loc)?;
+                    if *dome {
+                        write!(f, "!")?;
+                    }
+                }
+                for loc in extra_builds.iter().flatten() {
+                    write!(f, "^{}", loc)?;
+                }
+                Ok(())
+            }
+            Turn::Resign => write!(f, "resign"),
+        }
+    }
+}
+
+impl FromStr for Turn {
+    type Err = NotationError;
+
+    /// Parse a turn written by `Display`. The turn is not checked against any
+    /// game.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| NotationError::new(format!("{}: {}", msg, s));
+        if s == "resign" {
+            return Ok(Turn::Resign);
+        }
+        if !s.contains('>') {
+            let locs = s
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<Point>, _>>()?;
+            if locs.len() > MAX_WORKERS {
+                return Err(invalid("too many workers"));
+            }
+            return Ok(Turn::Place(WorkerLocs::new(&locs)));
+        }
+
+        // Hermes' other worker's move comes between the last move and the
+        // builds, which are put back together for the rest of the parsing
+        let (s, other_move) = match s.split_once('&') {
+            Some((main, other)) => {
+                let (from, rest) = other
+                    .split_once('>')
+                    .ok_or_else(|| invalid("expected the other worker's move"))?;
+                let (to, builds) = rest.split_at(rest.find('^').unwrap_or(rest.len()));
+                if main.rsplit('>').next().unwrap_or("").contains('^') {
+                    return Err(invalid("builds come after the last move"));
+                }
+                (
+                    format!("{}{}", main, builds),
+                    Some((from.parse()?, to.parse()?)),
+                )
+            }
+            None => (s.to_string(), None),
+        };
+
+        let segments: Vec<&str> = s.split('>').collect();
+        let (start, mv, extra_move) = match segments[..] {
+            [start, mv] => (start, mv, None),
+            [start, mv, extra] => (start, mv, Some(extra)),
+            _ => return Err(invalid("expected one or two moves")),
+        };
+
+        // The worker, with anything it did before moving
+        let mut parts = start.split('^');
+        let worker = parts.next().unwrap_or("");
+        let (from, force) = match worker.split_once('*') {
+            Some((from, opponent)) => (from.parse()?, Some(opponent.parse()?)),
+            None => (worker.parse()?, None),
+        };
+        let pre_build = parts.next().map(str::parse).transpose()?;
+        if parts.next().is_some() {
+            return Err(invalid("at most one build before moving"));
+        }
+
+        // Where the worker ends up, followed by the builds
+        let mut parts = extra_move.unwrap_or(mv).split('^');
+        let last = parts.next().unwrap_or("").parse()?;
+        let (to, extra_move) = match extra_move {
+            Some(_) => {
+                if mv.contains('^') {
+                    return Err(invalid("builds come after the last move"));
+                }
+                (mv.parse()?, Some(last))
+            }
+            None => (last, None),
+        };
+        let (build, dome) = match parts.next() {
+            Some(build) => match build.strip_suffix('!') {
+                Some(build) => (Some(build.parse()?), true),
+                None => (Some(build.parse()?), false),
+            },
+            None => (None, false),
+        };
+        let mut extra_builds = [None; 3];
+        for loc in parts {
+            match extra_builds.iter_mut().find(|slot| slot.is_none()) {
+                Some(slot) => *slot = Some(loc.parse()?),
+                None => return Err(invalid("too many builds")),
+            }
+        }
+
+        Ok(Turn::MoveBuild {
+            force,
+            pre_build,
+            from,
+            to,
+            extra_move,
+            other_move,
+            build,
+            dome,
+            extra_builds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod turn_tests {
+    use super::*;
+    use crate::santorini::{self, God};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    fn move_build(from: Point, to: Point, build: Option<Point>) -> Turn {
+        Turn::MoveBuild {
+            force: None,
+            pre_build: None,
+            from,
+            to,
+            extra_move: None,
+            other_move: None,
+            build,
+            dome: false,
+            extra_builds: [None; 3],
+        }
+    }
+
+    #[test]
+    fn notation() {
+        let turn = move_build(pt(1, 1), pt(1, 2), Some(pt(2, 2)));
+        assert_eq!(turn.to_string(), "b2>b3^c3");
+        assert_eq!("b2>b3^c3".parse(), Ok(turn));
+
+        let turn = move_build(pt(1, 1), pt(1, 2), None);
+        assert_eq!(turn.to_string(), "b2>b3");
+
+        assert_eq!(
+            Turn::Place([pt(0, 0), pt(3, 3)].into()).to_string(),
+            "a1,d4"
+        );
+        assert_eq!(Turn::Resign.to_string(), "resign");
+    }
+
+    #[test]
+    fn actions_match_turns() {
+        let g = santorini::new_game();
+        let placement = g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap();
+        assert_eq!(placement.to_string(), "b2,d4");
+        let g = g.apply(placement);
+        let g = g
+            .apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap())
+            .unwrap();
+
+        let mv = g.active_pawns()[0].can_move(pt(1, 2)).unwrap();
+        let g = g.apply(mv).unwrap();
+        let build = g.active_pawn().can_build(pt(2, 2)).unwrap();
+        assert_eq!(format!("{}{}", mv, build), "b2>b3^c3");
+    }
+
+    #[test]
+    fn apply_typed_turns() {
+        let g = santorini::new_game();
+        assert_eq!(g.apply_turn(Turn::Resign), None);
+        let g = g.apply_turn("b2,d4".parse().unwrap()).unwrap();
+        assert_eq!(g.apply_turn("b2,c4".parse().unwrap()), None);
+        let g = g.apply_turn("b4,d2".parse().unwrap()).unwrap().unwrap();
+
+        let g = g.apply_turn("b2>b3^c3".parse().unwrap()).unwrap().unwrap();
+        assert_eq!(g.player(), crate::santorini::Player::PlayerTwo);
+        assert_eq!(g.apply_turn("b4>a4".parse().unwrap()), None);
+        assert_eq!(g.apply_turn("b4>a4^c3".parse().unwrap()), None);
+        assert!(matches!(
+            g.apply_turn(Turn::Resign),
+            Some(ActionResult::Victory(_))
+        ));
+    }
+
+    #[test]
+    fn from_actions() {
+        for god in God::all() {
+            let g = santorini::new_game_with_gods([god, God::Mortal]);
+            let g = g.apply_turn("b2,c3".parse().unwrap()).unwrap();
+            let g = match g.apply_turn("c2,d4".parse().unwrap()).unwrap() {
+                PlaceResult::Move(g) => g,
+                PlaceResult::Place(_) => panic!("Not moving!"),
+            };
+            for (actions, result) in g.legal_turns() {
+                let turn = Turn::from(actions);
+                assert_eq!(g.apply_turn(turn), Some(result), "{}: {}", god.name(), turn);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let turns = [
+            Turn::Place([pt(1, 1), pt(3, 3)].into()),
+            Turn::Place(WorkerLocs::new(&[pt(2, 2)])),
+            Turn::Place(WorkerLocs::new(&[pt(0, 0), pt(2, 2), pt(3, 0)])),
+            Turn::Resign,
+            Turn::MoveBuild {
+                force: Some(pt(2, 2)),
+                pre_build: None,
+                from: pt(1, 1),
+                to: pt(2, 2),
+                extra_move: None,
+                other_move: None,
+                build: Some(pt(1, 1)),
+                dome: false,
+                extra_builds: [None; 3],
+            },
+            Turn::MoveBuild {
+                force: None,
+                pre_build: Some(pt(1, 2)),
+                from: pt(1, 1),
+                to: pt(2, 2),
+                extra_move: Some(pt(3, 3)),
+                other_move: None,
+                build: Some(pt(3, 2)),
+                dome: true,
+                extra_builds: [Some(pt(2, 3)), Some(pt(1, 3)), None],
+            },
+            Turn::MoveBuild {
+                force: None,
+                pre_build: None,
+                from: pt(1, 1),
+                to: pt(2, 1),
+                extra_move: Some(pt(3, 1)),
+                other_move: Some((pt(0, 3), pt(0, 2))),
+                build: Some(pt(1, 3)),
+                dome: false,
+                extra_builds: [None; 3],
+            },
+        ];
+        for turn in turns.iter() {
+            assert_eq!(turn.to_string().parse(), Ok(*turn), "{}", turn);
+        }
+    }
+
+    #[test]
+    fn invalid_turns() {
+        for s in [
+            "",
+            "b2,",
+            "b2>",
+            "b2>g3",
+            "b2^c2^c3>b3",
+            "b2>b3^c3>c4",
+            "b2>b3>b4>b5",
+            "b2>b3&a1",
+            "b2>b3^c3&a1>a2",
+            "b2>b3^a1^a2^a3^a4^a5",
+            "a1,b2,c3,d4",
+        ]
+        .iter()
+        {
+            assert!(s.parse::<Turn>().is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Taking moves and builds back.
+//!
+//! Searches that walk a game tree can apply an action, explore the position
+//! it leads to and then restore the position they started from with
+//! `unapply`, rather than keeping a copy of every position along the way.
+//! The undo tokens only record what the action changed.
+//!
+//! Only the move and the build ending a turn can be taken back. Powers used
+//! before moving, extra moves and extra builds are applied to copies as
+//! usual.
+
+use crate::santorini::{
+    worker_at, ActionResult, Build, BuildAction, CoordLevel, Game, GameState, Move, MoveAction,
+    Player, Point, Victory, Workers, MAX_PLAYERS,
+};
+
+/// What `Game<Move>::apply` changed, for taking the move back.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MoveUndo {
+    from: Point,
+    to: Point,
+    displaced: Option<Point>,
+    no_climbing: bool,
+    pre_move: Option<Point>,
+    athena: Option<Player>,
+    ply: u16,
+}
+
+/// What `Game<Build>::apply` changed, for taking the build back.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BuildUndo {
+    loc: Point,
+    level: CoordLevel,
+    player: Player,
+    active_loc: Point,
+    start: Point,
+    moves: u8,
+    moved_up: bool,
+    other_moved: bool,
+    builds: u8,
+    first_build: Option<Point>,
+    athena: Option<Player>,
+    /// The workers of players eliminated when the turn was handed over.
+    eliminated: Workers,
+    ply: u16,
+}
+
+impl MoveUndo {
+    /// The workers before the move, given the workers after it.
+    fn workers(&self, player: Player, workers: Workers) -> Workers {
+        let mut workers = workers;
+        *worker_at(&mut workers, |owner| owner == player, self.to) = self.from;
+        if let Some(displaced) = self.displaced {
+            *worker_at(&mut workers, |owner| owner != player, displaced) = self.to;
+        }
+        workers
+    }
+
+    fn restore<S: GameState>(&self, game: Game<S>, workers: Workers) -> Game<Move> {
+        Game {
+            state: Move {
+                workers: self.workers(game.player, workers),
+                no_climbing: self.no_climbing,
+                pre_move: self.pre_move,
+                athena: self.athena,
+            },
+            board: game.board,
+            player: game.player,
+            gods: game.gods,
+            players: game.players,
+            worker_count: game.worker_count,
+            ply: self.ply,
+        }
+    }
+}
+
+impl BuildUndo {
+    fn restore<S: GameState>(&self, game: Game<S>, workers: Workers) -> Game<Build> {
+        let mut workers = workers;
+        for (locs, eliminated) in workers.iter_mut().zip(self.eliminated.iter()) {
+            if eliminated.is_some() {
+                *locs = *eliminated;
+            }
+        }
+        let mut board = game.board;
+        board.unbuild(self.loc, self.level);
+        Game {
+            state: Build {
+                workers,
+                active_loc: self.active_loc,
+                start: self.start,
+                moves: self.moves,
+                moved_up: self.moved_up,
+                other_moved: self.other_moved,
+                builds: self.builds,
+                first_build: self.first_build,
+                athena: self.athena,
+            },
+            board,
+            player: self.player,
+            gods: game.gods,
+            players: game.players,
+            worker_count: game.worker_count,
+            ply: self.ply,
+        }
+    }
+}
+
+impl Game<Move> {
+    /// Move as `apply` does, also returning what is needed to take the move
+    /// back.
+    pub fn apply_with_undo(self, action: MoveAction) -> (ActionResult<Build>, MoveUndo) {
+        let undo = MoveUndo {
+            from: action.from,
+            to: action.to,
+            displaced: action.displaced,
+            no_climbing: self.state.no_climbing,
+            pre_move: self.state.pre_move,
+            athena: self.state.athena,
+            ply: self.ply,
+        };
+        (self.apply(action), undo)
+    }
+
+    /// Take back the build that ended the last turn.
+    pub fn unapply(self, undo: BuildUndo) -> Game<Build> {
+        undo.restore(self, self.state.workers)
+    }
+}
+
+impl Game<Build> {
+    /// Build as `apply` does, also returning what is needed to take the build
+    /// back.
+    pub fn apply_with_undo(self, action: BuildAction) -> (ActionResult<Move>, BuildUndo) {
+        let result = self.apply(action);
+        let after = match result {
+            ActionResult::Continue(game) => game.state.workers,
+            ActionResult::Victory(game) => game.state.workers,
+        };
+        let mut eliminated = [None; MAX_PLAYERS];
+        for (idx, locs) in self.state.workers.iter().enumerate() {
+            if after[idx].is_none() {
+                eliminated[idx] = *locs;
+            }
+        }
+
+        let undo = BuildUndo {
+            loc: action.loc,
+            level: self.board.level_at(action.loc),
+            player: self.player,
+            active_loc: self.state.active_loc,
+            start: self.state.start,
+            moves: self.state.moves,
+            moved_up: self.state.moved_up,
+            other_moved: self.state.other_moved,
+            builds: self.state.builds,
+            first_build: self.state.first_build,
+            athena: self.state.athena,
+            eliminated,
+            ply: self.ply,
+        };
+        (result, undo)
+    }
+
+    /// Take back the move that started the turn. Only valid before any extra
+    /// moves or builds.
+    pub fn unapply(self, undo: MoveUndo) -> Game<Move> {
+        undo.restore(self, self.state.workers)
+    }
+}
+
+impl Game<Victory> {
+    /// Take back the winning move.
+    pub fn unapply_move(self, undo: MoveUndo) -> Game<Move> {
+        undo.restore(self, self.state.workers)
+    }
+
+    /// Take back the build that left the last opponent unable to move.
+    pub fn unapply_build(self, undo: BuildUndo) -> Game<Build> {
+        undo.restore(self, self.state.workers)
+    }
+}
+
+impl ActionResult<Build> {
+    /// Take back the move that led here, whether or not it won.
+    pub fn unapply(self, undo: MoveUndo) -> Game<Move> {
+        match self {
+            ActionResult::Continue(game) => game.unapply(undo),
+            ActionResult::Victory(game) => game.unapply_move(undo),
+        }
+    }
+}
+
+impl ActionResult<Move> {
+    /// Take back the build that led here, whether or not it won.
+    pub fn unapply(self, undo: BuildUndo) -> Game<Build> {
+        match self {
+            ActionResult::Continue(game) => game.unapply(undo),
+            ActionResult::Victory(game) => game.unapply_build(undo),
+        }
+    }
+}
+
+#[cfg(test)]
+mod undo_tests {
+    use rand::rngs::SmallRng;
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+
+    use crate::santorini::{self, Board, GameBuilder, God, BOARD_HEIGHT, BOARD_WIDTH};
+
+    use super::*;
+
+    #[test]
+    fn random_games() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let gods = [
+            [God::Mortal, God::Apollo],
+            [God::Minotaur, God::Athena],
+            [God::Prometheus, God::Pan],
+        ];
+        for gods in gods.iter() {
+            for _ in 0..10 {
+                let g = santorini::new_game_with_gods(*gods);
+                let g = g.apply(g.placements().choose(&mut rng).unwrap());
+                let mut g = g.apply(g.placements().choose(&mut rng).unwrap()).unwrap();
+                loop {
+                    let pawns = g.active_pawns();
+                    let mv = pawns
+                        .iter()
+                        .flat_map(|pawn| pawn.actions())
+                        .choose(&mut rng)
+                        .unwrap();
+                    let (result, undo) = g.apply_with_undo(mv);
+                    assert_eq!(result.unapply(undo), g);
+                    assert_eq!(result.unapply(undo).hash(), g.hash());
+
+                    let built = match result {
+                        ActionResult::Continue(built) => built,
+                        ActionResult::Victory(_) => break,
+                    };
+                    let build = built.active_pawn().actions().choose(&mut rng).unwrap();
+                    let (result, undo) = built.apply_with_undo(build);
+                    assert_eq!(result.unapply(undo), built);
+                    assert_eq!(result.unapply(undo).hash(), built.hash());
+
+                    g = match result {
+                        ActionResult::Continue(g) => g,
+                        ActionResult::Victory(_) => break,
+                    };
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn eliminations() {
+        // Player Two is walled in and eliminated when the turn passes to them
+        let (w, h) = (BOARD_WIDTH.0, BOARD_HEIGHT.0);
+        let pt = |x: i8, y: i8| Point::new(x.into(), y.into());
+        let mut board = Board::new();
+        for &(x, y) in &[(2, 0), (0, 1), (1, 1), (2, 1)] {
+            board.set_level(pt(x, y), CoordLevel::Capped);
+        }
+        let g = GameBuilder::three_player([God::Mortal; 3])
+            .board(board)
+            .workers(Player::PlayerOne, &[pt(0, h - 1), pt(1, h - 1)])
+            .workers(Player::PlayerTwo, &[pt(0, 0), pt(1, 0)])
+            .workers(Player::PlayerThree, &[pt(w - 2, h - 1), pt(w - 1, h - 1)])
+            .build()
+            .unwrap();
+        let pawn = g.active_pawns()[0];
+        let built = g.apply(pawn.can_move(pt(0, h - 2)).unwrap()).unwrap();
+        let build = built.active_pawn().can_build(pt(0, h - 1)).unwrap();
+        let (result, undo) = built.apply_with_undo(build);
+        assert!(!result.unwrap().in_play(Player::PlayerTwo));
+        assert_eq!(result.unapply(undo), built);
+    }
+}
@@ -0,0 +1,284 @@
+//! Zobrist hashing of positions.
+//!
+//! Every feature of a position is assigned a random key, and a position hashes
+//! to the XOR of the keys of its features. The board keeps its part of the
+//! hash up to date as it is built on, and the rest is combined from the few
+//! remaining features whenever a game's hash is asked for.
+
+use crate::santorini::{
+    Board, Build, CoordLevel, Game, GameState, God, Move, PlaceOne, PlaceTwo, Player, Point,
+    Supply, Victory, Workers, BOARD_SQUARES, MAX_PLAYERS, MAX_WORKERS,
+};
+
+const SQUARES: usize = BOARD_SQUARES;
+/// More than the number of gods, leaving room for new ones.
+const GODS: usize = 32;
+/// More than the number of moves or builds a worker can make in a turn.
+const COUNTS: usize = 8;
+/// More than the number of any one building piece in the box.
+const PIECES: usize = 32;
+
+// Offsets of each feature's keys in the table
+const LEVELS: usize = 0;
+const WORKERS: usize = LEVELS + 5 * SQUARES;
+const PLAYERS: usize = WORKERS + MAX_PLAYERS * SQUARES;
+const GOD_KEYS: usize = PLAYERS + MAX_PLAYERS;
+const NO_CLIMBING: usize = GOD_KEYS + MAX_PLAYERS * GODS;
+const PRE_MOVE: usize = NO_CLIMBING + 1;
+const ACTIVE: usize = PRE_MOVE + SQUARES;
+const START: usize = ACTIVE + SQUARES;
+const MOVES: usize = START + SQUARES;
+const MOVED_UP: usize = MOVES + COUNTS;
+const OTHER_MOVED: usize = MOVED_UP + 1;
+const BUILDS: usize = OTHER_MOVED + 1;
+const FIRST_BUILD: usize = BUILDS + COUNTS;
+const ATHENA: usize = FIRST_BUILD + SQUARES;
+const WORKER_COUNT: usize = ATHENA + MAX_PLAYERS;
+const SUPPLY: usize = WORKER_COUNT + MAX_WORKERS + 1;
+const KEYS: usize = SUPPLY + 4 * PIECES;
+
+/// Keys drawn from splitmix64 with a fixed seed, so hashes are stable between
+/// runs.
+const fn key_table() -> [u64; KEYS] {
+    let mut keys = [0; KEYS];
+    let mut state: u64 = 0x5A07_0121_5EED_0001;
+    let mut idx = 0;
+    while idx < KEYS {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[idx] = z ^ (z >> 31);
+        idx += 1;
+    }
+    keys
+}
+
+static KEY_TABLE: [u64; KEYS] = key_table();
+
+fn square(loc: Point) -> usize {
+    (loc.word as usize) * 16 + (loc.nibble as usize) / 4
+}
+
+/// The key for a square being at a level. Ground level has no key, so an
+/// empty board hashes to 0.
+pub(super) fn level_key(loc: Point, level: CoordLevel) -> u64 {
+    match level {
+        CoordLevel::Ground => 0,
+        _ => KEY_TABLE[LEVELS + 5 * square(loc) + i8::from(level) as usize],
+    }
+}
+
+fn worker_keys(workers: Workers) -> u64 {
+    let mut hash = 0;
+    for (seat, locs) in workers.iter().enumerate() {
+        let offset = WORKERS + seat * SQUARES;
+        for loc in locs.iter().flatten() {
+            hash ^= KEY_TABLE[offset + square(*loc)];
+        }
+    }
+    hash
+}
+
+/// The key for the player to act, where Player One has none.
+fn player_key(player: Player) -> u64 {
+    match player {
+        Player::PlayerOne => 0,
+        player => KEY_TABLE[PLAYERS + player.index()],
+    }
+}
+
+fn optional_square_key(offset: usize, loc: Option<Point>) -> u64 {
+    loc.map_or(0, |loc| KEY_TABLE[offset + square(loc)])
+}
+
+fn flag_key(offset: usize, flag: bool) -> u64 {
+    if flag {
+        KEY_TABLE[offset]
+    } else {
+        0
+    }
+}
+
+fn count_key(offset: usize, count: u8) -> u64 {
+    KEY_TABLE[offset + (count as usize).min(COUNTS - 1)]
+}
+
+/// The key for the building pieces left, where an endless supply has none.
+fn supply_key(supply: Option<Supply>) -> u64 {
+    let supply = match supply {
+        Some(supply) => supply,
+        None => return 0,
+    };
+    let levels = [
+        CoordLevel::One,
+        CoordLevel::Two,
+        CoordLevel::Three,
+        CoordLevel::Capped,
+    ];
+    levels.iter().enumerate().fold(0, |hash, (idx, level)| {
+        let left = (supply.left(*level) as usize).min(PIECES - 1);
+        hash ^ KEY_TABLE[SUPPLY + idx * PIECES + left]
+    })
+}
+
+/// The part of a game's hash that describes its state.
+pub trait ZobristState {
+    fn zobrist(&self) -> u64;
+}
+
+impl ZobristState for PlaceOne {
+    fn zobrist(&self) -> u64 {
+        0
+    }
+}
+
+impl ZobristState for PlaceTwo {
+    fn zobrist(&self) -> u64 {
+        worker_keys(self.workers)
+    }
+}
+
+impl ZobristState for Move {
+    fn zobrist(&self) -> u64 {
+        worker_keys(self.workers)
+            ^ flag_key(NO_CLIMBING, self.no_climbing)
+            ^ optional_square_key(PRE_MOVE, self.pre_move)
+            ^ athena_key(self.athena)
+    }
+}
+
+impl ZobristState for Build {
+    fn zobrist(&self) -> u64 {
+        worker_keys(self.workers)
+            ^ KEY_TABLE[ACTIVE + square(self.active_loc)]
+            ^ KEY_TABLE[START + square(self.start)]
+            ^ count_key(MOVES, self.moves)
+            ^ flag_key(MOVED_UP, self.moved_up)
+            ^ flag_key(OTHER_MOVED, self.other_moved)
+            ^ count_key(BUILDS, self.builds)
+            ^ optional_square_key(FIRST_BUILD, self.first_build)
+            ^ athena_key(self.athena)
+    }
+}
+
+impl ZobristState for Victory {
+    fn zobrist(&self) -> u64 {
+        worker_keys(self.workers)
+    }
+}
+
+fn athena_key(athena: Option<Player>) -> u64 {
+    athena.map_or(0, |athena| KEY_TABLE[ATHENA + athena.index()])
+}
+
+fn god_key(player: Player, god: God) -> u64 {
+    KEY_TABLE[GOD_KEYS + player.index() * GODS + god as usize]
+}
+
+/// The key for how many workers each player has, where the usual two have
+/// none.
+fn worker_count_key(count: usize) -> u64 {
+    match count {
+        2 => 0,
+        count => KEY_TABLE[WORKER_COUNT + count],
+    }
+}
+
+impl Board {
+    /// The Zobrist hash of the heights on the board.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl<S: GameState> Game<S> {
+    /// The Zobrist hash of the position. Positions that are equal hash the
+    /// same, and different positions almost never do.
+    pub fn hash(&self) -> u64 {
+        // A third player's god tells three player games apart
+        self.players().fold(self.board.hash(), |hash, player| {
+            hash ^ god_key(player, self.god(player))
+        }) ^ self.state.zobrist()
+            ^ player_key(self.player)
+            ^ worker_count_key(self.worker_count())
+            ^ supply_key(self.board.supply())
+    }
+}
+
+#[cfg(test)]
+mod zobrist_tests {
+    use std::collections::HashSet;
+
+    use crate::santorini::{self, AnyGame, Board, CoordLevel, God, Point, Turn};
+
+    fn pt(x: i8, y: i8) -> Point {
+        Point::new(x.into(), y.into())
+    }
+
+    fn hash(game: AnyGame) -> u64 {
+        match game {
+            AnyGame::PlaceOne(game) => game.hash(),
+            AnyGame::PlaceTwo(game) => game.hash(),
+            AnyGame::Move(game) => game.hash(),
+            AnyGame::Build(game) => game.hash(),
+            AnyGame::Victory(game) => game.hash(),
+        }
+    }
+
+    #[test]
+    fn board_is_incremental() {
+        let mut board = Board::new();
+        assert_eq!(board.hash(), 0);
+
+        board.build(pt(1, 1));
+        board.build(pt(1, 1));
+        board.cap(pt(3, 3));
+        let mut direct = Board::new();
+        direct.set_level(pt(3, 3), CoordLevel::Capped);
+        direct.set_level(pt(1, 1), CoordLevel::Two);
+        assert_eq!(board, direct);
+        assert_eq!(board.hash(), direct.hash());
+
+        direct.set_level(pt(1, 1), CoordLevel::Ground);
+        direct.set_level(pt(3, 3), CoordLevel::Ground);
+        assert_eq!(direct.hash(), 0);
+    }
+
+    #[test]
+    fn transpositions_hash_the_same() {
+        let g = santorini::new_game();
+        let g = g.apply(g.can_place(&[pt(1, 1), pt(3, 3)]).unwrap());
+        let g: AnyGame = g.apply(g.can_place(&[pt(1, 3), pt(3, 1)]).unwrap()).into();
+
+        // Both players step out and back, building on the same squares in a
+        // different order
+        let play = |turns: &[&str]| {
+            turns
+                .iter()
+                .fold(g, |g, turn| g.apply_turn(turn.parse().unwrap()).unwrap())
+        };
+        let a = play(&["b2>c2^c3", "b4>a4^b3", "c2>b2^b1", "a4>b4^a3"]);
+        let b = play(&["b2>c2^b1", "b4>a4^a3", "c2>b2^c3", "a4>b4^b3"]);
+        assert_eq!(a, b);
+        assert_eq!(hash(a), hash(b));
+        assert_ne!(hash(a), hash(g));
+    }
+
+    #[test]
+    fn states_hash_differently() {
+        let mut hashes = HashSet::new();
+        let mut game: AnyGame = santorini::new_game_with_gods([God::Mortal, God::Pan]).into();
+        for turn in ["b2,d4", "b4,d2", "b2>c3^b2", "b4>a4^a3", "c3>c2^c3"].iter() {
+            assert!(hashes.insert(hash(game)));
+            game = game.apply_turn(turn.parse().unwrap()).unwrap();
+        }
+        assert!(hashes.insert(hash(game)));
+        assert!(hashes.insert(hash(game.apply_turn(Turn::Resign).unwrap())));
+
+        let other: AnyGame = santorini::new_game().into();
+        assert!(hashes.insert(hash(other)));
+        let limited: AnyGame = santorini::new_game().with_limited_components().into();
+        assert!(hashes.insert(hash(limited)));
+    }
+}
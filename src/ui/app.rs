@@ -1,37 +1,187 @@
-use std::io;
-use termion::event::{Event, Key};
-use termion::input::TermRead;
+use std::time::Duration;
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 use tui::Frame;
 
-use crate::santorini::{self, Build, Game, GameState, Move, PlaceOne, PlaceTwo, Player, Victory};
+use crate::santorini::{
+    self, ActionResult, AnyGame, Build, Game, GameHistory, GameState, God, Move, PlaceOne,
+    PlaceTwo, Player, Turn, Victory, VictoryReason,
+};
 
+use crate::ui::clock::Clocks;
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::Action;
+use crate::ui::saved::{self, SavedGame, Seat};
+use crate::ui::settings::{self, settings};
+use crate::ui::spectator::Spectator;
 use crate::ui::{
-    self, Back, BoardWidget, Screen, Term, UpdateError, PLAYER_ONE_TEXT_STYLE,
-    PLAYER_TWO_TEXT_STYLE,
+    self, input, theme, worker_locs, Back, BoardWidget, MenuWidget, Screen, Term, UpdateError,
+};
+
+use crate::player::{
+    self, changed_squares, FirstPlayer, FullPlayer, Progress, SearchInfo, StepResult,
 };
 
-use crate::player::{self, FullPlayer, StepResult};
+/// How long to wait for a key between redraws while a player thinks.
+const THINKING_POLL: Duration = Duration::from_millis(100);
+
+/// The frames of the spinner shown while a player thinks, one per poll.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// The spinner's frame once a player has been thinking for `elapsed`.
+fn spinner(elapsed: Duration) -> char {
+    let frame = elapsed.as_millis() / THINKING_POLL.as_millis();
+    SPINNER[frame as usize % SPINNER.len()]
+}
 
 pub struct App<T: GameState> {
     game: Game<T>,
-    player_one: Box<dyn FullPlayer>,
-    player_two: Box<dyn FullPlayer>,
+    /// The players in turn order.
+    players: Vec<Box<dyn FullPlayer>>,
+    /// Who sits in each seat, to save the game with.
+    seats: Vec<Seat>,
+    /// How the seats were put in turn order, to save the game with.
+    first_player: FirstPlayer,
+    /// The turns played, up to the start of the current one.
+    history: GameHistory,
+    /// The last player to announce a forced win, and the plies it takes.
+    claim: Option<(Player, u32)>,
+    /// The last player to search for a turn, and what it found.
+    search: Option<(Player, SearchInfo)>,
+    /// How far the player to act has got, if it's thinking in the
+    /// background.
+    thinking: Option<Progress>,
+    /// The menu over the board: the pause menu while the game is paused, or
+    /// the rematch menu once it's over.
+    paused: Option<MenuWidget<'static>>,
+    /// A message for the players, such as where the game was saved.
+    notice: Option<String>,
+    /// The pacing of a match between AIs, if it's one being watched.
+    spectator: Option<Spectator>,
+    /// The players' clocks, if the game is timed.
+    clocks: Option<Clocks>,
+}
+
+/// The items of the pause menu, in order.
+const PAUSE_ITEMS: [&str; 3] = ["Resume", "Save Game", "Main Menu"];
+
+fn pause_menu() -> MenuWidget<'static> {
+    MenuWidget::new(
+        Span::styled("Paused", Style::default().add_modifier(Modifier::BOLD)).into(),
+        PAUSE_ITEMS.iter().map(|item| Spans::from(*item)).collect(),
+    )
+}
+
+/// The items of the menu once the game is over, in order.
+const VICTORY_ITEMS: [&str; 3] = [
+    "Rematch (swap colors)",
+    "Rematch (same colors)",
+    "Main Menu",
+];
+
+fn victory_menu(winner: Player) -> MenuWidget<'static> {
+    MenuWidget::new(
+        Spans::from(vec![
+            player_name(winner),
+            Span::styled(" wins!", Style::default().add_modifier(Modifier::BOLD)),
+        ]),
+        VICTORY_ITEMS
+            .iter()
+            .map(|item| Spans::from(*item))
+            .collect(),
+    )
+}
+
+/// The states the clocks run in. Placing workers isn't timed.
+trait Clocked: GameState + Sized {
+    const TIMED: bool;
+
+    /// Lose the game on time.
+    fn time_out(game: Game<Self>) -> ActionResult<Move>;
+}
+
+impl Clocked for PlaceOne {
+    const TIMED: bool = false;
+
+    fn time_out(_: Game<PlaceOne>) -> ActionResult<Move> {
+        unreachable!("Placing workers isn't timed!")
+    }
+}
+
+impl Clocked for PlaceTwo {
+    const TIMED: bool = false;
+
+    fn time_out(_: Game<PlaceTwo>) -> ActionResult<Move> {
+        unreachable!("Placing workers isn't timed!")
+    }
+}
+
+impl Clocked for Move {
+    const TIMED: bool = true;
+
+    fn time_out(game: Game<Move>) -> ActionResult<Move> {
+        game.time_out()
+    }
+}
+
+impl Clocked for Build {
+    const TIMED: bool = true;
+
+    fn time_out(game: Game<Build>) -> ActionResult<Move> {
+        game.time_out()
+    }
 }
 
 impl<T: GameState> App<T> {
-    fn current_player_name(&self) -> Span {
-        match self.game.player() {
-            Player::PlayerOne => Span::styled("Player One", PLAYER_ONE_TEXT_STYLE),
-            Player::PlayerTwo => Span::styled("Player Two", PLAYER_TWO_TEXT_STYLE),
+    /// Seat the players for the game, carrying on from the turns in the
+    /// history. The clocks are set from the settings.
+    fn new(game: Game<T>, seats: Vec<Seat>, history: GameHistory) -> Self {
+        let clocks = settings()
+            .time_control
+            .map(|control| Clocks::new(control, seats.len()));
+        App {
+            game,
+            players: seats.iter().map(|seat| seat.player()).collect(),
+            seats,
+            first_player: FirstPlayer::Fixed,
+            history,
+            claim: None,
+            search: None,
+            thinking: None,
+            paused: None,
+            notice: None,
+            spectator: None,
+            clocks,
+        }
+    }
+
+    /// Record how the seats were put in turn order.
+    fn seated(self, first_player: FirstPlayer) -> Self {
+        App {
+            first_player,
+            ..self
         }
     }
 
+    /// Have the player to act ready for the game.
+    fn prepared(mut self) -> Self
+    where
+        dyn FullPlayer: player::Player<T>,
+    {
+        self.players[self.game.player().index()].prepare(&self.game);
+        self
+    }
+
+    fn current_player_name(&self) -> Span {
+        player_name(self.game.player())
+    }
+
     fn do_draw(&self, frame: &mut Frame<Back>, widget: BoardWidget, title: Spans) -> Rect {
-        let border = Block::default().title("Santorini").borders(Borders::ALL);
+        let border = Block::default()
+            .title(format!("Santorini - Turn {}", self.game.turn_number()))
+            .borders(Borders::ALL);
         frame.render_widget(border, frame.size());
 
         let segments = Layout::default()
@@ -40,8 +190,63 @@ impl<T: GameState> App<T> {
             .constraints([Constraint::Min(15), Constraint::Ratio(1, 3)].as_ref())
             .split(frame.size());
 
+        let mut lines = vec![Spans::from(vec![]), title];
+        let players = Player::iter().take(self.players.len());
+        if players
+            .clone()
+            .any(|player| self.game.god(*player) != God::Mortal)
+        {
+            let mut spans = vec![];
+            for player in players {
+                if !spans.is_empty() {
+                    spans.push(Span::raw("   "));
+                }
+                spans.push(player_name(*player));
+                spans.push(Span::raw(format!(": {}", self.game.god(*player).name())));
+            }
+            lines.push(Spans::from(spans));
+        }
+        if let Some(clocks) = &self.clocks {
+            let mut spans = vec![];
+            for player in Player::iter().take(self.players.len()) {
+                if !spans.is_empty() {
+                    spans.push(Span::raw("   "));
+                }
+                spans.push(player_name(*player));
+                spans.push(Span::raw(format!(" {}", clocks.display(*player))));
+            }
+            lines.push(Spans::from(spans));
+        }
+        if let Some((player, plies)) = self.claim {
+            lines.push(Spans::from(vec![
+                player_name(player),
+                Span::raw(format!(" announces a win within {} plies", plies)),
+            ]));
+        }
+        if let Some((player, info)) = self.search.as_ref().filter(|_| settings().show_search) {
+            lines.push(Spans::from(vec![
+                player_name(*player),
+                Span::raw(format!(": {}", info)),
+            ]));
+        }
+        if let Some(progress) = self.thinking {
+            lines.push(Spans::from(vec![
+                self.current_player_name(),
+                Span::raw(format!(
+                    " is thinking {} {}",
+                    spinner(progress.elapsed),
+                    progress
+                )),
+            ]));
+        }
+        if let Some(spectator) = &self.spectator {
+            lines.push(Spans::from(spectator.status()));
+        }
+        if let Some(notice) = &self.notice {
+            lines.push(Spans::from(notice.clone()));
+        }
         frame.render_widget(
-            Paragraph::new(vec![Spans::from(vec![]), title])
+            Paragraph::new(lines)
                 .alignment(Alignment::Center)
                 .wrap(Wrap { trim: false }),
             segments[0],
@@ -49,11 +254,23 @@ impl<T: GameState> App<T> {
         frame.render_widget(widget, segments[0]);
 
         let bold = Style::default().add_modifier(Modifier::BOLD);
+        let keys = settings().keys;
+        let key = |action: Action| Span::styled(keys.key(action).to_string(), bold);
+        let movement: Vec<String> = [Action::Up, Action::Left, Action::Down, Action::Right]
+            .iter()
+            .map(|action| keys.key(*action).to_string())
+            .collect();
+        // Letters read as one word, like WASD
+        let movement = if movement.iter().all(|name| name.chars().count() == 1) {
+            movement.concat().to_uppercase()
+        } else {
+            movement.join("/")
+        };
         let instructions = vec![
             Spans::from(vec![]),
             Spans::from(vec![
                 Span::raw("Use arrow keys or "),
-                Span::styled("WASD", bold),
+                Span::styled(movement, bold),
                 Span::raw(" to move cursor."),
             ]),
             Spans::from(vec![]),
@@ -61,7 +278,7 @@ impl<T: GameState> App<T> {
                 Span::raw("Use "),
                 Span::styled("Enter", bold),
                 Span::raw(" or "),
-                Span::styled("e", bold),
+                key(Action::Select),
                 Span::raw(" to select."),
             ]),
             Spans::from(vec![]),
@@ -69,13 +286,67 @@ impl<T: GameState> App<T> {
                 Span::raw("Use "),
                 Span::styled("Esc", bold),
                 Span::raw(" or "),
-                Span::styled("q", bold),
+                key(Action::Deselect),
                 Span::raw(" to deselect."),
             ]),
             Spans::from(vec![]),
             Spans::from(vec![
                 Span::raw("Use "),
-                Span::styled("F6", bold),
+                key(Action::MoveAgain),
+                Span::raw(" to move again, if your god allows it."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Pass),
+                Span::raw(" to skip an optional build."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Dome),
+                Span::raw(" to build a dome, if your god allows it."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::BuildFirst),
+                Span::raw(" to build before moving, if your god allows it."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Force),
+                Span::raw(" to force an opponent's worker before moving, if your god allows it."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Hint),
+                Span::raw(" for a hint."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Undo),
+                Span::raw(" to take back your turn."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Pause),
+                Span::raw(" to pause, save or leave the game."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                Span::styled("Space", bold),
+                Span::raw(" to have a thinking computer move now."),
+            ]),
+            Spans::from(vec![]),
+            Spans::from(vec![
+                Span::raw("Use "),
+                key(Action::Resign),
                 Span::raw(" to resign."),
             ]),
             Spans::from(vec![]),
@@ -93,38 +364,295 @@ impl<T: GameState> App<T> {
             segments[1],
         );
 
+        if let Some(menu) = &self.paused {
+            let menu_rect = centered(segments[0], 24, 9);
+            let block = Block::default().borders(Borders::ALL);
+            let menu_area = block.inner(menu_rect);
+            frame.render_widget(Clear, menu_rect);
+            frame.render_widget(block, menu_rect);
+            frame.render_widget(menu.clone(), menu_area);
+        }
+
         segments[0]
     }
 
     fn transition<U>(mut self, game: Game<U>) -> App<U>
+    where
+        T: Copy,
+        U: GameState + Copy,
+        dyn FullPlayer: player::Player<U>,
+        AnyGame: From<Game<T>> + From<Game<U>>,
+    {
+        if let Some(spectator) = &mut self.spectator {
+            spectator.applied(changed_squares(self.game.into(), game.into()));
+        }
+        if game.player() != self.game.player() {
+            self.record(game.into());
+            if let Some(clocks) = &mut self.clocks {
+                clocks.press(self.game.player());
+            }
+        }
+        self.resume(game)
+    }
+
+    /// Carry on from the game, with the player to act ready for it.
+    fn resume<U>(mut self, game: Game<U>) -> App<U>
     where
         U: GameState,
         dyn FullPlayer: player::Player<U>,
     {
-        match game.player() {
-            Player::PlayerOne => self.player_one.prepare(&game),
-            Player::PlayerTwo => self.player_two.prepare(&game),
-        };
+        self.players[game.player().index()].prepare(&game);
 
         App {
             game,
-            player_one: self.player_one,
-            player_two: self.player_two,
+            players: self.players,
+            seats: self.seats,
+            first_player: self.first_player,
+            history: self.history,
+            claim: self.claim,
+            search: self.search,
+            thinking: None,
+            paused: None,
+            notice: None,
+            spectator: self.spectator,
+            clocks: self.clocks,
         }
     }
+
+    /// Finish the game, with the clocks stopped where they are.
+    fn finish(mut self, game: Game<Victory>) -> App<Victory> {
+        if let Some(clocks) = &mut self.clocks {
+            clocks.stop();
+        }
+        App {
+            game,
+            players: self.players,
+            seats: self.seats,
+            first_player: self.first_player,
+            history: self.history,
+            claim: self.claim,
+            search: self.search,
+            thinking: None,
+            paused: None,
+            notice: None,
+            spectator: None,
+            clocks: self.clocks,
+        }
+    }
+
+    /// End the turn of a player who has run out of time. Losing on time
+    /// isn't a turn, so the history stays as it was.
+    fn time_out(self) -> Box<dyn Screen>
+    where
+        T: Clocked + Copy,
+        AnyGame: From<Game<T>>,
+    {
+        match T::time_out(self.game) {
+            ActionResult::Victory(game) => Box::new(self.finish(game)),
+            ActionResult::Continue(game) => Box::new(self.transition::<Move>(game)),
+        }
+    }
+
+    /// Add the turn just finished, which led to `next`, to the history.
+    fn record(&mut self, next: AnyGame) {
+        let turn = match next {
+            // Resigning part way through a turn takes back what was done
+            AnyGame::Victory(game) if matches!(game.reason(), VictoryReason::Resignation) => {
+                Some(Turn::Resign)
+            }
+            _ => self.history.current().turn_to(next),
+        };
+        match turn.and_then(|turn| self.history.push(turn)) {
+            Some(_) => (),
+            // Turns that can't be written down can't be taken back either
+            None => {
+                self.history = GameHistory::new(next);
+                if let Some(clocks) = &mut self.clocks {
+                    clocks.forget_turns();
+                }
+            }
+        }
+    }
+
+    /// Take back the turn in progress, or if it has only just started,
+    /// every turn back to the start of the player's previous one. The clocks
+    /// go back with the turns, though time spent on the turn in progress
+    /// stays spent.
+    fn undo(mut self, current: AnyGame) -> Box<dyn Screen> {
+        let player = current.player();
+        let target = if current == self.history.current() {
+            self.history
+                .iter()
+                .filter(|(_, _, game)| game.player() == player)
+                .map(|(ply, _, _)| ply)
+                .last()
+                .unwrap_or_else(|| self.history.len())
+        } else {
+            self.history.len()
+        };
+        while self.history.len() > target {
+            self.history.undo();
+            if let Some(clocks) = &mut self.clocks {
+                clocks.take_back();
+            }
+        }
+
+        self.claim = None;
+        self.search = None;
+        match self.history.current() {
+            AnyGame::PlaceOne(game) => Box::new(self.resume(game)),
+            AnyGame::PlaceTwo(game) => Box::new(self.resume(game)),
+            AnyGame::Move(game) => Box::new(self.resume(game)),
+            AnyGame::Build(game) => Box::new(self.resume(game)),
+            AnyGame::Victory(_) => unreachable!("Finished games aren't taken back!"),
+        }
+    }
+
+    /// Handle a key while the pause menu is up. Games are saved as of the
+    /// start of the current turn.
+    fn update_paused(mut self: Box<Self>) -> Result<Box<dyn Screen>, UpdateError>
+    where
+        T: 'static,
+        App<T>: Screen,
+    {
+        let mut menu = self.paused.take().expect("Not paused!");
+        if let Some(clocks) = &mut self.clocks {
+            clocks.stop();
+        }
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => {
+                self.paused = Some(menu);
+                return Ok(self);
+            }
+        };
+        match (key, settings::action(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (_, Some(Action::Deselect)) | (_, Some(Action::Pause)) => return Ok(self),
+            (_, Some(Action::Select)) => match PAUSE_ITEMS[menu.selected()] {
+                "Save Game" => {
+                    let game = SavedGame {
+                        seats: self.seats.clone(),
+                        first_player: self.first_player,
+                        record: self.history.record().clone(),
+                    };
+                    self.notice = Some(match saved::save(&game) {
+                        Ok(path) => format!("Game saved to {}", path.display()),
+                        Err(err) => format!("Couldn't save the game: {}", err),
+                    });
+                    return Ok(self);
+                }
+                "Main Menu" => return Ok(ui::main_menu()),
+                _ => return Ok(self),
+            },
+            (_, Some(Action::Up)) => menu.move_up(),
+            (_, Some(Action::Down)) => menu.move_down(),
+            _ => (),
+        }
+        self.paused = Some(menu);
+        Ok(self)
+    }
+}
+
+/// A rectangle of the size given in the middle of `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
 }
 
+/// Put a player's question to them in a box over the board.
+fn draw_prompt(frame: &mut Frame<Back>, area: Rect, prompt: &str) {
+    let prompt_rect = centered(area, prompt.len() as u16 + 4, 3);
+    frame.render_widget(Clear, prompt_rect);
+    frame.render_widget(
+        Paragraph::new(prompt)
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(Alignment::Center),
+        prompt_rect,
+    );
+}
+
+pub fn player_name(player: Player) -> Span<'static> {
+    let name = match player {
+        Player::PlayerOne => "Player One",
+        Player::PlayerTwo => "Player Two",
+        Player::PlayerThree => "Player Three",
+    };
+    Span::styled(name, theme().player(player).text)
+}
+
+/// Play a two player game, with the seats put in turn order as
+/// `first_player` says.
 pub fn new_app(
-    player_one: Box<dyn FullPlayer>,
-    player_two: Box<dyn FullPlayer>,
+    player_one: Seat,
+    player_two: Seat,
+    gods: [God; 2],
+    first_player: FirstPlayer,
+) -> Box<dyn Screen> {
+    let game = santorini::new_game_with_gods(gods);
+    let seats = vec![player_one, player_two];
+    Box::new(App::new(game, seats, GameHistory::new(game.into())).seated(first_player))
+}
+
+pub fn new_three_player_app(
+    player_one: Seat,
+    player_two: Seat,
+    player_three: Seat,
+    gods: [God; 3],
 ) -> Box<dyn Screen> {
+    let game = santorini::new_three_player_game(gods);
+    Box::new(App::new(
+        game,
+        vec![player_one, player_two, player_three],
+        GameHistory::new(game.into()),
+    ))
+}
+
+/// Watch two AIs play each other, with the seats put in turn order as
+/// `first_player` says.
+pub fn new_match(
+    player_one: Seat,
+    player_two: Seat,
+    gods: [God; 2],
+    first_player: FirstPlayer,
+) -> Box<dyn Screen> {
+    let game = santorini::new_game_with_gods(gods);
+    let seats = vec![player_one, player_two];
     Box::new(App {
-        game: santorini::new_game(),
-        player_one,
-        player_two,
+        players: seats.iter().map(|seat| seat.watched_player()).collect(),
+        spectator: Some(Spectator::new(ui::ai_delay())),
+        ..App::new(game, seats, GameHistory::new(game.into())).seated(first_player)
     })
 }
 
+/// Play on from a position set up by hand.
+pub fn position_app(game: Game<Move>, seats: Vec<Seat>) -> Box<dyn Screen> {
+    Box::new(App::new(game, seats, GameHistory::new(game.into())).prepared())
+}
+
+/// Pick a saved game back up where it was left.
+pub fn load_app(saved: SavedGame) -> Box<dyn Screen> {
+    let history = GameHistory::from(saved.record);
+    let (seats, first) = (saved.seats, saved.first_player);
+    match history.current() {
+        AnyGame::PlaceOne(game) => {
+            Box::new(App::new(game, seats, history).seated(first).prepared())
+        }
+        AnyGame::PlaceTwo(game) => {
+            Box::new(App::new(game, seats, history).seated(first).prepared())
+        }
+        AnyGame::Move(game) => Box::new(App::new(game, seats, history).seated(first).prepared()),
+        AnyGame::Build(game) => Box::new(App::new(game, seats, history).seated(first).prepared()),
+        AnyGame::Victory(game) => Box::new(App::new(game, seats, history).seated(first)),
+    }
+}
+
 macro_rules! standard_state {
     ($state:ty, $title: literal) => {
         impl Screen for App<$state> {
@@ -132,37 +660,139 @@ macro_rules! standard_state {
                 mut self: Box<Self>,
                 terminal: &mut Term,
             ) -> Result<Box<dyn Screen>, UpdateError> {
-                let active_player = match self.game.player() {
-                    Player::PlayerOne => &self.player_one,
-                    Player::PlayerTwo => &self.player_two,
-                };
+                let active_player = &self.players[self.game.player().index()];
 
                 terminal.draw(|f| {
-                    self.do_draw(
+                    let widget = active_player.render(&self.game);
+                    let widget = match &self.spectator {
+                        Some(spectator) if !spectator.changed.is_empty() => BoardWidget {
+                            highlights: &spectator.changed,
+                            ..widget
+                        },
+                        _ => widget,
+                    };
+                    let game_rect = self.do_draw(
                         f,
-                        active_player.render(&self.game),
+                        widget,
                         Spans::from(vec![
                             self.current_player_name(),
                             Span::raw(concat!(" to ", $title)),
                         ]),
                     );
+                    if let Some(prompt) = player::Player::<$state>::prompt(&**active_player) {
+                        draw_prompt(f, game_rect, prompt);
+                    }
                 })?;
 
-                let active_player = match self.game.player() {
-                    Player::PlayerOne => &mut self.player_one,
-                    Player::PlayerTwo => &mut self.player_two,
+                if self.paused.is_some() {
+                    return self.update_paused();
+                }
+
+                let player = self.game.player();
+                if let Some(clocks) = self.clocks.as_mut().filter(|_| <$state>::TIMED) {
+                    if clocks.flagged(player) {
+                        return Ok(self.time_out());
+                    }
+                    clocks.start(player);
+                    // Keep the clocks ticking on screen while a human thinks
+                    if self.seats[player.index()] == Seat::Human
+                        && !input::wait_event(THINKING_POLL)?
+                    {
+                        return Ok(self);
+                    }
+                }
+                let active_player = &mut self.players[player.index()];
+
+                let pending = self
+                    .spectator
+                    .as_mut()
+                    .and_then(|spectator| spectator.pending.take());
+                let result = match pending {
+                    Some(result) => result,
+                    None => active_player.step(&self.game)?,
                 };
+                if let Some(plies) = player::Player::<$state>::claim(&**active_player) {
+                    self.claim = Some((player, plies));
+                }
+                if let Some(info) = player::Player::<$state>::search_info(&**active_player) {
+                    self.search = Some((player, info));
+                }
+                self.thinking = None;
 
-                match active_player.step(&self.game)? {
-                    StepResult::NoMove => Ok(self),
+                // Hold the step back until the spectator is ready for it
+                if let Some(spectator) = &mut self.spectator {
+                    let step = matches!(
+                        result,
+                        StepResult::PlaceTwo(_)
+                            | StepResult::Move(_)
+                            | StepResult::Build(_)
+                            | StepResult::Victory(_)
+                    );
+                    if step && !spectator.ready() {
+                        spectator.pending = Some(result);
+                        let wait = spectator.wait(THINKING_POLL);
+                        return match input::poll_event(wait)? {
+                            Some(Event::Key(Key::Ctrl('c'))) => Err(UpdateError::Shutdown),
+                            Some(Event::Key(key))
+                                if settings::action(key) == Some(Action::Pause) =>
+                            {
+                                self.paused = Some(pause_menu());
+                                Ok(self)
+                            }
+                            Some(Event::Key(key)) => {
+                                spectator.handle(key);
+                                Ok(self)
+                            }
+                            _ => Ok(self),
+                        };
+                    }
+                }
+
+                match result {
+                    StepResult::NoMove if self.seats[player.index()] == Seat::Human => Ok(self),
+                    // The computer is still thinking, or still showing its step
+                    StepResult::NoMove | StepResult::InProgress(_) => {
+                        if let StepResult::InProgress(progress) = result {
+                            self.thinking = Some(progress);
+                        }
+                        match input::poll_event(THINKING_POLL)? {
+                            Some(Event::Key(Key::Ctrl('c'))) => Err(UpdateError::Shutdown),
+                            Some(Event::Key(key))
+                                if self
+                                    .spectator
+                                    .as_mut()
+                                    .map_or(false, |spectator| spectator.handle(key)) =>
+                            {
+                                Ok(self)
+                            }
+                            Some(Event::Key(Key::Char(' '))) => {
+                                player::Player::<$state>::stop(&**active_player);
+                                Ok(self)
+                            }
+                            Some(Event::Key(key))
+                                if settings::action(key) == Some(Action::Pause) =>
+                            {
+                                self.paused = Some(pause_menu());
+                                Ok(self)
+                            }
+                            _ => Ok(self),
+                        }
+                    }
                     StepResult::PlaceTwo(game) => Ok(Box::new(self.transition(game))),
                     StepResult::Move(game) => Ok(Box::new(self.transition(game))),
                     StepResult::Build(game) => Ok(Box::new(self.transition(game))),
-                    StepResult::Victory(game) => Ok(Box::new(App {
-                        game,
-                        player_one: self.player_one,
-                        player_two: self.player_two,
-                    })),
+                    StepResult::Victory(game) => {
+                        self.record(game.into());
+                        Ok(Box::new(self.finish(game)))
+                    }
+                    StepResult::Undo => {
+                        let current = self.game.into();
+                        Ok(self.undo(current))
+                    }
+                    StepResult::Pause => {
+                        self.paused = Some(pause_menu());
+                        Ok(self)
+                    }
                 }
             }
         }
@@ -174,8 +804,68 @@ standard_state!(PlaceTwo, "place");
 standard_state!(Move, "move");
 standard_state!(Build, "build");
 
+/// Start a game over from `game`, with the players already seated.
+fn restart<T>(
+    game: Game<T>,
+    players: Vec<Box<dyn FullPlayer>>,
+    seats: Vec<Seat>,
+    first_player: FirstPlayer,
+    spectator: Option<Spectator>,
+) -> Box<App<T>>
+where
+    T: GameState + Copy,
+    dyn FullPlayer: player::Player<T>,
+    AnyGame: From<Game<T>>,
+{
+    let history = GameHistory::new(game.into());
+    let app = App {
+        players,
+        spectator,
+        ..App::new(game, seats, history).seated(first_player)
+    };
+    Box::new(app.prepared())
+}
+
+impl App<Victory> {
+    /// Play the game again from the same start with the same players, who
+    /// keep their seats or move one seat along, taking their gods with them.
+    fn rematch(self, swap: bool) -> Box<dyn Screen> {
+        let mut players = self.players;
+        let mut seats = self.seats;
+        let first = self.first_player;
+        let shift = usize::from(swap);
+        players.rotate_left(shift);
+        seats.rotate_left(shift);
+        // Only matches being watched are between AIs alone
+        let spectator = if seats.contains(&Seat::Human) {
+            None
+        } else {
+            Some(Spectator::new(ui::ai_delay()))
+        };
+
+        match self.history.initial() {
+            AnyGame::PlaceOne(game) => {
+                restart(game.rotate_gods(shift), players, seats, first, spectator)
+            }
+            AnyGame::PlaceTwo(game) => {
+                restart(game.rotate_gods(shift), players, seats, first, spectator)
+            }
+            AnyGame::Move(game) => {
+                restart(game.rotate_gods(shift), players, seats, first, spectator)
+            }
+            AnyGame::Build(game) => {
+                restart(game.rotate_gods(shift), players, seats, first, spectator)
+            }
+            AnyGame::Victory(_) => ui::main_menu(),
+        }
+    }
+}
+
 impl Screen for App<Victory> {
-    fn update(self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+    fn update(mut self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        let winner = self.game.player();
+        let mut menu = self.paused.take().unwrap_or_else(|| victory_menu(winner));
+        self.paused = Some(menu.clone());
         terminal.draw(|f| {
             let widget = BoardWidget {
                 board: self.game.board(),
@@ -183,60 +873,62 @@ impl Screen for App<Victory> {
                 cursor: None,
 
                 highlights: &vec![],
-                player1_locs: self
-                    .game
-                    .player_pawns(santorini::Player::PlayerOne)
-                    .iter()
-                    .map(|pawn| pawn.pos())
-                    .collect(),
-                player2_locs: self
-                    .game
-                    .player_pawns(santorini::Player::PlayerTwo)
-                    .iter()
-                    .map(|pawn| pawn.pos())
-                    .collect(),
+                suggestions: vec![],
+                player1_locs: worker_locs(&self.game, Player::PlayerOne),
+                player2_locs: worker_locs(&self.game, Player::PlayerTwo),
+                player3_locs: worker_locs(&self.game, Player::PlayerThree),
             };
-            let game_rect = self.do_draw(f, widget, Spans::from(vec![]));
-            let announce_width = 20;
-            let announce_height = 7;
-            let x_off = (game_rect.width - announce_width) / 2;
-            let y_off = (game_rect.height - announce_height) / 2;
-            let announce_rect = Rect::new(
-                game_rect.x + x_off,
-                game_rect.y + y_off,
-                announce_width,
-                announce_height,
-            );
-            f.render_widget(Clear, announce_rect);
-
-            let text = vec![
+            let reason = match self.game.reason() {
+                VictoryReason::ReachedLevelThree { .. } => "Reached level three",
+                VictoryReason::OpponentStalemated { .. } => "Opponent stalemated",
+                VictoryReason::Resignation => "Opponent resigned",
+                VictoryReason::Timeout => "Opponent timed out",
+            };
+            self.do_draw(
+                f,
+                widget,
                 Spans::from(vec![
                     self.current_player_name(),
-                    Span::styled(" wins!", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(": "),
+                    Span::raw(reason),
                 ]),
-                Spans::from(vec![]),
-                Spans::from(vec![]),
-                Spans::from(Span::raw("Press any key to continue...")),
-            ];
-            f.render_widget(
-                Paragraph::new(text)
-                    .block(Block::default().borders(Borders::ALL))
-                    .alignment(Alignment::Center)
-                    .wrap(Wrap { trim: false }),
-                announce_rect,
             );
         })?;
 
-        if let Some(event) = io::stdin().events().next() {
-            match event? {
-                Event::Key(Key::Ctrl('c')) | Event::Key(Key::Char('q')) | Event::Key(Key::Esc) => {
-                    Err(UpdateError::Shutdown)
-                }
-                Event::Key(_) => Ok(ui::main_menu()),
-                _ => Ok(self),
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => {
+                self.paused = Some(menu);
+                return Ok(self);
             }
-        } else {
-            Ok(self)
+        };
+        match (key, settings::action(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (_, Some(Action::Deselect)) => return Ok(ui::main_menu()),
+            (_, Some(Action::Select)) => {
+                return match VICTORY_ITEMS[menu.selected()] {
+                    "Rematch (swap colors)" => Ok(self.rematch(true)),
+                    "Rematch (same colors)" => Ok(self.rematch(false)),
+                    _ => Ok(ui::main_menu()),
+                };
+            }
+            (_, Some(Action::Up)) => menu.move_up(),
+            (_, Some(Action::Down)) => menu.move_down(),
+            _ => (),
         }
+        self.paused = Some(menu);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod app_tests {
+    use super::*;
+
+    #[test]
+    fn spinner_turns_once_a_poll() {
+        let frames: String = (0..6).map(|poll| spinner(THINKING_POLL * poll)).collect();
+        assert_eq!(frames, "|/-\\|/");
+        assert_eq!(spinner(THINKING_POLL / 2), '|');
     }
 }
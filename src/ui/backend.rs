@@ -0,0 +1,142 @@
+use std::io;
+use tui::backend::{Backend, TestBackend};
+use tui::buffer::{Buffer, Cell};
+use tui::layout::Rect;
+
+#[cfg(feature = "crossterm")]
+use tui::backend::CrosstermBackend;
+#[cfg(feature = "termion")]
+use {
+    termion::input::MouseTerminal,
+    termion::raw::{IntoRawMode, RawTerminal},
+    tui::backend::TermionBackend,
+};
+
+#[cfg(not(any(feature = "termion", feature = "crossterm")))]
+compile_error!("Enable a terminal backend, with the termion or crossterm feature.");
+
+/// Stdout in raw mode, which it leaves once dropped.
+#[cfg(feature = "crossterm")]
+pub struct RawStdout(io::Stdout);
+
+#[cfg(feature = "crossterm")]
+impl io::Write for RawStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "crossterm")]
+impl Drop for RawStdout {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Draws either to the real terminal or, for headless replays, to an
+/// in-memory buffer.
+pub enum Back {
+    #[cfg(feature = "termion")]
+    Termion(TermionBackend<MouseTerminal<RawTerminal<io::Stdout>>>),
+    #[cfg(feature = "crossterm")]
+    Crossterm(CrosstermBackend<RawStdout>),
+    Test(TestBackend),
+}
+
+impl Back {
+    /// The real terminal, through crossterm if it was built in and termion
+    /// otherwise.
+    pub fn terminal() -> io::Result<Back> {
+        #[cfg(feature = "crossterm")]
+        return Back::crossterm();
+        #[cfg(not(feature = "crossterm"))]
+        return Back::termion();
+    }
+
+    #[cfg(feature = "termion")]
+    pub fn termion() -> io::Result<Back> {
+        let stdout = MouseTerminal::from(io::stdout().into_raw_mode()?);
+        Ok(Back::Termion(TermionBackend::new(stdout)))
+    }
+
+    #[cfg(feature = "crossterm")]
+    pub fn crossterm() -> io::Result<Back> {
+        crossterm::terminal::enable_raw_mode().map_err(io::Error::other)?;
+        Ok(Back::Crossterm(CrosstermBackend::new(RawStdout(
+            io::stdout(),
+        ))))
+    }
+
+    pub fn test(width: u16, height: u16) -> Back {
+        Back::Test(TestBackend::new(width, height))
+    }
+
+    /// Change the size of a test backend. The real terminal can only be
+    /// resized by the user, so this does nothing for it.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if let Back::Test(_) = self {
+            *self = Back::test(width, height);
+        }
+    }
+
+    /// The contents of a test backend.
+    pub fn buffer(&self) -> Option<&Buffer> {
+        match self {
+            Back::Test(backend) => Some(backend.buffer()),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! delegate {
+    ($self:ident, $backend:ident => $call:expr) => {
+        match $self {
+            #[cfg(feature = "termion")]
+            Back::Termion($backend) => $call,
+            #[cfg(feature = "crossterm")]
+            Back::Crossterm($backend) => $call,
+            Back::Test($backend) => $call,
+        }
+    };
+}
+
+impl Backend for Back {
+    fn draw<'a, I>(&mut self, content: I) -> Result<(), io::Error>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        delegate!(self, backend => backend.draw(content))
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), io::Error> {
+        delegate!(self, backend => backend.hide_cursor())
+    }
+
+    fn show_cursor(&mut self) -> Result<(), io::Error> {
+        delegate!(self, backend => backend.show_cursor())
+    }
+
+    fn get_cursor(&mut self) -> Result<(u16, u16), io::Error> {
+        delegate!(self, backend => backend.get_cursor())
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        delegate!(self, backend => backend.set_cursor(x, y))
+    }
+
+    fn clear(&mut self) -> Result<(), io::Error> {
+        delegate!(self, backend => backend.clear())
+    }
+
+    fn size(&self) -> Result<Rect, io::Error> {
+        delegate!(self, backend => backend.size())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        delegate!(self, backend => backend.flush())
+    }
+}
@@ -1,119 +1,443 @@
 use tui::buffer::Buffer;
 use tui::layout::Rect;
-use tui::style::Style;
+use tui::style::{Modifier, Style};
 use tui::widgets::{Block, Borders, Clear, Widget};
 
-use crate::santorini::{Board, Coord, CoordLevel, Player, Point, BOARD_HEIGHT, BOARD_WIDTH};
-
-use crate::ui::{
-    BoundsWidget, CAPPED_STYLE, GROUND_LEVEL_STYLE, LEVEL_ONE_STYLE, LEVEL_THREE_STYLE,
-    LEVEL_TWO_STYLE, PLAYER_ONE_CURSOR_STYLE, PLAYER_ONE_HIGHLIGHT_STYLE, PLAYER_ONE_STYLE,
-    PLAYER_TWO_CURSOR_STYLE, PLAYER_TWO_HIGHLIGHT_STYLE, PLAYER_TWO_STYLE,
+use crate::santorini::{
+    Board, Coord, CoordLevel, Game, GameState, NormalState, PlaceTwo, Player, Point, WorkerId,
+    BOARD_HEIGHT, BOARD_WIDTH,
 };
 
+use crate::ui::{theme, BoundsWidget, Theme};
+
 pub struct BoardWidget<'a> {
     pub board: Board,
 
     pub player: Player,
     pub cursor: Option<Point>,
     pub highlights: &'a Vec<Point>,
+    /// Squares of a turn suggested to the player, drawn over the highlights.
+    pub suggestions: Vec<Point>,
 
     pub player1_locs: Vec<Point>,
     pub player2_locs: Vec<Point>,
+    pub player3_locs: Vec<Point>,
+}
+
+/// Where a player has placed their workers, or nowhere if they have yet to.
+pub fn placed_locs(game: &Game<PlaceTwo>, player: Player) -> Vec<Point> {
+    game.placed(player)
+        .iter()
+        .flat_map(|locs| locs.to_vec())
+        .collect()
+}
+
+/// Where a player's workers stand, or nowhere if they are not in the game.
+pub fn worker_locs<T: GameState + NormalState>(game: &Game<T>, player: Player) -> Vec<Point> {
+    game.workers(player)
+        .iter()
+        .flat_map(|locs| locs.to_vec())
+        .collect()
 }
 
 const SQUARE_SIZE: u16 = 5;
-const BOARD_WIDGET_WIDTH: u16 = (BOARD_WIDTH.0 as u16) * SQUARE_SIZE;
-const BOARD_WIDGET_HEIGHT: u16 = (BOARD_HEIGHT.0 as u16) * SQUARE_SIZE;
+/// The squares of the compact board, drawn without borders when the full
+/// board doesn't fit.
+const COMPACT_SQUARE_WIDTH: u16 = 3;
+const COMPACT_SQUARE_HEIGHT: u16 = 2;
+
+/// Room for the rank numbers left of the board.
+const RANK_LABEL_WIDTH: u16 = 2;
+/// Room for the file letters below the board.
+const FILE_LABEL_HEIGHT: u16 = 1;
+const LABELED_WIDTH: u16 = (BOARD_WIDTH.0 as u16) * SQUARE_SIZE + RANK_LABEL_WIDTH;
+const LABELED_HEIGHT: u16 = (BOARD_HEIGHT.0 as u16) * SQUARE_SIZE + FILE_LABEL_HEIGHT;
+const COMPACT_WIDTH: u16 = (BOARD_WIDTH.0 as u16) * COMPACT_SQUARE_WIDTH + RANK_LABEL_WIDTH;
+const COMPACT_HEIGHT: u16 = (BOARD_HEIGHT.0 as u16) * COMPACT_SQUARE_HEIGHT + FILE_LABEL_HEIGHT;
+
+/// What fills a square built up to the level, denser the higher it stands.
+fn level_glyph(level: CoordLevel) -> &'static str {
+    match level {
+        CoordLevel::Ground => " ",
+        CoordLevel::One => "░",
+        CoordLevel::Two => "▒",
+        CoordLevel::Three | CoordLevel::Capped => "▓",
+    }
+}
+
+/// Drawn in the middle of a capped square.
+const DOME: &str = "●";
 
 impl<'a> BoardWidget<'a> {
-    fn style(&self, point: Point) -> Style {
-        for p in &self.player1_locs {
-            if point == *p {
-                return PLAYER_ONE_STYLE;
-            }
-        }
+    /// The worker standing on a square, if there is one.
+    fn worker_on(&self, point: Point) -> Option<WorkerId> {
+        let players = [&self.player1_locs, &self.player2_locs, &self.player3_locs];
+        Player::iter()
+            .zip(players.iter())
+            .filter_map(|(player, locs)| {
+                let index = locs.iter().position(|loc| *loc == point)?;
+                Some(WorkerId::new(*player, index))
+            })
+            .next()
+    }
 
-        for p in &self.player2_locs {
-            if point == *p {
-                return PLAYER_TWO_STYLE;
-            }
+    fn style(&self, theme: &Theme, point: Point) -> Style {
+        match self.worker_on(point) {
+            Some(worker) => theme.player(worker.player()).worker,
+            None => theme.level(self.board.level_at(point)),
         }
+    }
 
-        match self.board.level_at(point) {
-            CoordLevel::Ground => GROUND_LEVEL_STYLE,
-            CoordLevel::One => LEVEL_ONE_STYLE,
-            CoordLevel::Two => LEVEL_TWO_STYLE,
-            CoordLevel::Three => LEVEL_THREE_STYLE,
-            CoordLevel::Capped => CAPPED_STYLE,
+    /// Letter the worker on a square, if there is one, the way the notation
+    /// does, so a player can tell their workers apart by more than color.
+    fn render_worker(&self, point: Point, x: u16, y: u16, buf: &mut Buffer) {
+        if let Some(worker) = self.worker_on(point) {
+            buf.set_string(
+                x,
+                y,
+                ((b'A' + worker.index() as u8) as char).to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            );
         }
     }
 
-    fn border_style(&self, point: Point) -> Option<Style> {
+    fn border_style(&self, theme: &Theme, point: Point) -> Option<Style> {
         if Some(point) == self.cursor {
-            if self.player == Player::PlayerOne {
-                return Some(PLAYER_ONE_CURSOR_STYLE);
-            } else {
-                return Some(PLAYER_TWO_CURSOR_STYLE);
-            }
+            return Some(theme.player(self.player).cursor);
         }
 
-        for p in self.highlights {
-            if *p == point {
-                if self.player == Player::PlayerOne {
-                    return Some(PLAYER_ONE_HIGHLIGHT_STYLE);
-                } else {
-                    return Some(PLAYER_TWO_HIGHLIGHT_STYLE);
-                }
-            }
+        if self.suggestions.contains(&point) {
+            return Some(theme.suggestion);
+        }
+
+        if self.highlights.contains(&point) {
+            return Some(theme.player(self.player).highlight);
         }
 
         None
     }
+
+    /// Draw a square of the full board, with a border.
+    fn render_square(&self, theme: &Theme, point: Point, area: Rect, buf: &mut Buffer) {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .style(self.style(theme, point));
+        if let Some(style) = self.border_style(theme, point) {
+            block = block.border_style(style);
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        // Fill the inside by height, with a dome in the middle once the
+        // square is capped
+        let level = self.board.level_at(point);
+        for row in inner.top()..inner.bottom() {
+            buf.set_string(
+                inner.left(),
+                row,
+                level_glyph(level).repeat(inner.width as usize),
+                Style::default(),
+            );
+        }
+        if level == CoordLevel::Capped {
+            buf.set_string(
+                inner.left() + inner.width / 2,
+                inner.top() + inner.height / 2,
+                DOME,
+                Style::default(),
+            );
+        }
+        self.render_worker(
+            point,
+            inner.left() + inner.width / 2,
+            inner.top() + inner.height / 2,
+            buf,
+        );
+    }
+
+    /// Draw a square of the compact board: its top row filled by height,
+    /// with any worker in the middle, and its bottom row in the border's
+    /// style where it has one.
+    fn render_compact_square(&self, theme: &Theme, point: Point, area: Rect, buf: &mut Buffer) {
+        let level = self.board.level_at(point);
+        let style = self.style(theme, point);
+        let fill = level_glyph(level).repeat(area.width as usize);
+        buf.set_string(area.left(), area.top(), &fill, style);
+        if level == CoordLevel::Capped {
+            buf.set_string(area.left() + area.width / 2, area.top(), DOME, style);
+        }
+        self.render_worker(point, area.left() + area.width / 2, area.top(), buf);
+        let bottom = self.border_style(theme, point).unwrap_or(style);
+        buf.set_string(area.left(), area.top() + 1, &fill, bottom);
+    }
 }
 
 impl<'a> Widget for BoardWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        if area.width < BOARD_WIDGET_WIDTH || area.height < BOARD_WIDGET_HEIGHT {
+        let compact = area.width < LABELED_WIDTH || area.height < LABELED_HEIGHT;
+        let (square_width, square_height, width, height) = if compact {
+            (
+                COMPACT_SQUARE_WIDTH,
+                COMPACT_SQUARE_HEIGHT,
+                COMPACT_WIDTH,
+                COMPACT_HEIGHT,
+            )
+        } else {
+            (SQUARE_SIZE, SQUARE_SIZE, LABELED_WIDTH, LABELED_HEIGHT)
+        };
+        if area.width < width || area.height < height {
             BoundsWidget {
-                min_width: BOARD_WIDGET_WIDTH,
-                min_height: BOARD_WIDGET_HEIGHT,
+                min_width: width,
+                min_height: height,
             }
             .render(area, buf);
             return;
         }
 
-        let left = area.left() + (area.width - BOARD_WIDGET_WIDTH) / 2;
-        let top = area.top() + (area.height - BOARD_WIDGET_HEIGHT) / 2;
-        Clear.render(
-            Rect::new(left, top, BOARD_WIDGET_WIDTH, BOARD_WIDGET_HEIGHT),
-            buf,
-        );
+        let left = area.left() + (area.width - width) / 2;
+        let top = area.top() + (area.height - height) / 2;
+        Clear.render(Rect::new(left, top, width, height), buf);
+
+        // Label the ranks and files the way the notation names squares
+        for y in 0..BOARD_HEIGHT.0 as u16 {
+            buf.set_string(
+                left,
+                top + y * square_height + square_height / 2,
+                format!("{}", y + 1),
+                Style::default(),
+            );
+        }
+        for x in 0..BOARD_WIDTH.0 as u16 {
+            buf.set_string(
+                left + RANK_LABEL_WIDTH + x * square_width + square_width / 2,
+                top + height - FILE_LABEL_HEIGHT,
+                format!("{}", (b'a' + x as u8) as char),
+                Style::default(),
+            );
+        }
+        let left = left + RANK_LABEL_WIDTH;
+        let theme = theme();
 
         for x in 0..BOARD_WIDTH.0 as u16 {
             for y in 0..BOARD_HEIGHT.0 as u16 {
                 let area = Rect {
-                    x: left + x * SQUARE_SIZE,
-                    y: top + y * SQUARE_SIZE,
-                    width: SQUARE_SIZE,
-                    height: SQUARE_SIZE,
+                    x: left + x * square_width,
+                    y: top + y * square_height,
+                    width: square_width,
+                    height: square_height,
                 };
                 let point = Point::new(Coord::from(x as i8), Coord::from(y as i8));
-                let mut block = Block::default()
-                    .borders(Borders::ALL)
-                    .style(self.style(point));
-                if let Some(style) = self.border_style(point) {
-                    block = block.border_style(style);
+                if compact {
+                    self.render_compact_square(&theme, point, area, buf);
+                } else {
+                    self.render_square(&theme, point, area, buf);
                 }
-                block.render(area, buf);
-
-                buf.set_string(
-                    area.left() + (area.width / 2),
-                    area.top() + (area.height / 2),
-                    format!("{}", i8::from(self.board.level_at(point))),
-                    Style::default(),
-                );
             }
         }
     }
 }
+
+#[cfg(test)]
+mod board_tests {
+    use super::*;
+    use crate::santorini;
+
+    #[test]
+    fn coordinate_labels() {
+        let game = santorini::new_game();
+        let widget = BoardWidget {
+            board: game.board(),
+            player: game.player(),
+            cursor: None,
+            highlights: &vec![],
+            suggestions: vec![],
+            player1_locs: vec![],
+            player2_locs: vec![],
+            player3_locs: vec![],
+        };
+        let area = Rect::new(0, 0, LABELED_WIDTH, LABELED_HEIGHT);
+        let mut buf = Buffer::empty(area);
+        widget.render(area, &mut buf);
+
+        let row = |y: u16| -> String {
+            (0..area.width)
+                .map(|x| buf.get(x, y).symbol.clone())
+                .collect()
+        };
+        let files: String = (0..BOARD_WIDTH.0)
+            .map(|x| format!("    {}", (b'a' + x as u8) as char))
+            .collect();
+        assert_eq!(row(LABELED_HEIGHT - 1), files + "  ");
+        assert!(row(2).starts_with("1 "), "{}", row(2));
+        let last = (BOARD_HEIGHT.0 as u16 - 1) * SQUARE_SIZE + 2;
+        let rank = format!("{} ", BOARD_HEIGHT.0);
+        assert!(row(last).starts_with(&rank), "{}", row(last));
+
+        // The squares sit right of the ranks, with nothing built in the
+        // bottom right corner
+        assert_eq!(buf.get(RANK_LABEL_WIDTH, 0).symbol, "┌");
+        assert_eq!(buf.get(LABELED_WIDTH - 3, last).symbol, " ");
+    }
+
+    /// The levels in order along the top row from a1, with a dome on a2.
+    fn levels_board() -> Board {
+        let mut heights = [[0; BOARD_WIDTH.0 as usize]; BOARD_HEIGHT.0 as usize];
+        heights[0][..4].copy_from_slice(&[0, 1, 2, 3]);
+        heights[1][0] = 4;
+        Board::from_heights(heights).unwrap()
+    }
+
+    #[test]
+    fn levels() {
+        let board = levels_board();
+        let widget = BoardWidget {
+            board,
+            player: santorini::Player::PlayerOne,
+            cursor: None,
+            highlights: &vec![],
+            suggestions: vec![],
+            player1_locs: vec![],
+            player2_locs: vec![],
+            player3_locs: vec![],
+        };
+        let area = Rect::new(0, 0, LABELED_WIDTH, LABELED_HEIGHT);
+        let mut buf = Buffer::empty(area);
+        widget.render(area, &mut buf);
+
+        // The inside of the squares with levels on them, along the rank a
+        // row of the buffer is in
+        let inside = |row: u16| -> String {
+            (0..4)
+                .map(|x| {
+                    let left = RANK_LABEL_WIDTH + x * SQUARE_SIZE + 1;
+                    (left..left + 3)
+                        .map(|col| buf.get(col, row).symbol.clone())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+        assert_eq!(inside(1), "   |░░░|▒▒▒|▓▓▓");
+        assert_eq!(inside(2), "   |░░░|▒▒▒|▓▓▓");
+        assert!(inside(SQUARE_SIZE + 1).starts_with("▓▓▓|"));
+        assert!(inside(SQUARE_SIZE + 2).starts_with("▓●▓|"));
+    }
+
+    #[test]
+    fn compact() {
+        let board = levels_board();
+        let highlights = vec![];
+        let widget = || BoardWidget {
+            board,
+            player: santorini::Player::PlayerOne,
+            cursor: Some("a1".parse().unwrap()),
+            highlights: &highlights,
+            suggestions: vec![],
+            player1_locs: vec![],
+            player2_locs: vec![],
+            player3_locs: vec![],
+        };
+        let render = |width: u16, height: u16| {
+            let area = Rect::new(0, 0, width, height);
+            let mut buf = Buffer::empty(area);
+            widget().render(area, &mut buf);
+            buf
+        };
+        let row = |buf: &Buffer, y: u16| -> String {
+            (0..buf.area().width)
+                .map(|x| buf.get(x, y).symbol.clone())
+                .collect()
+        };
+
+        // Too small for the full board, the squares shrink to 3x2
+        let buf = render(LABELED_WIDTH, LABELED_HEIGHT - 1);
+        assert_eq!(
+            *buf.area(),
+            Rect::new(0, 0, LABELED_WIDTH, LABELED_HEIGHT - 1)
+        );
+        let top = (LABELED_HEIGHT - 1 - COMPACT_HEIGHT) / 2;
+        let margin = " ".repeat((LABELED_WIDTH - COMPACT_WIDTH) as usize / 2);
+        assert_eq!(
+            row(&buf, top).trim_end(),
+            format!("{}     ░░░▒▒▒▓▓▓", margin)
+        );
+        assert_eq!(row(&buf, top + 1).trim(), "1    ░░░▒▒▒▓▓▓");
+        assert_eq!(row(&buf, top + 2).trim(), "▓●▓");
+        assert_eq!(row(&buf, top + 3).trim(), "2 ▓▓▓");
+        let files: Vec<_> = (0..BOARD_WIDTH.0)
+            .map(|x| ((b'a' + x as u8) as char).to_string())
+            .collect();
+        assert_eq!(row(&buf, top + COMPACT_HEIGHT - 1).trim(), files.join("  "));
+        let cursor = buf.get(
+            (LABELED_WIDTH - COMPACT_WIDTH) / 2 + RANK_LABEL_WIDTH,
+            top + 1,
+        );
+        assert_eq!(
+            cursor.bg,
+            theme()
+                .player(santorini::Player::PlayerOne)
+                .cursor
+                .bg
+                .unwrap()
+        );
+
+        // The smallest compact board still fits, and anything smaller asks
+        // for more room
+        let buf = render(COMPACT_WIDTH, COMPACT_HEIGHT);
+        assert_eq!(row(&buf, 0).trim_end(), "     ░░░▒▒▒▓▓▓");
+        let buf = render(COMPACT_WIDTH - 1, COMPACT_HEIGHT);
+        assert!(row(&buf, 0).contains("Expand"), "{}", row(&buf, 0));
+    }
+
+    #[test]
+    fn worker_letters() {
+        let game = santorini::new_game();
+        let square = |name: &str| name.parse::<Point>().unwrap();
+        let corner = Point::new(Coord(BOARD_WIDTH.0 - 1), Coord(BOARD_HEIGHT.0 - 1));
+        let highlights = vec![];
+        let widget = || BoardWidget {
+            board: game.board(),
+            player: game.player(),
+            cursor: None,
+            highlights: &highlights,
+            suggestions: vec![],
+            player1_locs: vec![square("b2"), square("d3")],
+            player2_locs: vec![corner, square("a1")],
+            player3_locs: vec![],
+        };
+        let render = |width: u16, height: u16| {
+            let area = Rect::new(0, 0, width, height);
+            let mut buf = Buffer::empty(area);
+            widget().render(area, &mut buf);
+            buf
+        };
+
+        // Workers are lettered by their place in their player's list, in
+        // the middle of their square
+        let buf = render(LABELED_WIDTH, LABELED_HEIGHT);
+        let center = |x: u16, y: u16| {
+            let x = RANK_LABEL_WIDTH + x * SQUARE_SIZE + SQUARE_SIZE / 2;
+            buf.get(x, y * SQUARE_SIZE + SQUARE_SIZE / 2).symbol.clone()
+        };
+        assert_eq!(center(1, 1), "A");
+        assert_eq!(center(3, 2), "B");
+        let (right, bottom) = (BOARD_WIDTH.0 as u16 - 1, BOARD_HEIGHT.0 as u16 - 1);
+        assert_eq!(center(right, bottom), "A");
+        assert_eq!(center(0, 0), "B");
+        assert_eq!(center(2, 2), " ");
+
+        // The compact board puts them in the middle of each square's top row
+        let buf = render(COMPACT_WIDTH, COMPACT_HEIGHT);
+        let center = |x: u16, y: u16| {
+            let x = RANK_LABEL_WIDTH + x * COMPACT_SQUARE_WIDTH + COMPACT_SQUARE_WIDTH / 2;
+            buf.get(x, y * COMPACT_SQUARE_HEIGHT).symbol.clone()
+        };
+        assert_eq!(center(1, 1), "A");
+        assert_eq!(center(0, 0), "B");
+        assert_eq!(center(2, 2), " ");
+        assert_eq!(
+            buf.get(RANK_LABEL_WIDTH + 4, 3).bg,
+            theme().player(Player::PlayerOne).worker.bg.unwrap()
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::santorini::Player;
+
+/// How long each player has for the game, as minutes on the clock to start
+/// with and seconds added after each of their turns, written like `5+3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub const fn new(minutes: u64, increment_secs: u64) -> Self {
+        TimeControl {
+            base: Duration::from_secs(minutes * 60),
+            increment: Duration::from_secs(increment_secs),
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.base.as_secs() / 60,
+            self.increment.as_secs()
+        )
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected minutes+seconds: {}", s);
+        let (minutes, increment) = s.split_once('+').ok_or_else(invalid)?;
+        let minutes = minutes.trim().parse().map_err(|_| invalid())?;
+        let increment = increment.trim().parse().map_err(|_| invalid())?;
+        Ok(TimeControl::new(minutes, increment))
+    }
+}
+
+/// The players' chess clocks. Only the clock of the player whose turn it is
+/// runs, and it stops while the game is paused.
+pub struct Clocks {
+    increment: Duration,
+    /// Each player's time left, as of when their clock last stopped.
+    remaining: Vec<Duration>,
+    /// The player whose clock is running, and since when.
+    running: Option<(Player, Instant)>,
+    /// Everyone's time left at the start of each turn pressed so far, to go
+    /// back to when turns are taken back.
+    turns: Vec<Vec<Duration>>,
+    /// Everyone's time left at the start of the turn in progress.
+    turn_start: Vec<Duration>,
+}
+
+impl Clocks {
+    /// Clocks for the players in a game, each set to the time control.
+    pub fn new(control: TimeControl, players: usize) -> Self {
+        Clocks {
+            increment: control.increment,
+            remaining: vec![control.base; players],
+            running: None,
+            turns: vec![],
+            turn_start: vec![control.base; players],
+        }
+    }
+
+    /// A player's time left.
+    pub fn remaining(&self, player: Player) -> Duration {
+        let remaining = self.remaining[player.index()];
+        match self.running {
+            Some((running, since)) if running == player => {
+                remaining.saturating_sub(since.elapsed())
+            }
+            _ => remaining,
+        }
+    }
+
+    /// Whether a player has run out of time.
+    pub fn flagged(&self, player: Player) -> bool {
+        self.remaining(player) == Duration::from_secs(0)
+    }
+
+    /// Run a player's clock, stopping any other.
+    pub fn start(&mut self, player: Player) {
+        if !matches!(self.running, Some((running, _)) if running == player) {
+            self.stop();
+            self.running = Some((player, Instant::now()));
+        }
+    }
+
+    /// Stop whichever clock is running.
+    pub fn stop(&mut self) {
+        if let Some((player, _)) = self.running {
+            self.remaining[player.index()] = self.remaining(player);
+            self.running = None;
+        }
+    }
+
+    /// End a player's turn, stopping their clock and adding the increment
+    /// if it was running.
+    pub fn press(&mut self, player: Player) {
+        if matches!(self.running, Some((running, _)) if running == player) {
+            self.stop();
+            self.remaining[player.index()] += self.increment;
+        }
+        let start = self.remaining.clone();
+        self.turns.push(mem::replace(&mut self.turn_start, start));
+    }
+
+    /// Take back the last turn pressed, setting every clock to where it was
+    /// when that turn started. Turns from before the clocks were set up, such
+    /// as those of a loaded game, leave the clocks as they are.
+    pub fn take_back(&mut self) {
+        if let Some(start) = self.turns.pop() {
+            self.running = None;
+            self.remaining = start.clone();
+            self.turn_start = start;
+        }
+    }
+
+    /// Forget the turns pressed so far, which can no longer be taken back.
+    pub fn forget_turns(&mut self) {
+        self.stop();
+        self.turns.clear();
+        self.turn_start = self.remaining.clone();
+    }
+
+    /// A player's time left, as minutes and seconds.
+    pub fn display(&self, player: Player) -> String {
+        let secs = self.remaining(player).as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+
+    #[test]
+    fn time_controls() {
+        let control: TimeControl = "5+3".parse().unwrap();
+        assert_eq!(control, TimeControl::new(5, 3));
+        assert_eq!(control.base, Duration::from_secs(300));
+        assert_eq!(control.to_string(), "5+3");
+        assert!("5".parse::<TimeControl>().is_err());
+        assert!("five+3".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn running() {
+        let mut clocks = Clocks::new(TimeControl::new(1, 2), 2);
+        assert_eq!(clocks.display(Player::PlayerOne), "1:00");
+
+        // Only a clock that ran gets the increment
+        clocks.press(Player::PlayerOne);
+        assert_eq!(clocks.display(Player::PlayerOne), "1:00");
+        clocks.start(Player::PlayerOne);
+        clocks.press(Player::PlayerOne);
+        assert_eq!(clocks.display(Player::PlayerOne), "1:01");
+        assert!(clocks.remaining(Player::PlayerOne) > Duration::from_secs(61));
+
+        clocks.start(Player::PlayerTwo);
+        assert!(clocks.remaining(Player::PlayerTwo) <= Duration::from_secs(60));
+        assert!(!clocks.flagged(Player::PlayerTwo));
+
+        let mut clocks = Clocks::new(TimeControl::new(0, 5), 2);
+        assert!(clocks.flagged(Player::PlayerOne));
+        clocks.start(Player::PlayerOne);
+        clocks.press(Player::PlayerOne);
+        assert!(!clocks.flagged(Player::PlayerOne));
+        assert!(clocks.flagged(Player::PlayerTwo));
+    }
+
+    #[test]
+    fn taking_back() {
+        let mut clocks = Clocks::new(TimeControl::new(1, 2), 2);
+        clocks.start(Player::PlayerOne);
+        clocks.press(Player::PlayerOne);
+        clocks.start(Player::PlayerTwo);
+        clocks.press(Player::PlayerTwo);
+        assert_eq!(clocks.display(Player::PlayerTwo), "1:01");
+
+        // Each turn taken back puts both clocks back to its start
+        clocks.start(Player::PlayerOne);
+        clocks.take_back();
+        assert_eq!(clocks.display(Player::PlayerOne), "1:01");
+        assert_eq!(clocks.display(Player::PlayerTwo), "1:00");
+        clocks.take_back();
+        assert_eq!(clocks.remaining(Player::PlayerOne), Duration::from_secs(60));
+
+        // With nothing left to take back the clocks stay put
+        clocks.take_back();
+        assert_eq!(clocks.remaining(Player::PlayerOne), Duration::from_secs(60));
+
+        clocks.start(Player::PlayerOne);
+        clocks.press(Player::PlayerOne);
+        clocks.forget_turns();
+        clocks.take_back();
+        assert_eq!(clocks.display(Player::PlayerOne), "1:01");
+    }
+}
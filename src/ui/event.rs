@@ -0,0 +1,310 @@
+//! Terminal events, whichever backend read them.
+//!
+//! Events are also read back from the bytes a terminal sends for them, the
+//! way recordings store them, so sessions recorded with one backend replay
+//! the same with the other.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A key the UI can respond to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Backspace,
+    BackTab,
+    Esc,
+    F(u8),
+}
+
+/// The keys named rather than written as the character they type.
+const NAMED_KEYS: [(Key, &str); 16] = [
+    (Key::Char('\n'), "Enter"),
+    (Key::Char(' '), "Space"),
+    (Key::Char('\t'), "Tab"),
+    (Key::Up, "Up"),
+    (Key::Down, "Down"),
+    (Key::Left, "Left"),
+    (Key::Right, "Right"),
+    (Key::Home, "Home"),
+    (Key::End, "End"),
+    (Key::PageUp, "PageUp"),
+    (Key::PageDown, "PageDown"),
+    (Key::Insert, "Insert"),
+    (Key::Delete, "Delete"),
+    (Key::Backspace, "Backspace"),
+    (Key::BackTab, "BackTab"),
+    (Key::Esc, "Esc"),
+];
+
+impl fmt::Display for Key {
+    /// The key's name, as the config file and the instructions give it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((_, name)) = NAMED_KEYS.iter().find(|(key, _)| key == self) {
+            return write!(f, "{}", name);
+        }
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Ctrl(c) => write!(f, "Ctrl-{}", c),
+            Key::Alt(c) => write!(f, "Alt-{}", c),
+            Key::F(n) => write!(f, "F{}", n),
+            _ => unreachable!("Every other key is named!"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Read a key's name as `Display` writes it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((key, _)) = NAMED_KEYS.iter().find(|(_, name)| *name == s) {
+            return Ok(*key);
+        }
+        let unknown = || format!("unknown key: {}", s);
+        if let Some(c) = s.strip_prefix("Ctrl-").and_then(single_char) {
+            return Ok(Key::Ctrl(c));
+        }
+        if let Some(c) = s.strip_prefix("Alt-").and_then(single_char) {
+            return Ok(Key::Alt(c));
+        }
+        if let Some(n) = s.strip_prefix('F').filter(|n| !n.is_empty()) {
+            return n.parse().map(Key::F).map_err(|_| unknown());
+        }
+        single_char(s).map(Key::Char).ok_or_else(unknown)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    /// Anything else, such as the mouse.
+    Unsupported(Vec<u8>),
+}
+
+#[cfg(feature = "crossterm")]
+impl Event {
+    /// The event crossterm read, if it's one the UI can respond to.
+    pub fn from_crossterm(event: crossterm::event::Event) -> Option<Event> {
+        use crossterm::event::{KeyCode, KeyModifiers};
+
+        let key = match event {
+            crossterm::event::Event::Key(key) => key,
+            _ => return None,
+        };
+        let key = match key.code {
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::ALT) => Key::Alt(c),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Null => return None,
+        };
+        Some(Event::Key(key))
+    }
+}
+
+/// The key a CSI sequence ending in `~` stands for, by its number.
+fn tilde_key(number: u8) -> Option<Key> {
+    let key = match number {
+        1 | 7 => Key::Home,
+        2 => Key::Insert,
+        3 => Key::Delete,
+        4 | 8 => Key::End,
+        5 => Key::PageUp,
+        6 => Key::PageDown,
+        11..=15 => Key::F(number - 10),
+        17..=21 => Key::F(number - 11),
+        23 | 24 => Key::F(number - 12),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The number of a CSI sequence ending in `~` for the key, if it has one.
+fn tilde_number(key: Key) -> Option<u8> {
+    let number = match key {
+        Key::Insert => 2,
+        Key::Delete => 3,
+        Key::PageUp => 5,
+        Key::PageDown => 6,
+        Key::F(n @ 5) => n + 10,
+        Key::F(n @ 6..=10) => n + 11,
+        Key::F(n @ 11..=12) => n + 12,
+        _ => return None,
+    };
+    Some(number)
+}
+
+/// The event a terminal sent these bytes for.
+pub fn parse(bytes: &[u8]) -> Event {
+    let unsupported = || Event::Unsupported(bytes.to_vec());
+    let key = match bytes {
+        [] => return unsupported(),
+        [0x1b] => Key::Esc,
+        [0x1b, b'O', c @ b'P'..=b'S'] => Key::F(1 + c - b'P'),
+        [0x1b, b'[', b'[', c @ b'A'..=b'E'] => Key::F(1 + c - b'A'),
+        [0x1b, b'[', b'A'] => Key::Up,
+        [0x1b, b'[', b'B'] => Key::Down,
+        [0x1b, b'[', b'C'] => Key::Right,
+        [0x1b, b'[', b'D'] => Key::Left,
+        [0x1b, b'[', b'H'] => Key::Home,
+        [0x1b, b'[', b'F'] => Key::End,
+        [0x1b, b'[', b'Z'] => Key::BackTab,
+        [0x1b, b'[', number @ .., b'~'] => {
+            let number = std::str::from_utf8(number)
+                .ok()
+                .and_then(|number| number.parse().ok());
+            match number.and_then(tilde_key) {
+                Some(key) => key,
+                None => return unsupported(),
+            }
+        }
+        [0x1b, rest @ ..] => match std::str::from_utf8(rest).ok().and_then(single_char) {
+            Some(c) => Key::Alt(c),
+            None => return unsupported(),
+        },
+        [b'\n'] | [b'\r'] => Key::Char('\n'),
+        [b'\t'] => Key::Char('\t'),
+        [0x7f] => Key::Backspace,
+        [c @ 0x01..=0x1a] => Key::Ctrl((c - 0x01 + b'a') as char),
+        [c @ 0x1c..=0x1f] => Key::Ctrl((c - 0x1c + b'4') as char),
+        _ => match std::str::from_utf8(bytes).ok().and_then(single_char) {
+            Some(c) => Key::Char(c),
+            None => return unsupported(),
+        },
+    };
+    Event::Key(key)
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// The bytes a terminal sends for the event, as `parse` reads them.
+pub fn encode(event: &Event) -> Vec<u8> {
+    let key = match event {
+        Event::Key(key) => *key,
+        Event::Unsupported(bytes) => return bytes.clone(),
+    };
+    let csi = |end: &[u8]| [b"\x1b[".as_ref(), end].concat();
+    match key {
+        Key::Char('\n') => vec![b'\n'],
+        Key::Char(c) => c.to_string().into_bytes(),
+        Key::Ctrl(c @ 'a'..='z') => vec![c as u8 - b'a' + 0x01],
+        Key::Ctrl(c @ '4'..='7') => vec![c as u8 - b'4' + 0x1c],
+        Key::Ctrl(c) => c.to_string().into_bytes(),
+        Key::Alt(c) => [vec![0x1b], c.to_string().into_bytes()].concat(),
+        Key::Up => csi(b"A"),
+        Key::Down => csi(b"B"),
+        Key::Right => csi(b"C"),
+        Key::Left => csi(b"D"),
+        Key::Home => csi(b"H"),
+        Key::End => csi(b"F"),
+        Key::BackTab => csi(b"Z"),
+        Key::Backspace => vec![0x7f],
+        Key::Esc => vec![0x1b],
+        Key::F(n @ 1..=4) => vec![0x1b, b'O', b'P' + n - 1],
+        key => match tilde_number(key) {
+            Some(number) => csi(format!("{}~", number).as_bytes()),
+            None => vec![],
+        },
+    }
+}
+
+#[cfg(test)]
+mod event_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let keys = [
+            Key::Char('e'),
+            Key::Char('\n'),
+            Key::Char('é'),
+            Key::Ctrl('c'),
+            Key::Alt('x'),
+            Key::Up,
+            Key::Left,
+            Key::Home,
+            Key::PageDown,
+            Key::Delete,
+            Key::Backspace,
+            Key::BackTab,
+            Key::Esc,
+            Key::F(2),
+            Key::F(5),
+            Key::F(6),
+            Key::F(12),
+        ];
+        for key in keys.iter() {
+            let event = Event::Key(*key);
+            assert_eq!(parse(&encode(&event)), event, "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn names() {
+        let keys = [
+            Key::Char('w'),
+            Key::Char('F'),
+            Key::Char('\n'),
+            Key::Char(' '),
+            Key::Ctrl('x'),
+            Key::Alt('-'),
+            Key::PageUp,
+            Key::Esc,
+            Key::F(6),
+        ];
+        for key in keys.iter() {
+            assert_eq!(key.to_string().parse(), Ok(*key), "{:?}", key);
+        }
+        assert_eq!(Key::F(2).to_string(), "F2");
+        assert_eq!(Key::Char(' ').to_string(), "Space");
+        assert!("Fx".parse::<Key>().is_err());
+        assert!("Escape".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn terminal_bytes() {
+        assert_eq!(parse(b"\x1b[A"), Event::Key(Key::Up));
+        assert_eq!(parse(b"\x1bOQ"), Event::Key(Key::F(2)));
+        assert_eq!(parse(b"\x1b[17~"), Event::Key(Key::F(6)));
+        assert_eq!(parse(b"\r"), Event::Key(Key::Char('\n')));
+        assert_eq!(parse(b"\x03"), Event::Key(Key::Ctrl('c')));
+        assert_eq!(
+            parse(b"\x1b[<0;3;4M"),
+            Event::Unsupported(b"\x1b[<0;3;4M".to_vec())
+        );
+    }
+}
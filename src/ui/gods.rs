@@ -0,0 +1,161 @@
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use tui::layout::{Constraint, Direction, Layout, Margin};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders};
+
+use crate::santorini::{God, Player};
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::Action;
+use crate::ui::settings;
+use crate::ui::{self, input, player_name, theme, MenuWidget, Screen, Seat, Term, UpdateError};
+
+/// Starts the game once every player has a god, given in turn order.
+type StartGame = Box<dyn FnOnce(Vec<God>) -> Box<dyn Screen>>;
+
+/// Choose each player's god before the game starts, in a column a player.
+struct GodScreen {
+    /// Each player's choices, with the one under the cursor chosen.
+    columns: Vec<MenuWidget<'static>>,
+    /// The column being chosen in.
+    column: usize,
+    start: StartGame,
+}
+
+/// Choose gods for the players in the seats given, then start the game.
+pub fn god_screen(
+    seats: &[Seat],
+    start: impl FnOnce(Vec<God>) -> Box<dyn Screen> + 'static,
+) -> Box<dyn Screen> {
+    let columns = Player::iter()
+        .zip(seats)
+        .map(|(player, seat)| {
+            let title = Spans::from(vec![
+                player_name(*player),
+                Span::raw(format!(" ({})", seat.name())),
+            ]);
+            let mut items: Vec<Spans> = God::all()
+                .map(|god| match god {
+                    God::Mortal => Spans::from("None"),
+                    god => Spans::from(god.name()),
+                })
+                .collect();
+            items.push(Spans::from("Random"));
+            MenuWidget::new(title, items)
+        })
+        .collect();
+    Box::new(GodScreen {
+        columns,
+        column: 0,
+        start: Box::new(start),
+    })
+}
+
+/// The god at a place in the list, or None for a random one.
+fn choice(index: usize) -> Option<God> {
+    God::all().nth(index)
+}
+
+/// Settle the gods chosen at random, each a god that no other player has.
+fn resolve<R: Rng>(choices: &[Option<God>], rng: &mut R) -> Vec<God> {
+    let mut gods: Vec<God> = choices.iter().flatten().copied().collect();
+    choices
+        .iter()
+        .map(|choice| match choice {
+            Some(god) => *god,
+            None => {
+                let god = God::all()
+                    .filter(|god| *god != God::Mortal && !gods.contains(god))
+                    .choose(rng)
+                    .unwrap_or(God::Apollo);
+                gods.push(god);
+                god
+            }
+        })
+        .collect()
+}
+
+impl Screen for GodScreen {
+    fn update(mut self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        terminal.draw(|f| {
+            let border = Block::default()
+                .title("Santorini - Choose Gods")
+                .borders(Borders::ALL);
+            f.render_widget(border, f.size());
+
+            let area = f.size().inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            });
+            let count = self.columns.len() as u32;
+            let constraints: Vec<Constraint> =
+                (0..count).map(|_| Constraint::Ratio(1, count)).collect();
+            let segments = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(constraints)
+                .split(area);
+            for (idx, (player, menu)) in Player::iter().zip(&self.columns).enumerate() {
+                let mut block = Block::default().borders(Borders::ALL);
+                if idx == self.column {
+                    block = block.border_style(theme().player(*player).text);
+                }
+                let inner = block.inner(segments[idx]);
+                f.render_widget(block, segments[idx]);
+                f.render_widget(menu.clone(), inner);
+            }
+        })?;
+
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(self),
+        };
+        let last = self.columns.len() - 1;
+        match (key, settings::action(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (_, Some(Action::Deselect)) => return Ok(ui::main_menu()),
+            (_, Some(Action::Select)) => {
+                let choices: Vec<Option<God>> = self
+                    .columns
+                    .iter()
+                    .map(|menu| choice(menu.selected()))
+                    .collect();
+                return Ok((self.start)(resolve(&choices, &mut rand::thread_rng())));
+            }
+            (_, Some(Action::Up)) => self.columns[self.column].move_up(),
+            (_, Some(Action::Down)) => self.columns[self.column].move_down(),
+            (_, Some(Action::Left)) => self.column = self.column.saturating_sub(1),
+            (_, Some(Action::Right)) => self.column = (self.column + 1).min(last),
+            (Key::Char('\t'), _) => self.column = (self.column + 1) % (last + 1),
+            _ => (),
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod gods_tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn choosing() {
+        assert_eq!(choice(0), Some(God::Mortal));
+        assert_eq!(choice(1), Some(God::Apollo));
+        assert_eq!(choice(God::all().count()), None);
+
+        // Random gods are real ones, and never a god already taken
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let gods = resolve(&[Some(God::Atlas), None, None], &mut rng);
+            assert_eq!(gods[0], God::Atlas);
+            assert!(!gods[1..].contains(&God::Mortal), "{:?}", gods);
+            assert!(!gods[1..].contains(&God::Atlas), "{:?}", gods);
+            assert_ne!(gods[1], gods[2]);
+        }
+        assert_eq!(
+            resolve(&[Some(God::Mortal), Some(God::Pan)], &mut rng),
+            vec![God::Mortal, God::Pan]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::Local;
+use std::any::Any;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, Location};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
+
+use crate::ui::Term;
+
+static INSTALL_HOOK: Once = Once::new();
+/// Whether a guard currently owns the terminal.
+static GUARDED: AtomicBool = AtomicBool::new(false);
+/// A panic message waiting for the terminal to be restored.
+static PANIC_MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// The file panics are logged to while the terminal is in use.
+pub fn panic_log_path() -> PathBuf {
+    env::temp_dir().join("santorini-ai-panic.log")
+}
+
+fn describe(payload: &(dyn Any + Send), location: Option<&Location>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    match location {
+        Some(location) => format!("panicked at '{}', {}", message, location),
+        None => format!("panicked at '{}'", message),
+    }
+}
+
+fn log_panic(message: &str) -> io::Result<()> {
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(panic_log_path())?;
+    writeln!(log, "{} {}", Local::now().to_rfc3339(), message)
+}
+
+/// Owns the terminal while the UI runs, making sure it is put back the way
+/// it was found even if the UI panics.
+///
+/// While a guard is alive, panics are logged to `panic_log_path()` and their
+/// message is held back until the guard has been dropped and the terminal is
+/// usable again, instead of being printed into the raw mode screen.
+pub struct TerminalGuard {
+    terminal: Option<Term>,
+}
+
+impl TerminalGuard {
+    pub fn new(terminal: Term) -> Self {
+        INSTALL_HOOK.call_once(|| {
+            let default_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if !GUARDED.load(Ordering::SeqCst) {
+                    return default_hook(info);
+                }
+
+                let message = describe(info.payload(), info.location());
+                let _ = log_panic(&message);
+                *PANIC_MESSAGE.lock().unwrap_or_else(|err| err.into_inner()) = Some(message);
+            }));
+        });
+        GUARDED.store(true, Ordering::SeqCst);
+
+        TerminalGuard {
+            terminal: Some(terminal),
+        }
+    }
+}
+
+impl Deref for TerminalGuard {
+    type Target = Term;
+
+    fn deref(&self) -> &Term {
+        self.terminal.as_ref().expect("Terminal already restored!")
+    }
+}
+
+impl DerefMut for TerminalGuard {
+    fn deref_mut(&mut self) -> &mut Term {
+        self.terminal.as_mut().expect("Terminal already restored!")
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Some(mut terminal) = self.terminal.take() {
+            let _ = terminal.show_cursor();
+            // Dropping the backend disables mouse capture and leaves raw mode
+            drop(terminal);
+        }
+        let _ = io::stdout().flush();
+        GUARDED.store(false, Ordering::SeqCst);
+
+        let message = PANIC_MESSAGE
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(message) = message {
+            eprintln!("{}", message);
+            eprintln!("(also logged to {})", panic_log_path().display());
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! The source of terminal events for the UI.
+//!
+//! Events normally come straight from stdin, but a session can also be
+//! recorded to a file with `--record` and replayed later, headlessly against
+//! a `TestBackend`, with `--replay` or to exercise interactive flows in
+//! tests.
+//!
+//! A recording has one entry per line, each starting with the milliseconds
+//! since the session started:
+//!
+//! ```text
+//! 0 resize 80 24
+//! 1520 event 1b5b41
+//! ```
+//!
+//! Events are stored as the raw bytes read from the terminal, in hex, or
+//! for backends that don't give the bytes, as `event::encode` writes them.
+//!
+//! The terminal is read on a thread of its own, so the UI can wait for an
+//! event for a while without blocking for good.
+
+use crate::ui::event::{self, Event};
+use crate::ui::UpdateError;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    /// The raw bytes of a terminal event.
+    Event(Vec<u8>),
+    Resize(u16, u16),
+}
+
+/// A recorded session.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Recording {
+    pub records: Vec<(Duration, Record)>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// The terminal size at the start of the session, if it was recorded.
+    pub fn initial_size(&self) -> Option<(u16, u16)> {
+        match self.records.first() {
+            Some((_, Record::Resize(width, height))) => Some((*width, *height)),
+            _ => None,
+        }
+    }
+}
+
+fn format_record(f: &mut dyn fmt::Write, time: Duration, record: &Record) -> fmt::Result {
+    write!(f, "{} ", time.as_millis())?;
+    match record {
+        Record::Event(bytes) => {
+            write!(f, "event ")?;
+            for byte in bytes {
+                write!(f, "{:02x}", byte)?;
+            }
+            writeln!(f)
+        }
+        Record::Resize(width, height) => writeln!(f, "resize {} {}", width, height),
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (time, record) in &self.records {
+            format_record(f, *time, record)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recording {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut records = vec![];
+        for (idx, line) in s.lines().enumerate() {
+            let invalid = || format!("invalid record on line {}: {}", idx + 1, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let record = match fields[..] {
+                [] => continue,
+                // Only hex digits, which are one byte each, are sliced apart
+                [_, "event", bytes]
+                    if bytes.len() % 2 == 0
+                        && !bytes.is_empty()
+                        && bytes.bytes().all(|byte| byte.is_ascii_hexdigit()) =>
+                {
+                    let bytes = (0..bytes.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&bytes[i..i + 2], 16))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid())?;
+                    Record::Event(bytes)
+                }
+                [_, "resize", width, height] => Record::Resize(
+                    width.parse().map_err(|_| invalid())?,
+                    height.parse().map_err(|_| invalid())?,
+                ),
+                _ => return Err(invalid()),
+            };
+            let millis = fields[0].parse().map_err(|_| invalid())?;
+            records.push((Duration::from_millis(millis), record));
+        }
+        Ok(Recording { records })
+    }
+}
+
+type Events = Receiver<io::Result<(Event, Vec<u8>)>>;
+
+enum Source {
+    /// The events read from stdin, once reading has started.
+    Stdin(Option<Events>),
+    Replay(VecDeque<(Duration, Record)>),
+}
+
+/// Start reading stdin, passing its events on as they come.
+#[cfg(not(feature = "crossterm"))]
+fn read_stdin() -> Events {
+    use termion::input::TermReadEventsAndRaw;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for event in io::stdin().events_and_raw() {
+            let event = event.map(|(_, bytes)| (event::parse(&bytes), bytes));
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+/// Start reading the terminal's events, passing those the UI can respond to
+/// on as they come.
+#[cfg(feature = "crossterm")]
+fn read_stdin() -> Events {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let event = match crossterm::event::read() {
+            Ok(event) => match Event::from_crossterm(event) {
+                Some(event) => Ok(event),
+                None => continue,
+            },
+            Err(err) => Err(io::Error::other(err)),
+        };
+        let event = event.map(|event| {
+            let bytes = event::encode(&event);
+            (event, bytes)
+        });
+        if sender.send(event).is_err() {
+            return;
+        }
+    });
+    receiver
+}
+
+struct Recorder {
+    start: Instant,
+    file: File,
+    size: Option<(u16, u16)>,
+}
+
+impl Recorder {
+    fn write(&mut self, record: Record) -> io::Result<()> {
+        let mut line = String::new();
+        format_record(&mut line, self.start.elapsed(), &record)
+            .expect("Formatting a record failed!");
+        // Flush every record so the session survives a crash
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+struct Input {
+    source: Source,
+    /// An event read from stdin while waiting for one, not yet taken.
+    pending: Option<io::Result<(Event, Vec<u8>)>>,
+    recorder: Option<Recorder>,
+    resize: Option<(u16, u16)>,
+}
+
+thread_local! {
+    static INPUT: RefCell<Input> = const {
+        RefCell::new(Input {
+            source: Source::Stdin(None),
+            pending: None,
+            recorder: None,
+            resize: None,
+        })
+    };
+}
+
+/// Record every event read from now on to the given file.
+pub fn record<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let file = File::create(path)?;
+    INPUT.with(|input| {
+        input.borrow_mut().recorder = Some(Recorder {
+            start: Instant::now(),
+            file,
+            size: None,
+        })
+    });
+    Ok(())
+}
+
+/// Read events from the recording instead of stdin.
+pub fn replay(recording: Recording) {
+    INPUT.with(|input| {
+        let mut input = input.borrow_mut();
+        input.source = Source::Replay(recording.records.into());
+        input.resize = None;
+    });
+}
+
+/// Wait for the next event. Once there is no more input, this fails with
+/// `UpdateError::Shutdown`.
+pub fn next_event() -> Result<Event, UpdateError> {
+    read(None).map(|event| event.expect("Timed out without a timeout!"))
+}
+
+/// Wait up to `timeout` for the next event, returning None if there was
+/// none. Replays always time out, leaving their events for `next_event` so
+/// they stay in step with the screens that read them.
+pub fn poll_event(timeout: Duration) -> Result<Option<Event>, UpdateError> {
+    read(Some(timeout))
+}
+
+/// Wait up to `timeout` for an event to come in, leaving it for the next
+/// read, so a screen can keep redrawing while it waits on a player who
+/// blocks for input. Replays always have their next event ready.
+pub fn wait_event(timeout: Duration) -> Result<bool, UpdateError> {
+    INPUT.with(|input| {
+        let input = &mut *input.borrow_mut();
+        let events = match &mut input.source {
+            Source::Stdin(events) => events.get_or_insert_with(read_stdin),
+            Source::Replay(_) => return Ok(true),
+        };
+        if input.pending.is_none() {
+            input.pending = match events.recv_timeout(timeout) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => return Err(UpdateError::Shutdown),
+            };
+        }
+        Ok(true)
+    })
+}
+
+fn read(timeout: Option<Duration>) -> Result<Option<Event>, UpdateError> {
+    INPUT.with(|input| {
+        let input = &mut *input.borrow_mut();
+        if let (Some(timeout), Source::Replay(_)) = (timeout, &input.source) {
+            thread::sleep(timeout);
+            return Ok(None);
+        }
+        let (event, bytes) = match &mut input.source {
+            Source::Stdin(_) if input.pending.is_some() => {
+                input.pending.take().expect("Missing pending event!")?
+            }
+            Source::Stdin(events) => {
+                let events = events.get_or_insert_with(read_stdin);
+                let event = match timeout {
+                    Some(timeout) => match events.recv_timeout(timeout) {
+                        Ok(event) => event,
+                        Err(RecvTimeoutError::Timeout) => return Ok(None),
+                        Err(RecvTimeoutError::Disconnected) => return Err(UpdateError::Shutdown),
+                    },
+                    None => events.recv().map_err(|_| UpdateError::Shutdown)?,
+                };
+                event?
+            }
+            Source::Replay(records) => loop {
+                match records.pop_front() {
+                    Some((_, Record::Event(bytes))) => break (event::parse(&bytes), bytes),
+                    // Resizes are applied by the run loop before the next draw
+                    Some((_, Record::Resize(width, height))) => {
+                        input.resize = Some((width, height))
+                    }
+                    None => return Err(UpdateError::Shutdown),
+                }
+            },
+        };
+
+        if let Some(recorder) = &mut input.recorder {
+            recorder.write(Record::Event(bytes))?;
+        }
+        Ok(Some(event))
+    })
+}
+
+/// Record the terminal size if it has changed since the last call.
+pub fn notice_size(width: u16, height: u16) -> io::Result<()> {
+    INPUT.with(|input| match &mut input.borrow_mut().recorder {
+        Some(recorder) if recorder.size != Some((width, height)) => {
+            recorder.size = Some((width, height));
+            recorder.write(Record::Resize(width, height))
+        }
+        _ => Ok(()),
+    })
+}
+
+/// Take the most recent resize seen while replaying, including any resizes
+/// waiting at the front of the recording.
+pub fn take_resize() -> Option<(u16, u16)> {
+    INPUT.with(|input| {
+        let input = &mut *input.borrow_mut();
+        if let Source::Replay(records) = &mut input.source {
+            while let Some((_, Record::Resize(width, height))) = records.front() {
+                input.resize = Some((*width, *height));
+                records.pop_front();
+            }
+        }
+        input.resize.take()
+    })
+}
+
+#[cfg(test)]
+mod input_tests {
+    use super::*;
+    use crate::ui::event::Key;
+
+    #[test]
+    fn round_trip() {
+        let recording = Recording {
+            records: vec![
+                (Duration::from_millis(0), Record::Resize(80, 24)),
+                (
+                    Duration::from_millis(1520),
+                    Record::Event(vec![0x1b, 0x5b, 0x41]),
+                ),
+                (Duration::from_millis(1700), Record::Event(vec![b'e'])),
+            ],
+        };
+        let text = recording.to_string();
+        assert_eq!(text, "0 resize 80 24\n1520 event 1b5b41\n1700 event 65\n");
+        assert_eq!(text.parse::<Recording>(), Ok(recording.clone()));
+        assert_eq!(recording.initial_size(), Some((80, 24)));
+
+        assert!("12 event 1b5".parse::<Recording>().is_err());
+        assert!("12 jump".parse::<Recording>().is_err());
+        assert!("0 event aéa".parse::<Recording>().is_err());
+        assert!("0 event +1".parse::<Recording>().is_err());
+    }
+
+    #[test]
+    fn replay_events() {
+        replay(
+            "0 resize 80 24\n5 event 1b5b41\n7 resize 100 30\n9 event 65\n"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(take_resize(), Some((80, 24)));
+        assert_eq!(next_event().unwrap(), Event::Key(Key::Up));
+        assert_eq!(poll_event(Duration::from_millis(1)).unwrap(), None);
+        assert!(wait_event(Duration::from_millis(1)).unwrap());
+        assert_eq!(next_event().unwrap(), Event::Key(Key::Char('e')));
+        assert_eq!(take_resize(), Some((100, 30)));
+        assert_eq!(take_resize(), None);
+        assert!(matches!(next_event(), Err(UpdateError::Shutdown)));
+    }
+}
@@ -0,0 +1,224 @@
+//! The keys bound to each of the players' actions.
+//!
+//! Every action has one key of its own, read from the config file as
+//! `key_<action> = "<key>"`. The arrow keys, Enter and Esc can't be bound,
+//! and always move the cursor, select and deselect alongside the keys that
+//! are.
+
+use crate::santorini::Direction;
+use crate::ui::event::Key;
+
+/// Something a player can do with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Left,
+    Down,
+    Right,
+    Select,
+    Deselect,
+    /// Move again, for gods that allow it.
+    MoveAgain,
+    /// Skip an optional build.
+    Pass,
+    /// Build a dome, for gods that allow it.
+    Dome,
+    /// Build before moving, for gods that allow it.
+    BuildFirst,
+    /// Force an opponent's worker before moving, for gods that allow it.
+    Force,
+    Hint,
+    Undo,
+    Pause,
+    Resign,
+}
+
+impl Action {
+    /// Every action, in the order the key bindings are listed.
+    pub const ALL: [Action; 15] = [
+        Action::Up,
+        Action::Left,
+        Action::Down,
+        Action::Right,
+        Action::Select,
+        Action::Deselect,
+        Action::MoveAgain,
+        Action::Pass,
+        Action::Dome,
+        Action::BuildFirst,
+        Action::Force,
+        Action::Hint,
+        Action::Undo,
+        Action::Pause,
+        Action::Resign,
+    ];
+
+    /// The name the config file knows the action by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Left => "left",
+            Action::Down => "down",
+            Action::Right => "right",
+            Action::Select => "select",
+            Action::Deselect => "deselect",
+            Action::MoveAgain => "move_again",
+            Action::Pass => "pass",
+            Action::Dome => "dome",
+            Action::BuildFirst => "build_first",
+            Action::Force => "force",
+            Action::Hint => "hint",
+            Action::Undo => "undo",
+            Action::Pause => "pause",
+            Action::Resign => "resign",
+        }
+    }
+
+    /// What the action does, to list it by.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Up => "Cursor Up",
+            Action::Left => "Cursor Left",
+            Action::Down => "Cursor Down",
+            Action::Right => "Cursor Right",
+            Action::Select => "Select",
+            Action::Deselect => "Deselect",
+            Action::MoveAgain => "Move Again",
+            Action::Pass => "Skip Build",
+            Action::Dome => "Build Dome",
+            Action::BuildFirst => "Build Before Moving",
+            Action::Force => "Force Worker",
+            Action::Hint => "Hint",
+            Action::Undo => "Take Back",
+            Action::Pause => "Pause",
+            Action::Resign => "Resign",
+        }
+    }
+
+    /// The way the action moves the cursor, if it does.
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Action::Up => Some(Direction::North),
+            Action::Left => Some(Direction::West),
+            Action::Down => Some(Direction::South),
+            Action::Right => Some(Direction::East),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        Action::ALL
+            .iter()
+            .position(|action| *action == self)
+            .expect("Every action is listed!")
+    }
+}
+
+/// The keys that always do the same thing, and can't be bound.
+const FIXED_KEYS: [(Key, Action); 6] = [
+    (Key::Up, Action::Up),
+    (Key::Left, Action::Left),
+    (Key::Down, Action::Down),
+    (Key::Right, Action::Right),
+    (Key::Char('\n'), Action::Select),
+    (Key::Esc, Action::Deselect),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    /// The key bound to each action, in the order of `Action::ALL`.
+    keys: [Key; 15],
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap {
+            keys: [
+                Key::Char('w'),
+                Key::Char('a'),
+                Key::Char('s'),
+                Key::Char('d'),
+                Key::Char('e'),
+                Key::Char('q'),
+                Key::Char('m'),
+                Key::Char('p'),
+                Key::Char('c'),
+                Key::Char('b'),
+                Key::Char('f'),
+                Key::Char('h'),
+                Key::Char('u'),
+                Key::F(2),
+                Key::F(6),
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    /// The key bound to an action.
+    pub fn key(&self, action: Action) -> Key {
+        self.keys[action.index()]
+    }
+
+    /// The action a key does, if any.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        if let Some((_, action)) = FIXED_KEYS.iter().find(|(fixed, _)| *fixed == key) {
+            return Some(*action);
+        }
+        Action::ALL
+            .iter()
+            .find(|action| self.key(**action) == key)
+            .copied()
+    }
+
+    /// Whether a key can be bound. Keys that always do the same thing, and
+    /// keys whose names don't read back as the same key, can't be.
+    pub fn bindable(key: Key) -> bool {
+        !FIXED_KEYS.iter().any(|(fixed, _)| *fixed == key)
+            && key != Key::Ctrl('c')
+            && key.to_string().parse() == Ok(key)
+    }
+
+    /// Bind a key to an action. An action the key was bound to takes the
+    /// action's old key instead, so no key does two things.
+    pub fn bind(&mut self, action: Action, key: Key) {
+        debug_assert!(KeyMap::bindable(key), "Can't bind {}!", key);
+        let old = self.key(action);
+        if let Some(other) = self.keys.iter_mut().find(|bound| **bound == key) {
+            *other = old;
+        }
+        self.keys[action.index()] = key;
+    }
+}
+
+#[cfg(test)]
+mod keymap_tests {
+    use super::*;
+
+    #[test]
+    fn binding() {
+        let mut keys = KeyMap::default();
+        assert_eq!(keys.action(Key::Char('w')), Some(Action::Up));
+        assert_eq!(keys.action(Key::Up), Some(Action::Up));
+        assert_eq!(keys.action(Key::Char('\n')), Some(Action::Select));
+        assert_eq!(keys.action(Key::F(6)), Some(Action::Resign));
+        assert_eq!(keys.action(Key::Char('z')), None);
+
+        keys.bind(Action::Up, Key::Char('i'));
+        assert_eq!(keys.action(Key::Char('i')), Some(Action::Up));
+        assert_eq!(keys.action(Key::Char('w')), None);
+        assert_eq!(keys.action(Key::Up), Some(Action::Up));
+
+        // Taking another action's key swaps the two
+        keys.bind(Action::Hint, Key::Char('u'));
+        assert_eq!(keys.key(Action::Hint), Key::Char('u'));
+        assert_eq!(keys.key(Action::Undo), Key::Char('h'));
+
+        assert!(KeyMap::bindable(Key::Char('x')));
+        assert!(KeyMap::bindable(Key::F(5)));
+        assert!(!KeyMap::bindable(Key::Esc));
+        assert!(!KeyMap::bindable(Key::Ctrl('c')));
+        assert!(KeyMap::bindable(Key::Char('#')));
+        assert!(KeyMap::bindable(Key::Char('"')));
+    }
+}
@@ -1,13 +1,13 @@
-use std::io;
-use termion::event::{Event, Key};
-use termion::input::TermRead;
 use tui::buffer::Buffer;
 use tui::layout::{Alignment, Margin, Rect};
 use tui::style::Modifier;
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap};
 
-use crate::ui::{BoundsWidget, Screen, Term, UpdateError};
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::Action;
+use crate::ui::settings::action;
+use crate::ui::{input, BoundsWidget, Screen, Term, UpdateError};
 
 #[derive(Clone)]
 pub struct MenuWidget<'a> {
@@ -51,6 +51,11 @@ impl<'a> MenuWidget<'a> {
         }
     }
 
+    pub fn set_selected(&mut self, cursor: usize) {
+        assert!(cursor < self.items.len());
+        self.cursor = cursor;
+    }
+
     pub fn selected(&self) -> usize {
         self.cursor
     }
@@ -189,6 +194,10 @@ impl<'a, T> Menu<'a, T> {
         self.menu_widget.move_down()
     }
 
+    pub fn set_selected(&mut self, cursor: usize) {
+        self.menu_widget.set_selected(cursor)
+    }
+
     pub fn select(self) -> T {
         self.actions
             .into_iter()
@@ -227,24 +236,21 @@ impl<'a> Screen for Menu<'a, Result<Box<dyn Screen>, UpdateError>> {
             });
             f.render_widget(self.menu_widget.clone(), menu_area)
         })?;
-        if let Some(event) = io::stdin().events().next() {
-            match event? {
-                Event::Key(Key::Ctrl('c')) | Event::Key(Key::Char('q')) | Event::Key(Key::Esc) => {
-                    Err(UpdateError::Shutdown)
-                }
-                Event::Key(Key::Char('\n')) | Event::Key(Key::Char('e')) => self.select(),
-                Event::Key(Key::Up) | Event::Key(Key::Char('w')) => {
-                    self.move_up();
-                    Ok(Box::new(self.move_menu()))
-                }
-                Event::Key(Key::Down) | Event::Key(Key::Char('s')) => {
-                    self.move_down();
-                    Ok(Box::new(self.move_menu()))
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(Box::new(self.move_menu())),
+        };
+        match (key, action(key)) {
+            (Key::Ctrl('c'), _) | (_, Some(Action::Deselect)) => Err(UpdateError::Shutdown),
+            (_, Some(Action::Select)) => self.select(),
+            (_, action) => {
+                match action {
+                    Some(Action::Up) => self.move_up(),
+                    Some(Action::Down) => self.move_down(),
+                    _ => (),
                 }
-                _ => Ok(Box::new(self.move_menu())),
+                Ok(Box::new(self.move_menu()))
             }
-        } else {
-            Ok(Box::new(self.move_menu()))
         }
     }
 }
@@ -1,26 +1,44 @@
 use std::boxed::Box;
+use std::env;
 use std::io;
-use termion::input::MouseTerminal;
-use termion::raw::RawTerminal;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use thiserror::Error;
-use tui::backend::TermionBackend;
-use tui::style::{Color, Modifier, Style};
+use tui::buffer::Buffer;
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::Terminal;
 
-use crate::player::{HumanPlayer, MctsSantoriniParams};
+use crate::player::{Background, Difficulty, FirstPlayer, FullPlayer, HeuristicAI, Paced};
+use crate::santorini::Player;
 
 mod app;
+mod backend;
 mod board;
 mod bounds;
+mod clock;
+pub mod event;
+mod gods;
+mod guard;
+pub mod input;
+pub mod keymap;
 mod menu;
+pub mod saved;
+pub mod settings;
+mod setup;
+mod spectator;
+pub mod theme;
 
-pub use app::{new_app, App};
-pub use board::BoardWidget;
+pub use app::{load_app, new_app, new_match, new_three_player_app, player_name, position_app, App};
+pub use backend::Back;
+pub use board::{placed_locs, worker_locs, BoardWidget};
 pub use bounds::BoundsWidget;
+pub use guard::{panic_log_path, TerminalGuard};
 pub use menu::{Menu, MenuWidget};
+pub use saved::{SavedGame, Seat};
+pub use settings::{settings, Settings};
+pub use theme::{theme, Theme};
 
-pub type Back = TermionBackend<MouseTerminal<RawTerminal<io::Stdout>>>;
 pub type Term = Terminal<Back>;
 
 #[derive(Error, Debug)]
@@ -35,97 +53,421 @@ pub trait Screen {
     fn update(self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError>;
 }
 
+/// Update the screens until the user quits or the input runs out.
+pub fn run(terminal: &mut Term, mut screen: Box<dyn Screen>) -> Result<(), UpdateError> {
+    loop {
+        if let Some((width, height)) = input::take_resize() {
+            terminal.backend_mut().resize(width, height);
+        }
+        let size = terminal.size()?;
+        input::notice_size(size.width, size.height)?;
+
+        screen = match screen.update(terminal) {
+            Ok(screen) => screen,
+            Err(UpdateError::Shutdown) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+    }
+}
+
+/// Replay a recorded session from the main menu without a terminal,
+/// returning what would have been on screen at the end.
+pub fn replay_headless(recording: input::Recording) -> Result<Buffer, UpdateError> {
+    let (width, height) = recording.initial_size().unwrap_or((80, 24));
+    let mut terminal = Terminal::new(Back::test(width, height))?;
+    input::replay(recording);
+    run(&mut terminal, main_menu())?;
+    Ok(terminal
+        .backend()
+        .buffer()
+        .expect("Missing test buffer!")
+        .clone())
+}
+
+/// The text on screen, one line a row.
+pub fn screen_text(buffer: &Buffer) -> String {
+    let width = buffer.area().width as usize;
+    buffer
+        .content()
+        .chunks(width)
+        .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// How long each AI step stays highlighted before it is applied, taken from
+/// `SANTORINI_AI_DELAY` in milliseconds or else the settings.
+pub fn ai_delay() -> Duration {
+    env::var("SANTORINI_AI_DELAY")
+        .ok()
+        .and_then(|delay| delay.parse().ok())
+        .map_or_else(|| settings().ai_delay, Duration::from_millis)
+}
+
+/// Seat an AI, thinking on its own thread so the screen stays live and
+/// pacing its steps by `ai_delay`.
+fn computer(player: Box<dyn FullPlayer>) -> Box<dyn FullPlayer> {
+    Paced::new(Background::new(player).boxed(), ai_delay()).boxed()
+}
+
+/// The heuristic AI offered alongside the difficulties, looking as many
+/// plies ahead as `SANTORINI_HEURISTIC_DEPTH` or else the settings say, and
+/// visiting at most `SANTORINI_HEURISTIC_NODES` positions a turn, without a
+/// limit by default.
+fn heuristic_ai() -> Box<dyn FullPlayer> {
+    let depth = env::var("SANTORINI_HEURISTIC_DEPTH")
+        .ok()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or_else(|| settings().heuristic_depth);
+    let nodes = env::var("SANTORINI_HEURISTIC_NODES")
+        .ok()
+        .and_then(|nodes| nodes.parse().ok());
+    HeuristicAI::with_search(depth, nodes).boxed()
+}
+
+/// Who places first in games with the computer, by default: taking turns or
+/// picking at random according to `SANTORINI_FIRST_PLAYER` (`fixed`,
+/// `alternate` or `random`) or else the settings.
+pub fn first_player() -> FirstPlayer {
+    env::var("SANTORINI_FIRST_PLAYER")
+        .ok()
+        .and_then(|first| first.parse().ok())
+        .unwrap_or_else(|| settings().first_player)
+}
+
+/// Games with the computer started this session.
+static COMPUTER_GAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// The seats for the next game with the computer, in turn order, swapped or
+/// not as `first` says.
+fn seat_order(player_one: Seat, player_two: Seat, first: FirstPlayer) -> (Seat, Seat) {
+    if first.swap(COMPUTER_GAMES.fetch_add(1, Ordering::SeqCst)) {
+        (player_two, player_one)
+    } else {
+        (player_one, player_two)
+    }
+}
+
+/// Seat a human against the computer, with who places first decided by
+/// `first`.
+fn computer_game(human: Seat, computer: Seat, first: FirstPlayer) -> Box<dyn Screen> {
+    let (player_one, player_two) = seat_order(human, computer, first);
+    two_player_game(player_one, player_two, first)
+}
+
+/// Choose the players' gods, then start a game between them.
+fn two_player_game(player_one: Seat, player_two: Seat, first: FirstPlayer) -> Box<dyn Screen> {
+    gods::god_screen(&[player_one, player_two], move |gods| {
+        new_app(player_one, player_two, [gods[0], gods[1]], first)
+    })
+}
+
 pub fn main_menu<'a>() -> Box<dyn Screen> {
     Box::new(Menu::new(
         Span::styled("Santorini", Style::default().add_modifier(Modifier::BOLD)).into(),
         vec![
             (
                 Spans::from("2 Player Game"),
-                Box::new(|| Ok(new_app(HumanPlayer::new(), HumanPlayer::new()))),
+                Box::new(|| {
+                    Ok(two_player_game(
+                        Seat::Human,
+                        Seat::Human,
+                        FirstPlayer::Fixed,
+                    ))
+                }),
             ),
             (
-                Spans::from("1 Player Game"),
+                Spans::from("3 Player Game"),
                 Box::new(|| {
-                    Ok(new_app(
-                        HumanPlayer::new(),
-                        MctsSantoriniParams::default().boxed(),
-                    ))
+                    let seats = [Seat::Human; 3];
+                    Ok(gods::god_screen(&seats, |gods| {
+                        new_three_player_app(
+                            Seat::Human,
+                            Seat::Human,
+                            Seat::Human,
+                            [gods[0], gods[1], gods[2]],
+                        )
+                    }))
                 }),
             ),
+            (
+                Spans::from("1 Player Game"),
+                Box::new(|| Ok(difficulty_menu(first_player(), 0))),
+            ),
+            (
+                Spans::from("Watch AI Match"),
+                Box::new(|| Ok(match_menu(None))),
+            ),
+            (Spans::from("Load Game"), Box::new(load_menu)),
+            (
+                Spans::from("Set Up Position"),
+                Box::new(|| Ok(setup::setup_screen())),
+            ),
+            (
+                Spans::from("Settings"),
+                Box::new(|| Ok(settings::settings_screen())),
+            ),
             (Spans::from("Quit"), Box::new(|| Err(UpdateError::Shutdown))),
         ],
     ))
 }
 
-pub const PLAYER_ONE_STYLE: Style = Style {
-    bg: Some(Color::Indexed(21)),
-    fg: Some(Color::White),
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_ONE_TEXT_STYLE: Style = Style {
-    bg: None,
-    fg: Some(Color::Indexed(21)),
-    add_modifier: Modifier::BOLD,
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_ONE_CURSOR_STYLE: Style = Style {
-    bg: Some(Color::Indexed(45)),
-    fg: Some(Color::Black),
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_ONE_HIGHLIGHT_STYLE: Style = Style {
-    bg: Some(Color::Indexed(33)),
-    fg: Some(Color::Indexed(33)),
-    ..DEFAULT_STYLE
-};
-
-pub const PLAYER_TWO_STYLE: Style = Style {
-    bg: Some(Color::Indexed(160)),
-    fg: Some(Color::White),
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_TWO_TEXT_STYLE: Style = Style {
-    bg: None,
-    fg: Some(Color::Indexed(160)),
-    add_modifier: Modifier::BOLD,
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_TWO_CURSOR_STYLE: Style = Style {
-    bg: Some(Color::Indexed(213)),
-    fg: Some(Color::Black),
-    ..DEFAULT_STYLE
-};
-pub const PLAYER_TWO_HIGHLIGHT_STYLE: Style = Style {
-    bg: Some(Color::Indexed(204)),
-    fg: Some(Color::Indexed(204)),
-    ..DEFAULT_STYLE
-};
-
-const DEFAULT_STYLE: Style = Style {
-    bg: None,
-    fg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-};
-
-pub const GROUND_LEVEL_STYLE: Style = DEFAULT_STYLE;
-pub const LEVEL_ONE_STYLE: Style = Style {
-    bg: Some(Color::Indexed(250)),
-    fg: Some(Color::Black),
-    ..DEFAULT_STYLE
-};
-pub const LEVEL_TWO_STYLE: Style = Style {
-    bg: Some(Color::Indexed(245)),
-    fg: Some(Color::White),
-    ..DEFAULT_STYLE
-};
-pub const LEVEL_THREE_STYLE: Style = Style {
-    bg: Some(Color::Indexed(240)),
-    fg: Some(Color::White),
-    ..DEFAULT_STYLE
-};
-pub const CAPPED_STYLE: Style = Style {
-    bg: Some(Color::Indexed(235)),
-    fg: Some(Color::Indexed(235)),
-    ..DEFAULT_STYLE
-};
+/// What choosing a menu item leads to.
+type MenuAction = Box<dyn FnOnce() -> Result<Box<dyn Screen>, UpdateError>>;
+
+/// Choose how strong the computer is before playing it, and who places
+/// first, starting with the cursor on the item given.
+fn difficulty_menu(first: FirstPlayer, cursor: usize) -> Box<dyn Screen> {
+    let mut items: Vec<(Spans, MenuAction)> = Difficulty::ALL
+        .iter()
+        .map(|difficulty| {
+            let difficulty = *difficulty;
+            let start = move || {
+                Ok(computer_game(
+                    Seat::Human,
+                    Seat::Computer(difficulty),
+                    first,
+                ))
+            };
+            (
+                Spans::from(difficulty.to_string()),
+                Box::new(start) as MenuAction,
+            )
+        })
+        .collect();
+    items.push((
+        Spans::from("Heuristic"),
+        Box::new(move || Ok(computer_game(Seat::Human, Seat::Heuristic, first))),
+    ));
+    let choice = items.len();
+    items.push((
+        Spans::from(format!("First Player: {}", first)),
+        Box::new(move || {
+            let next = settings::cycle(&settings::FIRST_PLAYERS, &first, true);
+            Ok(difficulty_menu(next, choice))
+        }),
+    ));
+    items.push((Spans::from("Back"), Box::new(|| Ok(main_menu()))));
+    let mut menu = Menu::new(
+        Span::styled("Difficulty", Style::default().add_modifier(Modifier::BOLD)).into(),
+        items,
+    );
+    menu.set_selected(cursor);
+    Box::new(menu)
+}
+
+/// Choose the AIs for a match to watch, Player One's and then, once it has
+/// been chosen, Player Two's, before choosing their gods.
+fn match_menu(player_one: Option<Seat>) -> Box<dyn Screen> {
+    let mut items: Vec<(Spans, MenuAction)> = Seat::AIS
+        .iter()
+        .map(|seat| {
+            let seat = *seat;
+            let choose = move || match player_one {
+                None => Ok(match_menu(Some(seat))),
+                Some(player_one) => {
+                    let first = first_player();
+                    let (player_one, player_two) = seat_order(player_one, seat, first);
+                    let seats = [player_one, player_two];
+                    Ok(gods::god_screen(&seats, move |gods| {
+                        new_match(player_one, player_two, [gods[0], gods[1]], first)
+                    }))
+                }
+            };
+            (Spans::from(seat.name()), Box::new(choose) as MenuAction)
+        })
+        .collect();
+    items.push((Spans::from("Back"), Box::new(|| Ok(main_menu()))));
+    let title = match player_one {
+        None => player_name(Player::PlayerOne),
+        Some(_) => player_name(Player::PlayerTwo),
+    };
+    Box::new(Menu::new(title.into(), items))
+}
+
+/// Pick one of the saved games to carry on with.
+fn load_menu() -> Result<Box<dyn Screen>, UpdateError> {
+    let games = saved::saved_games()?;
+    let mut items: Vec<(Spans, MenuAction)> = games
+        .into_iter()
+        .map(|(name, game)| {
+            let seats: Vec<String> = game.seats.iter().map(|seat| seat.to_string()).collect();
+            let label = format!("{} ({})", name, seats.join(" v "));
+            let load = move || Ok(load_app(game));
+            (Spans::from(label), Box::new(load) as MenuAction)
+        })
+        .collect();
+    let title = if items.is_empty() {
+        "No Saved Games"
+    } else {
+        "Load Game"
+    };
+    items.push((Spans::from("Back"), Box::new(|| Ok(main_menu()))));
+    Ok(Box::new(Menu::new(
+        Span::styled(title, Style::default().add_modifier(Modifier::BOLD)).into(),
+        items,
+    )))
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    /// F2, which opens the pause menu.
+    const F2: &[u8] = b"\x1bOQ";
+    /// F6, which resigns.
+    const F6: &[u8] = b"\x1b[17~";
+
+    /// Replay the keys from the main menu on a 100x40 screen, returning the
+    /// text left on it. Each entry is either a run of single byte keys or
+    /// the escape sequence of one key, such as `F2`.
+    fn replay_keys(keys: &[&[u8]]) -> String {
+        let events = keys.iter().flat_map(|keys| {
+            if keys.starts_with(b"\x1b") {
+                vec![keys.to_vec()]
+            } else {
+                keys.iter().map(|key| vec![*key]).collect()
+            }
+        });
+        let mut recording = input::Recording::default();
+        recording
+            .records
+            .push((Duration::from_millis(0), input::Record::Resize(100, 40)));
+        for (idx, key) in events.enumerate() {
+            recording.records.push((
+                Duration::from_millis(100 * idx as u64),
+                input::Record::Event(key),
+            ));
+        }
+        screen_text(&replay_headless(recording).unwrap())
+    }
+
+    #[test]
+    fn replay_file() {
+        // Place Player One's workers from a recording saved to a file
+        let path = env::temp_dir().join(format!("santorini-replay-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "0 resize 100 40\n100 event 0a\n200 event 0a\n300 event 65\n400 event 64\n500 event 65\n",
+        )
+        .unwrap();
+        let recording = input::Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let text = screen_text(&replay_headless(recording).unwrap());
+        assert!(text.contains("Player Two to place"), "{}", text);
+    }
+
+    #[test]
+    fn replay_placement() {
+        // Start a two player game, place Player One, then have Player Two
+        // select a square, back out, and place elsewhere.
+        let text = replay_keys(&[b"\n\nedeseqdede"]);
+        assert!(text.contains("Player One to move"), "{}", text);
+    }
+
+    #[test]
+    fn replay_undo() {
+        // Place both players, then have Player One take back their placement
+        // and Player Two's with it
+        let text = replay_keys(&[b"\n\nedeseqdedeu"]);
+        assert!(text.contains("Player One to place"), "{}", text);
+    }
+
+    #[test]
+    fn replay_save_and_load() {
+        // Place both players, save from the pause menu, leave for the main
+        // menu and load the game back up
+        let dir = env::temp_dir().join(format!("santorini-saves-{}", std::process::id()));
+        saved::use_saves_dir(dir.clone());
+        let text = replay_keys(&[b"\n\nedeseqdede", F2, b"s\n", F2, b"ss\n", b"ssss\n\n"]);
+        let saves = saved::saved_games().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].1.seats, vec![Seat::Human, Seat::Human]);
+        assert!(text.contains("Player One to move"), "{}", text);
+    }
+
+    #[test]
+    fn replay_resign() {
+        // Place both players, then have Player One press F6 and answer the
+        // prompt, if at all, and carry on from the victory screen
+        let replay = |answers: &[u8]| replay_keys(&[b"\n\nedeseqdede", F6, answers]);
+
+        let text = replay(b"");
+        assert!(text.contains("Resign the game? (y/n)"), "{}", text);
+
+        let text = replay(b"n");
+        assert!(!text.contains("Resign the game?"), "{}", text);
+        assert!(text.contains("Player One to move"), "{}", text);
+
+        let text = replay(b"y");
+        assert!(text.contains("Player Two wins!"), "{}", text);
+        assert!(text.contains("Rematch (swap colors)"), "{}", text);
+
+        let text = replay(b"y\n");
+        assert!(text.contains("Player One to place"), "{}", text);
+        let text = replay(b"ys\n");
+        assert!(text.contains("Player One to place"), "{}", text);
+        let text = replay(b"yss\n");
+        assert!(text.contains("1 Player Game"), "{}", text);
+    }
+
+    #[test]
+    fn replay_gods() {
+        // Give Player One Apollo and Player Two Minotaur, then place Player
+        // One's workers
+        let text = replay_keys(&[b"\nsdss\nedede"]);
+        assert!(text.contains("Player Two to place"), "{}", text);
+        assert!(text.contains("Player One: Apollo"), "{}", text);
+        assert!(text.contains("Player Two: Minotaur"), "{}", text);
+    }
+
+    #[test]
+    fn replay_god_rematch() {
+        // Give Player One Apollo and Player Two Minotaur, place both players,
+        // have Player One resign and swap colors for the rematch
+        let text = replay_keys(&[b"\nsdss\nedeseqdede", F6, b"y\n"]);
+        assert!(text.contains("Player One to place"), "{}", text);
+        assert!(text.contains("Player One: Minotaur"), "{}", text);
+        assert!(text.contains("Player Two: Apollo"), "{}", text);
+    }
+
+    #[test]
+    fn replay_settings() {
+        // Raise the AI budget on the settings screen, without saving it
+        let text = replay_keys(&[b"ssssss\nd"]);
+        assert!(text.contains("AI Budget: 1000 simulations"), "{}", text);
+        assert_eq!(settings().ai_budget, 500);
+    }
+
+    #[test]
+    fn replay_key_bindings() {
+        // Bind the cursor's up to i on the key bindings screen, without
+        // saving it
+        let text = replay_keys(&[b"ssssss\nsssss\n\ni"]);
+        assert!(text.contains("Cursor Up: i"), "{}", text);
+        assert!(text.contains("Resign: F6"), "{}", text);
+        assert_eq!(settings().keys, keymap::KeyMap::default());
+    }
+
+    #[test]
+    fn replay_difficulty_menu() {
+        // Pick a one player game against the easiest computer
+        let text = replay_keys(&[b"ss\n\n\n"]);
+        assert!(text.contains("Player One to place"), "{}", text);
+    }
+
+    #[test]
+    fn replay_first_player() {
+        // Change who places first from the difficulty menu, twice
+        let text = replay_keys(&[b"ss\nww\n\n"]);
+        let next = settings::cycle(&settings::FIRST_PLAYERS, &first_player(), true);
+        let next = settings::cycle(&settings::FIRST_PLAYERS, &next, true);
+        assert!(
+            text.contains(&format!("First Player: {}", next)),
+            "{}",
+            text
+        );
+    }
+}
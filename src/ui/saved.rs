@@ -0,0 +1,288 @@
+//! Games saved from the pause menu, to be picked up again from the main menu.
+//!
+//! A saved game lists who sits in each seat, in turn order, then how it was
+//! decided who places first, then holds the game's record as `GameRecord`
+//! writes it:
+//!
+//! ```text
+//! seats human hard
+//! first alternate
+//! 00000/00000/00000/00000/00000 - - 1 p Mortal,Mortal
+//! b2,c3
+//! ```
+//!
+//! Saves from before the `first` line was written are read as `fixed`.
+//!
+//! Saves are kept under `SANTORINI_DATA_DIR`, or else the `santorini-ai`
+//! directory of the user's data directory, as `$XDG_DATA_HOME` or
+//! `~/.local/share` gives it.
+
+use chrono::Local;
+use std::cell::RefCell;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::player::{
+    Background, Difficulty, FirstPlayer, FullPlayer, HumanPlayer, MctsSantoriniParams,
+};
+use crate::santorini::GameRecord;
+
+/// Who plays a seat, as much as is needed to seat them again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seat {
+    Human,
+    Computer(Difficulty),
+    /// The heuristic AI offered alongside the difficulties.
+    Heuristic,
+    /// The default MCTS search with the budget from the settings.
+    Mcts,
+}
+
+impl Seat {
+    /// Every AI that can take a seat.
+    pub const AIS: [Seat; 6] = [
+        Seat::Computer(Difficulty::Easy),
+        Seat::Computer(Difficulty::Medium),
+        Seat::Computer(Difficulty::Hard),
+        Seat::Computer(Difficulty::Expert),
+        Seat::Heuristic,
+        Seat::Mcts,
+    ];
+
+    fn ai(self) -> Option<Box<dyn FullPlayer>> {
+        match self {
+            Seat::Human => None,
+            Seat::Computer(difficulty) => Some(difficulty.player()),
+            Seat::Heuristic => Some(super::heuristic_ai()),
+            Seat::Mcts => {
+                let params = MctsSantoriniParams::default().budget(super::settings().ai_budget);
+                Some(params.boxed())
+            }
+        }
+    }
+
+    pub fn player(self) -> Box<dyn FullPlayer> {
+        match self.ai() {
+            Some(ai) => super::computer(ai),
+            None => HumanPlayer::new(),
+        }
+    }
+
+    /// The player for a watched match, where the spectator paces the AIs.
+    pub fn watched_player(self) -> Box<dyn FullPlayer> {
+        match self.ai() {
+            Some(ai) => Background::new(ai).boxed(),
+            None => HumanPlayer::new(),
+        }
+    }
+
+    /// The name to show for the seat.
+    pub fn name(self) -> String {
+        match self {
+            Seat::Human => "Human".to_string(),
+            Seat::Computer(difficulty) => difficulty.to_string(),
+            Seat::Heuristic => "Heuristic".to_string(),
+            Seat::Mcts => "MCTS".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Seat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Seat::Human => write!(f, "human"),
+            Seat::Computer(difficulty) => write!(f, "{}", difficulty.to_string().to_lowercase()),
+            Seat::Heuristic => write!(f, "heuristic"),
+            Seat::Mcts => write!(f, "mcts"),
+        }
+    }
+}
+
+impl FromStr for Seat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Seat::Human),
+            "heuristic" => Ok(Seat::Heuristic),
+            "mcts" => Ok(Seat::Mcts),
+            _ => Difficulty::ALL
+                .iter()
+                .find(|difficulty| difficulty.to_string().to_lowercase() == s)
+                .map(|difficulty| Seat::Computer(*difficulty))
+                .ok_or_else(|| format!("unknown seat: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedGame {
+    /// The seats in turn order.
+    pub seats: Vec<Seat>,
+    /// How the seats were put in that order.
+    pub first_player: FirstPlayer,
+    pub record: GameRecord,
+}
+
+impl fmt::Display for SavedGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seats")?;
+        for seat in self.seats.iter() {
+            write!(f, " {}", seat)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "first {}", self.first_player)?;
+        write!(f, "{}", self.record)
+    }
+}
+
+impl FromStr for SavedGame {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, rest) = s.split_once('\n').unwrap_or((s, ""));
+        let seats = match first.trim().strip_prefix("seats") {
+            Some(seats) => seats
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<Seat>, _>>()?,
+            None => return Err("missing seats".to_string()),
+        };
+        let (first_player, rest) = match rest.split_once('\n') {
+            Some((first, record)) if first.starts_with("first ") => {
+                (first["first ".len()..].trim().parse()?, record)
+            }
+            _ => (FirstPlayer::Fixed, rest),
+        };
+        let record: GameRecord = rest.parse().map_err(|err| format!("{}", err))?;
+        if seats.len() != record.initial().player_count() {
+            return Err(format!("{} seats for the game's players", seats.len()));
+        }
+        Ok(SavedGame {
+            seats,
+            first_player,
+            record,
+        })
+    }
+}
+
+thread_local! {
+    /// Where this thread saves games instead, if `use_saves_dir` chose it.
+    static SAVES_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Save and load this thread's games under `dir`, in place of the saves
+/// directory the environment gives, so replays leave the user's saves be.
+pub fn use_saves_dir(dir: PathBuf) {
+    SAVES_DIR.with(|saves| *saves.borrow_mut() = Some(dir));
+}
+
+/// Where games are saved.
+pub fn saves_dir() -> PathBuf {
+    if let Some(dir) = SAVES_DIR.with(|saves| saves.borrow().clone()) {
+        return dir;
+    }
+    let data = match (
+        env::var_os("SANTORINI_DATA_DIR"),
+        env::var_os("XDG_DATA_HOME"),
+    ) {
+        (Some(dir), _) => return PathBuf::from(dir).join("saves"),
+        (None, Some(dir)) => PathBuf::from(dir),
+        (None, None) => env::var_os("HOME")
+            .map_or_else(env::temp_dir, PathBuf::from)
+            .join(".local")
+            .join("share"),
+    };
+    data.join("santorini-ai").join("saves")
+}
+
+/// Save the game to a new file named for the time, returning its path.
+pub fn save(game: &SavedGame) -> io::Result<PathBuf> {
+    let dir = saves_dir();
+    fs::create_dir_all(&dir)?;
+    let name = format!("game-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let path = dir.join(name).with_extension("txt");
+
+    // Write then rename so the save is never observed half written
+    let tmp = path.with_extension("txt.tmp");
+    fs::write(&tmp, game.to_string())?;
+    fs::rename(tmp, &path)?;
+    Ok(path)
+}
+
+/// Every saved game that can still be read, newest first, with its name.
+pub fn saved_games() -> io::Result<Vec<(String, SavedGame)>> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(saves_dir()) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    paths.sort();
+    paths.reverse();
+
+    let mut games = vec![];
+    for path in paths {
+        let game = match fs::read_to_string(&path).map(|text| text.parse()) {
+            Ok(Ok(game)) => game,
+            // Leave out saves from other versions, or damaged ones
+            _ => continue,
+        };
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        games.push((name, game));
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod saved_tests {
+    use super::*;
+    use crate::santorini::{self, Turn};
+
+    #[test]
+    fn round_trip() {
+        let mut record = GameRecord::new(santorini::new_game().into());
+        let turn: Turn = "b2,c3".parse().unwrap();
+        record.push(turn).unwrap();
+        let game = SavedGame {
+            seats: vec![Seat::Computer(Difficulty::Hard), Seat::Human],
+            first_player: FirstPlayer::Alternate,
+            record,
+        };
+        let text = game.to_string();
+        assert!(
+            text.starts_with("seats hard human\nfirst alternate\n"),
+            "{}",
+            text
+        );
+        assert_eq!(text.parse(), Ok(game.clone()));
+
+        // Saves without the first player are from fixed seating
+        let old = text.replace("first alternate\n", "");
+        let old: SavedGame = old.parse().unwrap();
+        assert_eq!(old.first_player, FirstPlayer::Fixed);
+        assert_eq!(old.record, game.record);
+        assert!(text
+            .replace("alternate", "sometimes")
+            .parse::<SavedGame>()
+            .is_err());
+
+        assert!("seats human\n".parse::<SavedGame>().is_err());
+        assert!(
+            "seats human robot\n00000/00000/00000/00000/00000 - - 1 p Mortal,Mortal"
+                .parse::<SavedGame>()
+                .is_err()
+        );
+        for seat in ["human", "easy", "expert", "heuristic", "mcts"].iter() {
+            assert_eq!(seat.parse::<Seat>().unwrap().to_string(), *seat);
+        }
+    }
+}
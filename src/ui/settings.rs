@@ -0,0 +1,558 @@
+//! Settings chosen from the main menu and kept between sessions.
+//!
+//! The settings are read from a TOML config file at startup, before any
+//! players are seated, and written back from the settings screen:
+//!
+//! ```toml
+//! ai_budget = 500
+//! ai_delay_ms = 500
+//! first_player = "fixed"
+//! heuristic_depth = 2
+//! theme = "default"
+//! show_search = true
+//! time_control = "5+3"
+//! key_up = "i"
+//! key_pause = "F2"
+//! ```
+//!
+//! The file is `SANTORINI_CONFIG` if set, or else `santorini-ai/config.toml`
+//! in the user's config directory, as `$XDG_CONFIG_HOME` or `~/.config` gives
+//! it. Settings left out of the file keep their defaults, and the
+//! `SANTORINI_*` variables read elsewhere in the UI take precedence over
+//! the file. The key bindings are described in `keymap`, and the older
+//! `movement_keys = "ijkl"` still binds the keys that move the cursor.
+
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tui::layout::Margin;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders};
+
+use crate::player::FirstPlayer;
+use crate::santorini::Direction;
+use crate::ui::clock::TimeControl;
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::{Action, KeyMap};
+use crate::ui::theme::{self, Theme};
+use crate::ui::{self, input, MenuWidget, Screen, Term, UpdateError};
+
+/// The settings in use, once they have been loaded or changed.
+static SETTINGS: Mutex<Option<Settings>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// Simulations a turn for the MCTS AI that plays watched games.
+    pub ai_budget: u32,
+    /// How long each AI step stays highlighted before it is applied.
+    pub ai_delay: Duration,
+    /// Who places first in games against the computer.
+    pub first_player: FirstPlayer,
+    /// How many plies the heuristic AI looks ahead.
+    pub heuristic_depth: u8,
+    /// The name of one of `theme::THEMES`.
+    pub theme: String,
+    pub keys: KeyMap,
+    /// Whether to show what the AIs found in their searches.
+    pub show_search: bool,
+    /// The players' clocks in new games, or None to play untimed.
+    pub time_control: Option<TimeControl>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            ai_budget: 500,
+            ai_delay: Duration::from_millis(500),
+            first_player: FirstPlayer::Fixed,
+            heuristic_depth: 2,
+            theme: theme::DEFAULT.name.to_string(),
+            keys: KeyMap::default(),
+            show_search: true,
+            time_control: None,
+        }
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "ai_budget = {}", self.ai_budget)?;
+        writeln!(f, "ai_delay_ms = {}", self.ai_delay.as_millis())?;
+        writeln!(f, "first_player = {}", quote(self.first_player))?;
+        writeln!(f, "heuristic_depth = {}", self.heuristic_depth)?;
+        writeln!(f, "theme = {}", quote(&self.theme))?;
+        writeln!(f, "show_search = {}", self.show_search)?;
+        match self.time_control {
+            Some(control) => writeln!(f, "time_control = {}", quote(control))?,
+            None => writeln!(f, "time_control = {}", quote("off"))?,
+        }
+        for action in Action::ALL.iter() {
+            writeln!(
+                f,
+                "key_{} = {}",
+                action.name(),
+                quote(self.keys.key(*action))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A TOML string holding the value, with any quotes in it escaped.
+fn quote(value: impl fmt::Display) -> toml::Value {
+    toml::Value::String(value.to_string())
+}
+
+fn string(value: &toml::Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string: {}", value))
+}
+
+fn number<T: TryFrom<i64>>(value: &toml::Value) -> Result<T, String> {
+    value
+        .as_integer()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| format!("expected a number: {}", value))
+}
+
+impl FromStr for Settings {
+    type Err = String;
+
+    /// Read the top level `key = value` pairs of a TOML file. Keys from
+    /// other versions are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table = match s.parse::<toml::Value>().map_err(|err| err.to_string())? {
+            toml::Value::Table(table) => table,
+            _ => return Err("expected key = value pairs".to_string()),
+        };
+        let mut settings = Settings::default();
+        for (key, value) in table.iter() {
+            let at = |err: String| format!("{}: {}", key, err);
+            match key.as_str() {
+                "ai_budget" => settings.ai_budget = number(value).map_err(at)?,
+                "ai_delay_ms" => {
+                    settings.ai_delay = Duration::from_millis(number(value).map_err(at)?)
+                }
+                "first_player" => {
+                    settings.first_player = string(value).and_then(str::parse).map_err(at)?
+                }
+                "heuristic_depth" => settings.heuristic_depth = number(value).map_err(at)?,
+                "theme" => {
+                    let theme = string(value).map_err(at)?;
+                    if Theme::named(theme).is_none() {
+                        return Err(at(format!("unknown theme: {}", theme)));
+                    }
+                    settings.theme = theme.to_string();
+                }
+                "movement_keys" => {
+                    let keys: Vec<char> = string(value).map_err(at)?.chars().collect();
+                    if keys.len() != 4 {
+                        return Err(at(format!("expected four movement keys: {}", value)));
+                    }
+                    let actions = [Action::Up, Action::Left, Action::Down, Action::Right];
+                    for (action, key) in actions.iter().zip(keys) {
+                        if !KeyMap::bindable(Key::Char(key)) {
+                            return Err(at(format!("can't bind {}", key)));
+                        }
+                        settings.keys.bind(*action, Key::Char(key));
+                    }
+                }
+                "show_search" => {
+                    settings.show_search = value
+                        .as_bool()
+                        .ok_or_else(|| at(format!("expected true or false: {}", value)))?
+                }
+                "time_control" => {
+                    settings.time_control = match string(value).map_err(at)? {
+                        "off" => None,
+                        control => Some(control.parse().map_err(at)?),
+                    }
+                }
+                key => {
+                    let action = key
+                        .strip_prefix("key_")
+                        .and_then(|name| Action::ALL.iter().find(|action| action.name() == name));
+                    if let Some(action) = action {
+                        let key: Key = string(value).and_then(str::parse).map_err(at)?;
+                        if !KeyMap::bindable(key) {
+                            return Err(at(format!("can't bind {}", key)));
+                        }
+                        settings.keys.bind(*action, key);
+                    }
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// The settings in use.
+pub fn settings() -> Settings {
+    SETTINGS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Use these settings from now on.
+pub fn set_settings(settings: Settings) {
+    *SETTINGS.lock().unwrap_or_else(|err| err.into_inner()) = Some(settings);
+}
+
+/// Where the settings are kept.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = env::var_os("SANTORINI_CONFIG") {
+        return PathBuf::from(path);
+    }
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::var_os("HOME")
+            .map_or_else(env::temp_dir, PathBuf::from)
+            .join(".config"),
+    };
+    config.join("santorini-ai").join("config.toml")
+}
+
+/// Read the settings from the config file, or the defaults if there isn't
+/// one yet.
+pub fn load() -> io::Result<Settings> {
+    match fs::read_to_string(config_path()) {
+        Ok(text) => text
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write the settings to the config file.
+pub fn save(settings: &Settings) -> io::Result<()> {
+    let path = config_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, settings.to_string())
+}
+
+/// The action a key is bound to, if any.
+pub fn action(key: Key) -> Option<Action> {
+    settings().keys.action(key)
+}
+
+/// The way a key moves the cursor, if it does.
+pub fn direction(key: Key) -> Option<Direction> {
+    action(key).and_then(Action::direction)
+}
+
+/// The choice after `current`, or before it going backwards, wrapping
+/// around.
+pub(crate) fn cycle<T: PartialEq + Clone>(choices: &[T], current: &T, forward: bool) -> T {
+    let len = choices.len();
+    let idx = match choices.iter().position(|choice| choice == current) {
+        Some(idx) if forward => (idx + 1) % len,
+        Some(idx) => (idx + len - 1) % len,
+        None => 0,
+    };
+    choices[idx].clone()
+}
+
+const BUDGETS: [u32; 6] = [100, 250, 500, 1000, 2000, 5000];
+const DELAYS: [u64; 5] = [0, 250, 500, 1000, 2000];
+pub(crate) const FIRST_PLAYERS: [FirstPlayer; 3] = [
+    FirstPlayer::Fixed,
+    FirstPlayer::Alternate,
+    FirstPlayer::Random,
+];
+const DEPTHS: [u8; 4] = [1, 2, 3, 4];
+const TIME_CONTROLS: [Option<TimeControl>; 6] = [
+    None,
+    Some(TimeControl::new(1, 0)),
+    Some(TimeControl::new(3, 2)),
+    Some(TimeControl::new(5, 3)),
+    Some(TimeControl::new(10, 5)),
+    Some(TimeControl::new(15, 10)),
+];
+
+/// The settings that can be changed, in the order they are listed.
+const OPTIONS: usize = 8;
+/// The option leading to the key bindings, which has no choices to cycle.
+const KEY_BINDINGS: usize = 5;
+
+/// Change the settings, then save them or go back without.
+struct SettingsScreen {
+    settings: Settings,
+    cursor: usize,
+}
+
+pub fn settings_screen() -> Box<dyn Screen> {
+    Box::new(SettingsScreen {
+        settings: settings(),
+        cursor: 0,
+    })
+}
+
+impl SettingsScreen {
+    fn labels(&self) -> Vec<String> {
+        let settings = &self.settings;
+        let on_off = |on| if on { "On" } else { "Off" };
+        vec![
+            format!("AI Budget: {} simulations", settings.ai_budget),
+            format!("AI Delay: {} ms", settings.ai_delay.as_millis()),
+            format!("First Player: {}", settings.first_player),
+            format!("Heuristic Depth: {}", settings.heuristic_depth),
+            format!("Theme: {}", settings.theme),
+            "Key Bindings".to_string(),
+            format!("Show Search: {}", on_off(settings.show_search)),
+            match settings.time_control {
+                Some(control) => format!("Time Control: {}", control),
+                None => "Time Control: Off".to_string(),
+            },
+            "Save".to_string(),
+            "Back".to_string(),
+        ]
+    }
+
+    /// Change the setting under the cursor to its next or previous choice.
+    fn change(&mut self, forward: bool) {
+        let settings = &mut self.settings;
+        match self.cursor {
+            0 => settings.ai_budget = cycle(&BUDGETS, &settings.ai_budget, forward),
+            1 => {
+                let millis = settings.ai_delay.as_millis() as u64;
+                settings.ai_delay = Duration::from_millis(cycle(&DELAYS, &millis, forward));
+            }
+            2 => settings.first_player = cycle(&FIRST_PLAYERS, &settings.first_player, forward),
+            3 => settings.heuristic_depth = cycle(&DEPTHS, &settings.heuristic_depth, forward),
+            4 => {
+                let names: Vec<&str> = theme::THEMES.iter().map(|theme| theme.name).collect();
+                settings.theme = cycle(&names, &settings.theme.as_str(), forward).to_string();
+            }
+            6 => settings.show_search = !settings.show_search,
+            7 => settings.time_control = cycle(&TIME_CONTROLS, &settings.time_control, forward),
+            _ => (),
+        }
+    }
+}
+
+impl Screen for SettingsScreen {
+    fn update(mut self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        let mut menu = MenuWidget::new(
+            Span::styled("Settings", Style::default().add_modifier(Modifier::BOLD)).into(),
+            self.labels().into_iter().map(Spans::from).collect(),
+        );
+        menu.set_selected(self.cursor);
+        terminal.draw(|f| {
+            let border = Block::default().title("Santorini").borders(Borders::ALL);
+            f.render_widget(border, f.size());
+            let menu_area = f.size().inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            });
+            f.render_widget(menu.clone(), menu_area)
+        })?;
+
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(self),
+        };
+        match (key, action(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (_, Some(Action::Deselect)) => return Ok(ui::main_menu()),
+            (_, Some(Action::Up)) => menu.move_up(),
+            (_, Some(Action::Down)) => menu.move_down(),
+            (_, Some(Action::Left)) => self.change(false),
+            (_, Some(Action::Right)) => self.change(true),
+            (_, Some(Action::Select)) => match self.cursor {
+                OPTIONS => {
+                    save(&self.settings)?;
+                    set_settings(self.settings);
+                    return Ok(ui::main_menu());
+                }
+                cursor if cursor > OPTIONS => return Ok(ui::main_menu()),
+                KEY_BINDINGS => {
+                    return Ok(Box::new(KeysScreen {
+                        settings: *self,
+                        cursor: 0,
+                        status: None,
+                    }))
+                }
+                _ => self.change(true),
+            },
+            _ => (),
+        }
+        self.cursor = menu.selected();
+        Ok(self)
+    }
+}
+
+/// Rebind the keys, by choosing an action and then pressing its new key.
+/// The bindings are saved along with the other settings.
+struct KeysScreen {
+    settings: SettingsScreen,
+    cursor: usize,
+    /// Why the last key pressed couldn't be bound.
+    status: Option<String>,
+}
+
+/// Waiting for the key to bind the action under the cursor to.
+struct BindingScreen(KeysScreen);
+
+impl Screen for KeysScreen {
+    fn update(mut self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        let keys = &self.settings.settings.keys;
+        let mut labels: Vec<Spans> = Action::ALL
+            .iter()
+            .map(|action| Spans::from(format!("{}: {}", action.description(), keys.key(*action))))
+            .collect();
+        labels.push(Spans::from("Back"));
+        let title = self
+            .status
+            .clone()
+            .unwrap_or_else(|| "Key Bindings".to_string());
+        let mut menu = MenuWidget::new(
+            Span::styled(title, Style::default().add_modifier(Modifier::BOLD)).into(),
+            labels,
+        );
+        menu.set_selected(self.cursor);
+        terminal.draw(|f| {
+            let border = Block::default().title("Santorini").borders(Borders::ALL);
+            f.render_widget(border, f.size());
+            let menu_area = f.size().inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            });
+            f.render_widget(menu.clone(), menu_area)
+        })?;
+
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(self),
+        };
+        self.status = None;
+        match (key, action(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (_, Some(Action::Deselect)) => return Ok(Box::new(self.settings)),
+            (_, Some(Action::Up)) => menu.move_up(),
+            (_, Some(Action::Down)) => menu.move_down(),
+            (_, Some(Action::Select)) if self.cursor < Action::ALL.len() => {
+                return Ok(Box::new(BindingScreen(*self)))
+            }
+            (_, Some(Action::Select)) => return Ok(Box::new(self.settings)),
+            _ => (),
+        }
+        self.cursor = menu.selected();
+        Ok(self)
+    }
+}
+
+impl Screen for BindingScreen {
+    fn update(self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        let mut keys = self.0;
+        let action = Action::ALL[keys.cursor];
+        terminal.draw(|f| {
+            let border = Block::default().title("Santorini").borders(Borders::ALL);
+            f.render_widget(border, f.size());
+            let menu_area = f.size().inner(&Margin {
+                horizontal: 1,
+                vertical: 1,
+            });
+            let prompt = MenuWidget::new(
+                Span::styled(
+                    format!("Press a key for {}", action.description()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )
+                .into(),
+                vec![Spans::from("Esc to cancel")],
+            );
+            f.render_widget(prompt, menu_area)
+        })?;
+
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(Box::new(BindingScreen(keys))),
+        };
+        match key {
+            Key::Ctrl('c') => return Err(UpdateError::Shutdown),
+            Key::Esc => (),
+            key if KeyMap::bindable(key) => keys.settings.settings.keys.bind(action, key),
+            key => keys.status = Some(format!("{} can't be bound", key)),
+        }
+        Ok(Box::new(keys))
+    }
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            ai_budget: 2000,
+            ai_delay: Duration::from_millis(0),
+            first_player: FirstPlayer::Alternate,
+            heuristic_depth: 3,
+            theme: "default".to_string(),
+            keys: {
+                let mut keys = KeyMap::default();
+                keys.bind(Action::Pause, Key::F(5));
+                keys.bind(Action::Up, Key::Char('i'));
+                keys.bind(Action::Hint, Key::Char('#'));
+                keys.bind(Action::Dome, Key::Char('"'));
+                keys
+            },
+            show_search: false,
+            time_control: Some(TimeControl::new(5, 3)),
+        };
+        assert_eq!(settings.to_string().parse(), Ok(settings));
+
+        let text = "# Stronger watched games\nai_budget = 1000\n\nfuture_setting = [1, 2]\n";
+        let parsed: Settings = text.parse().unwrap();
+        assert_eq!(
+            parsed,
+            Settings {
+                ai_budget: 1000,
+                ..Settings::default()
+            }
+        );
+
+        assert!("ai_budget = lots".parse::<Settings>().is_err());
+        assert!("theme = \"neon\"".parse::<Settings>().is_err());
+        assert!("first_player = fixed".parse::<Settings>().is_err());
+        assert!("show_search".parse::<Settings>().is_err());
+        assert!("time_control = \"5\"".parse::<Settings>().is_err());
+        assert!("key_pause = \"Esc\"".parse::<Settings>().is_err());
+        assert!("movement_keys = \"ij\"".parse::<Settings>().is_err());
+        assert!("ai_budget = -1".parse::<Settings>().is_err());
+        assert_eq!(
+            "theme = \"default\" # the usual colors".parse::<Settings>(),
+            Ok(Settings::default())
+        );
+        let parsed: Settings = "movement_keys = \"ijkl\"\nkey_hint = \"F1\""
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.keys.key(Action::Right), Key::Char('l'));
+        assert_eq!(parsed.keys.action(Key::F(1)), Some(Action::Hint));
+        assert_eq!(
+            "time_control = \"off\"".parse::<Settings>(),
+            Ok(Settings::default())
+        );
+    }
+
+    #[test]
+    fn cycling() {
+        assert_eq!(cycle(&BUDGETS, &5000, true), 100);
+        assert_eq!(cycle(&BUDGETS, &100, false), 5000);
+        assert_eq!(cycle(&BUDGETS, &123, true), 100);
+        assert_eq!(cycle(&DEPTHS, &4, true), 1);
+    }
+}
@@ -0,0 +1,269 @@
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Paragraph, Wrap};
+
+use crate::player::heuristic_ai;
+use crate::santorini::{
+    self, Board, CoordLevel, Game, GameBuilder, God, Move, Player, Point, PositionError, Turn,
+};
+use crate::ui::event::{Event, Key};
+use crate::ui::keymap::Action;
+use crate::ui::settings::{self, settings};
+use crate::ui::{
+    self, input, position_app, BoardWidget, Menu, MenuAction, Screen, Seat, Term, UpdateError,
+};
+
+/// Set up a position by hand, then play it out against an AI or have the
+/// engine evaluate it.
+struct SetupScreen {
+    board: Board,
+    /// Where Player One's and Player Two's workers stand.
+    workers: [Vec<Point>; 2],
+    /// The player to move.
+    player: Player,
+    cursor: Point,
+    /// What the engine made of the position, or why it couldn't be set up.
+    status: Option<String>,
+}
+
+pub fn setup_screen() -> Box<dyn Screen> {
+    Box::new(SetupScreen::new())
+}
+
+impl SetupScreen {
+    /// An empty board, with Player One to move.
+    fn new() -> Self {
+        SetupScreen {
+            board: santorini::new_game().board(),
+            workers: [vec![], vec![]],
+            player: Player::PlayerOne,
+            cursor: Point::new(0.into(), 0.into()),
+            status: None,
+        }
+    }
+
+    fn game(&self) -> Result<Game<Move>, PositionError> {
+        GameBuilder::new([God::Mortal; 2])
+            .board(self.board)
+            .workers(Player::PlayerOne, &self.workers[0])
+            .workers(Player::PlayerTwo, &self.workers[1])
+            .player(self.player)
+            .build()
+    }
+
+    /// Build up the square under the cursor, clearing it again after a dome.
+    fn raise(&mut self) {
+        let level = (i8::from(self.board.level_at(self.cursor)) + 1) % 5;
+        self.board.set_level(self.cursor, CoordLevel::from(level));
+    }
+
+    /// Put one of the player's workers on the square under the cursor, or
+    /// take it off if one is there already.
+    fn toggle_worker(&mut self, player: Player) {
+        let cursor = self.cursor;
+        let had_worker = self.workers[player.index()].contains(&cursor);
+        for locs in self.workers.iter_mut() {
+            locs.retain(|loc| *loc != cursor);
+        }
+        if !had_worker {
+            self.workers[player.index()].push(cursor);
+        }
+    }
+
+    /// The engine's evaluation of the position and the turn it would take.
+    fn evaluate(&self, game: &Game<Move>) -> String {
+        match heuristic_ai::best_turn(game, settings().heuristic_depth) {
+            Some((actions, score)) => {
+                format!("eval {:+.2}, best {}", score, Turn::from(actions))
+            }
+            None => "No legal turns".to_string(),
+        }
+    }
+
+    /// Choose the AI to play the position against, the human taking the
+    /// side to move.
+    fn opponent_menu(self, game: Game<Move>) -> Box<dyn Screen> {
+        let mut items: Vec<(Spans, MenuAction)> = Seat::AIS
+            .iter()
+            .map(|seat| {
+                let seat = *seat;
+                let play = move || {
+                    let seats = match game.player() {
+                        Player::PlayerOne => vec![Seat::Human, seat],
+                        _ => vec![seat, Seat::Human],
+                    };
+                    Ok(position_app(game, seats))
+                };
+                (Spans::from(seat.name()), Box::new(play) as MenuAction)
+            })
+            .collect();
+        items.push((
+            Spans::from("Back"),
+            Box::new(move || Ok(Box::new(self) as Box<dyn Screen>)),
+        ));
+        Box::new(Menu::new(
+            Span::styled(
+                "Play Against",
+                Style::default().add_modifier(Modifier::BOLD),
+            )
+            .into(),
+            items,
+        ))
+    }
+}
+
+impl Screen for SetupScreen {
+    fn update(mut self: Box<Self>, terminal: &mut Term) -> Result<Box<dyn Screen>, UpdateError> {
+        terminal.draw(|f| {
+            let border = Block::default()
+                .title("Santorini - Set Up Position")
+                .borders(Borders::ALL);
+            f.render_widget(border, f.size());
+
+            let segments = Layout::default()
+                .direction(Direction::Horizontal)
+                .margin(1)
+                .constraints([Constraint::Min(15), Constraint::Ratio(1, 3)].as_ref())
+                .split(f.size());
+
+            let widget = BoardWidget {
+                board: self.board,
+                player: self.player,
+                cursor: Some(self.cursor),
+
+                highlights: &vec![],
+                suggestions: vec![],
+                player1_locs: self.workers[0].clone(),
+                player2_locs: self.workers[1].clone(),
+                player3_locs: vec![],
+            };
+            f.render_widget(widget, segments[0]);
+
+            let bold = Style::default().add_modifier(Modifier::BOLD);
+            let key = |key: &'static str, action: &'static str| {
+                Spans::from(vec![
+                    Span::raw("Use "),
+                    Span::styled(key, bold),
+                    Span::raw(action),
+                ])
+            };
+            let mut lines = vec![
+                Spans::from(vec![]),
+                Spans::from(vec![ui::player_name(self.player), Span::raw(" to move")]),
+                Spans::from(vec![]),
+                key("b", " to build up, or clear a dome."),
+                Spans::from(vec![
+                    Span::raw("Use "),
+                    Span::styled("1", bold),
+                    Span::raw(" or "),
+                    Span::styled("2", bold),
+                    Span::raw(" to place or remove a worker."),
+                ]),
+                key("t", " to change the player to move."),
+                key("v", " for the engine's evaluation."),
+                key("p", " to play the position out."),
+                key("Esc", " to go back."),
+            ];
+            if let Some(status) = &self.status {
+                lines.push(Spans::from(vec![]));
+                lines.push(Spans::from(status.as_str()));
+            }
+            f.render_widget(
+                Paragraph::new(lines)
+                    .block(Block::default().title("Position").borders(Borders::ALL))
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: false }),
+                segments[1],
+            );
+        })?;
+
+        let key = match input::next_event()? {
+            Event::Key(key) => key,
+            _ => return Ok(self),
+        };
+        match (key, settings::direction(key)) {
+            (Key::Ctrl('c'), _) => return Err(UpdateError::Shutdown),
+            (key, _) if settings::action(key) == Some(Action::Deselect) => {
+                return Ok(ui::main_menu())
+            }
+            (_, Some(direction)) => {
+                self.cursor = (self.cursor + direction).unwrap_or(self.cursor);
+                return Ok(self);
+            }
+            (Key::Char('b'), _) => self.raise(),
+            (Key::Char('1'), _) => self.toggle_worker(Player::PlayerOne),
+            (Key::Char('2'), _) => self.toggle_worker(Player::PlayerTwo),
+            (Key::Char('t'), _) => {
+                // Positions are set up for two players
+                self.player = match self.player {
+                    Player::PlayerOne => Player::PlayerTwo,
+                    _ => Player::PlayerOne,
+                }
+            }
+            (Key::Char('v'), _) => {
+                self.status = Some(match self.game() {
+                    Ok(game) => self.evaluate(&game),
+                    Err(err) => err.to_string(),
+                });
+                return Ok(self);
+            }
+            (Key::Char('p'), _) => match self.game() {
+                Ok(game) => return Ok(self.opponent_menu(game)),
+                Err(err) => {
+                    self.status = Some(err.to_string());
+                    return Ok(self);
+                }
+            },
+            _ => return Ok(self),
+        }
+        // An evaluation is out of date once the position changes
+        self.status = None;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod setup_tests {
+    use super::*;
+
+    #[test]
+    fn editing() {
+        let mut setup = SetupScreen::new();
+        assert!(setup.game().is_err());
+
+        for _ in 0..5 {
+            setup.raise();
+        }
+        assert_eq!(setup.board.level_at(setup.cursor), CoordLevel::Ground);
+        setup.raise();
+        assert_eq!(setup.board.level_at(setup.cursor), CoordLevel::One);
+
+        let square = |name: &str| name.parse::<Point>().unwrap();
+        for (player, loc) in [
+            (Player::PlayerOne, "a1"),
+            (Player::PlayerOne, "c3"),
+            (Player::PlayerTwo, "b3"),
+            (Player::PlayerTwo, "d4"),
+        ]
+        .iter()
+        {
+            setup.cursor = square(loc);
+            setup.toggle_worker(*player);
+        }
+        setup.player = Player::PlayerTwo;
+        let game = setup.game().unwrap();
+        assert_eq!(game.player(), Player::PlayerTwo);
+        assert_eq!(game.board().level_at(square("a1")), CoordLevel::One);
+        assert!(setup.evaluate(&game).starts_with("eval "));
+
+        // Placing on a worker swaps whose it is, and again takes it off
+        setup.cursor = square("d4");
+        setup.toggle_worker(Player::PlayerOne);
+        assert_eq!(setup.workers[0].len(), 3);
+        assert_eq!(setup.workers[1].len(), 1);
+        setup.toggle_worker(Player::PlayerOne);
+        assert_eq!(setup.workers[0].len(), 2);
+        assert_eq!(setup.game().unwrap_err(), PositionError::WorkerCount);
+    }
+}
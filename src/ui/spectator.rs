@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use crate::player::StepResult;
+use crate::santorini::Point;
+use crate::ui::event::Key;
+
+/// Paces a match between AIs for the players watching it, who can pause
+/// it, step through it or fast-forward it.
+///
+/// The AIs think as soon as their turn comes, but a step they find is held
+/// back until the last one has been on screen for the delay.
+pub struct Spectator {
+    delay: Duration,
+    paused: bool,
+    /// Whether to apply the next step, even though the match is paused.
+    step: bool,
+    /// Whether to apply each step as soon as it's found.
+    fast: bool,
+    /// When the last step was applied.
+    shown: Instant,
+    /// The squares the last step changed.
+    pub changed: Vec<Point>,
+    /// A step found before it was time to apply it.
+    pub pending: Option<StepResult>,
+}
+
+impl Spectator {
+    pub fn new(delay: Duration) -> Self {
+        Spectator {
+            delay,
+            paused: false,
+            step: false,
+            fast: false,
+            shown: Instant::now(),
+            changed: vec![],
+            pending: None,
+        }
+    }
+
+    /// Whether a step found now should be applied straight away.
+    pub fn ready(&self) -> bool {
+        self.step || (!self.paused && (self.fast || self.shown.elapsed() >= self.delay))
+    }
+
+    /// How long to wait for a key before checking whether it's time for the
+    /// held step, at most `poll`.
+    pub fn wait(&self, poll: Duration) -> Duration {
+        if self.paused {
+            return poll;
+        }
+        self.delay
+            .checked_sub(self.shown.elapsed())
+            .map_or(Duration::from_millis(0), |remaining| remaining.min(poll))
+    }
+
+    /// Note a step applied, which changed the squares given.
+    pub fn applied(&mut self, changed: Vec<Point>) {
+        self.shown = Instant::now();
+        self.step = false;
+        self.changed = changed;
+        self.pending = None;
+    }
+
+    /// Handle one of the spectator's keys, returning whether it was one.
+    pub fn handle(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char('p') => self.paused = !self.paused,
+            Key::Char('n') => {
+                self.paused = true;
+                self.step = true;
+            }
+            Key::Char('f') => self.fast = !self.fast,
+            _ => return false,
+        }
+        true
+    }
+
+    /// What the match is doing and the keys to change it.
+    pub fn status(&self) -> String {
+        let pace = if self.paused {
+            "Paused".to_string()
+        } else if self.fast {
+            "Fast-forwarding".to_string()
+        } else {
+            format!("Watching, {} ms a step", self.delay.as_millis())
+        };
+        format!("{} - p to pause, n to step, f to fast-forward", pace)
+    }
+}
+
+#[cfg(test)]
+mod spectator_tests {
+    use super::*;
+
+    #[test]
+    fn pacing() {
+        let mut spectator = Spectator::new(Duration::from_secs(60));
+        let poll = Duration::from_millis(100);
+        assert!(!spectator.ready());
+        assert_eq!(spectator.wait(poll), poll);
+
+        assert!(spectator.handle(Key::Char('f')));
+        assert!(spectator.ready());
+
+        // Stepping pauses the match, letting one step through
+        assert!(spectator.handle(Key::Char('n')));
+        assert!(spectator.ready());
+        spectator.applied(vec![]);
+        assert!(!spectator.ready());
+        assert!(spectator.status().starts_with("Paused"));
+
+        assert!(spectator.handle(Key::Char('p')));
+        assert!(spectator.ready());
+        assert!(!spectator.handle(Key::Char('e')));
+
+        let spectator = Spectator::new(Duration::from_millis(0));
+        assert!(spectator.ready());
+        assert_eq!(spectator.wait(poll), Duration::from_millis(0));
+    }
+}
@@ -0,0 +1,198 @@
+//! The colors the board and the players' names are drawn in.
+//!
+//! The theme is picked by name in the settings and looked up whenever the
+//! screen is drawn, so a change on the settings screen shows straight away.
+
+use tui::style::{Color, Modifier, Style};
+
+use crate::santorini::{CoordLevel, Player};
+use crate::ui::settings;
+
+/// How one player's workers and name are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerStyles {
+    /// The squares their workers stand on.
+    pub worker: Style,
+    /// Their name.
+    pub text: Style,
+    /// The border of the square under the cursor, on their turn.
+    pub cursor: Style,
+    /// The borders of the squares highlighted for them.
+    pub highlight: Style,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// The name the settings know the theme by.
+    pub name: &'static str,
+    /// Player One's, Player Two's and Player Three's styles.
+    pub players: [PlayerStyles; 3],
+    /// The squares by level, from the ground up to a dome.
+    pub levels: [Style; 5],
+    /// The borders of the squares of a suggested turn.
+    pub suggestion: Style,
+}
+
+impl Theme {
+    pub fn player(&self, player: Player) -> PlayerStyles {
+        self.players[player.index()]
+    }
+
+    pub fn level(&self, level: CoordLevel) -> Style {
+        self.levels[i8::from(level) as usize]
+    }
+
+    /// The theme with the given name, if there is one.
+    pub fn named(name: &str) -> Option<Theme> {
+        THEMES.iter().find(|theme| theme.name == name).copied()
+    }
+}
+
+const DEFAULT_STYLE: Style = Style {
+    bg: None,
+    fg: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+
+/// Text in the color given.
+const fn text(fg: Color) -> Style {
+    Style {
+        fg: Some(fg),
+        add_modifier: Modifier::BOLD,
+        ..DEFAULT_STYLE
+    }
+}
+
+/// A square, or its border, filled with the color given.
+const fn fill(bg: Color, fg: Color) -> Style {
+    Style {
+        bg: Some(bg),
+        fg: Some(fg),
+        ..DEFAULT_STYLE
+    }
+}
+
+/// The same grays for each level in every theme but high contrast.
+const GRAY_LEVELS: [Style; 5] = [
+    DEFAULT_STYLE,
+    fill(Color::Indexed(250), Color::Black),
+    fill(Color::Indexed(245), Color::White),
+    fill(Color::Indexed(240), Color::White),
+    fill(Color::Indexed(235), Color::Indexed(250)),
+];
+
+pub const DEFAULT: Theme = Theme {
+    name: "default",
+    players: [
+        PlayerStyles {
+            worker: fill(Color::Indexed(21), Color::White),
+            text: text(Color::Indexed(21)),
+            cursor: fill(Color::Indexed(45), Color::Black),
+            highlight: fill(Color::Indexed(33), Color::Indexed(33)),
+        },
+        PlayerStyles {
+            worker: fill(Color::Indexed(160), Color::White),
+            text: text(Color::Indexed(160)),
+            cursor: fill(Color::Indexed(213), Color::Black),
+            highlight: fill(Color::Indexed(204), Color::Indexed(204)),
+        },
+        PlayerStyles {
+            worker: fill(Color::Indexed(28), Color::White),
+            text: text(Color::Indexed(28)),
+            cursor: fill(Color::Indexed(120), Color::Black),
+            highlight: fill(Color::Indexed(77), Color::Indexed(77)),
+        },
+    ],
+    levels: GRAY_LEVELS,
+    suggestion: fill(Color::Indexed(220), Color::Indexed(220)),
+};
+
+/// The basic terminal colors, which every terminal has, with levels far
+/// apart in brightness.
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high-contrast",
+    players: [
+        PlayerStyles {
+            worker: fill(Color::Blue, Color::White),
+            text: text(Color::LightBlue),
+            cursor: fill(Color::LightCyan, Color::Black),
+            highlight: fill(Color::Cyan, Color::Cyan),
+        },
+        PlayerStyles {
+            worker: fill(Color::Red, Color::White),
+            text: text(Color::LightRed),
+            cursor: fill(Color::LightMagenta, Color::Black),
+            highlight: fill(Color::Magenta, Color::Magenta),
+        },
+        PlayerStyles {
+            worker: fill(Color::Green, Color::Black),
+            text: text(Color::LightGreen),
+            cursor: fill(Color::LightGreen, Color::Black),
+            highlight: fill(Color::Green, Color::Green),
+        },
+    ],
+    levels: [
+        DEFAULT_STYLE,
+        fill(Color::White, Color::Black),
+        fill(Color::Gray, Color::Black),
+        fill(Color::DarkGray, Color::White),
+        fill(Color::Black, Color::White),
+    ],
+    suggestion: fill(Color::LightYellow, Color::LightYellow),
+};
+
+/// Blue, orange and purple from the Okabe-Ito palette, which stay apart
+/// with the common kinds of color blindness.
+pub const COLORBLIND: Theme = Theme {
+    name: "colorblind",
+    players: [
+        PlayerStyles {
+            worker: fill(Color::Indexed(25), Color::White),
+            text: text(Color::Indexed(32)),
+            cursor: fill(Color::Indexed(117), Color::Black),
+            highlight: fill(Color::Indexed(74), Color::Indexed(74)),
+        },
+        PlayerStyles {
+            worker: fill(Color::Indexed(166), Color::White),
+            text: text(Color::Indexed(166)),
+            cursor: fill(Color::Indexed(221), Color::Black),
+            highlight: fill(Color::Indexed(214), Color::Indexed(214)),
+        },
+        PlayerStyles {
+            worker: fill(Color::Indexed(132), Color::White),
+            text: text(Color::Indexed(175)),
+            cursor: fill(Color::Indexed(225), Color::Black),
+            highlight: fill(Color::Indexed(182), Color::Indexed(182)),
+        },
+    ],
+    levels: GRAY_LEVELS,
+    suggestion: fill(Color::Indexed(227), Color::Indexed(227)),
+};
+
+/// Every theme, in the order the settings screen cycles through them.
+pub const THEMES: [Theme; 3] = [DEFAULT, HIGH_CONTRAST, COLORBLIND];
+
+/// The theme chosen in the settings.
+pub fn theme() -> Theme {
+    Theme::named(&settings().theme).unwrap_or(DEFAULT)
+}
+
+#[cfg(test)]
+mod theme_tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        assert_eq!(Theme::named("colorblind"), Some(COLORBLIND));
+        assert_eq!(Theme::named("neon"), None);
+        assert_eq!(DEFAULT.level(CoordLevel::Two), GRAY_LEVELS[2]);
+
+        // Each player must stand out from the others in every theme
+        for theme in THEMES.iter() {
+            let workers: Vec<Style> = theme.players.iter().map(|p| p.worker).collect();
+            assert!(!workers[1..].contains(&workers[0]), "{}", theme.name);
+            assert_ne!(workers[1], workers[2], "{}", theme.name);
+        }
+    }
+}